@@ -5,25 +5,25 @@ use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 
 fn bench_blake2s(c: &mut Criterion) {
-    const DATA: &'static [u8] = b"hello, world!";
+    const DATA: &[u8] = b"hello, world!";
 
     c.bench_function("New Signing Key with Blake2s", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let _ = SigningKey::<LamportFixedDigest<Blake2s256>>::random(rng);
+            let _ = SigningKey::<LamportFixedDigest<Blake2s256>>::random(rng).unwrap();
         });
     });
     c.bench_function("Sign with Blake2s", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Blake2s256>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Blake2s256>>::random(rng).unwrap();
             sk.sign(DATA).unwrap();
         });
     });
     c.bench_function("Verify with Blake2s", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Blake2s256>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Blake2s256>>::random(rng).unwrap();
             let pk = VerifyingKey::from(&sk);
             let signature = sk.sign(DATA).unwrap();
             pk.verify(&signature, DATA).unwrap();
@@ -32,25 +32,25 @@ fn bench_blake2s(c: &mut Criterion) {
 }
 
 fn bench_blake2b(c: &mut Criterion) {
-    const DATA: &'static [u8] = b"hello, world!";
+    const DATA: &[u8] = b"hello, world!";
 
     c.bench_function("New Signing Key with Blake2b", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let _ = SigningKey::<LamportFixedDigest<Blake2b512>>::random(rng);
+            let _ = SigningKey::<LamportFixedDigest<Blake2b512>>::random(rng).unwrap();
         });
     });
     c.bench_function("Sign with Blake2b", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Blake2b512>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Blake2b512>>::random(rng).unwrap();
             sk.sign(DATA).unwrap();
         });
     });
     c.bench_function("Verify with Blake2b512", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Blake2b512>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Blake2b512>>::random(rng).unwrap();
             let pk = VerifyingKey::from(&sk);
             let signature = sk.sign(DATA).unwrap();
             pk.verify(&signature, DATA).unwrap();
@@ -1,56 +1,67 @@
 use criterion::*;
-use lamport_signature_plus::{LamportFixedDigest, SigningKey, VerifyingKey};
+use lamport_signature_plus::{LamportFixedDigest, PreparedVerifyingKey, SigningKey, VerifyingKey};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
 use sha2::{Sha256, Sha384, Sha512};
 
 fn bench_sha256(c: &mut Criterion) {
-    const DATA: &'static [u8] = b"hello, world!";
+    const DATA: &[u8] = b"hello, world!";
 
     c.bench_function("New Signing Key with Sha256", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let _ = SigningKey::<LamportFixedDigest<Sha256>>::random(rng);
+            let _ = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
         });
     });
     c.bench_function("Sign with Sha256", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Sha256>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
             sk.sign(DATA).unwrap();
         });
     });
     c.bench_function("Verify with Sha256", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Sha256>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
             let pk = VerifyingKey::from(&sk);
             let signature = sk.sign(DATA).unwrap();
             pk.verify(&signature, DATA).unwrap();
         });
     });
+    let rng = ChaChaRng::from_entropy();
+    let mut sk = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
+    let pk = VerifyingKey::from(&sk);
+    let prepared = PreparedVerifyingKey::from(&pk);
+    let signature = sk.sign(DATA).unwrap();
+    c.bench_function("Verify only with Sha256", |b| {
+        b.iter(|| pk.verify(&signature, DATA).unwrap());
+    });
+    c.bench_function("Verify prepared with Sha256", |b| {
+        b.iter(|| prepared.verify(&signature, DATA).unwrap());
+    });
 }
 
 fn bench_sha384(c: &mut Criterion) {
-    const DATA: &'static [u8] = b"hello, world!";
+    const DATA: &[u8] = b"hello, world!";
 
     c.bench_function("New Signing Key with Sha384", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let _ = SigningKey::<LamportFixedDigest<Sha384>>::random(rng);
+            let _ = SigningKey::<LamportFixedDigest<Sha384>>::random(rng).unwrap();
         });
     });
     c.bench_function("Sign with Sha384", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Sha384>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Sha384>>::random(rng).unwrap();
             sk.sign(DATA).unwrap();
         });
     });
     c.bench_function("Verify with Sha384", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Sha384>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Sha384>>::random(rng).unwrap();
             let pk = VerifyingKey::from(&sk);
             let signature = sk.sign(DATA).unwrap();
             pk.verify(&signature, DATA).unwrap();
@@ -59,25 +70,25 @@ fn bench_sha384(c: &mut Criterion) {
 }
 
 fn bench_sha512(c: &mut Criterion) {
-    const DATA: &'static [u8] = b"hello, world!";
+    const DATA: &[u8] = b"hello, world!";
 
     c.bench_function("New Signing Key with Sha512", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let _ = SigningKey::<LamportFixedDigest<Sha512>>::random(rng);
+            let _ = SigningKey::<LamportFixedDigest<Sha512>>::random(rng).unwrap();
         });
     });
     c.bench_function("Sign with Sha512", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Sha512>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Sha512>>::random(rng).unwrap();
             sk.sign(DATA).unwrap();
         });
     });
     c.bench_function("Verify with Sha512", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Sha512>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Sha512>>::random(rng).unwrap();
             let pk = VerifyingKey::from(&sk);
             let signature = sk.sign(DATA).unwrap();
             pk.verify(&signature, DATA).unwrap();
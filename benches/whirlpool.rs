@@ -5,25 +5,25 @@ use rand_chacha::ChaChaRng;
 use whirlpool::Whirlpool;
 
 fn bench_whirlpool(c: &mut Criterion) {
-    const DATA: &'static [u8] = b"hello, world!";
+    const DATA: &[u8] = b"hello, world!";
 
     c.bench_function("New Signing Key with Whirlpool", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let _ = SigningKey::<LamportFixedDigest<Whirlpool>>::random(rng);
+            let _ = SigningKey::<LamportFixedDigest<Whirlpool>>::random(rng).unwrap();
         });
     });
     c.bench_function("Sign with Whirlpool", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Whirlpool>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Whirlpool>>::random(rng).unwrap();
             sk.sign(DATA).unwrap();
         });
     });
     c.bench_function("Verify with Whirlpool", |b| {
         b.iter(|| {
             let rng = ChaChaRng::from_entropy();
-            let mut sk = SigningKey::<LamportFixedDigest<Whirlpool>>::random(rng);
+            let mut sk = SigningKey::<LamportFixedDigest<Whirlpool>>::random(rng).unwrap();
             let pk = VerifyingKey::from(&sk);
             let signature = sk.sign(DATA).unwrap();
             pk.verify(&signature, DATA).unwrap();
@@ -0,0 +1,25 @@
+#![no_main]
+
+use lamport_signature_plus::{
+    LamportFixedDigest, Signature, SignatureShare, SigningKey, SigningKeyShare, VerifyingKey,
+};
+use libfuzzer_sys::fuzz_target;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::Sha256;
+
+fn round_trip<V: Serialize + DeserializeOwned>(data: &[u8]) {
+    if let Ok(value) = serde_json::from_slice::<V>(data) {
+        let json = serde_json::to_string(&value).expect("serialization must succeed");
+        let restored = serde_json::from_str::<V>(&json).expect("serialized value must parse");
+        let restored_json = serde_json::to_string(&restored).expect("serialization must succeed");
+        assert_eq!(restored_json, json);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    round_trip::<SigningKey<LamportFixedDigest<Sha256>>>(data);
+    round_trip::<SigningKeyShare<LamportFixedDigest<Sha256>>>(data);
+    round_trip::<VerifyingKey<LamportFixedDigest<Sha256>>>(data);
+    round_trip::<Signature<LamportFixedDigest<Sha256>>>(data);
+    round_trip::<SignatureShare<LamportFixedDigest<Sha256>>>(data);
+});
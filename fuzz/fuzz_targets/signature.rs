@@ -0,0 +1,21 @@
+#![no_main]
+
+use lamport_signature_plus::{LamportExtendableDigest, LamportFixedDigest, Signature};
+use libfuzzer_sys::fuzz_target;
+use sha2::Sha256;
+use sha3::Shake128;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(value) = Signature::<LamportFixedDigest<Sha256>>::from_bytes(data) {
+        let bytes = value.to_bytes();
+        let restored = Signature::<LamportFixedDigest<Sha256>>::from_bytes(&bytes)
+            .expect("canonical bytes must parse");
+        assert_eq!(restored.to_bytes(), bytes);
+    }
+    if let Ok(value) = Signature::<LamportExtendableDigest<Shake128>>::from_bytes(data) {
+        let bytes = value.to_bytes();
+        let restored = Signature::<LamportExtendableDigest<Shake128>>::from_bytes(&bytes)
+            .expect("canonical bytes must parse");
+        assert_eq!(restored.to_bytes(), bytes);
+    }
+});
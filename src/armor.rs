@@ -0,0 +1,209 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! OpenPGP style ASCII armor (RFC 4880 §6.2) for email and chat transport.
+//!
+//! The body is padded standard base64 wrapped at 64 characters, followed by
+//! a CRC-24 checksum line:
+//!
+//! ```text
+//! -----BEGIN LAMPORT SIGNATURE-----
+//! Comment: optional headers
+//!
+//! <base64>
+//! =<crc24>
+//! -----END LAMPORT SIGNATURE-----
+//! ```
+//!
+//! Parsing skips any text around the armor, trims whitespace and carriage
+//! returns from each line, and accepts body lines of any length.
+use crate::codec::{base64_decode, base64_encode};
+use crate::limits::Limits;
+use crate::{LamportError, LamportResult};
+use zeroize::Zeroize;
+
+const LINE_LENGTH: usize = 64;
+const CRC24_INIT: u32 = 0x00b7_04ce;
+const CRC24_POLY: u32 = 0x0186_4cfb;
+
+/// A decoded armor block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Armored {
+    /// The label between `BEGIN` and the dashes, e.g. `LAMPORT SIGNATURE`.
+    pub label: String,
+    /// The `Key: Value` headers in order.
+    pub headers: Vec<(String, String)>,
+    /// The decoded body.
+    pub data: Vec<u8>,
+}
+
+impl Drop for Armored {
+    fn drop(&mut self) {
+        self.data.zeroize();
+    }
+}
+
+/// Armors the data under the label with optional headers.
+pub fn encode(label: &str, headers: &[(&str, &str)], data: &[u8]) -> String {
+    let mut body = base64_encode(data);
+    let mut output = String::with_capacity(body.len() + body.len() / LINE_LENGTH + 128);
+    output.push_str("-----BEGIN ");
+    output.push_str(label);
+    output.push_str("-----\n");
+    for (key, value) in headers {
+        output.push_str(key);
+        output.push_str(": ");
+        output.push_str(value);
+        output.push('\n');
+    }
+    output.push('\n');
+    for line in body.as_bytes().chunks(LINE_LENGTH) {
+        // base64 is ASCII so every chunk is valid UTF-8
+        output.push_str(core::str::from_utf8(line).unwrap_or_default());
+        output.push('\n');
+    }
+    body.zeroize();
+    output.push('=');
+    output.push_str(&base64_encode(&crc24(data).to_be_bytes()[1..]));
+    output.push_str("\n-----END ");
+    output.push_str(label);
+    output.push_str("-----\n");
+    output
+}
+
+/// Parses the first armor block in the input and checks its checksum.
+pub fn decode(input: &str) -> LamportResult<Armored> {
+    decode_with_limits(input, &Limits::DEFAULT)
+}
+
+/// Parses the first armor block in the input, rejecting data larger than `limits`.
+pub fn decode_with_limits(input: &str, limits: &Limits) -> LamportResult<Armored> {
+    let mut lines = input.lines().map(str::trim);
+    let label = lines
+        .by_ref()
+        .find_map(|line| line.strip_prefix("-----BEGIN ")?.strip_suffix("-----"))
+        .ok_or(LamportError::InvalidArmor)?
+        .to_string();
+    let end = format!("-----END {}-----", label);
+
+    let mut lines = lines.peekable();
+    let mut headers = Vec::new();
+    // Headers are only present if the first line looks like one
+    if lines.peek().is_some_and(|line| line.contains(": ")) {
+        for line in lines.by_ref() {
+            if line.is_empty() {
+                break;
+            }
+            let (key, value) = line.split_once(": ").ok_or(LamportError::InvalidArmor)?;
+            headers.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    let mut body = String::new();
+    let mut checksum = None;
+    let mut ended = false;
+    for line in lines {
+        if line == end {
+            ended = true;
+            break;
+        }
+        if let Some(crc) = line.strip_prefix('=') {
+            checksum = Some(crc);
+        } else if checksum.is_none() {
+            body.push_str(line);
+        } else {
+            return Err(LamportError::InvalidArmor);
+        }
+    }
+    let checksum = checksum
+        .filter(|_| ended)
+        .and_then(|crc| base64_decode(crc).ok())
+        .ok_or(LamportError::InvalidArmor)?;
+
+    // Base64 carries three bytes in every four characters
+    if let Err(e) = limits.check(body.len() / 4 * 3) {
+        body.zeroize();
+        return Err(e);
+    }
+    let data = base64_decode(&body).map_err(|_| LamportError::InvalidArmor);
+    body.zeroize();
+    let armored = Armored {
+        label,
+        headers,
+        data: data?,
+    };
+    if checksum != crc24(&armored.data).to_be_bytes()[1..] {
+        return Err(LamportError::InvalidArmor);
+    }
+    Ok(armored)
+}
+
+/// Computes the OpenPGP CRC-24 without branching on the data.
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for byte in data {
+        crc ^= u32::from(*byte) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            crc ^= CRC24_POLY & 0u32.wrapping_sub((crc >> 24) & 1);
+        }
+    }
+    crc & 0x00ff_ffff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest, Signature, SigningKey, VerifyingKey};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn ascii_armor() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let signature = sk.sign(b"armored").unwrap();
+
+        let armored = signature.to_armor();
+        assert!(armored.starts_with("-----BEGIN LAMPORT SIGNATURE-----\n\n"));
+        assert!(armored.ends_with("-----END LAMPORT SIGNATURE-----\n"));
+        assert!(armored.lines().all(|line| line.len() <= 64));
+        let restored = Signature::<LamportFixedDigest<Sha256>>::from_armor(&armored).unwrap();
+        assert!(pk.verify(&restored, b"armored").is_ok());
+        let restored =
+            SigningKey::<LamportFixedDigest<Sha256>>::from_armor(&sk.to_armor()).unwrap();
+        assert_eq!(restored.to_bytes(), sk.to_bytes());
+        assert_eq!(
+            VerifyingKey::<LamportFixedDigest<Sha256>>::from_armor(&armored).unwrap_err(),
+            LamportError::InvalidArmor
+        );
+
+        // Surrounding text, CRLF line endings, indentation and rewrapped lines are accepted
+        let text = encode(
+            "LAMPORT TEST",
+            &[("Comment", "hello: world")],
+            b"hello world",
+        );
+        let mangled = format!(
+            "Forwarded message\r\n{}",
+            text.replace('\n', "\r\n  ")
+                .replace("aGVsbG8g", "aGVs\r\nbG8g")
+        );
+        let decoded = decode(&mangled).unwrap();
+        assert_eq!(decoded.label, "LAMPORT TEST");
+        assert_eq!(
+            decoded.headers,
+            vec![("Comment".to_string(), "hello: world".to_string())]
+        );
+        assert_eq!(decoded.data, b"hello world");
+
+        // RFC 4880 CRC-24 of "hello world"
+        assert!(text.contains("\n=sDy3\n"));
+        let corrupted = text.replace("aGVsbG8g", "aGVsbG9g");
+        assert_eq!(decode(&corrupted), Err(LamportError::InvalidArmor));
+        let truncated = text.replace("-----END LAMPORT TEST-----", "");
+        assert_eq!(decode(&truncated), Err(LamportError::InvalidArmor));
+    }
+}
@@ -0,0 +1,33 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportIncrementalDigest, LamportResult, Signature, VerifyingKey};
+use futures_core::Stream;
+use std::future::poll_fn;
+use std::pin::pin;
+
+impl<T: LamportIncrementalDigest> VerifyingKey<T> {
+    /// Verifies the [`Signature`] over a message read from an async byte stream.
+    ///
+    /// Each chunk is hashed as it arrives, so large uploads are neither
+    /// buffered nor block the executor. Adapt an `AsyncRead` with e.g.
+    /// `tokio_util::io::ReaderStream`. The first I/O error from the stream is
+    /// returned as [`crate::LamportError::IoError`].
+    pub async fn verify_async_stream<S, B>(
+        &self,
+        signature: &Signature<T>,
+        stream: S,
+    ) -> LamportResult<()>
+    where
+        S: Stream<Item = std::io::Result<B>>,
+        B: AsRef<[u8]>,
+    {
+        let mut stream = pin!(stream);
+        let mut hasher = T::hasher();
+        while let Some(chunk) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+            T::update(&mut hasher, chunk?.as_ref());
+        }
+        self.verify_digest(signature, &T::finalize(hasher))
+    }
+}
@@ -0,0 +1,156 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Opt-in unpadded base64url (RFC 4648 §5) for human-readable serde formats.
+//!
+//! Values are written as hex by default. Base64url is a third smaller, so
+//! JSON heavy systems can switch individual fields to it with
+//! `#[serde(with = "lamport_signature_plus::base64url")]`, or call the
+//! functions directly:
+//!
+//! ```
+//! use lamport_signature_plus::{base64url, generate_keys, LamportFixedDigest, VerifyingKey};
+//! use rand::SeedableRng;
+//! use sha2::Sha256;
+//!
+//! let rng = rand_chacha::ChaCha8Rng::from_seed([0u8; 32]);
+//! let (_, key) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+//! let mut json = Vec::new();
+//! base64url::serialize(&key, &mut serde_json::Serializer::new(&mut json)).unwrap();
+//! let decoded: VerifyingKey<LamportFixedDigest<Sha256>> =
+//!     base64url::deserialize(&mut serde_json::Deserializer::from_slice(&json)).unwrap();
+//! assert_eq!(decoded, key);
+//! ```
+//!
+//! The choice is made per field rather than for the whole crate, so data
+//! written by one user never becomes unreadable to another. An annotated field
+//! only decodes base64url and every other field only hex, so each value has a
+//! single textual form. Binary formats use the canonical bytes either way.
+use crate::codec::{base64url_encode, TextEncoding};
+use crate::limits::deserialize_encoded;
+use crate::DecodeWithLimits;
+use serde::de::value::Error as ValueError;
+use serde::de::Deserializer;
+use serde::ser::{Impossible, Serialize, Serializer};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Serializes the value as base64url in human-readable formats.
+///
+/// The value must serialize as bytes in binary formats, as every key,
+/// share and signature in this crate does.
+pub fn serialize<V, S>(value: &V, s: S) -> Result<S::Ok, S::Error>
+where
+    V: Serialize + ?Sized,
+    S: Serializer,
+{
+    if !s.is_human_readable() {
+        return value.serialize(s);
+    }
+    let bytes = value
+        .serialize(CanonicalBytes)
+        .map_err(serde::ser::Error::custom)?;
+    let mut text = base64url_encode(&bytes);
+    let result = text.serialize(s);
+    text.zeroize();
+    result
+}
+
+/// Deserializes a value written as base64url in human-readable formats.
+///
+/// Hex is rejected. The text is checked against the value's
+/// [`DecodeWithLimits::LIMITS`], such as the limit of a [`Bounded`](crate::Bounded)
+/// value, before it is decoded.
+pub fn deserialize<'de, V, D>(d: D) -> Result<V, D::Error>
+where
+    V: DecodeWithLimits,
+    D: Deserializer<'de>,
+{
+    deserialize_encoded(d, &V::LIMITS, TextEncoding::Base64Url)
+}
+
+/// A binary format that accepts a single byte string.
+struct CanonicalBytes;
+
+fn not_bytes() -> ValueError {
+    serde::ser::Error::custom("the value does not serialize as bytes")
+}
+
+macro_rules! reject {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, ValueError> {
+                Err(not_bytes())
+            }
+        )*
+    };
+}
+
+impl Serializer for CanonicalBytes {
+    type Ok = Zeroizing<Vec<u8>>;
+    type Error = ValueError;
+    type SerializeSeq = Impossible<Self::Ok, ValueError>;
+    type SerializeTuple = Impossible<Self::Ok, ValueError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, ValueError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, ValueError>;
+    type SerializeMap = Impossible<Self::Ok, ValueError>;
+    type SerializeStruct = Impossible<Self::Ok, ValueError>;
+    type SerializeStructVariant = Impossible<Self::Ok, ValueError>;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, ValueError> {
+        Ok(Zeroizing::new(v.to_vec()))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<Self::Ok, ValueError> {
+        Err(not_bytes())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, ValueError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<Self::Ok, ValueError> {
+        Err(not_bytes())
+    }
+
+    reject! {
+        serialize_bool(bool) -> Self::Ok;
+        serialize_i8(i8) -> Self::Ok;
+        serialize_i16(i16) -> Self::Ok;
+        serialize_i32(i32) -> Self::Ok;
+        serialize_i64(i64) -> Self::Ok;
+        serialize_u8(u8) -> Self::Ok;
+        serialize_u16(u16) -> Self::Ok;
+        serialize_u32(u32) -> Self::Ok;
+        serialize_u64(u64) -> Self::Ok;
+        serialize_f32(f32) -> Self::Ok;
+        serialize_f64(f64) -> Self::Ok;
+        serialize_char(char) -> Self::Ok;
+        serialize_str(&str) -> Self::Ok;
+        serialize_none() -> Self::Ok;
+        serialize_unit() -> Self::Ok;
+        serialize_unit_struct(&'static str) -> Self::Ok;
+        serialize_unit_variant(&'static str, u32, &'static str) -> Self::Ok;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+}
@@ -0,0 +1,420 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest_size, check_security};
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec};
+use rand::{CryptoRng, RngCore};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+const MESSAGE_DOMAIN: &[u8] = b"lamport_signature_plus biba message v1";
+const BIN_DOMAIN: &[u8] = b"lamport_signature_plus biba bin v1";
+
+/// The most counters [`BibaSigningKey::sign`] tries before giving up.
+pub const BIBA_MAX_COUNTER: u64 = 1 << 16;
+
+/// The number of secret balls `t` and of bins `n` of a BiBa key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BibaParams {
+    balls: u32,
+    bins: u32,
+}
+
+impl BibaParams {
+    /// Supports 2 to 65536 balls and at least 2 bins.
+    ///
+    /// A signature exists for a counter when two of the `t` balls fall in the
+    /// same of the `n` bins, which happens with probability about
+    /// `1 - e^(-t^2 / 2n)`. More bins make forgeries harder and signing
+    /// slower, for example 1024 balls in 762 bins from the BiBa paper
+    /// almost always sign with the first counter.
+    pub fn new(balls: u32, bins: u32) -> LamportResult<Self> {
+        if !(2..=1 << 16).contains(&balls) || bins < 2 {
+            return Err(LamportError::InvalidBibaParameters);
+        }
+        Ok(Self { balls, bins })
+    }
+
+    /// The number of secret balls in a key.
+    pub fn balls(&self) -> u32 {
+        self.balls
+    }
+
+    /// The number of bins the balls are thrown into.
+    pub fn bins(&self) -> u32 {
+        self.bins
+    }
+
+    fn to_bytes(self) -> [u8; 8] {
+        let mut bytes = [0u8; 8];
+        bytes[..4].copy_from_slice(&self.balls.to_be_bytes());
+        bytes[4..].copy_from_slice(&self.bins.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(input: &[u8]) -> Option<Self> {
+        let balls = u32::from_be_bytes(input.get(..4)?.try_into().ok()?);
+        let bins = u32::from_be_bytes(input.get(4..8)?.try_into().ok()?);
+        Self::new(balls, bins).ok()
+    }
+
+    fn len(&self) -> usize {
+        usize::try_from(self.balls).expect("at most 65536 balls")
+    }
+}
+
+/// A BiBa ("bins and balls") one-time signing key for broadcast authentication.
+///
+/// The key is `t` random balls, and the public key is the digest of each.
+/// To sign, the message and a counter choose a hash function that throws
+/// every ball into one of `n` bins, and the counter is increased until two
+/// balls land in the same bin. The signature is the counter and the two
+/// colliding balls, so it is short and fast to verify while signing is a
+/// search. A forger who only knows the two revealed balls must find a
+/// message and counter for which they collide.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BibaSigningKey<T: LamportDigest> {
+    pub(crate) params: BibaParams,
+    pub(crate) used: bool,
+    pub(crate) balls: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(BibaSigningKey);
+vec_impl!(BibaSigningKey);
+
+impl<T: LamportDigest> Zeroize for BibaSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.balls.zeroize();
+    }
+}
+
+impl<T: LamportDigest> BibaSigningKey<T> {
+    /// Constructs a random [`BibaSigningKey`].
+    pub fn random(params: BibaParams, mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let n = T::digest_size_in_bits() / 8;
+        let mut data = vec![0u8; params.len() * n];
+        rng.fill_bytes(&mut data);
+        Ok(Self {
+            params,
+            used: false,
+            balls: MultiVec {
+                data,
+                axes: [params.len(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The parameters.
+    pub fn params(&self) -> BibaParams {
+        self.params
+    }
+
+    /// Has the key already signed a message.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs the data with the first counter that makes two balls collide.
+    ///
+    /// Returns [`LamportError::BibaCollisionNotFound`] if none of the first
+    /// [`BIBA_MAX_COUNTER`] counters do, and the key stays unused.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<BibaSignature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let data = data.as_ref();
+        let mut seen = HashMap::with_capacity(self.params.len());
+        for counter in 0..BIBA_MAX_COUNTER {
+            let selector = selector::<T>(counter, data);
+            seen.clear();
+            for (index, ball) in self.balls.data.chunks_exact(self.balls.axes[1]).enumerate() {
+                let bin = bin::<T>(&selector, ball, self.params.bins);
+                if let Some(first) = seen.insert(bin, index) {
+                    self.used = true;
+                    let mut values = self.balls[first].to_vec();
+                    values.extend_from_slice(ball);
+                    return Ok(BibaSignature {
+                        params: self.params,
+                        counter,
+                        indices: [first, index]
+                            .map(|i| u32::try_from(i).expect("at most 65536 balls")),
+                        values: MultiVec {
+                            data: values,
+                            axes: [2, self.balls.axes[1]],
+                        },
+                        algorithm: PhantomData,
+                    });
+                }
+            }
+        }
+        Err(LamportError::BibaCollisionNotFound)
+    }
+
+    /// Converts the [`BibaSigningKey`] to canonical bytes.
+    ///
+    /// The format is the ball and bin counts as big endian `u32`s, the used
+    /// flag, then the balls.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9 + self.balls.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.push(self.used as u8);
+        bytes.extend_from_slice(&self.balls.data);
+        bytes
+    }
+
+    /// Constructs a [`BibaSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let params = BibaParams::from_bytes(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        let n = T::digest_size_in_bits() / 8;
+        if input.len() != 9 + params.len() * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let used = match input[8] {
+            0 => false,
+            1 => true,
+            _ => return Err(LamportError::InvalidPrivateKeyBytes),
+        };
+        Ok(Self {
+            params,
+            used,
+            balls: MultiVec {
+                data: input[9..].to_vec(),
+                axes: [params.len(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A BiBa verifying key, the digest of each ball.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BibaVerifyingKey<T: LamportDigest> {
+    pub(crate) params: BibaParams,
+    pub(crate) commitments: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(BibaVerifyingKey);
+vec_impl!(BibaVerifyingKey);
+
+impl<T: LamportDigest> From<&BibaSigningKey<T>> for BibaVerifyingKey<T> {
+    fn from(value: &BibaSigningKey<T>) -> Self {
+        let data = value
+            .balls
+            .data
+            .chunks_exact(value.balls.axes[1])
+            .flat_map(T::digest)
+            .collect();
+        Self {
+            params: value.params,
+            commitments: MultiVec {
+                data,
+                axes: value.balls.axes,
+            },
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> BibaVerifyingKey<T> {
+    /// The parameters.
+    pub fn params(&self) -> BibaParams {
+        self.params
+    }
+
+    /// Verifies the signature.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &BibaSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let n = self.commitments.axes[1];
+        let [first, second] = signature.indices.map(|i| i as usize);
+        if signature.params != self.params
+            || signature.values.axes != [2, n]
+            || signature.values.data.len() != 2 * n
+            || first == second
+            || first >= self.params.len()
+            || second >= self.params.len()
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let selector = selector::<T>(signature.counter, data.as_ref());
+        let valid = T::digest(&signature.values[0]) == self.commitments[first]
+            && T::digest(&signature.values[1]) == self.commitments[second]
+            && bin::<T>(&selector, &signature.values[0], self.params.bins)
+                == bin::<T>(&selector, &signature.values[1], self.params.bins);
+        if valid {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the [`BibaVerifyingKey`] to canonical bytes, the ball and
+    /// bin counts as big endian `u32`s, then the digests.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.commitments.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.commitments.data);
+        bytes
+    }
+
+    /// Constructs a [`BibaVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let params = BibaParams::from_bytes(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        let n = T::digest_size_in_bits() / 8;
+        if input.len() != 8 + params.len() * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            params,
+            commitments: MultiVec {
+                data: input[8..].to_vec(),
+                axes: [params.len(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature generated by [`BibaSigningKey`], the counter and the two colliding balls.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BibaSignature<T: LamportDigest> {
+    pub(crate) params: BibaParams,
+    pub(crate) counter: u64,
+    pub(crate) indices: [u32; 2],
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(BibaSignature);
+vec_impl!(BibaSignature);
+
+impl<T: LamportDigest> BibaSignature<T> {
+    /// The counter that made the balls collide.
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    /// The indices of the two colliding balls.
+    pub fn indices(&self) -> [u32; 2] {
+        self.indices
+    }
+
+    /// Converts the [`BibaSignature`] to canonical bytes.
+    ///
+    /// The format is the ball and bin counts as big endian `u32`s, the
+    /// counter as a big endian `u64`, the two indices as big endian `u32`s,
+    /// then the two balls.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(24 + self.values.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.counter.to_be_bytes());
+        bytes.extend_from_slice(&self.indices[0].to_be_bytes());
+        bytes.extend_from_slice(&self.indices[1].to_be_bytes());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`BibaSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let params = BibaParams::from_bytes(input).ok_or(LamportError::InvalidSignatureBytes)?;
+        let n = T::digest_size_in_bits() / 8;
+        if n == 0 || input.len() != 24 + 2 * n {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let read = |range: std::ops::Range<usize>| {
+            u32::from_be_bytes(input[range].try_into().expect("four bytes"))
+        };
+        Ok(Self {
+            params,
+            counter: u64::from_be_bytes(input[8..16].try_into().expect("eight bytes")),
+            indices: [read(16..20), read(20..24)],
+            values: MultiVec {
+                data: input[24..].to_vec(),
+                axes: [2, n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// The digest of the message and counter that selects the hash function.
+fn selector<T: LamportDigest>(counter: u64, data: &[u8]) -> Vec<u8> {
+    T::digest_concat(&[MESSAGE_DOMAIN, &counter.to_be_bytes(), data])
+}
+
+/// The bin the ball falls into for the selected hash function.
+fn bin<T: LamportDigest>(selector: &[u8], ball: &[u8], bins: u32) -> u64 {
+    let digest = T::digest_concat(&[BIN_DOMAIN, selector, ball]);
+    let mut prefix = [0u8; 8];
+    let length = digest.len().min(8);
+    prefix[..length].copy_from_slice(&digest[..length]);
+    u64::from_be_bytes(prefix) % u64::from(bins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn biba() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let params = BibaParams::new(64, 512).unwrap();
+        let mut sk = BibaSigningKey::<D>::random(params, &mut rng).unwrap();
+        let pk = BibaVerifyingKey::from(&sk);
+        let signature = sk.sign(b"broadcast").unwrap();
+        assert!(sk.used());
+        assert_eq!(
+            sk.sign(b"again").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert!(pk.verify(&signature, b"broadcast").is_ok());
+        assert!(pk.verify(&signature, b"other").is_err());
+        let [first, second] = signature.indices();
+        assert_ne!(first, second);
+
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), 24 + 2 * 32);
+        let decoded = BibaSignature::<D>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        let pk = BibaVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        assert!(pk.verify(&decoded, b"broadcast").is_ok());
+        let restored = BibaSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        assert!(restored.used());
+
+        // The same ball twice is not a collision
+        let mut repeated = decoded.clone();
+        repeated.indices = [first, first];
+        assert!(pk.verify(&repeated, b"broadcast").is_err());
+        let mut wrong_counter = decoded;
+        wrong_counter.counter += 1;
+        assert!(pk.verify(&wrong_counter, b"broadcast").is_err());
+
+        assert!(BibaParams::new(1, 512).is_err());
+        assert!(BibaParams::new(64, 1).is_err());
+        let sparse = BibaParams::new(2, u32::MAX).unwrap();
+        let mut sk = BibaSigningKey::<D>::random(sparse, &mut rng).unwrap();
+        assert_eq!(
+            sk.sign(b"broadcast").unwrap_err(),
+            LamportError::BibaCollisionNotFound
+        );
+        assert!(!sk.used());
+    }
+}
@@ -0,0 +1,248 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::{
+    LamportDigest, LamportError, LamportResult, Signature, SignatureShare, SigningKey,
+    SigningKeyShare,
+};
+use std::collections::BTreeSet;
+
+const BUNDLE_VERSION: u8 = 1;
+const KEY_SHARES: u8 = 1;
+const SIGNATURE_SHARES: u8 = 2;
+
+/// A container for several [`SigningKeyShare`]s or [`SignatureShare`]s of the same key.
+///
+/// The bundle checks that every share has the same threshold and a distinct
+/// identifier, and for signature shares records the digest of the signed
+/// message, so tooling can move one well-formed blob instead of loose shares.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShareBundle<T: LamportDigest> {
+    pub(crate) threshold: u8,
+    pub(crate) contents: BundleContents<T>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum BundleContents<T: LamportDigest> {
+    KeyShares(Vec<SigningKeyShare<T>>),
+    SignatureShares {
+        message_digest: Vec<u8>,
+        shares: Vec<SignatureShare<T>>,
+    },
+}
+
+serde_impl!(@limits ShareBundle);
+vec_impl!(ShareBundle);
+
+impl<T: LamportDigest> ShareBundle<T> {
+    /// Bundles [`SigningKeyShare`]s.
+    pub fn from_key_shares(shares: Vec<SigningKeyShare<T>>) -> LamportResult<Self> {
+        let threshold = check_shares(shares.iter().map(|s| (s.identifier, s.threshold)))?;
+        Ok(Self {
+            threshold,
+            contents: BundleContents::KeyShares(shares),
+        })
+    }
+
+    /// Bundles [`SignatureShare`]s created over `data`.
+    pub fn from_signature_shares<B: AsRef<[u8]>>(
+        shares: Vec<SignatureShare<T>>,
+        data: B,
+    ) -> LamportResult<Self> {
+        let threshold = check_shares(shares.iter().map(|s| (s.identifier, s.threshold)))?;
+        Ok(Self {
+            threshold,
+            contents: BundleContents::SignatureShares {
+                message_digest: T::digest(data.as_ref()),
+                shares,
+            },
+        })
+    }
+
+    /// The threshold shared by every share in the bundle.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// The number of shares in the bundle.
+    pub fn len(&self) -> usize {
+        match &self.contents {
+            BundleContents::KeyShares(shares) => shares.len(),
+            BundleContents::SignatureShares { shares, .. } => shares.len(),
+        }
+    }
+
+    /// Returns true if the bundle has no shares. Bundles are never empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The key shares, if this is a bundle of [`SigningKeyShare`]s.
+    pub fn key_shares(&self) -> Option<&[SigningKeyShare<T>]> {
+        match &self.contents {
+            BundleContents::KeyShares(shares) => Some(shares),
+            BundleContents::SignatureShares { .. } => None,
+        }
+    }
+
+    /// The signature shares, if this is a bundle of [`SignatureShare`]s.
+    pub fn signature_shares(&self) -> Option<&[SignatureShare<T>]> {
+        match &self.contents {
+            BundleContents::KeyShares(_) => None,
+            BundleContents::SignatureShares { shares, .. } => Some(shares),
+        }
+    }
+
+    /// The digest of the signed message, if this is a bundle of [`SignatureShare`]s.
+    pub fn message_digest(&self) -> Option<&[u8]> {
+        match &self.contents {
+            BundleContents::KeyShares(_) => None,
+            BundleContents::SignatureShares { message_digest, .. } => Some(message_digest),
+        }
+    }
+
+    /// Combines a bundle of [`SigningKeyShare`]s into the [`SigningKey`].
+    pub fn combine_key(&self) -> LamportResult<SigningKey<T>> {
+        let shares = self.key_shares().ok_or(LamportError::InvalidBundleBytes)?;
+        SigningKey::combine(shares)
+    }
+
+    /// Combines a bundle of [`SignatureShare`]s into the [`Signature`].
+    pub fn combine_signature(&self) -> LamportResult<Signature<T>> {
+        let shares = self
+            .signature_shares()
+            .ok_or(LamportError::InvalidBundleBytes)?;
+        Signature::combine(shares)
+    }
+
+    /// Converts the [`ShareBundle`] to canonical bytes.
+    ///
+    /// The header is a version byte, a kind byte, the digest size in bits as a
+    /// big endian `u32`, the threshold, the message digest for signature
+    /// shares, and the share count. Each share follows as a big endian `u32`
+    /// length and its canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (kind, digest, entries) = match &self.contents {
+            BundleContents::KeyShares(shares) => (
+                KEY_SHARES,
+                &[][..],
+                shares.iter().map(|s| s.to_bytes()).collect::<Vec<_>>(),
+            ),
+            BundleContents::SignatureShares {
+                message_digest,
+                shares,
+            } => (
+                SIGNATURE_SHARES,
+                message_digest.as_slice(),
+                shares.iter().map(|s| s.to_bytes()).collect::<Vec<_>>(),
+            ),
+        };
+        let bits = u32::try_from(T::digest_size_in_bits()).unwrap_or(u32::MAX);
+        let mut bytes = Vec::with_capacity(
+            8 + digest.len() + entries.iter().map(|e| 4 + e.len()).sum::<usize>(),
+        );
+        bytes.push(BUNDLE_VERSION);
+        bytes.push(kind);
+        bytes.extend_from_slice(&bits.to_be_bytes());
+        bytes.push(self.threshold);
+        bytes.extend_from_slice(digest);
+        bytes.push(u8::try_from(entries.len()).unwrap_or(u8::MAX));
+        for entry in entries {
+            let length = u32::try_from(entry.len()).unwrap_or(u32::MAX);
+            bytes.extend_from_slice(&length.to_be_bytes());
+            bytes.extend_from_slice(&entry);
+        }
+        bytes
+    }
+
+    /// Constructs a [`ShareBundle`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`ShareBundle`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let mut reader = Reader(input);
+        if reader.take(1)?[0] != BUNDLE_VERSION {
+            return Err(LamportError::InvalidBundleBytes);
+        }
+        let kind = reader.take(1)?[0];
+        let mut bits = [0u8; 4];
+        bits.copy_from_slice(reader.take(4)?);
+        if usize::try_from(u32::from_be_bytes(bits)).ok() != Some(T::digest_size_in_bits()) {
+            return Err(LamportError::InvalidBundleBytes);
+        }
+        let threshold = reader.take(1)?[0];
+        let message_digest = match kind {
+            KEY_SHARES => None,
+            SIGNATURE_SHARES => Some(reader.take(T::digest_size_in_bits() / 8)?.to_vec()),
+            _ => return Err(LamportError::InvalidBundleBytes),
+        };
+        let count = reader.take(1)?[0];
+        let mut entries = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let mut length = [0u8; 4];
+            length.copy_from_slice(reader.take(4)?);
+            let length = usize::try_from(u32::from_be_bytes(length))
+                .map_err(|_| LamportError::InvalidBundleBytes)?;
+            entries.push(reader.take(length)?);
+        }
+        if !reader.0.is_empty() {
+            return Err(LamportError::InvalidBundleBytes);
+        }
+
+        let bundle = match message_digest {
+            None => Self::from_key_shares(
+                entries
+                    .into_iter()
+                    .map(SigningKeyShare::from_bytes)
+                    .collect::<LamportResult<_>>()?,
+            )?,
+            Some(message_digest) => {
+                let shares = entries
+                    .into_iter()
+                    .map(SignatureShare::from_bytes)
+                    .collect::<LamportResult<Vec<_>>>()?;
+                let threshold = check_shares(shares.iter().map(|s| (s.identifier, s.threshold)))?;
+                Self {
+                    threshold,
+                    contents: BundleContents::SignatureShares {
+                        message_digest,
+                        shares,
+                    },
+                }
+            }
+        };
+        if bundle.threshold != threshold {
+            return Err(LamportError::InvalidBundleBytes);
+        }
+        Ok(bundle)
+    }
+}
+
+fn check_shares(mut shares: impl Iterator<Item = (u8, u8)>) -> LamportResult<u8> {
+    let (identifier, threshold) = shares.next().ok_or(LamportError::InvalidBundleBytes)?;
+    let mut seen = BTreeSet::from([identifier]);
+    for (identifier, t) in shares {
+        if t != threshold || !seen.insert(identifier) {
+            return Err(LamportError::InvalidBundleBytes);
+        }
+    }
+    Ok(threshold)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> LamportResult<&'a [u8]> {
+        if self.0.len() < length {
+            return Err(LamportError::InvalidBundleBytes);
+        }
+        let (head, tail) = self.0.split_at(length);
+        self.0 = tail;
+        Ok(head)
+    }
+}
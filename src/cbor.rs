@@ -0,0 +1,71 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportDigest, LamportError, LamportResult, Signature, SigningKey, VerifyingKey};
+use ciborium::value::Value;
+use serde::Serialize;
+
+/// Encodes the value as deterministic CBOR (RFC 8949 §4.2.1).
+///
+/// Integers, lengths and floats already use their shortest form and all
+/// lengths are definite, so only map keys need sorting by their encoded bytes.
+pub(crate) fn to_canonical_cbor<V: Serialize + ?Sized>(value: &V) -> LamportResult<Vec<u8>> {
+    let value = Value::serialized(value).map_err(|e| LamportError::General(e.to_string()))?;
+    let value = canonicalize(value)?;
+    encode(&value)
+}
+
+fn canonicalize(value: Value) -> LamportResult<Value> {
+    Ok(match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(canonicalize)
+                .collect::<LamportResult<_>>()?,
+        ),
+        Value::Tag(tag, inner) => Value::Tag(tag, Box::new(canonicalize(*inner)?)),
+        Value::Map(entries) => {
+            let mut keyed = Vec::with_capacity(entries.len());
+            for (k, v) in entries {
+                let k = canonicalize(k)?;
+                keyed.push((encode(&k)?, k, canonicalize(v)?));
+            }
+            keyed.sort_by(|a, b| a.0.cmp(&b.0));
+            if keyed.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(LamportError::General(
+                    "duplicate map key in canonical CBOR".to_string(),
+                ));
+            }
+            Value::Map(keyed.into_iter().map(|(_, k, v)| (k, v)).collect())
+        }
+        other => other,
+    })
+}
+
+fn encode(value: &Value) -> LamportResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes)
+        .map_err(|e| LamportError::General(e.to_string()))?;
+    Ok(bytes)
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs the deterministic CBOR encoding of the value.
+    pub fn sign_serde<V: Serialize + ?Sized>(&mut self, value: &V) -> LamportResult<Signature<T>> {
+        let bytes = to_canonical_cbor(value)?;
+        self.sign(bytes)
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies a [`Signature`] over the deterministic CBOR encoding of the value.
+    pub fn verify_serde<V: Serialize + ?Sized>(
+        &self,
+        signature: &Signature<T>,
+        value: &V,
+    ) -> LamportResult<()> {
+        let bytes = to_canonical_cbor(value)?;
+        self.verify(signature, bytes)
+    }
+}
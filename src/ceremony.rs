@@ -0,0 +1,357 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::{LamportError, LamportResult, SEED_BYTES};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+/// The number of bytes in a ceremony identifier.
+pub const CEREMONY_ID_BYTES: usize = 32;
+
+/// The number of bytes each participant contributes to a [`SeedCeremony`].
+pub const ENTROPY_BYTES: usize = 32;
+
+const COMMITMENT_DOMAIN: &[u8] = b"lamport_signature_plus ceremony commitment v1";
+const SEED_DOMAIN: &[u8] = b"lamport_signature_plus ceremony seed v1";
+
+/// A participant's binding commitment to its entropy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EntropyCommitment {
+    /// The ceremony this commitment belongs to.
+    pub ceremony_id: [u8; CEREMONY_ID_BYTES],
+    /// The identifier of the committing participant.
+    pub participant: u8,
+    /// Hash of the ceremony, participant and entropy.
+    pub commitment: [u8; 32],
+}
+
+/// A participant's revealed entropy.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct EntropyReveal {
+    /// The ceremony this reveal belongs to.
+    pub ceremony_id: [u8; CEREMONY_ID_BYTES],
+    /// The identifier of the revealing participant.
+    pub participant: u8,
+    /// The committed entropy.
+    pub entropy: [u8; ENTROPY_BYTES],
+}
+
+/// Combines entropy from several participants into a master seed for
+/// [`SigningKey::from_seed`](crate::SigningKey::from_seed).
+///
+/// Every participant commits to its entropy before anyone reveals, so no
+/// participant can choose its contribution after seeing the others. As long
+/// as one participant is honest the seed is unpredictable to the rest. The
+/// resulting [`CeremonyTranscript`] lets anyone recompute the seed later.
+#[derive(Debug, Clone)]
+pub struct SeedCeremony {
+    ceremony_id: [u8; CEREMONY_ID_BYTES],
+    participants: usize,
+    commitments: BTreeMap<u8, [u8; 32]>,
+    reveals: BTreeMap<u8, [u8; ENTROPY_BYTES]>,
+}
+
+impl SeedCeremony {
+    /// Starts a ceremony between `participants` parties.
+    pub fn new(participants: usize, mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        if participants == 0 || participants > usize::from(u8::MAX) {
+            return Err(LamportError::InvalidSessionState);
+        }
+        let mut ceremony_id = [0u8; CEREMONY_ID_BYTES];
+        rng.fill_bytes(&mut ceremony_id);
+        Ok(Self {
+            ceremony_id,
+            participants,
+            commitments: BTreeMap::new(),
+            reveals: BTreeMap::new(),
+        })
+    }
+
+    /// The identifier to send to participants.
+    pub fn ceremony_id(&self) -> [u8; CEREMONY_ID_BYTES] {
+        self.ceremony_id
+    }
+
+    /// Returns true once every participant has committed and reveals are accepted.
+    pub fn is_committed(&self) -> bool {
+        self.commitments.len() == self.participants
+    }
+
+    /// Returns true once every participant has revealed.
+    pub fn is_ready(&self) -> bool {
+        self.reveals.len() == self.participants
+    }
+
+    /// Records a participant's commitment.
+    pub fn add_commitment(&mut self, commitment: &EntropyCommitment) -> LamportResult<()> {
+        if self.is_committed() || commitment.ceremony_id != self.ceremony_id {
+            return Err(LamportError::InvalidSessionState);
+        }
+        if commitment.participant == 0 || self.commitments.contains_key(&commitment.participant) {
+            return Err(LamportError::InvalidCommitment);
+        }
+        self.commitments
+            .insert(commitment.participant, commitment.commitment);
+        Ok(())
+    }
+
+    /// Records a participant's revealed entropy, which must match its commitment.
+    pub fn add_reveal(&mut self, reveal: &EntropyReveal) -> LamportResult<()> {
+        if !self.is_committed() || reveal.ceremony_id != self.ceremony_id {
+            return Err(LamportError::InvalidSessionState);
+        }
+        let expected = self
+            .commitments
+            .get(&reveal.participant)
+            .ok_or(LamportError::InvalidCommitment)?;
+        if commit(&self.ceremony_id, reveal.participant, &reveal.entropy) != *expected
+            || self.reveals.contains_key(&reveal.participant)
+        {
+            return Err(LamportError::InvalidCommitment);
+        }
+        self.reveals.insert(reveal.participant, reveal.entropy);
+        Ok(())
+    }
+
+    /// Returns the transcript of the completed ceremony.
+    pub fn finalize(&self) -> LamportResult<CeremonyTranscript> {
+        if !self.is_ready() {
+            return Err(LamportError::InvalidSessionState);
+        }
+        Ok(CeremonyTranscript {
+            ceremony_id: self.ceremony_id,
+            entries: self
+                .commitments
+                .iter()
+                .zip(self.reveals.values())
+                .map(|((participant, commitment), entropy)| (*participant, *commitment, *entropy))
+                .collect(),
+        })
+    }
+}
+
+/// A party contributing entropy to a [`SeedCeremony`].
+#[derive(Debug, Clone)]
+pub struct CeremonyParticipant {
+    identifier: u8,
+    entropy: [u8; ENTROPY_BYTES],
+}
+
+impl Zeroize for CeremonyParticipant {
+    fn zeroize(&mut self) {
+        self.entropy.zeroize();
+    }
+}
+
+impl CeremonyParticipant {
+    /// Creates a participant with fresh entropy. The identifier must not be zero.
+    pub fn new(identifier: u8, mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        if identifier == 0 {
+            return Err(LamportError::InvalidCommitment);
+        }
+        let mut entropy = [0u8; ENTROPY_BYTES];
+        rng.fill_bytes(&mut entropy);
+        Ok(Self {
+            identifier,
+            entropy,
+        })
+    }
+
+    /// The identifier of this participant.
+    pub fn identifier(&self) -> u8 {
+        self.identifier
+    }
+
+    /// Commits to this participant's entropy for the ceremony.
+    pub fn commit(&self, ceremony_id: &[u8; CEREMONY_ID_BYTES]) -> EntropyCommitment {
+        EntropyCommitment {
+            ceremony_id: *ceremony_id,
+            participant: self.identifier,
+            commitment: commit(ceremony_id, self.identifier, &self.entropy),
+        }
+    }
+
+    /// Reveals this participant's entropy for the ceremony.
+    pub fn reveal(&self, ceremony_id: &[u8; CEREMONY_ID_BYTES]) -> EntropyReveal {
+        EntropyReveal {
+            ceremony_id: *ceremony_id,
+            participant: self.identifier,
+            entropy: self.entropy,
+        }
+    }
+}
+
+/// The complete record of a [`SeedCeremony`].
+///
+/// Anyone holding the transcript can check every reveal against its
+/// commitment and recompute the seed, so the transcript is as secret as the
+/// seed itself and should only be given to auditors.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CeremonyTranscript {
+    pub(crate) ceremony_id: [u8; CEREMONY_ID_BYTES],
+    pub(crate) entries: Vec<(u8, [u8; 32], [u8; ENTROPY_BYTES])>,
+}
+
+serde_impl!(@limits @concrete CeremonyTranscript);
+
+impl Zeroize for CeremonyTranscript {
+    fn zeroize(&mut self) {
+        for (_, _, entropy) in self.entries.iter_mut() {
+            entropy.zeroize();
+        }
+    }
+}
+
+impl CeremonyTranscript {
+    /// The identifier of the ceremony.
+    pub fn ceremony_id(&self) -> [u8; CEREMONY_ID_BYTES] {
+        self.ceremony_id
+    }
+
+    /// The identifiers of the participants.
+    pub fn participants(&self) -> impl Iterator<Item = u8> + '_ {
+        self.entries.iter().map(|(participant, _, _)| *participant)
+    }
+
+    /// Checks every reveal against its commitment and returns the combined seed.
+    pub fn verify(&self) -> LamportResult<[u8; SEED_BYTES]> {
+        if self.entries.is_empty()
+            || self.entries.windows(2).any(|w| w[0].0 >= w[1].0)
+            || self.entries[0].0 == 0
+        {
+            return Err(LamportError::InvalidCommitment);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(SEED_DOMAIN);
+        hasher.update(self.ceremony_id);
+        for (participant, commitment, entropy) in &self.entries {
+            if commit(&self.ceremony_id, *participant, entropy) != *commitment {
+                return Err(LamportError::InvalidCommitment);
+            }
+            hasher.update([*participant]);
+            hasher.update(entropy);
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// Converts the transcript to canonical bytes.
+    ///
+    /// The format is the ceremony id, the participant count and, for each
+    /// participant in order, its identifier, commitment and entropy.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            CEREMONY_ID_BYTES + 1 + self.entries.len() * (1 + 32 + ENTROPY_BYTES),
+        );
+        bytes.extend_from_slice(&self.ceremony_id);
+        bytes.push(u8::try_from(self.entries.len()).unwrap_or(u8::MAX));
+        for (participant, commitment, entropy) in &self.entries {
+            bytes.push(*participant);
+            bytes.extend_from_slice(commitment);
+            bytes.extend_from_slice(entropy);
+        }
+        bytes
+    }
+
+    /// Constructs a [`CeremonyTranscript`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`CeremonyTranscript`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        const ENTRY: usize = 1 + 32 + ENTROPY_BYTES;
+        if input.len() < CEREMONY_ID_BYTES + 1
+            || input.len() != CEREMONY_ID_BYTES + 1 + usize::from(input[CEREMONY_ID_BYTES]) * ENTRY
+        {
+            return Err(LamportError::InvalidCommitment);
+        }
+        let mut ceremony_id = [0u8; CEREMONY_ID_BYTES];
+        ceremony_id.copy_from_slice(&input[..CEREMONY_ID_BYTES]);
+        let entries = input[CEREMONY_ID_BYTES + 1..]
+            .chunks_exact(ENTRY)
+            .map(|entry| {
+                let mut commitment = [0u8; 32];
+                let mut entropy = [0u8; ENTROPY_BYTES];
+                commitment.copy_from_slice(&entry[1..33]);
+                entropy.copy_from_slice(&entry[33..]);
+                (entry[0], commitment, entropy)
+            })
+            .collect();
+        Ok(Self {
+            ceremony_id,
+            entries,
+        })
+    }
+}
+
+fn commit(
+    ceremony_id: &[u8; CEREMONY_ID_BYTES],
+    participant: u8,
+    entropy: &[u8; ENTROPY_BYTES],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITMENT_DOMAIN);
+    hasher.update(ceremony_id);
+    hasher.update([participant]);
+    hasher.update(entropy);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LamportFixedDigest, SigningKey, VerifyingKey};
+    use rand::SeedableRng;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn seed_ceremony() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut ceremony = SeedCeremony::new(3, &mut rng).unwrap();
+        let id = ceremony.ceremony_id();
+        let participants = (1..=3)
+            .map(|i| CeremonyParticipant::new(i, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+
+        assert!(ceremony.add_reveal(&participants[0].reveal(&id)).is_err());
+        for p in &participants {
+            ceremony.add_commitment(&p.commit(&id)).unwrap();
+        }
+        assert!(ceremony.is_committed());
+        assert!(ceremony
+            .add_commitment(&participants[0].commit(&id))
+            .is_err());
+        let mut bad = participants[1].reveal(&id);
+        bad.entropy[0] ^= 1;
+        assert!(ceremony.add_reveal(&bad).is_err());
+        assert!(ceremony.finalize().is_err());
+        for p in participants.iter().rev() {
+            ceremony.add_reveal(&p.reveal(&id)).unwrap();
+        }
+
+        let transcript = ceremony.finalize().unwrap();
+        let seed = transcript.verify().unwrap();
+        let decoded = CeremonyTranscript::from_bytes(transcript.to_bytes()).unwrap();
+        assert_eq!(decoded, transcript);
+        assert_eq!(decoded.verify().unwrap(), seed);
+        assert_eq!(decoded.participants().collect::<Vec<_>>(), [1, 2, 3]);
+
+        let mut tampered = transcript.to_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(CeremonyTranscript::from_bytes(tampered)
+            .unwrap()
+            .verify()
+            .is_err());
+
+        let mut sk = SigningKey::<LamportFixedDigest<Sha256>>::from_seed(&seed).unwrap();
+        let pk = VerifyingKey::from(&sk);
+        let signature = sk.sign(b"ceremony").unwrap();
+        assert!(pk.verify(&signature, b"ceremony").is_ok());
+    }
+}
@@ -0,0 +1,489 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::signable::possession_message;
+use crate::{LamportDigest, LamportError, LamportResult, Signature, SigningKey, VerifyingKey};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+const LINK_DOMAIN: &[u8] = b"lamport_signature_plus key chain link v1";
+
+/// A sequence of one-time keys where each key certifies the next.
+///
+/// Signature `i` is made by key `i` over the next verifying key and the
+/// digest of the message, so every signature also hands off to key `i + 1`
+/// and the signing key is replaced. A relying party that trusts key `0`
+/// checks signature `N` by following the [`ChainLink`]s of signatures
+/// `0..=N` with [`VerifyingKey::verify_chain`].
+///
+/// Every [`ChainSignature`] carries all links so far, so its size and the
+/// cost of verifying it grow with the number of signatures. The chain is
+/// bounded by its [`Limits`]: it refuses to sign once its canonical bytes
+/// would exceed them, so every chain and signature it produces still decodes
+/// under the same limits. [`CertChain::max_links`] is the resulting number
+/// of signatures.
+///
+/// The chain is stateful. Its canonical bytes hold the current key and every
+/// link so far, and must be persisted after every signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertChain<T: LamportDigest> {
+    pub(crate) current: SigningKey<T>,
+    pub(crate) root: VerifyingKey<T>,
+    pub(crate) links: Vec<ChainLink<T>>,
+    pub(crate) limits: Limits,
+}
+
+serde_impl!(@limits CertChain);
+vec_impl!(CertChain);
+
+impl<T: LamportDigest> Zeroize for CertChain<T> {
+    fn zeroize(&mut self) {
+        self.current.zeroize();
+    }
+}
+
+/// One signature of a [`CertChain`], certifying the next verifying key.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ChainLink<T: LamportDigest> {
+    pub(crate) next: VerifyingKey<T>,
+    pub(crate) message_digest: Vec<u8>,
+    pub(crate) signature: Signature<T>,
+}
+
+serde_impl!(ChainLink);
+vec_impl!(ChainLink);
+
+impl<T: LamportDigest> Clone for ChainLink<T> {
+    fn clone(&self) -> Self {
+        Self {
+            next: VerifyingKey {
+                zero_values: self.next.zero_values.clone(),
+                one_values: self.next.one_values.clone(),
+                fingerprint: self.next.fingerprint,
+                algorithm: PhantomData,
+            },
+            message_digest: self.message_digest.clone(),
+            signature: Signature {
+                data: self.signature.data.clone(),
+                algorithm: PhantomData,
+            },
+        }
+    }
+}
+
+/// A signature made by a [`CertChain`], with the links back to key `0`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChainSignature<T: LamportDigest> {
+    pub(crate) links: Vec<ChainLink<T>>,
+}
+
+serde_impl!(@limits ChainSignature);
+vec_impl!(ChainSignature);
+
+impl<T: LamportDigest> CertChain<T> {
+    /// Creates a chain starting from a random key `0`.
+    pub fn random(rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        SigningKey::random(rng).map(Self::new)
+    }
+
+    /// Creates a chain starting from an unused key `0`, bounded by the default [`Limits`].
+    pub fn new(signing_key: SigningKey<T>) -> Self {
+        Self::with_limits(signing_key, Limits::DEFAULT)
+    }
+
+    /// Creates a chain starting from an unused key `0`, bounded by `limits`.
+    pub fn with_limits(signing_key: SigningKey<T>, limits: Limits) -> Self {
+        Self {
+            root: VerifyingKey::from(&signing_key),
+            current: signing_key,
+            links: Vec::new(),
+            limits,
+        }
+    }
+
+    /// The verifying key of key `0`, which relying parties must trust.
+    pub fn verifying_key(&self) -> &VerifyingKey<T> {
+        &self.root
+    }
+
+    /// The index of the key that signs next.
+    pub fn index(&self) -> usize {
+        self.links.len()
+    }
+
+    /// The links of every signature made so far.
+    pub fn links(&self) -> &[ChainLink<T>] {
+        &self.links
+    }
+
+    /// The number of signatures the chain makes before its canonical bytes
+    /// would exceed its [`Limits`].
+    pub fn max_links(&self) -> usize {
+        let (signing_key_length, key_length, link_length) = lengths::<T>();
+        let fixed = signing_key_length + key_length + 4;
+        self.limits
+            .max_input_bytes()
+            .saturating_sub(fixed)
+            .checked_div(link_length)
+            .unwrap_or(0)
+    }
+
+    /// Signs the data with the current key, certifying a new random key to replace it.
+    ///
+    /// Returns [`LamportError::KeyPoolExhausted`] without signing once the
+    /// chain has made [`CertChain::max_links`] signatures.
+    pub fn sign<B: AsRef<[u8]>>(
+        &mut self,
+        data: B,
+        rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<ChainSignature<T>> {
+        if self.links.len() >= self.max_links() {
+            return Err(LamportError::KeyPoolExhausted);
+        }
+        let next_key = SigningKey::random(rng)?;
+        let next = VerifyingKey::from(&next_key);
+        let message_digest = T::digest(data.as_ref());
+        let signature = self
+            .current
+            .sign_vectored(&link_parts(&next.to_bytes(), &message_digest))?;
+        self.current.zeroize();
+        self.current = next_key;
+        self.links.push(ChainLink {
+            next,
+            message_digest,
+            signature,
+        });
+        Ok(ChainSignature {
+            links: self.links.clone(),
+        })
+    }
+
+    /// Proves possession of the chain with a signature over the [`KeyId`](crate::KeyId) of
+    /// key `0` and `context`.
+    ///
+    /// As with [`CertChain::sign`], the current key certifies its replacement,
+    /// so the chain keeps signing after a registry accepts it. Relying parties
+    /// check the proof with [`VerifyingKey::check_chain_possession`].
+    pub fn prove_possession(
+        &mut self,
+        context: &[u8],
+        rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<ChainSignature<T>> {
+        let key_id = self.root.fingerprint();
+        self.sign(possession_message(&key_id, &key_id, context), rng)
+    }
+
+    /// Converts the [`CertChain`] to canonical bytes.
+    ///
+    /// The format is the current signing key, the verifying key of key `0`,
+    /// and the links as in [`ChainSignature::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.current.to_bytes();
+        bytes.extend_from_slice(&self.root.to_bytes());
+        bytes.extend_from_slice(&links_to_bytes(&self.links));
+        bytes
+    }
+
+    /// Constructs a [`CertChain`] from canonical bytes.
+    ///
+    /// The links are checked, so the current key must be the one certified last.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`CertChain`] from canonical bytes no larger than `limits`.
+    ///
+    /// The restored chain is bounded by the same limits.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let (signing_key_length, key_length, _) = lengths::<T>();
+        if input.len() < signing_key_length + key_length {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (current, rest) = input.split_at(signing_key_length);
+        let (root, links) = rest.split_at(key_length);
+        let current = SigningKey::from_bytes(current)?;
+        let root = VerifyingKey::from_bytes(root)?;
+        let links = links_from_bytes(links).map_err(|_| LamportError::InvalidPrivateKeyBytes)?;
+        let last = links.last().map_or(&root, |link| &link.next);
+        if current.used || *last != VerifyingKey::from(&current) {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let chain = Self {
+            current,
+            root,
+            links,
+            limits: *limits,
+        };
+        if !chain.links.is_empty() {
+            chain.root.verify_links(&chain.links)?;
+        }
+        Ok(chain)
+    }
+}
+
+impl<T: LamportDigest> ChainLink<T> {
+    /// The verifying key certified by this link.
+    pub fn next(&self) -> &VerifyingKey<T> {
+        &self.next
+    }
+
+    /// The digest of the message signed with this link.
+    pub fn message_digest(&self) -> &[u8] {
+        &self.message_digest
+    }
+
+    /// The one-time signature over the next key and message digest.
+    pub fn signature(&self) -> &Signature<T> {
+        &self.signature
+    }
+
+    /// Converts the [`ChainLink`] to canonical bytes, the next verifying key,
+    /// the message digest and the signature.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.next.to_bytes();
+        bytes.extend_from_slice(&self.message_digest);
+        bytes.extend_from_slice(&self.signature.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`ChainLink`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let (_, key_length, link_length) = lengths::<T>();
+        if input.len() != link_length {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (next, rest) = input.split_at(key_length);
+        let (message_digest, signature) = rest.split_at(T::digest_size_in_bits() / 8);
+        Ok(Self {
+            next: VerifyingKey::from_bytes(next)?,
+            message_digest: message_digest.to_vec(),
+            signature: Signature::from_bytes(signature)?,
+        })
+    }
+}
+
+impl<T: LamportDigest> ChainSignature<T> {
+    /// The index of the key that made the signature.
+    pub fn index(&self) -> usize {
+        self.links.len().saturating_sub(1)
+    }
+
+    /// The links from key `0` to the signing key, the last of which signs the message.
+    pub fn links(&self) -> &[ChainLink<T>] {
+        &self.links
+    }
+
+    /// Converts the [`ChainSignature`] to canonical bytes.
+    ///
+    /// The format is the number of links as a big endian `u32` followed by
+    /// each [`ChainLink::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        links_to_bytes(&self.links)
+    }
+
+    /// Constructs a [`ChainSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`ChainSignature`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let links = links_from_bytes(input)?;
+        if links.is_empty() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self { links })
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies a [`ChainSignature`] when this is key `0` of the chain.
+    ///
+    /// Every link must be signed by the key certified by the link before
+    /// it, and the last link must be over the digest of the data.
+    pub fn verify_chain<B: AsRef<[u8]>>(
+        &self,
+        signature: &ChainSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let last = signature
+            .links
+            .last()
+            .ok_or(LamportError::InvalidSignatureBytes)?;
+        if last.message_digest != T::digest(data.as_ref()) {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        self.verify_links(&signature.links)
+    }
+
+    /// Checks a proof of possession created with [`CertChain::prove_possession`]
+    /// when this is key `0` of the chain.
+    pub fn check_chain_possession(
+        &self,
+        proof: &ChainSignature<T>,
+        context: &[u8],
+    ) -> LamportResult<()> {
+        let key_id = self.fingerprint();
+        self.verify_chain(proof, possession_message(&key_id, &key_id, context))
+    }
+
+    fn verify_links(&self, links: &[ChainLink<T>]) -> LamportResult<()> {
+        let mut key = self;
+        for link in links {
+            key.verify_vectored(
+                &link.signature,
+                &link_parts(&link.next.to_bytes(), &link.message_digest),
+            )?;
+            key = &link.next;
+        }
+        Ok(())
+    }
+}
+
+fn link_parts<'a>(next: &'a [u8], message_digest: &'a [u8]) -> [&'a [u8]; 3] {
+    [LINK_DOMAIN, next, message_digest]
+}
+
+/// The lengths of a signing key, a verifying key and a link.
+fn lengths<T: LamportDigest>() -> (usize, usize, usize) {
+    let bits = T::digest_size_in_bits();
+    let signature_length = bits * (bits / 8);
+    let key_length = signature_length * 2;
+    (
+        key_length + 1,
+        key_length,
+        key_length + bits / 8 + signature_length,
+    )
+}
+
+fn links_to_bytes<T: LamportDigest>(links: &[ChainLink<T>]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 + links.len() * lengths::<T>().2);
+    bytes.extend_from_slice(
+        &u32::try_from(links.len())
+            .expect("fewer than 2^32 links")
+            .to_be_bytes(),
+    );
+    for link in links {
+        bytes.extend_from_slice(&link.to_bytes());
+    }
+    bytes
+}
+
+fn links_from_bytes<T: LamportDigest>(input: &[u8]) -> LamportResult<Vec<ChainLink<T>>> {
+    let link_length = lengths::<T>().2;
+    let count = <[u8; 4]>::try_from(input.get(..4).ok_or(LamportError::InvalidSignatureBytes)?)
+        .map(u32::from_be_bytes)
+        .map_err(|_| LamportError::InvalidSignatureBytes)?;
+    let rest = &input[4..];
+    if link_length == 0
+        || rest.len() / link_length != count as usize
+        || !rest.len().is_multiple_of(link_length)
+    {
+        return Err(LamportError::InvalidSignatureBytes);
+    }
+    rest.chunks_exact(link_length)
+        .map(ChainLink::from_bytes)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn cert_chain() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut chain = CertChain::<D>::random(&mut rng).unwrap();
+        let root = chain.verifying_key().clone();
+        let first = chain.sign(b"first", &mut rng).unwrap();
+        let second = chain.sign(b"second", &mut rng).unwrap();
+        let third = chain.sign(b"third", &mut rng).unwrap();
+        assert_eq!(chain.index(), 3);
+        assert_eq!(first.index(), 0);
+        assert_eq!(third.index(), 2);
+        assert!(root.verify_chain(&first, b"first").is_ok());
+        assert!(root.verify_chain(&second, b"second").is_ok());
+        assert!(root.verify_chain(&third, b"third").is_ok());
+        assert!(root.verify_chain(&third, b"second").is_err());
+        let (_, other) = generate_keys::<D, _>(&mut rng).unwrap();
+        assert!(other.verify_chain(&third, b"third").is_err());
+
+        // A link cannot be swapped for one certifying another key
+        let mut forged = third.clone();
+        forged.links[1].next = other;
+        assert!(root.verify_chain(&forged, b"third").is_err());
+
+        let decoded = ChainSignature::<D>::from_bytes(third.to_bytes()).unwrap();
+        assert_eq!(decoded.to_bytes(), third.to_bytes());
+        assert!(root.verify_chain(&decoded, b"third").is_ok());
+        assert!(ChainSignature::<D>::from_bytes(&third.to_bytes()[1..]).is_err());
+        assert!(ChainSignature::<D>::from_bytes([0u8; 4]).is_err());
+
+        let mut restored = CertChain::<D>::from_bytes(chain.to_bytes()).unwrap();
+        assert_eq!(restored.index(), 3);
+        let fourth = restored.sign(b"fourth", &mut rng).unwrap();
+        assert!(root.verify_chain(&fourth, b"fourth").is_ok());
+        // The stored state must be the one certified by the last link
+        let mut stale = chain.to_bytes();
+        let signing_key_length = 1 + 256 * 32 * 2;
+        stale[..signing_key_length].copy_from_slice(&restored.current.to_bytes());
+        assert!(CertChain::<D>::from_bytes(stale).is_err());
+    }
+
+    #[test]
+    fn chain_length_is_bounded() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (signing_key_length, key_length, link_length) = lengths::<D>();
+        let limits = Limits::new(signing_key_length + key_length + 4 + 2 * link_length);
+        let mut chain = CertChain::with_limits(SigningKey::<D>::random(&mut rng).unwrap(), limits);
+        assert_eq!(chain.max_links(), 2);
+        assert_eq!(CertChain::new(chain.current.clone()).max_links(), 2725);
+        chain.sign(b"first", &mut rng).unwrap();
+        let second = chain.sign(b"second", &mut rng).unwrap();
+        let current = chain.current.to_bytes();
+        assert_eq!(
+            chain.sign(b"third", &mut rng).unwrap_err(),
+            LamportError::KeyPoolExhausted
+        );
+        assert_eq!(chain.current.to_bytes(), current);
+
+        // Everything the chain produced decodes under its limits
+        assert!(ChainSignature::<D>::from_bytes_with_limits(&second.to_bytes(), &limits).is_ok());
+        let mut restored =
+            CertChain::<D>::from_bytes_with_limits(&chain.to_bytes(), &limits).unwrap();
+        assert!(restored.sign(b"third", &mut rng).is_err());
+        let mut restored = CertChain::<D>::from_bytes(chain.to_bytes()).unwrap();
+        assert!(restored.sign(b"third", &mut rng).is_ok());
+    }
+
+    #[test]
+    fn chain_possession() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut chain = CertChain::<D>::random(&mut rng).unwrap();
+        let root = chain.verifying_key().clone();
+        let proof = chain.prove_possession(b"registry", &mut rng).unwrap();
+        assert!(root.check_chain_possession(&proof, b"registry").is_ok());
+        assert!(root
+            .check_chain_possession(&proof, b"other registry")
+            .is_err());
+        let (_, other) = generate_keys::<D, _>(&mut rng).unwrap();
+        assert!(other.check_chain_possession(&proof, b"registry").is_err());
+
+        // The chain keeps signing after proving possession
+        let signature = chain.sign(b"accepted", &mut rng).unwrap();
+        assert!(root.verify_chain(&signature, b"accepted").is_ok());
+    }
+}
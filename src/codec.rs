@@ -0,0 +1,323 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Constant-time text codecs for the human-readable serde formats.
+//!
+//! Table based codecs index memory with the secret bytes they convert, which
+//! can leak key material through cache timing. These only use arithmetic and
+//! masks, so the running time depends only on the input length.
+//!
+//! Hex is the text encoding of every value. Fields can opt into unpadded
+//! base64url with [`crate::base64url`], and each field only decodes the
+//! encoding it is written in. Padded standard base64 is used by the ASCII
+//! armor and DSSE envelopes.
+use zeroize::{Zeroize, Zeroizing};
+
+/// Encodes the bytes as lowercase hex.
+pub(crate) fn hex_encode(data: &[u8]) -> String {
+    let mut output = String::with_capacity(data.len() * 2);
+    for byte in data {
+        output.push(hex_digit(byte >> 4));
+        output.push(hex_digit(byte & 0x0f));
+    }
+    output
+}
+
+/// Decodes upper or lowercase hex.
+pub(crate) fn hex_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    let input = input.as_bytes();
+    if !input.len().is_multiple_of(2) {
+        return Err("hex string has an odd length");
+    }
+    let mut output = Vec::with_capacity(input.len() / 2);
+    let mut invalid = 0u16;
+    for pair in input.chunks_exact(2) {
+        let (high, high_valid) = hex_value(pair[0]);
+        let (low, low_valid) = hex_value(pair[1]);
+        invalid |= !(high_valid & low_valid) & 0xff;
+        output.push(low_byte((high << 4) | low));
+    }
+    if invalid == 0 {
+        Ok(output)
+    } else {
+        Err("invalid hex character")
+    }
+}
+
+/// The characters for the sextets 62 and 63 of a base64 alphabet.
+type Alphabet = [u8; 2];
+
+/// The URL and filename safe alphabet (RFC 4648 §5).
+const URL_SAFE: Alphabet = [b'-', b'_'];
+
+/// The standard alphabet (RFC 4648 §4).
+const STANDARD: Alphabet = [b'+', b'/'];
+
+/// Encodes the bytes as unpadded base64url (RFC 4648 §5).
+pub(crate) fn base64url_encode(data: &[u8]) -> String {
+    base64_encode_with(data, URL_SAFE)
+}
+
+/// Decodes unpadded base64url, rejecting non-zero trailing bits.
+pub(crate) fn base64url_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    base64_decode_with(input, &[URL_SAFE])
+}
+
+/// Encodes the bytes as padded standard base64 (RFC 4648 §4).
+pub(crate) fn base64_encode(data: &[u8]) -> String {
+    let mut output = base64_encode_with(data, STANDARD);
+    while !output.len().is_multiple_of(4) {
+        output.push('=');
+    }
+    output
+}
+
+/// Decodes standard or URL-safe base64, with or without padding.
+pub(crate) fn base64_decode(input: &str) -> Result<Vec<u8>, &'static str> {
+    let unpadded = if input.len().is_multiple_of(4) {
+        input
+            .strip_suffix("==")
+            .or_else(|| input.strip_suffix('='))
+            .unwrap_or(input)
+    } else {
+        input
+    };
+    base64_decode_with(unpadded, &[STANDARD, URL_SAFE])
+}
+
+fn base64_encode_with(data: &[u8], alphabet: Alphabet) -> String {
+    let mut output = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let mut block = [0u8; 3];
+        block[..chunk.len()].copy_from_slice(chunk);
+        let sextets = [
+            block[0] >> 2,
+            ((block[0] & 0x03) << 4) | (block[1] >> 4),
+            ((block[1] & 0x0f) << 2) | (block[2] >> 6),
+            block[2] & 0x3f,
+        ];
+        for sextet in &sextets[..chunk.len() + 1] {
+            output.push(base64_digit(*sextet, alphabet));
+        }
+        block.zeroize();
+    }
+    output
+}
+
+/// Decodes unpadded base64 in any of the alphabets, rejecting non-zero trailing bits.
+fn base64_decode_with(input: &str, alphabets: &[Alphabet]) -> Result<Vec<u8>, &'static str> {
+    let input = input.as_bytes();
+    if input.len() % 4 == 1 {
+        return Err("base64 string has an invalid length");
+    }
+    let mut output = Vec::with_capacity(input.len() / 4 * 3 + 2);
+    let mut invalid = 0u16;
+    for chunk in input.chunks(4) {
+        let mut block = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let (value, valid) = base64_value(*c, alphabets);
+            invalid |= !valid & 0xff;
+            block |= u32::from(value) << (18 - 6 * i);
+        }
+        let bytes = block.to_be_bytes();
+        let length = chunk.len() - 1;
+        output.extend_from_slice(&bytes[1..1 + length]);
+        // Bits past the last whole byte must be zero for a canonical encoding
+        invalid |= u16::from(bytes[1 + length..].iter().fold(0, |a, b| a | b));
+        block.zeroize();
+    }
+    if invalid == 0 {
+        Ok(output)
+    } else {
+        Err("invalid base64 string")
+    }
+}
+
+fn base64_digit(sextet: u8, alphabet: Alphabet) -> char {
+    let v = u16::from(sextet);
+    let mut c = v.wrapping_add(b'A'.into());
+    c = c.wrapping_add((25u16.wrapping_sub(v) >> 8) & 6);
+    c = c.wrapping_sub((51u16.wrapping_sub(v) >> 8) & 75);
+    let is_62 = in_range(v, 62, 62);
+    let is_63 = in_range(v, 63, 63);
+    c = (c & !(is_62 | is_63))
+        | (is_62 & u16::from(alphabet[0]))
+        | (is_63 & u16::from(alphabet[1]));
+    char::from(low_byte(c))
+}
+
+/// Returns the value of the base64 character and a mask that is `0xff` when it is valid.
+fn base64_value(c: u8, alphabets: &[Alphabet]) -> (u8, u16) {
+    let c = u16::from(c);
+    let upper = in_range(c, b'A', b'Z');
+    let lower = in_range(c, b'a', b'z');
+    let digit = in_range(c, b'0', b'9');
+    let (is_62, is_63) = alphabets.iter().fold((0, 0), |(is_62, is_63), a| {
+        (
+            is_62 | in_range(c, a[0], a[0]),
+            is_63 | in_range(c, a[1], a[1]),
+        )
+    });
+    let value = (upper & c.wrapping_sub(65))
+        | (lower & c.wrapping_sub(71))
+        | (digit & c.wrapping_add(4))
+        | (is_62 & 62)
+        | (is_63 & 63);
+    (low_byte(value), upper | lower | digit | is_62 | is_63)
+}
+
+fn hex_digit(nibble: u8) -> char {
+    let n = u16::from(nibble);
+    // Adds the gap between '9' and 'a' only when the nibble is above 9
+    let c = n + 0x30 + ((9u16.wrapping_sub(n) >> 8) & 0x27);
+    char::from(low_byte(c))
+}
+
+/// Returns the value of the hex character and a mask that is `0xff` when it is valid.
+fn hex_value(c: u8) -> (u16, u16) {
+    let c = u16::from(c);
+    let digit = in_range(c, b'0', b'9');
+    let lower = in_range(c, b'a', b'f');
+    let upper = in_range(c, b'A', b'F');
+    let value = (digit & c.wrapping_sub(0x30))
+        | (lower & c.wrapping_sub(0x57))
+        | (upper & c.wrapping_sub(0x37));
+    (value & 0x0f, digit | lower | upper)
+}
+
+/// Returns `0xff` if `low <= c <= high`, otherwise `0`.
+fn in_range(c: u16, low: u8, high: u8) -> u16 {
+    let below = u16::from(low).wrapping_sub(1).wrapping_sub(c);
+    let above = c.wrapping_sub(u16::from(high) + 1);
+    ((below & above) >> 8) & 0xff
+}
+
+fn low_byte(value: u16) -> u8 {
+    value.to_le_bytes()[0]
+}
+
+/// Encodes with the human-readable codec, hex.
+pub(crate) fn text_encode(data: &[u8]) -> String {
+    hex_encode(data)
+}
+
+/// The text encoding of a value in the human-readable serde formats.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum TextEncoding {
+    /// Hex, the default.
+    Hex,
+    /// Unpadded base64url, for fields annotated with [`crate::base64url`].
+    Base64Url,
+}
+
+impl TextEncoding {
+    /// The size of the text once decoded, computed from its length alone.
+    pub(crate) fn decoded_len(self, input: &str) -> usize {
+        match self {
+            Self::Hex => input.len() / 2,
+            Self::Base64Url => input.len() / 4 * 3 + (input.len() % 4).saturating_sub(1),
+        }
+    }
+
+    /// Decodes the text.
+    pub(crate) fn decode(self, input: &str) -> Result<Zeroizing<Vec<u8>>, &'static str> {
+        match self {
+            Self::Hex => hex_decode(input),
+            Self::Base64Url => base64url_decode(input),
+        }
+        .map(Zeroizing::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{base64url, generate_keys, LamportFixedDigest, Signature, VerifyingKey};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn constant_time_hex() {
+        let all = (0..=255u8).collect::<Vec<_>>();
+        let encoded = hex_encode(&all);
+        assert_eq!(encoded, hex::encode(&all));
+        assert_eq!(hex_decode(&encoded).unwrap(), all);
+        assert_eq!(hex_decode(&encoded.to_uppercase()).unwrap(), all);
+        assert!(hex_decode("abc").is_err());
+        for bad in ["0g", "g0", "/0", ":0", "@0", "G0", "`0", "0 "] {
+            assert!(hex_decode(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn constant_time_base64url() {
+        let vectors: [(&[u8], &str); 8] = [
+            (b"", ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (b"fooba", "Zm9vYmE"),
+            (b"foobar", "Zm9vYmFy"),
+            (&[0xfb, 0xff], "-_8"),
+        ];
+        for (data, text) in vectors {
+            assert_eq!(base64url_encode(data), text);
+            assert_eq!(base64url_decode(text).unwrap(), data);
+        }
+        let all = (0..=255u8).collect::<Vec<_>>();
+        assert_eq!(base64url_decode(&base64url_encode(&all)).unwrap(), all);
+        for bad in ["Z", "Zh", "Zm9v+", "Zm9v/A", "Zm=v", "Zm9vYmF"] {
+            assert!(base64url_decode(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn serde_text_codecs() {
+        type D = LamportFixedDigest<Sha256>;
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<D, _>(rng).unwrap();
+        let signature = sk.sign(b"codecs").unwrap();
+
+        // Hex is the default and base64url is opt-in per value
+        let hex = serde_json::to_string(&signature).unwrap();
+        assert_eq!(hex, format!("\"{}\"", hex::encode(signature.to_bytes())));
+        let mut base64 = Vec::new();
+        base64url::serialize(&signature, &mut serde_json::Serializer::new(&mut base64)).unwrap();
+        let base64 = String::from_utf8(base64).unwrap();
+        assert_eq!(
+            base64,
+            format!("\"{}\"", base64url_encode(&signature.to_bytes()))
+        );
+        assert!(base64.len() < hex.len());
+
+        // Each encoding only decodes the way it was written
+        let plain = serde_json::from_str::<Signature<D>>(&hex).unwrap();
+        assert_eq!(plain.to_bytes(), signature.to_bytes());
+        let opted: Signature<D> =
+            base64url::deserialize(&mut serde_json::Deserializer::from_str(&base64)).unwrap();
+        assert_eq!(opted.to_bytes(), signature.to_bytes());
+        assert!(serde_json::from_str::<Signature<D>>(&base64).is_err());
+        assert!(base64url::deserialize::<Signature<D>, _>(
+            &mut serde_json::Deserializer::from_str(&hex)
+        )
+        .is_err());
+        let key_text = base64url_encode(&pk.to_bytes());
+        assert!(serde_json::from_str::<VerifyingKey<D>>(&format!("\"{}\"", key_text)).is_err());
+        let key: VerifyingKey<D> = base64url::deserialize(&mut serde_json::Deserializer::from_str(
+            &format!("\"{}\"", key_text),
+        ))
+        .unwrap();
+        assert_eq!(key, pk);
+
+        // Text that is valid hex but not a signature is rejected
+        let short = serde_json::to_string(&hex::encode([0u8; 3])).unwrap();
+        assert!(serde_json::from_str::<Signature<D>>(&short).is_err());
+        assert!(serde_json::from_str::<Signature<D>>("\"not base64 or hex!\"").is_err());
+
+        // Only values with canonical bytes can be written as base64url
+        assert!(base64url::serialize(&1u8, &mut serde_json::Serializer::new(Vec::new())).is_err());
+    }
+}
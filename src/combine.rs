@@ -0,0 +1,163 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::gf256::Interpolator;
+use crate::hash::check_security;
+use crate::signature::SignatureShare;
+use crate::{
+    LamportDigest, LamportError, LamportResult, MultiVec, Signature, SigningKey, SigningKeyShare,
+};
+use std::collections::{btree_map::Entry, BTreeMap};
+use std::marker::PhantomData;
+
+/// Caches the Lagrange coefficients for each set of participants that
+/// shares are combined from.
+///
+/// Coordinators usually combine many signatures or keys from the same quorum,
+/// so the coefficients are computed on first use and reused afterwards.
+/// Entries are keyed by the identifiers in the order the shares are given.
+#[derive(Debug, Default)]
+pub struct CombineContext {
+    interpolators: BTreeMap<Vec<u8>, Interpolator>,
+}
+
+impl CombineContext {
+    /// Constructs an empty [`CombineContext`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of participant sets with cached coefficients.
+    pub fn len(&self) -> usize {
+        self.interpolators.len()
+    }
+
+    /// Returns true if no coefficients have been cached.
+    pub fn is_empty(&self) -> bool {
+        self.interpolators.is_empty()
+    }
+
+    /// Removes all cached coefficients.
+    pub fn clear(&mut self) {
+        self.interpolators.clear();
+    }
+
+    /// Combines multiple signature shares into a single signature.
+    pub fn combine_signature<T: LamportDigest>(
+        &mut self,
+        shares: &[SignatureShare<T>],
+    ) -> LamportResult<Signature<T>> {
+        if shares.is_empty() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        if shares.len() < shares[0].threshold as usize {
+            return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
+        }
+        if shares.iter().any(|s| s.data.axes != shares[0].data.axes) {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+
+        let interpolator = self.interpolator(shares.iter().map(|s| s.identifier))?;
+        let mut out = Signature {
+            data: MultiVec::fill(shares[0].data.axes, 0u8),
+            algorithm: PhantomData,
+        };
+        interpolator.combine_slices(
+            shares.iter().map(|s| s.data.data.as_slice()),
+            &mut out.data.data,
+        );
+        Ok(out)
+    }
+
+    /// Reconstructs the signing key from the secret shares created by [`SigningKey::split`].
+    pub fn combine_signing_key<T: LamportDigest>(
+        &mut self,
+        shares: &[SigningKeyShare<T>],
+    ) -> LamportResult<SigningKey<T>> {
+        check_security::<T>()?;
+        if shares.is_empty() {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        if shares.len() < shares[0].threshold as usize {
+            return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
+        }
+        if shares.iter().any(|s| {
+            s.zero_values.axes != shares[0].zero_values.axes
+                || s.one_values.axes != shares[0].one_values.axes
+        }) {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+
+        let interpolator = self.interpolator(shares.iter().map(|s| s.identifier))?;
+        let mut out = SigningKey {
+            zero_values: MultiVec::fill(shares[0].zero_values.axes, 0u8),
+            one_values: MultiVec::fill(shares[0].one_values.axes, 0u8),
+            used: shares.iter().any(|s| s.used),
+            algorithm: PhantomData,
+        };
+        interpolator.combine_slices(
+            shares.iter().map(|s| s.zero_values.data.as_slice()),
+            &mut out.zero_values.data,
+        );
+        interpolator.combine_slices(
+            shares.iter().map(|s| s.one_values.data.as_slice()),
+            &mut out.one_values.data,
+        );
+        Ok(out)
+    }
+
+    fn interpolator(
+        &mut self,
+        identifiers: impl Iterator<Item = u8>,
+    ) -> LamportResult<&Interpolator> {
+        match self.interpolators.entry(identifiers.collect()) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let interpolator = Interpolator::new(entry.key())?;
+                Ok(entry.insert(interpolator))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn combine_context() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let mut shares = sk.split(2, 3, &mut rng).unwrap();
+        let mut context = CombineContext::new();
+        assert!(context.is_empty());
+        for message in [b"first".as_slice(), b"second"] {
+            let mut shares = sk.split(2, 3, &mut rng).unwrap();
+            let signatures = shares
+                .iter_mut()
+                .map(|share| share.sign(message).unwrap())
+                .collect::<Vec<_>>();
+            let signature = context.combine_signature(&signatures[1..]).unwrap();
+            assert!(pk.verify(&signature, message).is_ok());
+        }
+        assert_eq!(context.len(), 1);
+
+        let restored_key = context.combine_signing_key(&shares[1..]).unwrap();
+        assert_eq!(restored_key.to_bytes(), sk.to_bytes());
+        assert_eq!(context.len(), 1);
+        let restored_key = context.combine_signing_key(&shares[..2]).unwrap();
+        assert_eq!(restored_key.to_bytes(), sk.to_bytes());
+        assert_eq!(context.len(), 2);
+
+        shares[1].identifier = shares[0].identifier;
+        assert!(context.combine_signing_key(&shares[..2]).is_err());
+        assert_eq!(context.len(), 2);
+        context.clear();
+        assert!(context.is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size};
+use crate::{
+    LamportDigest, LamportError, LamportResult, MultiVec, Signature, SigningKey, VerifyingKey,
+};
+use std::marker::PhantomData;
+
+/// A [`VerifyingKey`] compressed to a single digest.
+///
+/// The key is the digest of [`VerifyingKey::to_bytes`], every zero
+/// commitment followed by every one commitment. A [`CompactKeySignature`]
+/// carries the commitments that its revealed values do not hash to, so the
+/// verifier can rebuild the full key and compare its digest.
+///
+/// A SHA-256 key shrinks from 16KB to 32 bytes, and signatures double in size.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompactVerifyingKey<T: LamportDigest> {
+    pub(crate) digest: Vec<u8>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(CompactVerifyingKey);
+vec_impl!(CompactVerifyingKey);
+
+impl<T: LamportDigest> From<&VerifyingKey<T>> for CompactVerifyingKey<T> {
+    fn from(key: &VerifyingKey<T>) -> Self {
+        Self {
+            digest: T::digest_concat(&[key.zero_values.as_ref(), key.one_values.as_ref()]),
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> From<&SigningKey<T>> for CompactVerifyingKey<T> {
+    fn from(key: &SigningKey<T>) -> Self {
+        Self::from(&VerifyingKey::from(key))
+    }
+}
+
+impl<T: LamportDigest> CompactVerifyingKey<T> {
+    /// Converts the [`CompactVerifyingKey`] to canonical bytes, the key digest.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.digest.clone()
+    }
+
+    /// Constructs a [`CompactVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        if input.len() != T::digest_size_in_bits() / 8 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            digest: input.to_vec(),
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Verifies the [`CompactKeySignature`].
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &CompactKeySignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        let bits = digest.len() * 8;
+        let shape = [bits, digest.len()];
+        if signature.signature.data.axes != shape
+            || signature.complements.axes != shape
+            || signature.signature.data.data.len() != bits * digest.len()
+            || signature.complements.data.len() != bits * digest.len()
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let revealed = (0..bits)
+            .map(|offset| T::digest(&signature.signature.data[offset]))
+            .collect::<Vec<_>>();
+        let mut zero_values = Vec::with_capacity(bits);
+        let mut one_values = Vec::with_capacity(bits);
+        for (offset, hashed_value) in revealed.iter().enumerate() {
+            let complement = &signature.complements[offset];
+            if (digest[offset / 8] >> (offset % 8)) & 1 == 1 {
+                zero_values.push(complement);
+                one_values.push(hashed_value.as_slice());
+            } else {
+                zero_values.push(hashed_value.as_slice());
+                one_values.push(complement);
+            }
+        }
+        zero_values.append(&mut one_values);
+        if T::digest_concat(&zero_values) == self.digest {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+}
+
+/// A [`Signature`] with the commitments needed to verify it against a [`CompactVerifyingKey`].
+///
+/// For each digest bit, the complement is the commitment of the value that
+/// was not revealed.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompactKeySignature<T: LamportDigest> {
+    pub(crate) signature: Signature<T>,
+    pub(crate) complements: MultiVec<u8, 2>,
+}
+
+serde_impl!(CompactKeySignature);
+vec_impl!(CompactKeySignature);
+
+impl<T: LamportDigest> CompactKeySignature<T> {
+    /// The underlying [`Signature`], which verifies against the full [`VerifyingKey`].
+    pub fn signature(&self) -> &Signature<T> {
+        &self.signature
+    }
+
+    /// Converts the [`CompactKeySignature`] to canonical bytes, the
+    /// signature followed by the complements.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.signature.to_bytes();
+        bytes.extend_from_slice(&self.complements.data);
+        bytes
+    }
+
+    /// Constructs a [`CompactKeySignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let half = bits * (bits / 8);
+        if input.len() != half * 2 {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (signature, complements) = input.split_at(half);
+        Ok(Self {
+            signature: Signature::from_bytes(signature)?,
+            complements: MultiVec {
+                data: complements.to_vec(),
+                axes: [bits, bits / 8],
+            },
+        })
+    }
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs the data with a [`CompactKeySignature`] for a [`CompactVerifyingKey`].
+    ///
+    /// This uses the key just like [`SigningKey::sign`], so the key can only
+    /// sign once in either form.
+    pub fn sign_compact_key<B: AsRef<[u8]>>(
+        &mut self,
+        data: B,
+    ) -> LamportResult<CompactKeySignature<T>> {
+        let digest = T::digest(data.as_ref());
+        let signature = self.sign_digest(&digest)?;
+        let bits = digest.len() * 8;
+        let mut complements = Vec::with_capacity(bits * digest.len());
+        for offset in 0..bits {
+            let hidden = if (digest[offset / 8] >> (offset % 8)) & 1 == 1 {
+                &self.zero_values[offset]
+            } else {
+                &self.one_values[offset]
+            };
+            complements.extend_from_slice(&T::digest(hidden));
+        }
+        Ok(CompactKeySignature {
+            signature,
+            complements: MultiVec {
+                data: complements,
+                axes: [bits, digest.len()],
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn compact_key() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+        let compact = CompactVerifyingKey::from(&pk);
+        assert_eq!(compact.to_bytes().len(), 32);
+        assert_eq!(
+            CompactVerifyingKey::from(&sk).to_bytes(),
+            compact.to_bytes()
+        );
+        let signature = sk.sign_compact_key(b"compact key").unwrap();
+        assert!(sk.sign_compact_key(b"again").is_err());
+        assert!(compact.verify(&signature, b"compact key").is_ok());
+        assert!(compact.verify(&signature, b"other").is_err());
+        assert!(pk.verify(signature.signature(), b"compact key").is_ok());
+
+        let bytes = signature.to_bytes();
+        assert_eq!(bytes.len(), 2 * 256 * 32);
+        let decoded = CompactKeySignature::<D>::from_bytes(&bytes).unwrap();
+        assert!(compact.verify(&decoded, b"compact key").is_ok());
+        assert!(CompactKeySignature::<D>::from_bytes(&bytes[1..]).is_err());
+        let mut tampered = bytes;
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let tampered = CompactKeySignature::<D>::from_bytes(&tampered).unwrap();
+        assert!(compact.verify(&tampered, b"compact key").is_err());
+
+        let decoded = CompactVerifyingKey::<D>::from_bytes(compact.to_bytes()).unwrap();
+        assert!(decoded.verify(&signature, b"compact key").is_ok());
+        assert!(CompactVerifyingKey::<D>::from_bytes([0u8; 31]).is_err());
+        let (_, other) = generate_keys::<D, _>(&mut rng).unwrap();
+        assert!(CompactVerifyingKey::from(&other)
+            .verify(&signature, b"compact key")
+            .is_err());
+    }
+}
@@ -0,0 +1,199 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::check_digest;
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec, SigningKey, VerifyingKey};
+use std::marker::PhantomData;
+
+/// A [`Signature`](crate::Signature) that only reveals secret values for set bits.
+///
+/// The signed string is the digest followed by a checksum, the number of
+/// zero bits in the digest. A secret value is revealed for each set bit: the
+/// one value of a set digest bit, and the zero value of row `i` for set bit
+/// `i` of the checksum, which has `bit_length(bits)` bits. Clearing a digest
+/// bit raises the checksum, which would need a value that was not revealed,
+/// so the signature cannot be changed to sign another digest.
+///
+/// About half the bits are set, so the signature is about half the size of
+/// a [`Signature`](crate::Signature). It is made by the same one-time
+/// [`SigningKey`] and checked by the same [`VerifyingKey`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompactSignature<T: LamportDigest> {
+    pub(crate) data: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(CompactSignature);
+vec_impl!(CompactSignature);
+
+impl<T: LamportDigest> CompactSignature<T> {
+    /// The number of revealed secret values.
+    pub fn len(&self) -> usize {
+        self.data.axes[0]
+    }
+
+    /// Always false, a signature reveals at least one value.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Converts the [`CompactSignature`] to canonical bytes, the revealed values in order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data.data.clone()
+    }
+
+    /// Constructs a [`CompactSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let n = bits / 8;
+        let rows = input.len() / n.max(1);
+        if n == 0
+            || !input.len().is_multiple_of(n)
+            || rows == 0
+            || rows > bits + checksum_bits(bits)
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            data: MultiVec {
+                data: input.to_vec(),
+                axes: [rows, n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// The number of bits needed for a checksum of up to `bits`.
+fn checksum_bits(bits: usize) -> usize {
+    (usize::BITS - bits.leading_zeros()) as usize
+}
+
+/// The commitments revealed for each set bit of the digest and its checksum.
+///
+/// Returns whether each is a one commitment, and its row.
+fn revealed_rows(digest: &[u8]) -> Vec<(bool, usize)> {
+    let bits = digest.len() * 8;
+    let mut rows = Vec::with_capacity(bits);
+    let mut zeros = 0usize;
+    for (i, byte) in digest.iter().enumerate() {
+        for j in 0..8 {
+            if (byte >> j) & 1 == 1 {
+                rows.push((true, i * 8 + j));
+            } else {
+                zeros += 1;
+            }
+        }
+    }
+    for i in 0..checksum_bits(bits) {
+        if (zeros >> i) & 1 == 1 {
+            rows.push((false, i));
+        }
+    }
+    rows
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs the data with a [`CompactSignature`].
+    ///
+    /// This uses the key just like [`SigningKey::sign`], so the key can only
+    /// sign once in either form.
+    pub fn sign_compact<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<CompactSignature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        self.used = true;
+        let n = digest.len();
+        let rows = revealed_rows(&digest);
+        let mut values = Vec::with_capacity(rows.len() * n);
+        for (one, row) in &rows {
+            let source = if *one {
+                &self.one_values
+            } else {
+                &self.zero_values
+            };
+            values.extend_from_slice(&source[*row]);
+        }
+        Ok(CompactSignature {
+            data: MultiVec {
+                data: values,
+                axes: [rows.len(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies a [`CompactSignature`].
+    pub fn verify_compact<B: AsRef<[u8]>>(
+        &self,
+        signature: &CompactSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        let rows = revealed_rows(&digest);
+        if signature.data.axes != [rows.len(), digest.len()]
+            || signature.data.data.len() != rows.len() * digest.len()
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let valid = rows.iter().enumerate().all(|(i, (one, row))| {
+            let commitment = if *one {
+                self.one_commitment(*row)
+            } else {
+                self.zero_commitment(*row)
+            };
+            commitment == Some(T::digest(&signature.data[i]).as_slice())
+        });
+        if valid {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn compact_signature() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+        let signature = sk.sign_compact(b"compact").unwrap();
+        assert!(sk.used());
+        assert_eq!(
+            sk.sign(b"again").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert!(pk.verify_compact(&signature, b"compact").is_ok());
+        assert!(pk.verify_compact(&signature, b"other").is_err());
+        // About half of the 256 digest bits and 9 checksum bits are set
+        assert!(signature.len() > 96 && signature.len() < 170);
+        assert_eq!(signature.to_bytes().len(), signature.len() * 32);
+
+        let bytes = signature.to_bytes();
+        let decoded = CompactSignature::<D>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), signature.to_bytes());
+        let truncated = CompactSignature::<D>::from_bytes(&bytes[32..]).unwrap();
+        assert!(pk.verify_compact(&truncated, b"compact").is_err());
+        assert!(CompactSignature::<D>::from_bytes(&bytes[1..]).is_err());
+        assert!(CompactSignature::<D>::from_bytes([0u8; 32 * 266]).is_err());
+        let mut tampered = bytes;
+        tampered[0] ^= 1;
+        let tampered = CompactSignature::<D>::from_bytes(&tampered).unwrap();
+        assert!(pk.verify_compact(&tampered, b"compact").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! [DSSE](https://github.com/secure-systems-lab/dsse) envelopes, the
+//! signature wrapper used by in-toto and SLSA attestations.
+//!
+//! Signatures are made over the pre-authentication encoding of the payload
+//! type and payload, so a signature for one type cannot be replayed as
+//! another. The `keyid` of each signature is the hex [`KeyId`] of the signer.
+use crate::codec::{base64_decode, base64_encode, hex_decode};
+use crate::limits::Limits;
+use crate::{
+    KeyId, KeyRegistry, LamportDigest, LamportError, LamportResult, Signature, SignerPolicy,
+    SigningKey, VerifyingKey,
+};
+use serde_json::{json, Map, Value};
+
+/// Computes the DSSE v1 pre-authentication encoding.
+///
+/// `"DSSEv1" SP LEN(type) SP type SP LEN(body) SP body`, with the lengths
+/// written as ASCII decimal.
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(payload_type.len() + payload.len() + 32);
+    output.extend_from_slice(b"DSSEv1 ");
+    output.extend_from_slice(payload_type.len().to_string().as_bytes());
+    output.push(b' ');
+    output.extend_from_slice(payload_type.as_bytes());
+    output.push(b' ');
+    output.extend_from_slice(payload.len().to_string().as_bytes());
+    output.push(b' ');
+    output.extend_from_slice(payload);
+    output
+}
+
+/// A signature entry in a [`DsseEnvelope`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DsseSignature {
+    keyid: String,
+    sig: Vec<u8>,
+}
+
+impl DsseSignature {
+    /// The key hint, empty if the signer did not provide one.
+    pub fn keyid(&self) -> &str {
+        &self.keyid
+    }
+
+    /// The raw signature bytes.
+    pub fn sig(&self) -> &[u8] {
+        &self.sig
+    }
+
+    fn key_id(&self) -> Option<KeyId> {
+        hex_decode(&self.keyid)
+            .ok()
+            .and_then(|bytes| KeyId::from_bytes(bytes).ok())
+    }
+}
+
+/// A DSSE envelope holding one payload and any number of signatures.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DsseEnvelope {
+    payload_type: String,
+    payload: Vec<u8>,
+    signatures: Vec<DsseSignature>,
+}
+
+impl DsseEnvelope {
+    /// Constructs an unsigned envelope, e.g. with the payload type
+    /// `application/vnd.in-toto+json` for in-toto statements.
+    pub fn new<S: Into<String>, B: Into<Vec<u8>>>(payload_type: S, payload: B) -> Self {
+        Self {
+            payload_type: payload_type.into(),
+            payload: payload.into(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// The payload type.
+    pub fn payload_type(&self) -> &str {
+        &self.payload_type
+    }
+
+    /// The payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The signatures in the order they were added.
+    pub fn signatures(&self) -> &[DsseSignature] {
+        &self.signatures
+    }
+
+    /// Signs the envelope with the one-time key and adds the signature.
+    pub fn sign<T: LamportDigest>(
+        &mut self,
+        signing_key: &mut SigningKey<T>,
+    ) -> LamportResult<KeyId> {
+        let signature = signing_key.sign_attached(pae(&self.payload_type, &self.payload))?;
+        let key_id = signature.key_id();
+        self.signatures.push(DsseSignature {
+            keyid: key_id.to_string(),
+            sig: signature.signature().to_bytes(),
+        });
+        Ok(key_id)
+    }
+
+    /// Verifies that the [`VerifyingKey`] signed the envelope.
+    ///
+    /// Signatures without a `keyid` are tried as well.
+    pub fn verify<T: LamportDigest>(&self, verifying_key: &VerifyingKey<T>) -> LamportResult<()> {
+        let message = pae(&self.payload_type, &self.payload);
+        let key_id = verifying_key.fingerprint();
+        let verified = self
+            .signatures
+            .iter()
+            .filter(|s| s.keyid.is_empty() || s.key_id() == Some(key_id))
+            .filter_map(|s| Signature::<T>::from_bytes(&s.sig).ok())
+            .any(|signature| verifying_key.verify(&signature, &message).is_ok());
+        if verified {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Verifies the signatures with the keys of the registry and checks the policy.
+    ///
+    /// Signatures are matched to keys by their `keyid`, and each key counts once.
+    pub fn verify_policy<T: LamportDigest>(
+        &self,
+        registry: &KeyRegistry<T>,
+        policy: SignerPolicy,
+    ) -> LamportResult<()> {
+        let message = pae(&self.payload_type, &self.payload);
+        let mut signers = Vec::with_capacity(self.signatures.len());
+        for s in &self.signatures {
+            let Some(key_id) = s.key_id() else {
+                continue;
+            };
+            let Ok(signature) = Signature::<T>::from_bytes(&s.sig) else {
+                continue;
+            };
+            if !signers.contains(&key_id) && registry.verify(&signature, &message, &key_id).is_ok()
+            {
+                signers.push(key_id);
+            }
+        }
+        policy.check(signers.len(), registry)
+    }
+
+    /// Serializes the envelope as DSSE JSON with standard base64 fields.
+    pub fn to_json(&self) -> String {
+        let signatures = self
+            .signatures
+            .iter()
+            .map(|s| json!({ "keyid": s.keyid, "sig": base64_encode(&s.sig) }))
+            .collect::<Vec<_>>();
+        json!({
+            "payload": base64_encode(&self.payload),
+            "payloadType": self.payload_type,
+            "signatures": signatures,
+        })
+        .to_string()
+    }
+
+    /// Parses DSSE JSON, accepting standard or URL-safe base64.
+    pub fn from_json(input: &str) -> LamportResult<Self> {
+        Self::from_json_with_limits(input, &Limits::DEFAULT)
+    }
+
+    /// Parses DSSE JSON no larger than `limits`.
+    pub fn from_json_with_limits(input: &str, limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let value =
+            serde_json::from_str::<Value>(input).map_err(|_| LamportError::InvalidEnvelope)?;
+        let object = value.as_object().ok_or(LamportError::InvalidEnvelope)?;
+        let payload = base64_decode(string_field(object, "payload")?)
+            .map_err(|_| LamportError::InvalidEnvelope)?;
+        let payload_type = string_field(object, "payloadType")?.to_string();
+        let signatures = object
+            .get("signatures")
+            .and_then(Value::as_array)
+            .ok_or(LamportError::InvalidEnvelope)?
+            .iter()
+            .map(|s| {
+                let s = s.as_object().ok_or(LamportError::InvalidEnvelope)?;
+                let keyid = match s.get("keyid") {
+                    None => String::new(),
+                    Some(keyid) => keyid
+                        .as_str()
+                        .ok_or(LamportError::InvalidEnvelope)?
+                        .to_string(),
+                };
+                let sig = base64_decode(string_field(s, "sig")?)
+                    .map_err(|_| LamportError::InvalidEnvelope)?;
+                Ok(DsseSignature { keyid, sig })
+            })
+            .collect::<LamportResult<Vec<_>>>()?;
+        Ok(Self {
+            payload_type,
+            payload,
+            signatures,
+        })
+    }
+}
+
+fn string_field<'a>(object: &'a Map<String, Value>, name: &str) -> LamportResult<&'a str> {
+    object
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or(LamportError::InvalidEnvelope)
+}
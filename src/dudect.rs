@@ -0,0 +1,151 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! A [dudect](https://eprint.iacr.org/2016/1123) style statistical timing test.
+//!
+//! Inputs are drawn from two classes, usually one fixed secret and freshly
+//! random secrets, and the operation is timed on both in random order.
+//! Welch's t-test then compares the two timing distributions. A constant
+//! time operation keeps `|t|` small however many samples are taken, while
+//! a leak makes it grow with the sample count.
+//!
+//! Only available with the `dudect` feature. The results depend on the
+//! target, compiler flags and machine load, so run the ignored
+//! `tests/dudect.rs` tests in release mode on an otherwise idle machine:
+//!
+//! ```text
+//! cargo test --release --features dudect --test dudect -- --ignored
+//! ```
+use rand::RngCore;
+use std::time::Instant;
+
+/// The `|t|` above which the classes are taken to have different timings.
+pub const T_THRESHOLD: f64 = 4.5;
+
+/// The number of inputs prepared ahead of timing them.
+pub const BATCH_SIZE: usize = 64;
+
+/// The percentile of all measurements above which samples are dropped as
+/// interrupts or scheduling noise.
+const CROP_PERCENT: usize = 95;
+
+/// The input class of a measurement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Class {
+    /// The same secret for every measurement.
+    Fixed,
+    /// A fresh random secret for every measurement.
+    Random,
+}
+
+/// The outcome of a [`t_test`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimingReport {
+    /// Welch's t statistic between the two classes.
+    pub t: f64,
+    /// The number of measurements kept after cropping outliers.
+    pub samples: usize,
+}
+
+impl TimingReport {
+    /// Returns true if `|t|` exceeds [`T_THRESHOLD`].
+    pub fn is_leaky(&self) -> bool {
+        self.t.abs() > T_THRESHOLD
+    }
+}
+
+/// Times `operation` on `samples` inputs split randomly between the classes.
+///
+/// `rng` only picks the class of each measurement. Inputs are built by
+/// `prepare` in batches of [`BATCH_SIZE`] before any of them are timed, so
+/// neither the cost of preparing an input nor its effect on the caches
+/// leaks into the measurement of `operation`.
+pub fn t_test<I, P, O>(
+    samples: usize,
+    mut rng: impl RngCore,
+    mut prepare: P,
+    mut operation: O,
+) -> TimingReport
+where
+    P: FnMut(Class) -> I,
+    O: FnMut(I),
+{
+    let mut measurements = Vec::with_capacity(samples);
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    while measurements.len() < samples {
+        let size = BATCH_SIZE.min(samples - measurements.len());
+        batch.extend((0..size).map(|_| {
+            let class = if rng.next_u32() & 1 == 0 {
+                Class::Fixed
+            } else {
+                Class::Random
+            };
+            (class, prepare(class))
+        }));
+        for (class, input) in batch.drain(..) {
+            let start = Instant::now();
+            operation(input);
+            measurements.push((class, start.elapsed().as_secs_f64()));
+        }
+    }
+
+    let mut sorted = measurements.iter().map(|(_, t)| *t).collect::<Vec<_>>();
+    sorted.sort_by(f64::total_cmp);
+    let cutoff = sorted
+        .get(sorted.len() * CROP_PERCENT / 100)
+        .copied()
+        .unwrap_or(f64::INFINITY);
+
+    let mut fixed = Welford::default();
+    let mut random = Welford::default();
+    let mut kept = 0;
+    for (class, t) in measurements.into_iter().filter(|(_, t)| *t <= cutoff) {
+        match class {
+            Class::Fixed => fixed.push(t),
+            Class::Random => random.push(t),
+        }
+        kept += 1;
+    }
+    TimingReport {
+        t: fixed.welch_t(&random),
+        samples: kept,
+    }
+}
+
+/// Running mean and variance.
+#[derive(Debug, Default)]
+struct Welford {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Welford {
+    fn push(&mut self, x: f64) {
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        if self.n < 2.0 {
+            0.0
+        } else {
+            self.m2 / (self.n - 1.0)
+        }
+    }
+
+    fn welch_t(&self, other: &Self) -> f64 {
+        if self.n < 2.0 || other.n < 2.0 {
+            return 0.0;
+        }
+        let se = (self.variance() / self.n + other.variance() / other.n).sqrt();
+        if se == 0.0 {
+            0.0
+        } else {
+            (self.mean - other.mean) / se
+        }
+    }
+}
@@ -2,14 +2,26 @@
     Copyright Michael Lodder. All Rights Reserved.
     SPDX-License-Identifier: Apache-2.0
 */
+use alloc::string::String;
 use thiserror::Error;
 
 /// Errors in lamport signing scheme.
-#[derive(Error, Debug)]
+///
+/// The type only needs `core` and `alloc`. [`std::error::Error`] and the
+/// [`LamportError::IoError`] variant require the `std` feature.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum LamportError {
     /// I/O error.
-    #[error("I/O error: {0}")]
-    IoError(#[from] std::io::Error),
+    ///
+    /// The kind and message of the [`std::io::Error`] are kept so the error can be cloned and compared.
+    #[cfg(feature = "std")]
+    #[error("I/O error: {message}")]
+    IoError {
+        /// The kind of the I/O error.
+        kind: std::io::ErrorKind,
+        /// The I/O error message.
+        message: String,
+    },
     /// Vsss error.
     #[error("Vsss error: {0}")]
     VsssError(vsss_rs::Error),
@@ -22,11 +34,161 @@ pub enum LamportError {
     /// Invalid signature bytes.
     #[error("Invalid signature bytes.")]
     InvalidSignatureBytes,
+    /// Invalid key id bytes.
+    #[error("Invalid key id bytes.")]
+    InvalidKeyIdBytes,
+    /// The signature was bound to a different verifying key.
+    #[error("Signature key id does not match the verifying key.")]
+    KeyIdMismatch,
+    /// Invalid merkle inclusion or consistency proof.
+    #[error("Invalid merkle proof.")]
+    InvalidMerkleProof,
+    /// No key with the key id is known.
+    #[error("Unknown key id.")]
+    UnknownKeyId,
+    /// The key has been revoked.
+    #[error("The key has been revoked.")]
+    RevokedKey,
+    /// Invalid key registry bytes.
+    #[error("Invalid key registry bytes.")]
+    InvalidRegistryBytes,
+    /// Invalid key log bytes.
+    #[error("Invalid key log bytes.")]
+    InvalidKeyLogBytes,
+    /// The operation is not allowed in the current session state.
+    #[error("Invalid session state.")]
+    InvalidSessionState,
+    /// A session commitment was missing, duplicated, or did not match the revealed share.
+    #[error("Invalid session commitment.")]
+    InvalidCommitment,
+    /// A threshold session participant's commitment or share was rejected.
+    ///
+    /// The commitment was duplicated, missing or did not match the revealed
+    /// share, or the participant was excluded from the session.
+    #[error("Participant {0} was rejected.")]
+    InvalidParticipant(u8),
+    /// A threshold session participant's share was made for a different threshold.
+    #[error("Participant {identifier} has a share for threshold {found}, expected {expected}.")]
+    ThresholdMismatch {
+        /// The identifier of the participant.
+        identifier: u8,
+        /// The threshold of the session.
+        expected: usize,
+        /// The threshold of the share.
+        found: u8,
+    },
+    /// Invalid protocol message bytes.
+    #[error("Invalid message bytes.")]
+    InvalidMessageBytes,
+    /// The protocol message was written with an unknown format version.
+    #[error("Unsupported message version {0}.")]
+    UnsupportedMessageVersion(u8),
+    /// Invalid share bundle bytes, or shares that do not belong in one bundle.
+    #[error("Invalid share bundle bytes.")]
+    InvalidBundleBytes,
+    /// The key escrow is malformed or cannot be opened with the recovery key.
+    #[error("Invalid key escrow.")]
+    InvalidEscrow,
+    /// The digest output does not match the declared digest size.
+    #[error("Invalid digest length.")]
+    InvalidDigest,
+    /// The digest is too short to create keys with.
+    #[error("Digest size of {0} bits is below the minimum security level.")]
+    InsecureDigest(usize),
+    /// The wrapped key or key encryption key is invalid.
+    #[error("Invalid wrapped key.")]
+    InvalidKeyWrap,
+    /// The forgery proof is malformed, or the signature is not a forgery.
+    #[error("Invalid forgery proof.")]
+    InvalidForgeryProof,
+    /// The signature's layout does not match the digest it claims to use.
+    #[error(
+        "Invalid signature shape: expected axes {expected:?}, found {found:?} with {length} bytes."
+    )]
+    InvalidSignatureShape {
+        /// The axes required by the digest, in bits and bytes.
+        expected: [usize; 2],
+        /// The axes of the signature.
+        found: [usize; 2],
+        /// The number of bytes in the signature.
+        length: usize,
+    },
+    /// Too few registered keys signed to satisfy the signer policy.
+    #[error("Signer policy not satisfied: {valid} valid signers, {required} required.")]
+    PolicyNotSatisfied {
+        /// The number of registered keys with a valid signature.
+        valid: usize,
+        /// The number of signers the policy requires.
+        required: usize,
+    },
+    /// Invalid DSSE envelope.
+    #[error("Invalid DSSE envelope.")]
+    InvalidEnvelope,
+    /// Invalid ASCII armor, or armor with the wrong label or checksum.
+    #[error("Invalid ASCII armor.")]
+    InvalidArmor,
+    /// The input is larger than the decoding [`crate::Limits`].
+    #[error("Input of {length} bytes exceeds the limit of {limit} bytes.")]
+    InputTooLarge {
+        /// The size of the input.
+        length: usize,
+        /// The limit from [`crate::Limits::max_input_bytes`].
+        limit: usize,
+    },
+    /// The operation was stopped by a [`crate::CancellationToken`].
+    #[error("The operation was cancelled.")]
+    Cancelled,
+    /// The seed expansion version is unknown or not shared with the peer.
+    #[error("Unsupported seed KDF version {0}.")]
+    UnsupportedSeedKdf(u8),
+    /// The block store is unformatted or too small, or a write did not take effect.
+    #[error("Invalid block store.")]
+    InvalidBlockStore,
+    /// Every key in the pool has been used.
+    #[error("The key pool is exhausted.")]
+    KeyPoolExhausted,
+    /// The storage key does not decrypt the key pool.
+    #[error("The storage key does not match the key pool.")]
+    InvalidStorageKey,
+    /// The Winternitz parameter is not 2, 4, 16 or 256.
+    #[error("Invalid Winternitz parameter {0}.")]
+    InvalidWinternitzParameter(usize),
+    /// The hypertree height, layers or Winternitz parameter are out of range.
+    #[error("Invalid hypertree parameters.")]
+    InvalidHypertreeParameters,
+    /// The LMS or LM-OTS typecode is not supported.
+    #[error("Unsupported LMS typecode {0}.")]
+    UnsupportedLmsTypecode(u32),
+    /// The HORS or FORS parameters or maximum uses are out of range or too large for the digest.
+    #[error("Invalid HORS or FORS parameters.")]
+    InvalidHorsParameters,
+    /// The BiBa ball or bin counts are out of range.
+    #[error("Invalid BiBa parameters.")]
+    InvalidBibaParameters,
+    /// No two BiBa balls fell in the same bin for any counter that was tried.
+    #[error("No BiBa collision found.")]
+    BibaCollisionNotFound,
+    /// The key state is malformed or failed its integrity check.
+    #[error("Invalid key state.")]
+    InvalidKeyState,
+    /// The personalization tag is empty or too long.
+    #[error("Invalid personalization tag.")]
+    InvalidPersonalization,
     /// General Purpose errors
     #[error("General error: {0}")]
     General(String),
 }
 
+#[cfg(feature = "std")]
+impl From<std::io::Error> for LamportError {
+    fn from(err: std::io::Error) -> Self {
+        LamportError::IoError {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
 impl From<vsss_rs::Error> for LamportError {
     fn from(err: vsss_rs::Error) -> Self {
         LamportError::VsssError(err)
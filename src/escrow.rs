@@ -0,0 +1,342 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{KeyFactory, LamportDigest, LamportError, LamportResult, SigningKey};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use hpke::aead::ChaCha20Poly1305 as HpkeAead;
+use hpke::kdf::HkdfSha256;
+use hpke::kem::X25519HkdfSha256;
+use hpke::{Deserializable, Kem, OpModeR, OpModeS, Serializable};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroizing;
+
+type EscrowKem = X25519HkdfSha256;
+
+const ESCROW_VERSION: u8 = 1;
+const KEY_INFO: &[u8] = b"lamport_signature_plus key escrow v1";
+const SEED_INFO: &[u8] = b"lamport_signature_plus seed escrow v1";
+const KEY_BYTES: usize = 32;
+const TAG_BYTES: usize = 16;
+const ENCAPPED_BYTES: usize = 32;
+const WRAPPED_BYTES: usize = KEY_BYTES + TAG_BYTES;
+
+/// An X25519 HPKE public key of a recovery officer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HpkePublicKey(pub(crate) <EscrowKem as Kem>::PublicKey);
+
+/// An X25519 HPKE secret key able to recover a [`KeyEscrow`].
+#[derive(Clone)]
+pub struct HpkeSecretKey(pub(crate) <EscrowKem as Kem>::PrivateKey);
+
+impl std::fmt::Debug for HpkeSecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("HpkeSecretKey(..)")
+    }
+}
+
+impl HpkeSecretKey {
+    /// Generates a new recovery key.
+    pub fn random(mut rng: impl RngCore + CryptoRng) -> Self {
+        Self(EscrowKem::gen_keypair(&mut rng).0)
+    }
+
+    /// The public key to escrow to.
+    pub fn public_key(&self) -> HpkePublicKey {
+        HpkePublicKey(EscrowKem::sk_to_pk(&self.0))
+    }
+
+    /// Converts the key to bytes.
+    pub fn to_bytes(&self) -> [u8; KEY_BYTES] {
+        self.0.to_bytes().into()
+    }
+
+    /// Constructs the key from bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        <EscrowKem as Kem>::PrivateKey::from_bytes(input.as_ref())
+            .map(Self)
+            .map_err(|e| LamportError::General(e.to_string()))
+    }
+}
+
+impl HpkePublicKey {
+    /// Converts the key to bytes.
+    pub fn to_bytes(&self) -> [u8; KEY_BYTES] {
+        self.0.to_bytes().into()
+    }
+
+    /// Constructs the key from bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        <EscrowKem as Kem>::PublicKey::from_bytes(input.as_ref())
+            .map(Self)
+            .map_err(|e| LamportError::General(e.to_string()))
+    }
+}
+
+/// An encrypted backup of a [`SigningKey`] that any one designated recovery key can open.
+///
+/// The key is encrypted once with a random ChaCha20-Poly1305 key, which is in
+/// turn sealed to each recipient with HPKE (X25519, HKDF-SHA256,
+/// ChaCha20-Poly1305). The escrow header is authenticated by every layer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyEscrow<T: LamportDigest> {
+    pub(crate) recipients: Vec<Recipient>,
+    pub(crate) ciphertext: Vec<u8>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(KeyEscrow);
+vec_impl!(KeyEscrow);
+
+/// An encrypted backup of the master seed of a [`KeyFactory`], sealed the same
+/// way as a [`KeyEscrow`].
+///
+/// The seed is all that is needed to regenerate every key of the factory and
+/// of its children, so a whole key pool is recovered from a few dozen bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeedEscrow<T: LamportDigest> {
+    pub(crate) recipients: Vec<Recipient>,
+    pub(crate) ciphertext: Vec<u8>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(SeedEscrow);
+vec_impl!(SeedEscrow);
+
+/// A recipient's encapsulated key and sealed data key.
+type Recipient = ([u8; ENCAPPED_BYTES], [u8; WRAPPED_BYTES]);
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Encrypts the key so that any of the `recipients` can recover it.
+    pub fn escrow(
+        &self,
+        recipients: &[HpkePublicKey],
+        rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<KeyEscrow<T>> {
+        let plaintext = Zeroizing::new(self.to_bytes());
+        let (recipients, ciphertext) = seal::<T>(KEY_INFO, &plaintext, recipients, rng)?;
+        Ok(KeyEscrow {
+            recipients,
+            ciphertext,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> KeyFactory<T> {
+    /// Encrypts the master seed so that any of the `recipients` can recover
+    /// the factory and every key derived from it.
+    pub fn escrow(
+        &self,
+        recipients: &[HpkePublicKey],
+        rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<SeedEscrow<T>> {
+        let plaintext = Zeroizing::new(self.to_bytes());
+        let (recipients, ciphertext) = seal::<T>(SEED_INFO, &plaintext, recipients, rng)?;
+        Ok(SeedEscrow {
+            recipients,
+            ciphertext,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> KeyEscrow<T> {
+    /// The number of recovery keys that can open the escrow.
+    pub fn recipients(&self) -> usize {
+        self.recipients.len()
+    }
+
+    /// Decrypts the [`SigningKey`] with one of the designated recovery keys.
+    pub fn recover(&self, secret_key: &HpkeSecretKey) -> LamportResult<SigningKey<T>> {
+        let plaintext = open::<T>(KEY_INFO, &self.recipients, &self.ciphertext, secret_key)?;
+        SigningKey::from_bytes(&*plaintext)
+    }
+
+    /// Converts the [`KeyEscrow`] to canonical bytes.
+    ///
+    /// The format is the header (version byte, digest size in bits as a big
+    /// endian `u32`, recipient count) followed by each recipient's encapsulated
+    /// key and sealed data key, then the encrypted signing key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        escrow_to_bytes::<T>(&self.recipients, &self.ciphertext)
+    }
+
+    /// Constructs a [`KeyEscrow`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let bits = T::digest_size_in_bits();
+        let key_length = 1 + bits * (bits / 8) * 2;
+        let (recipients, ciphertext) = escrow_from_bytes::<T>(input.as_ref(), key_length)?;
+        Ok(Self {
+            recipients,
+            ciphertext,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> SeedEscrow<T> {
+    /// The number of recovery keys that can open the escrow.
+    pub fn recipients(&self) -> usize {
+        self.recipients.len()
+    }
+
+    /// Decrypts the [`KeyFactory`] with one of the designated recovery keys.
+    pub fn recover(&self, secret_key: &HpkeSecretKey) -> LamportResult<KeyFactory<T>> {
+        let plaintext = open::<T>(SEED_INFO, &self.recipients, &self.ciphertext, secret_key)?;
+        KeyFactory::from_bytes(&*plaintext)
+    }
+
+    /// Converts the [`SeedEscrow`] to canonical bytes, in the format of
+    /// [`KeyEscrow::to_bytes`] with the encrypted factory in place of the key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        escrow_to_bytes::<T>(&self.recipients, &self.ciphertext)
+    }
+
+    /// Constructs a [`SeedEscrow`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (recipients, ciphertext) =
+            escrow_from_bytes::<T>(input.as_ref(), KeyFactory::<T>::BYTES)?;
+        Ok(Self {
+            recipients,
+            ciphertext,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// Encrypts the plaintext with a random data key sealed to each recipient.
+fn seal<T: LamportDigest>(
+    info: &[u8],
+    plaintext: &[u8],
+    recipients: &[HpkePublicKey],
+    mut rng: impl RngCore + CryptoRng,
+) -> LamportResult<(Vec<Recipient>, Vec<u8>)> {
+    let aad = header::<T>(recipients.len())?;
+    let mut data_key = Zeroizing::new([0u8; KEY_BYTES]);
+    rng.fill_bytes(&mut data_key[..]);
+
+    let mut wrapped = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let (encapped, sealed) = hpke::single_shot_seal::<HpkeAead, HkdfSha256, EscrowKem, _>(
+            &OpModeS::Base,
+            &recipient.0,
+            info,
+            &data_key[..],
+            &aad,
+            &mut rng,
+        )
+        .map_err(|e| LamportError::General(e.to_string()))?;
+        let sealed: [u8; WRAPPED_BYTES] = sealed
+            .as_slice()
+            .try_into()
+            .map_err(|_| LamportError::General("unexpected sealed key length".to_string()))?;
+        wrapped.push((encapped.to_bytes().into(), sealed));
+    }
+
+    let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&data_key[..]))
+        .encrypt(
+            &[0u8; 12].into(),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|e| LamportError::General(e.to_string()))?;
+    Ok((wrapped, ciphertext))
+}
+
+/// Opens a data key sealed to the secret key and decrypts the ciphertext.
+fn open<T: LamportDigest>(
+    info: &[u8],
+    recipients: &[Recipient],
+    ciphertext: &[u8],
+    secret_key: &HpkeSecretKey,
+) -> LamportResult<Zeroizing<Vec<u8>>> {
+    let aad = header::<T>(recipients.len())?;
+    let data_key = recipients
+        .iter()
+        .find_map(|(encapped, sealed)| {
+            let encapped = <EscrowKem as Kem>::EncappedKey::from_bytes(encapped).ok()?;
+            hpke::single_shot_open::<HpkeAead, HkdfSha256, EscrowKem>(
+                &OpModeR::Base,
+                &secret_key.0,
+                &encapped,
+                info,
+                sealed,
+                &aad,
+            )
+            .ok()
+        })
+        .map(Zeroizing::new)
+        .ok_or(LamportError::InvalidEscrow)?;
+    if data_key.len() != KEY_BYTES {
+        return Err(LamportError::InvalidEscrow);
+    }
+    ChaCha20Poly1305::new(Key::from_slice(&data_key[..]))
+        .decrypt(
+            &[0u8; 12].into(),
+            Payload {
+                msg: ciphertext,
+                aad: &aad,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| LamportError::InvalidEscrow)
+}
+
+fn escrow_to_bytes<T: LamportDigest>(recipients: &[Recipient], ciphertext: &[u8]) -> Vec<u8> {
+    let mut bytes = header::<T>(recipients.len()).unwrap_or_default();
+    for (encapped, sealed) in recipients {
+        bytes.extend_from_slice(encapped);
+        bytes.extend_from_slice(sealed);
+    }
+    bytes.extend_from_slice(ciphertext);
+    bytes
+}
+
+/// Splits an escrow into its recipients and a ciphertext of `plaintext_length` bytes.
+fn escrow_from_bytes<T: LamportDigest>(
+    input: &[u8],
+    plaintext_length: usize,
+) -> LamportResult<(Vec<Recipient>, Vec<u8>)> {
+    if input.len() < 6 {
+        return Err(LamportError::InvalidEscrow);
+    }
+    let count = usize::from(input[5]);
+    let expected = header::<T>(count)?;
+    if input[..6] != expected[..] {
+        return Err(LamportError::InvalidEscrow);
+    }
+    let body = &input[6..];
+    if body.len() != count * (ENCAPPED_BYTES + WRAPPED_BYTES) + plaintext_length + TAG_BYTES {
+        return Err(LamportError::InvalidEscrow);
+    }
+    let (entries, ciphertext) = body.split_at(count * (ENCAPPED_BYTES + WRAPPED_BYTES));
+    let recipients = entries
+        .chunks_exact(ENCAPPED_BYTES + WRAPPED_BYTES)
+        .map(|chunk| {
+            let mut encapped = [0u8; ENCAPPED_BYTES];
+            let mut sealed = [0u8; WRAPPED_BYTES];
+            encapped.copy_from_slice(&chunk[..ENCAPPED_BYTES]);
+            sealed.copy_from_slice(&chunk[ENCAPPED_BYTES..]);
+            (encapped, sealed)
+        })
+        .collect();
+    Ok((recipients, ciphertext.to_vec()))
+}
+
+fn header<T: LamportDigest>(recipients: usize) -> LamportResult<Vec<u8>> {
+    let count = u8::try_from(recipients)
+        .ok()
+        .filter(|c| *c > 0)
+        .ok_or(LamportError::InvalidEscrow)?;
+    let bits = u32::try_from(T::digest_size_in_bits()).map_err(|_| LamportError::InvalidEscrow)?;
+    let mut header = Vec::with_capacity(6);
+    header.push(ESCROW_VERSION);
+    header.extend_from_slice(&bits.to_be_bytes());
+    header.push(count);
+    Ok(header)
+}
@@ -0,0 +1,403 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Fail-stop Lamport signatures.
+//!
+//! Each secret value is twice the digest length, so every commitment in the
+//! [`VerifyingKey`] has on the order of `2^bits` preimages and the
+//! published key reveals almost nothing about which one the signer holds.
+//! A forger that finds any preimage for a bit the signer never revealed
+//! almost certainly finds a different one, and the signer can then show the
+//! two values as a hash collision in a [`ForgeryProof`]. A forgery therefore
+//! proves the digest was broken instead of silently implicating the signer.
+use crate::hash::{check_digest, check_security};
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec, VerifyingKey};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroize;
+
+/// A one-time fail-stop signing key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FailStopSigningKey<T: LamportDigest> {
+    pub(crate) zero_values: MultiVec<u8, 2>,
+    pub(crate) one_values: MultiVec<u8, 2>,
+    pub(crate) used: bool,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(FailStopSigningKey);
+vec_impl!(FailStopSigningKey);
+
+impl<T: LamportDigest> Zeroize for FailStopSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.zero_values.zeroize();
+        self.one_values.zeroize();
+    }
+}
+
+/// A signature created by a [`FailStopSigningKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FailStopSignature<T: LamportDigest> {
+    pub(crate) data: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(FailStopSignature);
+vec_impl!(FailStopSignature);
+
+/// Two different secret values with the same commitment in a [`VerifyingKey`].
+///
+/// Created by [`FailStopSigningKey::prove_forgery`] and checkable by anyone
+/// holding the [`VerifyingKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForgeryProof<T: LamportDigest> {
+    pub(crate) position: u32,
+    pub(crate) bit: u8,
+    pub(crate) first: Vec<u8>,
+    pub(crate) second: Vec<u8>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(ForgeryProof);
+vec_impl!(ForgeryProof);
+
+impl<T: LamportDigest> From<&FailStopSigningKey<T>> for VerifyingKey<T> {
+    fn from(value: &FailStopSigningKey<T>) -> Self {
+        VerifyingKey::new(
+            commit::<T>(&value.zero_values),
+            commit::<T>(&value.one_values),
+        )
+    }
+}
+
+impl<T: LamportDigest> FailStopSigningKey<T> {
+    /// Constructs a [`FailStopSigningKey`] with the specified RNG.
+    ///
+    /// Returns [`LamportError::InsecureDigest`] if the digest is below the
+    /// minimum security level, see [`LamportDigest`].
+    pub fn random(mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let bits = T::digest_size_in_bits();
+        let axes = [bits, value_length::<T>()];
+        let mut zero_values = MultiVec::fill(axes, 0u8);
+        let mut one_values = MultiVec::fill(axes, 0u8);
+        rng.fill_bytes(&mut zero_values.data);
+        rng.fill_bytes(&mut one_values.data);
+        Ok(Self {
+            zero_values,
+            one_values,
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Has this key been used.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs the data.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<FailStopSignature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let data_hash = T::digest(data.as_ref());
+        check_digest::<T>(&data_hash)?;
+        let length = value_length::<T>();
+        let mut data = MultiVec::fill(self.zero_values.axes, 0u8);
+        {
+            let mut signature_iter = data.iter_mut();
+            let mut zero_iter = self.zero_values.iter();
+            let mut one_iter = self.one_values.iter();
+            for byte in data_hash.iter() {
+                for j in 0..8 {
+                    let choice = Choice::from((*byte >> j) & 1);
+                    for _ in 0..length {
+                        *signature_iter.next().expect("more values") = u8::conditional_select(
+                            zero_iter.next().expect("more values"),
+                            one_iter.next().expect("more values"),
+                            choice,
+                        );
+                    }
+                }
+            }
+        }
+        self.used = true;
+        Ok(FailStopSignature {
+            data,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Produces a [`ForgeryProof`] from a signature this key did not create.
+    ///
+    /// Returns [`LamportError::InvalidForgeryProof`] if `signature` is not a
+    /// valid signature over `data`, or if it reveals only this key's own
+    /// secret values and so could have been created by the signer.
+    pub fn prove_forgery<B: AsRef<[u8]>>(
+        &self,
+        signature: &FailStopSignature<T>,
+        data: B,
+    ) -> LamportResult<ForgeryProof<T>> {
+        let verifying_key = VerifyingKey::from(self);
+        verifying_key
+            .verify_fail_stop(signature, data.as_ref())
+            .map_err(|_| LamportError::InvalidForgeryProof)?;
+        let data_hash = T::digest(data.as_ref());
+        for (i, byte) in data_hash.iter().enumerate() {
+            for j in 0..8 {
+                let position = i * 8 + j;
+                let bit = (byte >> j) & 1;
+                let secret = if bit == 1 {
+                    &self.one_values[position]
+                } else {
+                    &self.zero_values[position]
+                };
+                let forged = &signature.data[position];
+                if !bool::from(secret.ct_eq(forged)) {
+                    return Ok(ForgeryProof {
+                        position: u32::try_from(position)
+                            .map_err(|_| LamportError::InvalidForgeryProof)?,
+                        bit,
+                        first: secret.to_vec(),
+                        second: forged.to_vec(),
+                        algorithm: PhantomData,
+                    });
+                }
+            }
+        }
+        Err(LamportError::InvalidForgeryProof)
+    }
+
+    /// Converts the [`FailStopSigningKey`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.zero_values.len() * 2);
+        bytes.push(self.used as u8);
+        bytes.extend_from_slice(self.zero_values.as_ref());
+        bytes.extend_from_slice(self.one_values.as_ref());
+        bytes
+    }
+
+    /// Constructs a [`FailStopSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let length = value_length::<T>();
+        if input.len() != 1 + bits * length * 2 || input[0] > 1 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (zero_values, one_values) = input[1..].split_at(bits * length);
+        Ok(Self {
+            zero_values: MultiVec {
+                data: zero_values.to_vec(),
+                axes: [bits, length],
+            },
+            one_values: MultiVec {
+                data: one_values.to_vec(),
+                axes: [bits, length],
+            },
+            used: input[0] == 1,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies a [`FailStopSignature`].
+    pub fn verify_fail_stop<B: AsRef<[u8]>>(
+        &self,
+        signature: &FailStopSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let bits = T::digest_size_in_bits();
+        if signature.data.axes != [bits, value_length::<T>()] {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let data_digest = T::digest(data.as_ref());
+        check_digest::<T>(&data_digest)?;
+        let res = data_digest.iter().enumerate().all(|(i, byte)| {
+            (0..8).all(|j| {
+                let offset = i * 8 + j;
+                let hashed_value = T::digest(&signature.data[offset]);
+                let cmp = if (byte >> j) & 1 == 1 {
+                    &self.one_values[offset]
+                } else {
+                    &self.zero_values[offset]
+                };
+                hashed_value == cmp
+            })
+        });
+        if res {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+}
+
+impl<T: LamportDigest> FailStopSignature<T> {
+    /// Converts the signature data into a linearized vector.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.data.data.clone()
+    }
+
+    /// Constructs a [`FailStopSignature`] from a byte sequence.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let length = value_length::<T>();
+        if input.len() != bits * length {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            data: MultiVec {
+                data: input.to_vec(),
+                axes: [bits, length],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> ForgeryProof<T> {
+    /// The digest bit position of the colliding commitment.
+    pub fn position(&self) -> usize {
+        self.position as usize
+    }
+
+    /// Checks both values differ and hash to the same commitment in the key.
+    pub fn verify(&self, key: &VerifyingKey<T>) -> LamportResult<()> {
+        let position = self.position();
+        if position >= T::digest_size_in_bits() || self.first == self.second {
+            return Err(LamportError::InvalidForgeryProof);
+        }
+        let commitment = if self.bit == 1 {
+            &key.one_values[position]
+        } else {
+            &key.zero_values[position]
+        };
+        if T::digest(&self.first) == commitment && T::digest(&self.second) == commitment {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidForgeryProof)
+        }
+    }
+
+    /// Converts the proof to canonical bytes.
+    ///
+    /// The format is the position as a big endian `u32`, the bit value and
+    /// the two colliding values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(5 + self.first.len() + self.second.len());
+        bytes.extend_from_slice(&self.position.to_be_bytes());
+        bytes.push(self.bit);
+        bytes.extend_from_slice(&self.first);
+        bytes.extend_from_slice(&self.second);
+        bytes
+    }
+
+    /// Constructs a [`ForgeryProof`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let length = value_length::<T>();
+        if input.len() != 5 + length * 2 || input[4] > 1 {
+            return Err(LamportError::InvalidForgeryProof);
+        }
+        let mut position = [0u8; 4];
+        position.copy_from_slice(&input[..4]);
+        let (first, second) = input[5..].split_at(length);
+        Ok(Self {
+            position: u32::from_be_bytes(position),
+            bit: input[4],
+            first: first.to_vec(),
+            second: second.to_vec(),
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// Secret values are twice the digest length.
+fn value_length<T: LamportDigest>() -> usize {
+    T::digest_size_in_bits() / 4
+}
+
+fn commit<T: LamportDigest>(values: &MultiVec<u8, 2>) -> MultiVec<u8, 2> {
+    let bits = T::digest_size_in_bits();
+    let mut data = Vec::with_capacity(bits * bits / 8);
+    for row in values.data.chunks_exact(values.axes[1]) {
+        data.extend_from_slice(&T::digest(row));
+    }
+    MultiVec {
+        data,
+        axes: [bits, bits / 8],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, impl_lamport_digest, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[derive(Debug)]
+    struct WeakDigest;
+
+    impl_lamport_digest!(insecure WeakDigest, 8, |data: &[u8]| {
+        use sha2::Digest;
+        [Sha256::digest(data)[0]]
+    });
+
+    #[test]
+    fn fail_stop() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut sk = FailStopSigningKey::<LamportFixedDigest<Sha256>>::random(&mut rng).unwrap();
+        let pk = VerifyingKey::from(&sk);
+        let signature = sk.sign(b"fail stop").unwrap();
+        assert!(sk.sign(b"fail stop").is_err());
+        assert!(pk.verify_fail_stop(&signature, b"fail stop").is_ok());
+        assert!(pk.verify_fail_stop(&signature, b"fail go").is_err());
+        assert!(sk.prove_forgery(&signature, b"fail stop").is_err());
+
+        let sk =
+            FailStopSigningKey::<LamportFixedDigest<Sha256>>::from_bytes(sk.to_bytes()).unwrap();
+        assert!(sk.used());
+        let signature =
+            FailStopSignature::<LamportFixedDigest<Sha256>>::from_bytes(signature.to_bytes())
+                .unwrap();
+        assert!(pk.verify_fail_stop(&signature, b"fail stop").is_ok());
+
+        // With a broken 8-bit digest an attacker can brute force preimages of the commitments.
+        let sk = FailStopSigningKey::<WeakDigest>::random(&mut rng).unwrap();
+        let pk = VerifyingKey::from(&sk);
+        let message = b"forged";
+        let digest = WeakDigest::digest(message)[0];
+        let mut forged = Vec::new();
+        for i in 0..8 {
+            let commitment = if (digest >> i) & 1 == 1 {
+                &pk.one_values[i]
+            } else {
+                &pk.zero_values[i]
+            };
+            let preimage = (0..=u16::MAX)
+                .map(u16::to_be_bytes)
+                .find(|v| WeakDigest::digest(v) == commitment)
+                .unwrap();
+            forged.extend_from_slice(&preimage);
+        }
+        let forged = FailStopSignature::<WeakDigest>::from_bytes(forged).unwrap();
+        assert!(pk.verify_fail_stop(&forged, message).is_ok());
+
+        let proof = sk.prove_forgery(&forged, message).unwrap();
+        assert!(proof.verify(&pk).is_ok());
+        let proof = ForgeryProof::<WeakDigest>::from_bytes(proof.to_bytes()).unwrap();
+        assert!(proof.verify(&pk).is_ok());
+        let (_, other) = generate_keys::<WeakDigest, _>(&mut rng).unwrap();
+        assert!(proof.verify(&other).is_err());
+    }
+}
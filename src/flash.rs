@@ -0,0 +1,480 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::key_state::hmac_sha256;
+use crate::{
+    LamportDigest, LamportError, LamportResult, SeedKdf, SignatureWithKey, SigningKey,
+    VerifyingKey, SEED_BYTES,
+};
+use core::fmt::{self, Debug, Formatter};
+use hmac::Mac;
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroizing;
+
+const POOL_MAGIC: &[u8; 8] = b"LMPTPOL2";
+const NONCE_BYTES: usize = 16;
+const CHECK_BYTES: usize = 16;
+const HEADER_BYTES: usize = POOL_MAGIC.len() + 1 + 4 + NONCE_BYTES + CHECK_BYTES;
+const SEED_DOMAIN: &[u8] = b"lamport_signature_plus flash pool seed";
+const CHECK_DOMAIN: &[u8] = b"lamport_signature_plus flash pool check";
+
+/// Page oriented storage with NOR flash semantics.
+///
+/// Erased bytes read as `0xff` and setting a bit back to one requires erasing
+/// the whole page. Writes are made of [`BlockStore::word_size`] words, and
+/// between erases each word is programmed at most once with data and then
+/// at most once more with all zeros. Flash with ECC, where programming a
+/// word again corrupts its check bits, typically still allows the second
+/// write because the check bits of all zeros are zeros; a store that
+/// cannot must fail the write.
+pub trait BlockStore {
+    /// The number of bytes in a page.
+    fn page_size(&self) -> usize;
+
+    /// The number of pages.
+    fn page_count(&self) -> usize;
+
+    /// The number of bytes programmed at once, e.g. 8 or 16 for ECC NOR flash.
+    ///
+    /// Must divide the page size. Defaults to 1.
+    fn word_size(&self) -> usize {
+        1
+    }
+
+    /// Reads `buffer.len()` bytes from the page starting at `offset`.
+    fn read(&self, page: usize, offset: usize, buffer: &mut [u8]) -> LamportResult<()>;
+
+    /// Programs the data into the page starting at `offset`.
+    ///
+    /// The offset and length are multiples of the word size. Must return an
+    /// error rather than succeed partially if the data tries to set a
+    /// cleared bit.
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> LamportResult<()>;
+
+    /// Sets every byte of the page to `0xff`.
+    fn erase(&mut self, page: usize) -> LamportResult<()>;
+}
+
+impl<S: BlockStore + ?Sized> BlockStore for &mut S {
+    fn page_size(&self) -> usize {
+        (**self).page_size()
+    }
+
+    fn page_count(&self) -> usize {
+        (**self).page_count()
+    }
+
+    fn word_size(&self) -> usize {
+        (**self).word_size()
+    }
+
+    fn read(&self, page: usize, offset: usize, buffer: &mut [u8]) -> LamportResult<()> {
+        (**self).read(page, offset, buffer)
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> LamportResult<()> {
+        (**self).write(page, offset, data)
+    }
+
+    fn erase(&mut self, page: usize) -> LamportResult<()> {
+        (**self).erase(page)
+    }
+}
+
+/// A [`BlockStore`] kept in memory that enforces flash semantics and counts erases.
+///
+/// With a word size of one, any write that only clears bits succeeds. With
+/// larger words it behaves like ECC flash: a word can be programmed only
+/// while erased, or with all zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBlockStore {
+    page_size: usize,
+    word_size: usize,
+    data: Vec<u8>,
+    erase_counts: Vec<u64>,
+}
+
+impl MemoryBlockStore {
+    /// Constructs an erased store of `page_count` pages of `page_size` bytes.
+    pub fn new(page_size: usize, page_count: usize) -> Self {
+        Self::with_word_size(page_size, page_count, 1)
+    }
+
+    /// Constructs an erased store programmed in words of `word_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `word_size` is zero or does not divide `page_size`.
+    pub fn with_word_size(page_size: usize, page_count: usize, word_size: usize) -> Self {
+        assert!(
+            word_size > 0 && page_size.is_multiple_of(word_size),
+            "the word size must divide the page size"
+        );
+        Self {
+            page_size,
+            word_size,
+            data: vec![0xff; page_size * page_count],
+            erase_counts: vec![0; page_count],
+        }
+    }
+
+    /// The number of times each page has been erased.
+    pub fn erase_counts(&self) -> &[u64] {
+        &self.erase_counts
+    }
+
+    fn range(&self, page: usize, offset: usize, length: usize) -> LamportResult<usize> {
+        if page >= self.erase_counts.len() || offset + length > self.page_size {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        Ok(page * self.page_size + offset)
+    }
+}
+
+impl BlockStore for MemoryBlockStore {
+    fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    fn page_count(&self) -> usize {
+        self.erase_counts.len()
+    }
+
+    fn word_size(&self) -> usize {
+        self.word_size
+    }
+
+    fn read(&self, page: usize, offset: usize, buffer: &mut [u8]) -> LamportResult<()> {
+        let start = self.range(page, offset, buffer.len())?;
+        buffer.copy_from_slice(&self.data[start..start + buffer.len()]);
+        Ok(())
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> LamportResult<()> {
+        let start = self.range(page, offset, data.len())?;
+        if !offset.is_multiple_of(self.word_size) || !data.len().is_multiple_of(self.word_size) {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        let target = &mut self.data[start..start + data.len()];
+        if target.iter().zip(data).any(|(old, new)| new & !old != 0) {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        if self.word_size > 1 {
+            let mut words = target
+                .chunks_exact(self.word_size)
+                .zip(data.chunks_exact(self.word_size));
+            if !words
+                .all(|(old, new)| old.iter().all(|b| *b == 0xff) || new.iter().all(|b| *b == 0))
+            {
+                return Err(LamportError::InvalidBlockStore);
+            }
+        }
+        target.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn erase(&mut self, page: usize) -> LamportResult<()> {
+        let start = self.range(page, 0, self.page_size)?;
+        self.data[start..start + self.page_size].fill(0xff);
+        self.erase_counts[page] += 1;
+        Ok(())
+    }
+}
+
+/// A pool of one-time keys stored directly on a [`BlockStore`].
+///
+/// Only the 32 byte seed of each key is stored, see [`SigningKey::from_seed_with_kdf`],
+/// and the seeds are encrypted under a storage key that is not kept on the
+/// flash, e.g. one held by a secure element or derived from a device secret.
+/// Reading the flash alone reveals no key. Each seed is XORed with
+/// HMAC-SHA-256 of the storage key over a random nonce chosen at
+/// provisioning and the key's index.
+///
+/// Every field starts on a word boundary and is padded to whole words, see
+/// [`BlockStore::word_size`]. The layout starts at the first page:
+///
+/// - the header, written last when provisioning so an interrupted
+///   provisioning is not mistaken for a pool: `LMPTPOL2`, the [`SeedKdf`]
+///   version, the number of keys as a big endian `u32`, the 16 byte nonce
+///   and a 16 byte check value of the storage key,
+/// - the used flags, one word per key that is programmed to zeros when the
+///   key is taken,
+/// - the encrypted seeds.
+///
+/// Taking a key zeroes its flag and reads it back before the seed is used,
+/// then overwrites the seed with zeros, so a power loss at any point can
+/// lose an unused key but never sign twice with one. Both steps only
+/// program words with zeros and neither erases a page, so the pages are
+/// only erased once per provisioning. Keys are taken in index order and the
+/// pool caches the index of the next unused key, found once when opening.
+///
+/// The pool is meant for firmware, but the crate still requires the `std`
+/// feature, so it cannot yet be built for `no_std` targets.
+pub struct FlashKeyPool<T: LamportDigest, S: BlockStore> {
+    store: S,
+    kdf: SeedKdf,
+    capacity: u32,
+    nonce: [u8; NONCE_BYTES],
+    storage_key: Zeroizing<Vec<u8>>,
+    next: u32,
+    algorithm: PhantomData<T>,
+}
+
+impl<T: LamportDigest, S: BlockStore + Debug> Debug for FlashKeyPool<T, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FlashKeyPool")
+            .field("store", &self.store)
+            .field("kdf", &self.kdf)
+            .field("capacity", &self.capacity)
+            .field("next", &self.next)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: LamportDigest, S: BlockStore> FlashKeyPool<T, S> {
+    /// Erases the store and fills it with `capacity` fresh seeds encrypted
+    /// under the storage key.
+    ///
+    /// Pages that already read as erased are skipped to save wear.
+    pub fn provision(
+        mut store: S,
+        capacity: u32,
+        kdf: SeedKdf,
+        storage_key: &[u8],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        let length = Self::layout_length(capacity, &store)?;
+        let page_size = store.page_size();
+        let mut page = vec![0u8; page_size];
+        for index in 0..length.div_ceil(page_size) {
+            store.read(index, 0, &mut page)?;
+            if page.iter().any(|b| *b != 0xff) {
+                store.erase(index)?;
+            }
+        }
+
+        let mut nonce = [0u8; NONCE_BYTES];
+        rng.fill_bytes(&mut nonce);
+        let mut pool = Self {
+            store,
+            kdf,
+            capacity,
+            nonce,
+            storage_key: Zeroizing::new(storage_key.to_vec()),
+            next: 0,
+            algorithm: PhantomData,
+        };
+        let mut seed = Zeroizing::new([0u8; SEED_BYTES]);
+        for index in 0..capacity {
+            rng.fill_bytes(seed.as_mut());
+            pool.apply_keystream(index, &mut seed);
+            let offset = pool.seed_offset(index);
+            pool.write_words(offset, seed.as_ref())?;
+        }
+        let mut header = [0u8; HEADER_BYTES];
+        header[..8].copy_from_slice(POOL_MAGIC);
+        header[8] = kdf.version();
+        header[9..13].copy_from_slice(&capacity.to_be_bytes());
+        header[13..29].copy_from_slice(&nonce);
+        header[29..].copy_from_slice(&pool.check_value());
+        pool.write_words(0, &header)?;
+        Ok(pool)
+    }
+
+    /// Opens a pool written by [`FlashKeyPool::provision`] with the same storage key.
+    ///
+    /// Returns [`LamportError::InvalidStorageKey`] if the storage key differs.
+    pub fn open(store: S, storage_key: &[u8]) -> LamportResult<Self> {
+        let mut pool = Self {
+            store,
+            kdf: SeedKdf::default(),
+            capacity: 0,
+            nonce: [0u8; NONCE_BYTES],
+            storage_key: Zeroizing::new(storage_key.to_vec()),
+            next: 0,
+            algorithm: PhantomData,
+        };
+        let mut header = [0u8; HEADER_BYTES];
+        pool.read_at(0, &mut header)?;
+        if &header[..8] != POOL_MAGIC {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        pool.kdf = SeedKdf::try_from(header[8])?;
+        let capacity = u32::from_be_bytes([header[9], header[10], header[11], header[12]]);
+        Self::layout_length(capacity, &pool.store)?;
+        pool.capacity = capacity;
+        pool.nonce.copy_from_slice(&header[13..29]);
+        if pool.check_value().ct_eq(&header[29..]).unwrap_u8() == 0 {
+            return Err(LamportError::InvalidStorageKey);
+        }
+        while pool.next < capacity && pool.is_used(pool.next)? {
+            pool.next += 1;
+        }
+        Ok(pool)
+    }
+
+    /// The number of keys the pool was provisioned with.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The seed expansion used by the pool.
+    pub fn kdf(&self) -> SeedKdf {
+        self.kdf
+    }
+
+    /// Has the key at `index` been taken.
+    pub fn is_used(&self, index: u32) -> LamportResult<bool> {
+        if index >= self.capacity {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        let mut flag = vec![0u8; self.store.word_size()];
+        self.read_at(self.flag_offset(index), &mut flag)?;
+        Ok(flag.iter().any(|b| *b != 0xff))
+    }
+
+    /// The number of keys that have not been taken.
+    pub fn remaining(&self) -> u32 {
+        self.capacity - self.next
+    }
+
+    /// The [`VerifyingKey`] of the unused key at `index`, e.g. to publish it in advance.
+    pub fn verifying_key(&self, index: u32) -> LamportResult<VerifyingKey<T>> {
+        if self.is_used(index)? {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let signing_key = self.derive(index)?;
+        Ok(VerifyingKey::from(&signing_key))
+    }
+
+    /// Takes the next unused key and signs the data with it.
+    ///
+    /// Returns the index of the key with the signature, or
+    /// [`LamportError::KeyPoolExhausted`] once every key has been taken.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<(u32, SignatureWithKey<T>)> {
+        if self.next == self.capacity {
+            return Err(LamportError::KeyPoolExhausted);
+        }
+        let index = self.next;
+
+        // The flag must be durable before the seed is used
+        let zeros = vec![0u8; self.store.word_size()];
+        self.write_words(self.flag_offset(index), &zeros)?;
+        if !self.is_used(index)? {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        self.next += 1;
+
+        let mut signing_key = self.derive(index)?;
+        // Zero the whole slot, since padding a partial word would reprogram it
+        let slot = vec![0u8; SEED_BYTES.next_multiple_of(self.store.word_size())];
+        self.write_words(self.seed_offset(index), &slot)?;
+        let signature = signing_key.sign_with_key(data)?;
+        Ok((index, signature))
+    }
+
+    /// Returns the underlying store.
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    fn derive(&self, index: u32) -> LamportResult<SigningKey<T>> {
+        let mut seed = Zeroizing::new([0u8; SEED_BYTES]);
+        self.read_at(self.seed_offset(index), seed.as_mut())?;
+        self.apply_keystream(index, &mut seed);
+        SigningKey::from_seed_with_kdf(&seed, self.kdf)
+    }
+
+    /// Encrypts or decrypts the seed at `index`.
+    fn apply_keystream(&self, index: u32, seed: &mut [u8; SEED_BYTES]) {
+        let keystream = hmac_sha256(&self.storage_key)
+            .chain_update(SEED_DOMAIN)
+            .chain_update(self.nonce)
+            .chain_update(index.to_be_bytes())
+            .finalize()
+            .into_bytes();
+        for (byte, key) in seed.iter_mut().zip(keystream) {
+            *byte ^= key;
+        }
+    }
+
+    fn check_value(&self) -> [u8; CHECK_BYTES] {
+        let tag = hmac_sha256(&self.storage_key)
+            .chain_update(CHECK_DOMAIN)
+            .chain_update(self.nonce)
+            .finalize()
+            .into_bytes();
+        let mut check = [0u8; CHECK_BYTES];
+        check.copy_from_slice(&tag[..CHECK_BYTES]);
+        check
+    }
+
+    /// The number of bytes a pool of `capacity` keys takes on the store.
+    ///
+    /// The capacity may come from an untrusted header, so every step is
+    /// checked and a layout that overflows or does not fit is rejected.
+    fn layout_length(capacity: u32, store: &S) -> LamportResult<usize> {
+        let word_size = store.word_size();
+        let page_size = store.page_size();
+        if word_size == 0 || page_size == 0 || !page_size.is_multiple_of(word_size) {
+            return Err(LamportError::InvalidBlockStore);
+        }
+        let length = usize::try_from(capacity).ok().and_then(|capacity| {
+            let per_key = SEED_BYTES
+                .checked_next_multiple_of(word_size)?
+                .checked_add(word_size)?;
+            HEADER_BYTES
+                .checked_next_multiple_of(word_size)?
+                .checked_add(capacity.checked_mul(per_key)?)
+        });
+        match (length, page_size.checked_mul(store.page_count())) {
+            (Some(length), Some(available)) if length <= available => Ok(length),
+            _ => Err(LamportError::InvalidBlockStore),
+        }
+    }
+
+    fn flag_offset(&self, index: u32) -> usize {
+        let word_size = self.store.word_size();
+        HEADER_BYTES.next_multiple_of(word_size) + index as usize * word_size
+    }
+
+    fn seed_offset(&self, index: u32) -> usize {
+        let word_size = self.store.word_size();
+        HEADER_BYTES.next_multiple_of(word_size)
+            + self.capacity as usize * word_size
+            + index as usize * SEED_BYTES.next_multiple_of(word_size)
+    }
+
+    fn read_at(&self, mut address: usize, mut buffer: &mut [u8]) -> LamportResult<()> {
+        let page_size = self.store.page_size();
+        while !buffer.is_empty() {
+            let offset = address % page_size;
+            let length = buffer.len().min(page_size - offset);
+            let (head, tail) = buffer.split_at_mut(length);
+            self.store.read(address / page_size, offset, head)?;
+            address += length;
+            buffer = tail;
+        }
+        Ok(())
+    }
+
+    /// Writes the data at a word aligned address, padding it with `0xff` to whole words.
+    fn write_words(&mut self, mut address: usize, data: &[u8]) -> LamportResult<()> {
+        let page_size = self.store.page_size();
+        let mut padded = Zeroizing::new(data.to_vec());
+        padded.resize(data.len().next_multiple_of(self.store.word_size()), 0xff);
+        let mut data = padded.as_slice();
+        while !data.is_empty() {
+            let offset = address % page_size;
+            let length = data.len().min(page_size - offset);
+            self.store
+                .write(address / page_size, offset, &data[..length])?;
+            address += length;
+            data = &data[length..];
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,522 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size, check_security};
+use crate::hors::split_indices;
+use crate::{LamportDigest, LamportError, LamportResult, MerkleSubtree, MultiVec};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const LEAF_DOMAIN: &[u8] = b"lamport_signature_plus fors leaf v1";
+const NODE_DOMAIN: &[u8] = b"lamport_signature_plus fors node v1";
+const ROOTS_DOMAIN: &[u8] = b"lamport_signature_plus fors roots v1";
+
+/// The number of trees `k` and the height `a` of each tree of a FORS key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ForsParams {
+    k: usize,
+    a: u32,
+}
+
+impl ForsParams {
+    /// Supports `k` of 1 to 64 and `a` of 1 to 16.
+    ///
+    /// The digest must have at least `k * a` bits, which is checked when a
+    /// key is created.
+    pub fn new(k: usize, a: u32) -> LamportResult<Self> {
+        if k == 0 || k > 64 || a == 0 || a > 16 {
+            return Err(LamportError::InvalidHorsParameters);
+        }
+        Ok(Self { k, a })
+    }
+
+    /// The number of trees, each contributing one revealed value to a signature.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The height of each tree.
+    pub fn a(&self) -> u32 {
+        self.a
+    }
+
+    /// The number of leaves in each tree.
+    pub fn t(&self) -> usize {
+        1 << self.a
+    }
+
+    /// The bits of security left against forging a signature on a message
+    /// after `signatures` signatures with the same key.
+    ///
+    /// After `r` signatures at most `r` of the `t` leaves of each tree are
+    /// revealed, so a new message is forgeable if its leaf in every tree was
+    /// revealed, with probability `(r / t)^k`, or `k * (a - log2(r))` bits.
+    pub fn security_bits(&self, signatures: u32) -> f64 {
+        let k = f64::from(u8::try_from(self.k).expect("k is at most 64"));
+        if signatures == 0 {
+            return k * f64::from(self.a);
+        }
+        (k * (f64::from(self.a) - f64::from(signatures).log2())).max(0.0)
+    }
+
+    pub(crate) fn check<T: LamportDigest>(&self) -> LamportResult<usize> {
+        let bits = T::digest_size_in_bits();
+        if self.k * self.a as usize > bits {
+            return Err(LamportError::InvalidHorsParameters);
+        }
+        Ok(bits / 8)
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 2] {
+        [
+            u8::try_from(self.k).expect("k is at most 64"),
+            self.a.to_le_bytes()[0],
+        ]
+    }
+
+    pub(crate) fn from_bytes<T: LamportDigest>(input: &[u8]) -> Option<(Self, usize)> {
+        let header = input.get(..2)?;
+        let params = Self::new(usize::from(header[0]), u32::from(header[1])).ok()?;
+        let n = params.check::<T>().ok()?;
+        Some((params, n))
+    }
+
+    fn leaf_hash<T: LamportDigest>(
+        &self,
+        pub_seed: &[u8],
+        tree: usize,
+        leaf: u32,
+        secret: &[u8],
+    ) -> Vec<u8> {
+        let tree = u32::try_from(tree).expect("k is at most 64");
+        T::digest_concat(&[
+            LEAF_DOMAIN,
+            pub_seed,
+            &tree.to_be_bytes(),
+            &leaf.to_be_bytes(),
+            secret,
+        ])
+    }
+
+    fn node_hash<T: LamportDigest>(
+        &self,
+        pub_seed: &[u8],
+        tree: usize,
+        level: u32,
+        index: u32,
+        left: &[u8],
+        right: &[u8],
+    ) -> Vec<u8> {
+        let tree = u32::try_from(tree).expect("k is at most 64");
+        T::digest_concat(&[
+            NODE_DOMAIN,
+            pub_seed,
+            &tree.to_be_bytes(),
+            &level.to_be_bytes(),
+            &index.to_be_bytes(),
+            left,
+            right,
+        ])
+    }
+
+    fn roots_hash<T: LamportDigest>(pub_seed: &[u8], roots: &[Vec<u8>]) -> Vec<u8> {
+        let mut parts = Vec::with_capacity(roots.len() + 2);
+        parts.push(ROOTS_DOMAIN);
+        parts.push(pub_seed);
+        parts.extend(roots.iter().map(Vec::as_slice));
+        T::digest_concat(&parts)
+    }
+}
+
+/// A FORS few-time signing key.
+///
+/// FORS is HORS with the `t` secret values split into `k` sets of `2^a`
+/// and each set compressed into a Merkle tree, so the verifying key is a
+/// single digest of the `k` roots instead of `k * 2^a` digests. A signature
+/// reveals one secret value per tree with its authentication path, chosen
+/// by `a` bits of the message digest each. See [`ForsParams::security_bits`]
+/// for the security left after several signatures. The key refuses to sign
+/// more than the `max_uses` messages it was created for.
+///
+/// The key is stateful. Its canonical bytes include the number of uses and
+/// must be persisted after every signature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForsSigningKey<T: LamportDigest> {
+    pub(crate) params: ForsParams,
+    pub(crate) max_uses: u32,
+    pub(crate) uses: u32,
+    pub(crate) pub_seed: Vec<u8>,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(ForsSigningKey);
+vec_impl!(ForsSigningKey);
+
+impl<T: LamportDigest> Zeroize for ForsSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> ForsSigningKey<T> {
+    /// Constructs a [`ForsSigningKey`] that signs at most `max_uses` messages.
+    pub fn random(
+        params: ForsParams,
+        max_uses: u32,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let n = params.check::<T>()?;
+        if max_uses == 0 {
+            return Err(LamportError::InvalidHorsParameters);
+        }
+        let mut pub_seed = vec![0u8; n];
+        rng.fill_bytes(&mut pub_seed);
+        let mut data = vec![0u8; params.k * params.t() * n];
+        rng.fill_bytes(&mut data);
+        Ok(Self {
+            params,
+            max_uses,
+            uses: 0,
+            pub_seed,
+            values: MultiVec {
+                data,
+                axes: [params.k * params.t(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The parameters.
+    pub fn params(&self) -> ForsParams {
+        self.params
+    }
+
+    /// The number of messages the key was created to sign.
+    pub fn max_uses(&self) -> u32 {
+        self.max_uses
+    }
+
+    /// The number of messages the key can still sign.
+    pub fn remaining(&self) -> u32 {
+        self.max_uses - self.uses
+    }
+
+    /// The bits of security left once the key has signed `max_uses` messages.
+    pub fn security_bits(&self) -> f64 {
+        self.params.security_bits(self.max_uses)
+    }
+
+    /// Signs the data.
+    ///
+    /// Returns [`LamportError::PrivateKeyReuseError`] once the key has signed
+    /// `max_uses` messages.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<ForsSignature<T>> {
+        if self.uses >= self.max_uses {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        self.uses += 1;
+        self.sign_digest(&digest)
+    }
+
+    /// Signs a digest of at least `k * a` bits without counting the use.
+    pub(crate) fn sign_digest(&self, digest: &[u8]) -> LamportResult<ForsSignature<T>> {
+        let n = self.values.axes[1];
+        let t = self.params.t();
+        let mut data = Vec::with_capacity(self.params.k * (self.params.a as usize + 1) * n);
+        for (tree, leaf) in split_indices(digest, self.params.k, self.params.a)
+            .into_iter()
+            .enumerate()
+        {
+            let secret = &self.values.data[(tree * t + leaf) * n..(tree * t + leaf + 1) * n];
+            data.extend_from_slice(secret);
+            let leaf = u32::try_from(leaf).expect("a is at most 16");
+            for node in self.tree(tree).auth_path(leaf)? {
+                data.extend_from_slice(&node);
+            }
+        }
+        Ok(ForsSignature {
+            params: self.params,
+            values: MultiVec {
+                data,
+                axes: [self.params.k * (self.params.a as usize + 1), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The digest of the roots of every tree.
+    pub(crate) fn root(&self) -> Vec<u8> {
+        let roots = (0..self.params.k)
+            .map(|tree| self.tree(tree).root().to_vec())
+            .collect::<Vec<_>>();
+        ForsParams::roots_hash::<T>(&self.pub_seed, &roots)
+    }
+
+    fn tree(&self, tree: usize) -> MerkleSubtree {
+        let n = self.values.axes[1];
+        let t = self.params.t();
+        let params = self.params;
+        let pub_seed = &self.pub_seed;
+        MerkleSubtree::build(
+            params.a,
+            |leaf| {
+                let start = (tree * t + leaf as usize) * n;
+                params.leaf_hash::<T>(pub_seed, tree, leaf, &self.values.data[start..start + n])
+            },
+            |level, index, left, right| {
+                params.node_hash::<T>(pub_seed, tree, level, index, left, right)
+            },
+        )
+    }
+
+    /// Converts the [`ForsSigningKey`] to canonical bytes.
+    ///
+    /// The format is `k` and `a` as single bytes, the maximum and current
+    /// uses as big endian `u32`s, the public seed, then the secret values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(10 + self.pub_seed.len() + self.values.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.max_uses.to_be_bytes());
+        bytes.extend_from_slice(&self.uses.to_be_bytes());
+        bytes.extend_from_slice(&self.pub_seed);
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`ForsSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let (params, n) =
+            ForsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 10 + (1 + params.k * params.t()) * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let max_uses = u32::from_be_bytes([input[2], input[3], input[4], input[5]]);
+        let uses = u32::from_be_bytes([input[6], input[7], input[8], input[9]]);
+        if max_uses == 0 || uses > max_uses {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (pub_seed, values) = input[10..].split_at(n);
+        Ok(Self {
+            params,
+            max_uses,
+            uses,
+            pub_seed: pub_seed.to_vec(),
+            values: MultiVec {
+                data: values.to_vec(),
+                axes: [params.k * params.t(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A FORS verifying key, the public seed and the digest of the tree roots.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForsVerifyingKey<T: LamportDigest> {
+    pub(crate) params: ForsParams,
+    pub(crate) pub_seed: Vec<u8>,
+    pub(crate) root: Vec<u8>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(ForsVerifyingKey);
+vec_impl!(ForsVerifyingKey);
+
+impl<T: LamportDigest> From<&ForsSigningKey<T>> for ForsVerifyingKey<T> {
+    fn from(value: &ForsSigningKey<T>) -> Self {
+        Self {
+            params: value.params,
+            pub_seed: value.pub_seed.clone(),
+            root: value.root(),
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> ForsVerifyingKey<T> {
+    /// The parameters.
+    pub fn params(&self) -> ForsParams {
+        self.params
+    }
+
+    /// Verifies the signature.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &ForsSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        if signature.params != self.params || signature.values.axes[1] != self.root.len() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        let root = signature.recover_root(&self.pub_seed, &digest);
+        if root.ct_eq(&self.root).into() {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the [`ForsVerifyingKey`] to canonical bytes,
+    /// `k` and `a` as single bytes, the public seed, then the root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.pub_seed.len() + self.root.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.pub_seed);
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    /// Constructs a [`ForsVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let (params, n) =
+            ForsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 2 + 2 * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (pub_seed, root) = input[2..].split_at(n);
+        Ok(Self {
+            params,
+            pub_seed: pub_seed.to_vec(),
+            root: root.to_vec(),
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature generated by [`ForsSigningKey`], a secret value and an
+/// authentication path for each tree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForsSignature<T: LamportDigest> {
+    pub(crate) params: ForsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(ForsSignature);
+vec_impl!(ForsSignature);
+
+impl<T: LamportDigest> ForsSignature<T> {
+    /// Recomputes the digest of the tree roots of the key with public seed
+    /// `pub_seed`, giving the key's root if the signature is valid.
+    pub(crate) fn recover_root(&self, pub_seed: &[u8], digest: &[u8]) -> Vec<u8> {
+        let n = self.values.axes[1];
+        let params = self.params;
+        let roots = self
+            .values
+            .data
+            .chunks_exact((params.a as usize + 1) * n)
+            .zip(split_indices(digest, params.k, params.a))
+            .enumerate()
+            .map(|(tree, (values, leaf))| {
+                let leaf = u32::try_from(leaf).expect("a is at most 16");
+                let (secret, path) = values.split_at(n);
+                let path = path.chunks_exact(n).map(<[u8]>::to_vec).collect::<Vec<_>>();
+                MerkleSubtree::root_from_path(
+                    leaf,
+                    &params.leaf_hash::<T>(pub_seed, tree, leaf, secret),
+                    &path,
+                    |level, index, left, right| {
+                        params.node_hash::<T>(pub_seed, tree, level, index, left, right)
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        ForsParams::roots_hash::<T>(pub_seed, &roots)
+    }
+
+    /// Converts the [`ForsSignature`] to canonical bytes, `k` and `a` as
+    /// single bytes then the secret value and path of each tree.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.values.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`ForsSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let (params, n) =
+            ForsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidSignatureBytes)?;
+        let rows = params.k * (params.a as usize + 1);
+        if input.len() != 2 + rows * n {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[2..].to_vec(),
+                axes: [rows, n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn fors() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let params = ForsParams::new(8, 4).unwrap();
+        assert_eq!(params.t(), 16);
+        assert_eq!(params.security_bits(0), 32.0);
+        assert_eq!(params.security_bits(4), 16.0);
+
+        let mut sk = ForsSigningKey::<D>::random(params, 3, &mut rng).unwrap();
+        let pk = ForsVerifyingKey::from(&sk);
+        assert_eq!(pk.to_bytes().len(), 2 + 64);
+        assert_eq!(sk.security_bits(), 8.0 * (4.0 - 3f64.log2()));
+        for message in [&b"first"[..], b"second", b"third"] {
+            let signature = sk.sign(message).unwrap();
+            assert_eq!(signature.to_bytes().len(), 2 + 8 * 5 * 32);
+            assert!(pk.verify(&signature, message).is_ok());
+            assert!(pk.verify(&signature, b"other").is_err());
+        }
+        assert_eq!(sk.remaining(), 0);
+        assert_eq!(
+            sk.sign(b"fourth").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+
+        let mut sk = ForsSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(sk.remaining(), 0);
+        sk.max_uses = 4;
+        let signature = sk.sign(b"fourth").unwrap();
+        let mut bytes = signature.to_bytes();
+        let decoded = ForsSignature::<D>::from_bytes(&bytes).unwrap();
+        let pk = ForsVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        assert!(pk.verify(&decoded, b"fourth").is_ok());
+        bytes[40] ^= 1;
+        let tampered = ForsSignature::<D>::from_bytes(&bytes).unwrap();
+        assert!(pk.verify(&tampered, b"fourth").is_err());
+
+        assert!(ForsParams::new(65, 4).is_err());
+        assert!(ForsParams::new(8, 17).is_err());
+        let too_large = ForsParams::new(64, 5).unwrap();
+        assert_eq!(
+            ForsSigningKey::<D>::random(too_large, 1, &mut rng).unwrap_err(),
+            LamportError::InvalidHorsParameters
+        );
+    }
+}
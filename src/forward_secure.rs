@@ -0,0 +1,405 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::{
+    InclusionProof, KeyId, LamportDigest, LamportError, LamportResult, MerkleTree, Progress,
+    Signature, SigningKey, VerifyingKey, MERKLE_HASH_BYTES, SEED_BYTES,
+};
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+use zeroize::{Zeroize, Zeroizing};
+
+const EVOLVE_DOMAIN: &[u8] = b"lamport_signature_plus forward secure evolve v1";
+const MESSAGE_DOMAIN: &[u8] = b"lamport_signature_plus forward secure message v1";
+
+/// A signer whose one-time key changes every period and cannot be wound back.
+///
+/// The key for period `i` is [`SigningKey::from_seed`] of `seed_i`, where
+/// `seed_{i+1} = SHA-256(tag || seed_i)`. [`ForwardSecureKey::advance`]
+/// overwrites the current seed with the next one, so someone who steals the
+/// state in period `i` cannot sign for any earlier period. The fingerprints
+/// of every period key are the leaves of a [`MerkleTree`] whose root, in a
+/// [`ForwardSecurePublicKey`], is all a verifier needs.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForwardSecureKey<T: LamportDigest> {
+    pub(crate) seed: [u8; SEED_BYTES],
+    pub(crate) period: u64,
+    pub(crate) used: bool,
+    pub(crate) tree: MerkleTree,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(@limits ForwardSecureKey);
+vec_impl!(ForwardSecureKey);
+
+impl<T: LamportDigest> Zeroize for ForwardSecureKey<T> {
+    fn zeroize(&mut self) {
+        self.seed.zeroize();
+    }
+}
+
+/// The long term public key of a [`ForwardSecureKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForwardSecurePublicKey<T: LamportDigest> {
+    pub(crate) root: [u8; MERKLE_HASH_BYTES],
+    pub(crate) periods: u64,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(ForwardSecurePublicKey);
+vec_impl!(ForwardSecurePublicKey);
+
+/// A signature made by a [`ForwardSecureKey`] in a specific period.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForwardSecureSignature<T: LamportDigest> {
+    pub(crate) period: u64,
+    pub(crate) signature: Signature<T>,
+    pub(crate) verifying_key: VerifyingKey<T>,
+    pub(crate) proof: InclusionProof,
+}
+
+serde_impl!(@limits ForwardSecureSignature);
+vec_impl!(ForwardSecureSignature);
+
+impl<T: LamportDigest> ForwardSecureKey<T> {
+    /// Creates a key for `periods` periods starting from `seed`.
+    ///
+    /// Every period key is derived once here to build the public tree.
+    pub fn new(seed: [u8; SEED_BYTES], periods: u64) -> LamportResult<Self> {
+        Self::new_with_progress(seed, periods, &mut Progress::new())
+    }
+
+    /// Creates a key like [`ForwardSecureKey::new`], reporting each derived
+    /// period key and stopping early if cancelled.
+    pub fn new_with_progress(
+        seed: [u8; SEED_BYTES],
+        periods: u64,
+        progress: &mut Progress<'_>,
+    ) -> LamportResult<Self> {
+        if periods == 0 {
+            return Err(LamportError::General(
+                "a forward secure key requires at least one period".to_string(),
+            ));
+        }
+        let mut leaves = Vec::with_capacity(usize::try_from(periods).unwrap_or_default());
+        let mut current = Zeroizing::new(seed);
+        for period in 0..periods {
+            progress.check()?;
+            leaves.push(period_key_id::<T>(&current)?);
+            evolve(&mut current);
+            progress.report(period + 1, periods);
+        }
+        Ok(Self {
+            seed,
+            period: 0,
+            used: false,
+            tree: MerkleTree::new(leaves)?,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The public key that verifies signatures from every period.
+    pub fn public_key(&self) -> ForwardSecurePublicKey<T> {
+        ForwardSecurePublicKey {
+            root: self.tree.root(),
+            periods: self.periods(),
+            algorithm: PhantomData,
+        }
+    }
+
+    /// The current period.
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    /// The total number of periods.
+    pub fn periods(&self) -> u64 {
+        self.tree.len() as u64
+    }
+
+    /// Has the key for the current period been used.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Moves to the next period, erasing the current seed.
+    ///
+    /// Returns an error once the last period has been reached.
+    pub fn advance(&mut self) -> LamportResult<()> {
+        if self.period + 1 >= self.periods() {
+            return Err(LamportError::General(
+                "the forward secure key has no periods left".to_string(),
+            ));
+        }
+        evolve(&mut self.seed);
+        self.period += 1;
+        self.used = false;
+        Ok(())
+    }
+
+    /// Signs the data with the key for the current period.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<ForwardSecureSignature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let mut signing_key = SigningKey::<T>::from_seed(&self.seed)?;
+        let verifying_key = VerifyingKey::from(&signing_key);
+        let signature = signing_key.sign(period_message(self.period, data.as_ref()));
+        signing_key.zeroize();
+        let proof = self
+            .tree
+            .prove(usize::try_from(self.period).map_err(|_| LamportError::InvalidMerkleProof)?)?;
+        self.used = true;
+        Ok(ForwardSecureSignature {
+            period: self.period,
+            signature: signature?,
+            verifying_key,
+            proof,
+        })
+    }
+
+    /// Converts the [`ForwardSecureKey`] to canonical bytes.
+    ///
+    /// The format is the current seed, the period as a big endian `u64`, the
+    /// used flag, and the period count as a big endian `u64` followed by the
+    /// [`KeyId`] of every period key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SEED_BYTES + 17 + self.tree.len() * KeyId::BYTES);
+        bytes.extend_from_slice(&self.seed);
+        bytes.extend_from_slice(&self.period.to_be_bytes());
+        bytes.push(self.used as u8);
+        bytes.extend_from_slice(&self.periods().to_be_bytes());
+        for leaf in self.tree.leaves() {
+            bytes.extend_from_slice(leaf.as_bytes());
+        }
+        bytes
+    }
+
+    /// Constructs a [`ForwardSecureKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`ForwardSecureKey`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        if input.len() < SEED_BYTES + 17
+            || !(input.len() - SEED_BYTES - 17).is_multiple_of(KeyId::BYTES)
+        {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let mut seed = [0u8; SEED_BYTES];
+        seed.copy_from_slice(&input[..SEED_BYTES]);
+        let period = read_u64(&input[SEED_BYTES..SEED_BYTES + 8])?;
+        let used = match input[SEED_BYTES + 8] {
+            0 => false,
+            1 => true,
+            _ => return Err(LamportError::InvalidPrivateKeyBytes),
+        };
+        let periods = read_u64(&input[SEED_BYTES + 9..SEED_BYTES + 17])?;
+        let leaves = input[SEED_BYTES + 17..]
+            .chunks_exact(KeyId::BYTES)
+            .map(KeyId::from_bytes)
+            .collect::<LamportResult<Vec<_>>>()?;
+        if leaves.len() as u64 != periods || period >= periods {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let index = usize::try_from(period).map_err(|_| LamportError::InvalidPrivateKeyBytes)?;
+        if period_key_id::<T>(&seed)? != leaves[index] {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            seed,
+            period,
+            used,
+            tree: MerkleTree::new(leaves)?,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> ForwardSecurePublicKey<T> {
+    /// The root of the tree of period keys.
+    pub fn root(&self) -> [u8; MERKLE_HASH_BYTES] {
+        self.root
+    }
+
+    /// The total number of periods.
+    pub fn periods(&self) -> u64 {
+        self.periods
+    }
+
+    /// Verifies a [`ForwardSecureSignature`] made in `period` over the data.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &ForwardSecureSignature<T>,
+        period: u64,
+        data: B,
+    ) -> LamportResult<()> {
+        if signature.period != period
+            || signature.proof.index() != period
+            || signature.proof.tree_size() != self.periods
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        signature
+            .proof
+            .verify_key(&signature.verifying_key, &self.root)?;
+        signature
+            .verifying_key
+            .verify(&signature.signature, period_message(period, data.as_ref()))
+    }
+
+    /// Converts the [`ForwardSecurePublicKey`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MERKLE_HASH_BYTES + 8);
+        bytes.extend_from_slice(&self.root);
+        bytes.extend_from_slice(&self.periods.to_be_bytes());
+        bytes
+    }
+
+    /// Constructs a [`ForwardSecurePublicKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        if input.len() != MERKLE_HASH_BYTES + 8 {
+            return Err(LamportError::InvalidKeyIdBytes);
+        }
+        let mut root = [0u8; MERKLE_HASH_BYTES];
+        root.copy_from_slice(&input[..MERKLE_HASH_BYTES]);
+        Ok(Self {
+            root,
+            periods: read_u64(&input[MERKLE_HASH_BYTES..])?,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> ForwardSecureSignature<T> {
+    /// The period the signature was made in.
+    pub fn period(&self) -> u64 {
+        self.period
+    }
+
+    /// The one-time signature.
+    pub fn signature(&self) -> &Signature<T> {
+        &self.signature
+    }
+
+    /// The one-time key of the period.
+    pub fn verifying_key(&self) -> &VerifyingKey<T> {
+        &self.verifying_key
+    }
+
+    /// Converts the [`ForwardSecureSignature`] to canonical bytes.
+    ///
+    /// The format is the period as a big endian `u64`, the signature, the
+    /// verifying key and the inclusion proof of the key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let signature = self.signature.to_bytes();
+        let verifying_key = self.verifying_key.to_bytes();
+        let proof = self.proof.to_bytes();
+        let mut bytes = Vec::with_capacity(8 + signature.len() + verifying_key.len() + proof.len());
+        bytes.extend_from_slice(&self.period.to_be_bytes());
+        bytes.extend_from_slice(&signature);
+        bytes.extend_from_slice(&verifying_key);
+        bytes.extend_from_slice(&proof);
+        bytes
+    }
+
+    /// Constructs a [`ForwardSecureSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`ForwardSecureSignature`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let bits = T::digest_size_in_bits();
+        let signature_length = bits * (bits / 8);
+        let key_length = signature_length * 2;
+        if input.len() < 8 + signature_length + key_length {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (period, rest) = input.split_at(8);
+        let (signature, rest) = rest.split_at(signature_length);
+        let (verifying_key, proof) = rest.split_at(key_length);
+        Ok(Self {
+            period: read_u64(period)?,
+            signature: Signature::from_bytes(signature)?,
+            verifying_key: VerifyingKey::from_bytes(verifying_key)?,
+            proof: InclusionProof::from_bytes(proof)?,
+        })
+    }
+}
+
+fn evolve(seed: &mut [u8; SEED_BYTES]) {
+    let mut hasher = Sha256::new();
+    hasher.update(EVOLVE_DOMAIN);
+    hasher.update(&seed[..]);
+    let next: [u8; SEED_BYTES] = hasher.finalize().into();
+    seed.zeroize();
+    *seed = next;
+}
+
+fn period_key_id<T: LamportDigest>(seed: &[u8; SEED_BYTES]) -> LamportResult<KeyId> {
+    let mut signing_key = SigningKey::<T>::from_seed(seed)?;
+    let key_id = VerifyingKey::from(&signing_key).fingerprint();
+    signing_key.zeroize();
+    Ok(key_id)
+}
+
+fn period_message(period: u64, data: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(MESSAGE_DOMAIN.len() + 8 + data.len());
+    message.extend_from_slice(MESSAGE_DOMAIN);
+    message.extend_from_slice(&period.to_be_bytes());
+    message.extend_from_slice(data);
+    message
+}
+
+fn read_u64(input: &[u8]) -> LamportResult<u64> {
+    <[u8; 8]>::try_from(input)
+        .map(u64::from_be_bytes)
+        .map_err(|_| LamportError::InvalidPrivateKeyBytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+
+    #[test]
+    fn forward_secure() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut sk = ForwardSecureKey::<D>::new([3u8; SEED_BYTES], 4).unwrap();
+        let pk = sk.public_key();
+        assert_eq!(pk.periods(), 4);
+
+        let first = sk.sign(b"period 0").unwrap();
+        assert!(sk.sign(b"period 0").is_err());
+        assert!(pk.verify(&first, 0, b"period 0").is_ok());
+        assert!(pk.verify(&first, 1, b"period 0").is_err());
+        assert!(pk.verify(&first, 0, b"period 1").is_err());
+
+        sk.advance().unwrap();
+        let restored = ForwardSecureKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(restored.period(), 1);
+        assert_eq!(restored.public_key().to_bytes(), pk.to_bytes());
+        let mut sk = restored;
+        let second = sk.sign(b"period 1").unwrap();
+        let second = ForwardSecureSignature::<D>::from_bytes(second.to_bytes()).unwrap();
+        assert!(pk.verify(&second, 1, b"period 1").is_ok());
+        assert_ne!(first.verifying_key(), second.verifying_key());
+
+        sk.advance().unwrap();
+        sk.advance().unwrap();
+        assert!(sk.advance().is_err());
+        let pk = ForwardSecurePublicKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        assert!(pk.verify(&first, 0, b"period 0").is_ok());
+
+        let mut tampered = sk.to_bytes();
+        tampered[0] ^= 1;
+        assert!(ForwardSecureKey::<D>::from_bytes(tampered).is_err());
+    }
+}
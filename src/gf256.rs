@@ -0,0 +1,292 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! GF(256) arithmetic for the threshold layer.
+//!
+//! The field uses the AES polynomial x^8 + x^4 + x^3 + x + 1, the same as
+//! vsss_rs's `Gf256`, so shares are interchangeable with it.
+//!
+//! The log and antilog tables are only indexed by share identifiers, which
+//! are public. Secret bytes are multiplied with [`PublicMul`], which selects
+//! precomputed multiples of the public operand with masks instead of
+//! indexing memory with the secret.
+use crate::{LamportError, LamportResult};
+use rand::RngCore;
+use zeroize::Zeroize;
+
+const EXP: [u8; 510] = exp_table();
+const LOG: [u8; 256] = log_table();
+
+/// Powers of the generator 3, repeated so the sum of two logs can index it directly.
+const fn exp_table() -> [u8; 510] {
+    let mut table = [0u8; 510];
+    let mut x = 1u8;
+    let mut i = 0;
+    while i < 255 {
+        table[i] = x;
+        table[i + 255] = x;
+        x ^= xtime(x);
+        i += 1;
+    }
+    table
+}
+
+const fn log_table() -> [u8; 256] {
+    let exp = exp_table();
+    let mut table = [0u8; 256];
+    let mut i = 0u8;
+    while i < 255 {
+        table[exp[i as usize] as usize] = i;
+        i += 1;
+    }
+    table
+}
+
+/// Multiplies by x modulo the field polynomial.
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ (0x1b & 0u8.wrapping_sub(a >> 7))
+}
+
+/// Multiplies two public field elements.
+pub(crate) fn mul(a: u8, b: u8) -> u8 {
+    if a == 0 || b == 0 {
+        0
+    } else {
+        EXP[usize::from(LOG[usize::from(a)]) + usize::from(LOG[usize::from(b)])]
+    }
+}
+
+/// Inverts a public, non-zero field element.
+pub(crate) fn invert(a: u8) -> u8 {
+    debug_assert_ne!(a, 0);
+    EXP[255 - usize::from(LOG[usize::from(a)])]
+}
+
+/// Multiplication of secret field elements by a public constant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct PublicMul([u8; 8]);
+
+impl PublicMul {
+    /// Precomputes the constant times each power of two.
+    pub(crate) fn new(constant: u8) -> Self {
+        let mut multiples = [constant; 8];
+        for i in 1..8 {
+            multiples[i] = xtime(multiples[i - 1]);
+        }
+        Self(multiples)
+    }
+
+    /// Adds the product of each byte of `secret` and the constant to `output`.
+    ///
+    /// Eight bytes are processed at once in a `u64`, with each bit of the
+    /// secret bytes expanded to a byte mask.
+    pub(crate) fn mul_add_assign(&self, secret: &[u8], output: &mut [u8]) {
+        debug_assert_eq!(secret.len(), output.len());
+        const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+        let mut secret_words = secret.chunks_exact(8);
+        let mut output_words = output.chunks_exact_mut(8);
+        for (s, o) in (&mut secret_words).zip(&mut output_words) {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(s);
+            let w = u64::from_le_bytes(word);
+            let mut r = 0u64;
+            for (i, m) in self.0.iter().enumerate() {
+                let mask = ((w >> i) & LOW_BITS) * 0xff;
+                r ^= mask & (u64::from(*m) * LOW_BITS);
+            }
+            word.copy_from_slice(o);
+            let sum = u64::from_le_bytes(word) ^ r;
+            o.copy_from_slice(&sum.to_le_bytes());
+        }
+        for (s, o) in secret_words
+            .remainder()
+            .iter()
+            .zip(output_words.into_remainder())
+        {
+            *o ^= self.mul(*s);
+        }
+    }
+
+    /// Multiplies `secret` by the constant in constant time.
+    pub(crate) fn mul(&self, secret: u8) -> u8 {
+        self.0.iter().enumerate().fold(0u8, |r, (i, m)| {
+            r ^ (m & 0u8.wrapping_sub((secret >> i) & 1))
+        })
+    }
+}
+
+/// Splits secret bytes into Shamir shares for the identifiers `1..=shares`.
+#[derive(Debug)]
+pub(crate) struct ShareSplitter {
+    points: Vec<PublicMul>,
+    coefficients: Vec<u8>,
+}
+
+impl Drop for ShareSplitter {
+    fn drop(&mut self) {
+        self.coefficients.zeroize();
+    }
+}
+
+impl ShareSplitter {
+    pub(crate) fn new(threshold: usize, shares: usize) -> LamportResult<Self> {
+        if shares < threshold {
+            return Err(LamportError::VsssError(
+                vsss_rs::Error::SharingLimitLessThanThreshold,
+            ));
+        }
+        if threshold < 2 {
+            return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
+        }
+        let points = (1..=shares)
+            .map(|x| u8::try_from(x).map(PublicMul::new))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| {
+                LamportError::General(format!("unable to create identifier for {}", shares))
+            })?;
+        Ok(Self {
+            points,
+            coefficients: vec![0u8; threshold - 1],
+        })
+    }
+
+    /// Writes the share of `secret` for identifier `i + 1` to `output[i]`.
+    pub(crate) fn split(&mut self, secret: u8, mut rng: impl RngCore, output: &mut [u8]) {
+        debug_assert_eq!(output.len(), self.points.len());
+        rng.fill_bytes(&mut self.coefficients);
+        for (o, x) in output.iter_mut().zip(&self.points) {
+            // Horner's rule from the highest coefficient down to the secret
+            let mut y = 0u8;
+            for c in self.coefficients.iter().rev() {
+                y = x.mul(y ^ c);
+            }
+            *o = y ^ secret;
+        }
+    }
+}
+
+/// Recombines Shamir shares at zero.
+///
+/// The Lagrange basis only depends on the identifiers, so it is computed once
+/// and applied to whole rows of share bytes.
+#[derive(Debug)]
+pub(crate) struct Interpolator {
+    basis: Vec<PublicMul>,
+}
+
+impl Interpolator {
+    pub(crate) fn new(identifiers: &[u8]) -> LamportResult<Self> {
+        if identifiers.len() < 2 {
+            return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
+        }
+        if identifiers.contains(&0) {
+            return Err(LamportError::VsssError(
+                vsss_rs::Error::SharingInvalidIdentifier,
+            ));
+        }
+        let basis = identifiers
+            .iter()
+            .enumerate()
+            .map(|(i, x_i)| {
+                let mut numerator = 1u8;
+                let mut denominator = 1u8;
+                for (j, x_j) in identifiers.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    if x_i == x_j {
+                        return Err(LamportError::VsssError(
+                            vsss_rs::Error::SharingDuplicateIdentifier,
+                        ));
+                    }
+                    numerator = mul(numerator, *x_j);
+                    denominator = mul(denominator, x_j ^ x_i);
+                }
+                Ok(PublicMul::new(mul(numerator, invert(denominator))))
+            })
+            .collect::<LamportResult<Vec<_>>>()?;
+        Ok(Self { basis })
+    }
+
+    /// Writes the secret bytes to `output` from whole rows of share values,
+    /// in the same order as the identifiers.
+    pub(crate) fn combine_slices<'a>(
+        &self,
+        values: impl IntoIterator<Item = &'a [u8]>,
+        output: &mut [u8],
+    ) {
+        output.fill(0);
+        for (basis, y) in self.basis.iter().zip(values) {
+            basis.mul_add_assign(y, output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn gf256_wide_combine() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut rows = vec![vec![0u8; 27]; 3];
+        for row in rows.iter_mut() {
+            rng.fill_bytes(row);
+        }
+        let interpolator = Interpolator::new(&[1, 4, 9]).unwrap();
+        let mut output = [0xffu8; 27];
+        interpolator.combine_slices(rows.iter().map(|r| r.as_slice()), &mut output);
+        for (i, o) in output.iter().enumerate() {
+            let mut byte = [0u8];
+            interpolator.combine_slices(rows.iter().map(|r| &r[i..=i]), &mut byte);
+            assert_eq!(*o, byte[0]);
+        }
+    }
+
+    #[test]
+    fn gf256_matches_vsss() {
+        use vsss_rs::{combine_shares, shamir, Gf256};
+
+        for a in 0..=255u8 {
+            let public = PublicMul::new(a);
+            for b in 0..=255u8 {
+                let expected = (Gf256(a) * Gf256(b)).0;
+                assert_eq!(mul(a, b), expected);
+                assert_eq!(public.mul(b), expected);
+            }
+        }
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut splitter = ShareSplitter::new(3, 5).unwrap();
+        let mut shares = [0u8; 5];
+        splitter.split(0xa7, &mut rng, &mut shares);
+        let vsss_shares = [[2u8, shares[1]], [4, shares[3]], [5, shares[4]]];
+        assert_eq!(
+            combine_shares::<Gf256, u8, [u8; 2]>(&vsss_shares)
+                .unwrap()
+                .0,
+            0xa7
+        );
+
+        let vsss_shares =
+            shamir::split_secret::<Gf256, u8, [u8; 2]>(3, 5, Gf256(0x5c), &mut rng).unwrap();
+        let interpolator = Interpolator::new(&[1, 3, 5]).unwrap();
+        let mut secret = [0u8];
+        interpolator.combine_slices(
+            [
+                &vsss_shares[0][1..],
+                &vsss_shares[2][1..],
+                &vsss_shares[4][1..],
+            ],
+            &mut secret,
+        );
+        assert_eq!(secret[0], 0x5c);
+
+        assert!(Interpolator::new(&[1, 1, 2]).is_err());
+        assert!(Interpolator::new(&[0, 1]).is_err());
+        assert!(ShareSplitter::new(4, 3).is_err());
+    }
+}
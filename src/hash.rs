@@ -2,7 +2,7 @@
     Copyright Michael Lodder. All Rights Reserved.
     SPDX-License-Identifier: Apache-2.0
 */
-use crate::MultiVec;
+use crate::{LamportError, LamportResult, MultiVec};
 use digest::core_api::BlockSizeUser;
 use digest::{
     generic_array::typenum::Unsigned, Digest, ExtendableOutput, FixedOutput, HashMarker, Update,
@@ -12,30 +12,81 @@ use rand::{CryptoRng, RngCore};
 use std::marker::PhantomData;
 
 /// A trait for providing Lamport supported digest functions.
+///
+/// The trait can be implemented for any hash backend, or with
+/// [`impl_lamport_digest`](crate::impl_lamport_digest) for plain functions.
+/// Implementations must uphold these invariants, which are checked where a
+/// violation could otherwise weaken the scheme:
+///
+/// - [`digest_size_in_bits`](LamportDigest::digest_size_in_bits) is a non-zero multiple of 8.
+/// - [`digest`](LamportDigest::digest) and [`digest_concat`](LamportDigest::digest_concat)
+///   always return exactly that many bits.
+/// - [`random`](LamportDigest::random) and [`random_pair`](LamportDigest::random_pair)
+///   return values with axes `[bits, bits / 8]`.
+///
+/// Signing and verifying return [`LamportError::InvalidDigest`] when a message
+/// digest has the wrong length, so a short digest cannot sign or verify fewer
+/// bits than the key commits to. Generating a key from malformed random values,
+/// or with a digest whose output does not have the declared size, returns the
+/// same error.
+///
+/// Digest sizes that are not a whole number of bytes are rejected with
+/// [`LamportError::InvalidDigest`] rather than rounded, because keys,
+/// signatures and their byte encodings all store `bits / 8` bytes per row.
+///
+/// Signing keys can only be generated or loaded for digests of at least
+/// [`RECOMMENDED_DIGEST_BITS`], or at least [`MIN_DIGEST_BITS`] with
+/// [`ALLOW_REDUCED_SECURITY`](LamportDigest::ALLOW_REDUCED_SECURITY), e.g.
+/// through [`LamportReducedDigest`]. Verifying keys and signatures load for
+/// any digest, so signatures issued before the check can still be verified.
 pub trait LamportDigest {
+    /// Accept digests shorter than [`RECOMMENDED_DIGEST_BITS`] but at least
+    /// [`MIN_DIGEST_BITS`], which give less than 112 bits of security against
+    /// quantum preimage search.
+    const ALLOW_REDUCED_SECURITY: bool = false;
+    /// Accept digests of any size.
+    ///
+    /// Only for tests and research that need a deliberately broken digest.
+    const ALLOW_INSECURE: bool = false;
+
     /// The size of the digest in bits.
     fn digest_size_in_bits() -> usize;
     /// Compute the digest on the provided data.
     fn digest(data: &[u8]) -> Vec<u8>;
 
+    /// Compute the digest on the concatenation of `parts`.
+    ///
+    /// The default copies the parts into one buffer. Implementations backed
+    /// by an incremental hasher should feed each part directly instead.
+    fn digest_concat(parts: &[&[u8]]) -> Vec<u8> {
+        Self::digest(&parts.concat())
+    }
+
     /// Hash the input [`MultiVec`] data to output another [`MultiVec`].
-    fn hash(data: &MultiVec<u8, 2>) -> MultiVec<u8, 2> {
+    ///
+    /// Returns [`LamportError::InvalidDigest`] if the data or a digest does
+    /// not have the declared digest size.
+    fn hash(data: &MultiVec<u8, 2>) -> LamportResult<MultiVec<u8, 2>> {
         let bits = Self::digest_size_in_bits();
         let bytes = bits / 8;
-        assert_eq!(data.axes[0], bits);
-        assert_eq!(data.axes[1], bytes);
+        if bytes == 0 || data.axes != [bits, bytes] || data.data.len() != bits * bytes {
+            return Err(LamportError::InvalidDigest);
+        }
         let mut outer = MultiVec::fill([bits, bytes], 0);
         {
             let mut outer_iter = outer.iter_mut();
 
             for row in data.data.chunks_exact(bytes) {
                 let hashed = Self::digest(row);
+                if hashed.len() != bytes {
+                    return Err(LamportError::InvalidDigest);
+                }
                 for byte in hashed.iter() {
                     *outer_iter.next().expect("another value") = *byte;
                 }
             }
         }
-        outer
+        Ok(outer)
     }
 
     /// Generate a random [`MultiVec`] data.
@@ -49,6 +100,201 @@ pub trait LamportDigest {
             axes: [bits, bytes],
         }
     }
+
+    /// Generate two random [`MultiVec`]s with a single call to the RNG.
+    ///
+    /// For stream RNGs the output is identical to calling [`LamportDigest::random`] twice.
+    fn random_pair(mut rng: impl RngCore + CryptoRng) -> (MultiVec<u8, 2>, MultiVec<u8, 2>) {
+        let bits = Self::digest_size_in_bits();
+        let bytes = bits / 8;
+        let mut first = vec![0u8; bits * bytes * 2];
+        rng.fill_bytes(&mut first);
+        let second = first.split_off(bits * bytes);
+        (
+            MultiVec {
+                data: first,
+                axes: [bits, bytes],
+            },
+            MultiVec {
+                data: second,
+                axes: [bits, bytes],
+            },
+        )
+    }
+}
+
+/// A [`LamportDigest`] that can hash a message fed in pieces.
+///
+/// The result must equal [`LamportDigest::digest`] of the concatenated
+/// pieces, so messages can be streamed without being buffered.
+pub trait LamportIncrementalDigest: LamportDigest {
+    /// The hashing state.
+    type Hasher;
+
+    /// Starts hashing a message.
+    fn hasher() -> Self::Hasher;
+    /// Feeds the next piece of the message.
+    fn update(hasher: &mut Self::Hasher, data: &[u8]);
+    /// Finishes hashing and returns the digest.
+    fn finalize(hasher: Self::Hasher) -> Vec<u8>;
+}
+
+/// A [`LamportDigest`] that can hash each secret value with a tweak.
+///
+/// [`TweakableSigningKey`](crate::TweakableSigningKey)s commit to every
+/// secret value with [`tweak_hash`](LamportTweakableDigest::tweak_hash) of
+/// the key identifier and the value's position instead of a plain digest,
+/// like the addresses of SPHINCS+. An attacker can then only invert one
+/// commitment at a time, rather than any of the commitments of every key at
+/// once, so the security of a key does not drop with the number of keys in use.
+pub trait LamportTweakableDigest: LamportDigest {
+    /// Hash the secret value at `position` of the key with `identifier`.
+    ///
+    /// The default hashes a domain separation tag, the identifier, the
+    /// position as a big endian `u32` and the value.
+    fn tweak_hash(identifier: &[u8], position: u32, value: &[u8]) -> Vec<u8> {
+        Self::digest_concat(&[TWEAK_DOMAIN, identifier, &position.to_be_bytes(), value])
+    }
+}
+
+const TWEAK_DOMAIN: &[u8] = b"lamport_signature_plus tweakable hash v1";
+
+/// The shortest digest, in bits, keys can be created for.
+pub const MIN_DIGEST_BITS: usize = 128;
+
+/// The shortest digest, in bits, keys can be created for without
+/// [`LamportDigest::ALLOW_REDUCED_SECURITY`].
+pub const RECOMMENDED_DIGEST_BITS: usize = 224;
+
+/// Checks that `T` meets the minimum security level for generating or loading signing keys.
+///
+/// Digest sizes that are not a whole number of bytes are always rejected,
+/// since every layout in the crate stores `bits / 8` bytes per row.
+pub(crate) fn check_security<T: LamportDigest>() -> LamportResult<()> {
+    check_digest_size::<T>()?;
+    let bits = T::digest_size_in_bits();
+    // A digest whose output disagrees with its declared size cannot build keys
+    if T::digest(&[]).len() != bits / 8 {
+        return Err(LamportError::InvalidDigest);
+    }
+    if T::ALLOW_INSECURE
+        || bits >= RECOMMENDED_DIGEST_BITS
+        || (bits >= MIN_DIGEST_BITS && T::ALLOW_REDUCED_SECURITY)
+    {
+        Ok(())
+    } else {
+        Err(LamportError::InsecureDigest(bits))
+    }
+}
+
+/// Checks that `T` declares a usable digest size and that `digest` has that size.
+pub(crate) fn check_digest<T: LamportDigest>(digest: &[u8]) -> LamportResult<()> {
+    check_digest_size::<T>()?;
+    if digest.len() != T::digest_size_in_bits() / 8 {
+        return Err(LamportError::InvalidDigest);
+    }
+    Ok(())
+}
+
+/// Checks that `T` declares a non-zero digest size that is a multiple of 8 bits.
+pub(crate) fn check_digest_size<T: LamportDigest>() -> LamportResult<()> {
+    let bits = T::digest_size_in_bits();
+    if bits == 0 || !bits.is_multiple_of(8) {
+        return Err(LamportError::InvalidDigest);
+    }
+    Ok(())
+}
+
+/// Checks random values from `T` have the axes `[bits, bits / 8]`.
+pub(crate) fn check_random<T: LamportDigest>(values: &MultiVec<u8, 2>) -> LamportResult<()> {
+    check_digest_size::<T>()?;
+    let bits = T::digest_size_in_bits();
+    if values.axes != [bits, bits / 8] || values.data.len() != bits * (bits / 8) {
+        return Err(LamportError::InvalidDigest);
+    }
+    Ok(())
+}
+
+/// Implements [`LamportDigest`] for a hash function outside of the RustCrypto trait ecosystem,
+/// such as an FFI binding or a hardware accelerator.
+///
+/// The digest size is given in bits and must be a non-zero multiple of 8 and at
+/// least [`RECOMMENDED_DIGEST_BITS`], which is checked at compile time. Prefix the
+/// type with `reduced` to accept digests down to [`MIN_DIGEST_BITS`], or with
+/// `insecure` to accept any size in tests. The hash function can be any path or closure that takes
+/// `&[u8]` and returns a value implementing `AsRef<[u8]>`. Its output length is checked
+/// against the declared size on every call so a misbehaving backend cannot
+/// silently produce malformed keys or signatures.
+///
+/// # Example
+///
+/// ```
+/// use lamport_signature_plus::{impl_lamport_digest, generate_keys};
+/// use rand::SeedableRng;
+/// use sha2::Digest;
+///
+/// fn accelerated_sha256(data: &[u8]) -> [u8; 32] {
+///     sha2::Sha256::digest(data).into()
+/// }
+///
+/// #[derive(Debug)]
+/// struct AcceleratedSha256;
+///
+/// impl_lamport_digest!(AcceleratedSha256, 256, accelerated_sha256);
+///
+/// let rng = rand_chacha::ChaCha8Rng::from_seed([0u8; 32]);
+/// let (mut sk, pk) = generate_keys::<AcceleratedSha256, _>(rng).unwrap();
+/// let signature = sk.sign(b"hello, world!").expect("signing failed");
+/// assert!(pk.verify(&signature, b"hello, world!").is_ok());
+/// ```
+#[macro_export]
+macro_rules! impl_lamport_digest {
+    ($name:ty, $bits:expr, $hash:expr) => {
+        const _: () = assert!(
+            $bits >= $crate::RECOMMENDED_DIGEST_BITS,
+            "digest size must be at least RECOMMENDED_DIGEST_BITS"
+        );
+        $crate::impl_lamport_digest!(@impl $name, $bits, $hash, false, false);
+    };
+    (reduced $name:ty, $bits:expr, $hash:expr) => {
+        const _: () = assert!(
+            $bits >= $crate::MIN_DIGEST_BITS,
+            "digest size must be at least MIN_DIGEST_BITS"
+        );
+        $crate::impl_lamport_digest!(@impl $name, $bits, $hash, true, false);
+    };
+    (insecure $name:ty, $bits:expr, $hash:expr) => {
+        $crate::impl_lamport_digest!(@impl $name, $bits, $hash, false, true);
+    };
+    (@impl $name:ty, $bits:expr, $hash:expr, $reduced:expr, $insecure:expr) => {
+        const _: () = assert!(
+            {
+                let bits: usize = $bits;
+                bits > 0 && bits.is_multiple_of(8)
+            },
+            "digest size must be a non-zero multiple of 8 bits"
+        );
+
+        impl $crate::LamportDigest for $name {
+            const ALLOW_REDUCED_SECURITY: bool = $reduced;
+            const ALLOW_INSECURE: bool = $insecure;
+
+            fn digest_size_in_bits() -> usize {
+                $bits
+            }
+
+            fn digest(data: &[u8]) -> Vec<u8> {
+                let output = ($hash)(data);
+                let output: &[u8] = output.as_ref();
+                assert_eq!(
+                    output.len(),
+                    $bits / 8,
+                    "hash output length does not match the declared digest size"
+                );
+                output.to_vec()
+            }
+        }
+    };
 }
 
 /// Lamport signature scheme than uses fixed output functions.
@@ -68,6 +314,38 @@ where
     fn digest(data: &[u8]) -> Vec<u8> {
         T::digest(data).to_vec()
     }
+
+    fn digest_concat(parts: &[&[u8]]) -> Vec<u8> {
+        let mut hasher = T::default();
+        for part in parts {
+            Update::update(&mut hasher, part);
+        }
+        hasher.finalize_fixed().to_vec()
+    }
+}
+
+impl<T> LamportIncrementalDigest for LamportFixedDigest<T>
+where
+    T: BlockSizeUser + Default + FixedOutput + HashMarker,
+{
+    type Hasher = T;
+
+    fn hasher() -> T {
+        T::default()
+    }
+
+    fn update(hasher: &mut T, data: &[u8]) {
+        Update::update(hasher, data);
+    }
+
+    fn finalize(hasher: T) -> Vec<u8> {
+        hasher.finalize_fixed().to_vec()
+    }
+}
+
+impl<T> LamportTweakableDigest for LamportFixedDigest<T> where
+    T: BlockSizeUser + Default + FixedOutput + HashMarker
+{
 }
 
 /// Lamport signature scheme than uses extendable output functions.
@@ -85,11 +363,368 @@ where
     }
 
     fn digest(data: &[u8]) -> Vec<u8> {
+        Self::digest_concat(&[data])
+    }
+
+    fn digest_concat(parts: &[&[u8]]) -> Vec<u8> {
         let mut hasher = T::default();
+        for part in parts {
+            hasher.update(part);
+        }
+        let mut reader = hasher.finalize_xof();
+        let mut output = vec![0u8; 64];
+        reader.read(&mut output);
+        output
+    }
+}
+
+impl<T> LamportIncrementalDigest for LamportExtendableDigest<T>
+where
+    T: Default + ExtendableOutput + Update,
+{
+    type Hasher = T;
+
+    fn hasher() -> T {
+        T::default()
+    }
+
+    fn update(hasher: &mut T, data: &[u8]) {
         hasher.update(data);
+    }
+
+    fn finalize(hasher: T) -> Vec<u8> {
         let mut reader = hasher.finalize_xof();
         let mut output = vec![0u8; 64];
         reader.read(&mut output);
         output
     }
 }
+
+impl<T> LamportTweakableDigest for LamportExtendableDigest<T> where
+    T: Default + ExtendableOutput + Update
+{
+}
+
+/// Accepts a digest of at least [`MIN_DIGEST_BITS`] that is shorter than
+/// [`RECOMMENDED_DIGEST_BITS`] for signing keys.
+///
+/// Wrap a digest such as `LamportFixedDigest<Sha1>` to keep signing with
+/// keys created before the minimum security check. Keys, signatures and
+/// their byte encodings are the same as for the wrapped digest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct LamportReducedDigest<T: LamportDigest>(PhantomData<T>);
+
+impl<T: LamportDigest> LamportDigest for LamportReducedDigest<T> {
+    const ALLOW_REDUCED_SECURITY: bool = true;
+    const ALLOW_INSECURE: bool = T::ALLOW_INSECURE;
+
+    fn digest_size_in_bits() -> usize {
+        T::digest_size_in_bits()
+    }
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        T::digest(data)
+    }
+
+    fn digest_concat(parts: &[&[u8]]) -> Vec<u8> {
+        T::digest_concat(parts)
+    }
+
+    fn hash(data: &MultiVec<u8, 2>) -> LamportResult<MultiVec<u8, 2>> {
+        T::hash(data)
+    }
+
+    fn random(rng: impl RngCore + CryptoRng) -> MultiVec<u8, 2> {
+        T::random(rng)
+    }
+
+    fn random_pair(rng: impl RngCore + CryptoRng) -> (MultiVec<u8, 2>, MultiVec<u8, 2>) {
+        T::random_pair(rng)
+    }
+}
+
+impl<T: LamportIncrementalDigest> LamportIncrementalDigest for LamportReducedDigest<T> {
+    type Hasher = T::Hasher;
+
+    fn hasher() -> T::Hasher {
+        T::hasher()
+    }
+
+    fn update(hasher: &mut T::Hasher, data: &[u8]) {
+        T::update(hasher, data);
+    }
+
+    fn finalize(hasher: T::Hasher) -> Vec<u8> {
+        T::finalize(hasher)
+    }
+}
+
+impl<T: LamportTweakableDigest> LamportTweakableDigest for LamportReducedDigest<T> {
+    fn tweak_hash(identifier: &[u8], position: u32, value: &[u8]) -> Vec<u8> {
+        T::tweak_hash(identifier, position, value)
+    }
+}
+
+/// Computes the digest of a vectored message.
+///
+/// Each part is prefixed with its length as a big endian `u64`, so
+/// `["ab", "c"]` and `["a", "bc"]` hash differently.
+pub(crate) fn digest_vectored<T: LamportDigest>(parts: &[&[u8]]) -> Vec<u8> {
+    let lengths = parts
+        .iter()
+        .map(|part| (part.len() as u64).to_be_bytes())
+        .collect::<Vec<_>>();
+    let mut input = Vec::with_capacity(parts.len() * 2);
+    for (length, part) in lengths.iter().zip(parts) {
+        input.push(&length[..]);
+        input.push(*part);
+    }
+    T::digest_concat(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generate_keys, FailStopSigningKey, SigningKey, SigningKeyShare, TweakableSigningKey,
+        TwoPartyKeyShare, TwoPartyRole, VerifyingKey, SEED_BYTES,
+    };
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    use sha3::{Sha3_256, Shake128};
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[derive(Debug)]
+    struct CustomDigest;
+
+    impl_lamport_digest!(CustomDigest, 384, |data: &[u8]| {
+        use sha2::Digest;
+        sha2::Sha384::digest(data)
+    });
+
+    #[test]
+    fn sign_custom_digest() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<CustomDigest, _>(rng).unwrap();
+        assert_eq!(pk.rows(), 384);
+
+        let message = b"hello, world!";
+        let signature = sk.sign(message).unwrap();
+        assert!(pk.verify(&signature, message).is_ok());
+        assert!(pk.verify(&signature, b"hello, world").is_err());
+    }
+
+    #[test]
+    fn incremental_digest() {
+        fn check<T: LamportIncrementalDigest>() {
+            let message = [0x5au8; 1000];
+            let mut hasher = T::hasher();
+            for chunk in message.chunks(77) {
+                T::update(&mut hasher, chunk);
+            }
+            assert_eq!(T::finalize(hasher), T::digest(&message));
+            assert_eq!(T::finalize(T::hasher()), T::digest(&[]));
+        }
+
+        check::<LamportFixedDigest<Sha256>>();
+        check::<LamportFixedDigest<Sha3_256>>();
+        check::<LamportExtendableDigest<Shake128>>();
+    }
+
+    #[test]
+    fn custom_digest_output_is_checked() {
+        #[derive(Debug)]
+        struct ShortDigest;
+
+        impl LamportDigest for ShortDigest {
+            fn digest_size_in_bits() -> usize {
+                256
+            }
+
+            fn digest(data: &[u8]) -> Vec<u8> {
+                LamportFixedDigest::<Sha256>::digest(data)[..16].to_vec()
+            }
+        }
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        assert_eq!(
+            SigningKey::<ShortDigest>::random(&mut rng).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+        let values = MultiVec::fill([256, 32], 0u8);
+        assert_eq!(
+            ShortDigest::hash(&values).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+        assert_eq!(
+            LamportFixedDigest::<Sha256>::hash(&MultiVec::fill([256, 16], 0u8)).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+    }
+
+    #[test]
+    fn custom_digest_random_is_checked() {
+        #[derive(Debug)]
+        struct ShortRandom;
+
+        impl LamportDigest for ShortRandom {
+            fn digest_size_in_bits() -> usize {
+                256
+            }
+
+            fn digest(data: &[u8]) -> Vec<u8> {
+                LamportFixedDigest::<Sha256>::digest(data)
+            }
+
+            fn random_pair(
+                mut rng: impl RngCore + CryptoRng,
+            ) -> (MultiVec<u8, 2>, MultiVec<u8, 2>) {
+                let mut data = vec![0u8; 256];
+                rng.fill_bytes(&mut data);
+                let values = MultiVec {
+                    data,
+                    axes: [256, 1],
+                };
+                (values.clone(), values)
+            }
+        }
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        assert_eq!(
+            SigningKey::<ShortRandom>::random(&mut rng).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+        assert_eq!(
+            TwoPartyKeyShare::<ShortRandom>::generate(TwoPartyRole::First, &mut rng).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+    }
+
+    #[derive(Debug)]
+    struct Digest160;
+
+    impl_lamport_digest!(reduced Digest160, 160, |data: &[u8]| {
+        use sha2::Digest;
+        let mut output = [0u8; 20];
+        output.copy_from_slice(&Sha256::digest(data)[..20]);
+        output
+    });
+
+    #[test]
+    fn minimum_digest_security() {
+        #[derive(Debug)]
+        struct Digest128;
+
+        impl LamportDigest for Digest128 {
+            fn digest_size_in_bits() -> usize {
+                128
+            }
+
+            fn digest(data: &[u8]) -> Vec<u8> {
+                LamportFixedDigest::<Sha256>::digest(data)[..16].to_vec()
+            }
+        }
+
+        assert_eq!(
+            SigningKey::<Digest128>::from_bytes([0u8; 128 * 16 * 2 + 1]).unwrap_err(),
+            LamportError::InsecureDigest(128)
+        );
+        // Verifying keys still load so existing signatures can be checked
+        assert!(VerifyingKey::<Digest128>::from_bytes([0u8; 128 * 16 * 2]).is_ok());
+        let insecure = Err(LamportError::InsecureDigest(128));
+        let rng = || rand_chacha::ChaCha8Rng::from_seed(SEED);
+        assert_eq!(SigningKey::<Digest128>::random(rng()).map(drop), insecure);
+        assert_eq!(
+            SigningKey::<Digest128>::from_seed(&[1u8; SEED_BYTES]).map(drop),
+            insecure
+        );
+        assert_eq!(
+            FailStopSigningKey::<Digest128>::random(rng()).map(drop),
+            insecure
+        );
+        assert_eq!(
+            FailStopSigningKey::<Digest128>::from_bytes([0u8; 1 + 128 * 32 * 2]).map(drop),
+            insecure
+        );
+        assert_eq!(
+            TweakableSigningKey::<Digest128>::random(rng()).map(drop),
+            insecure
+        );
+
+        // Loading or combining shares must not bypass the check
+        let mut share = vec![0u8; 128 * 16 * 2 + 3];
+        share[..2].copy_from_slice(&[1, 2]);
+        assert_eq!(
+            SigningKeyShare::<Digest128>::from_bytes(&share).map(drop),
+            insecure
+        );
+        assert_eq!(
+            SigningKeyShare::<Digest128>::from_vec(share).map(drop),
+            insecure
+        );
+        let mut share = vec![0u8; 128 * 16 * 2 + 2];
+        share[0] = 1;
+        assert_eq!(
+            TwoPartyKeyShare::<Digest128>::from_bytes(&share).map(drop),
+            insecure
+        );
+        assert_eq!(SigningKey::<Digest128>::combine(&[]).map(drop), insecure);
+
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<Digest160, _>(rng).unwrap();
+        let signature = sk.sign(b"reduced").unwrap();
+        assert!(pk.verify(&signature, b"reduced").is_ok());
+
+        // The wrapper opts a digest into reduced security
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportReducedDigest<Digest128>, _>(rng).unwrap();
+        let signature = sk.sign(b"reduced").unwrap();
+        assert!(pk.verify(&signature, b"reduced").is_ok());
+        let bytes = pk.to_bytes();
+        let pk = VerifyingKey::<Digest128>::from_bytes(&bytes).unwrap();
+        let signature = crate::Signature::<Digest128>::from_bytes(signature.to_bytes()).unwrap();
+        assert!(pk.verify(&signature, b"reduced").is_ok());
+    }
+
+    #[test]
+    fn odd_sized_digest() {
+        #[derive(Debug)]
+        struct Digest252;
+
+        impl LamportDigest for Digest252 {
+            const ALLOW_INSECURE: bool = true;
+
+            fn digest_size_in_bits() -> usize {
+                252
+            }
+
+            fn digest(data: &[u8]) -> Vec<u8> {
+                let mut output = LamportFixedDigest::<Sha256>::digest(data);
+                output[31] &= 0x0f;
+                output
+            }
+        }
+
+        let bytes = 252 / 8;
+        assert_eq!(
+            SigningKey::<Digest252>::from_bytes(vec![0u8; 252 * bytes * 2 + 1]).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+        assert_eq!(
+            VerifyingKey::<Digest252>::from_bytes(vec![0u8; 252 * bytes * 2]).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+        assert_eq!(
+            SigningKey::<Digest252>::from_seed(&[1u8; SEED_BYTES]).map(drop),
+            Err(LamportError::InvalidDigest)
+        );
+        assert_eq!(
+            generate_keys::<Digest252, _>(rand_chacha::ChaCha8Rng::from_seed(SEED)).map(drop),
+            Err(LamportError::InvalidDigest)
+        );
+        assert_eq!(
+            check_digest::<Digest252>(&[0u8; 32]).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+    }
+}
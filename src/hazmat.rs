@@ -0,0 +1,56 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Low-level operations that bypass the safety rails of the main API.
+//!
+//! These functions are only available with the `hazmat` feature. Misusing them
+//! can completely break the security of the scheme, so they should only be
+//! used by protocols that know exactly what they are doing.
+use crate::{LamportDigest, LamportError, LamportResult, Signature, SigningKey, VerifyingKey};
+
+/// Signs a caller supplied bit string instead of hashing a message.
+///
+/// `bits` must be exactly the digest length of `T` and is consumed least
+/// significant bit first within each byte, the same order used for message
+/// digests by [`SigningKey::sign`]. The security of the signature depends on
+/// `bits` being the output of a collision resistant function the verifier
+/// can recompute, such as a hash computed by an external component.
+pub fn sign_bits<T: LamportDigest>(
+    key: &mut SigningKey<T>,
+    bits: &[u8],
+) -> LamportResult<Signature<T>> {
+    check_bits_length::<T>(bits)?;
+    key.sign_digest(bits)
+}
+
+/// Verifies a [`Signature`] created by [`sign_bits`] over the same bit string.
+pub fn verify_bits<T: LamportDigest>(
+    key: &VerifyingKey<T>,
+    signature: &Signature<T>,
+    bits: &[u8],
+) -> LamportResult<()> {
+    check_bits_length::<T>(bits)?;
+    key.verify_digest(signature, bits)
+}
+
+/// Clears the used flag so the key will sign again.
+///
+/// Signing two different messages with the same key reveals enough preimages
+/// for anyone to forge signatures. This exists only for test harnesses and
+/// forensic tooling that deliberately re-sign with an already compromised key.
+pub fn force_reset_used<T: LamportDigest>(key: &mut SigningKey<T>) {
+    key.used = false;
+}
+
+fn check_bits_length<T: LamportDigest>(bits: &[u8]) -> LamportResult<()> {
+    let expected = T::digest_size_in_bits() / 8;
+    if bits.len() != expected {
+        return Err(LamportError::General(format!(
+            "expected {} bytes of bits, found {}",
+            expected,
+            bits.len()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,433 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size, check_security};
+use crate::{KeyId, LamportDigest, LamportError, LamportResult, MultiVec};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+/// Splits the digest into `count` indices of `bits` bits, most significant bit first.
+pub(crate) fn split_indices(digest: &[u8], count: usize, bits: u32) -> Vec<usize> {
+    let mut indices = Vec::with_capacity(count);
+    let mut total = 0usize;
+    let mut available = 0u32;
+    let mut bytes = digest.iter();
+    for _ in 0..count {
+        while available < bits {
+            total = (total << 8) | bytes.next().map_or(0, |b| usize::from(*b));
+            available += 8;
+        }
+        available -= bits;
+        indices.push((total >> available) & ((1 << bits) - 1));
+        total &= (1 << available) - 1;
+    }
+    indices
+}
+
+/// The number of revealed values `k` and the number of secret values `t = 2^log_t` of a HORS key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct HorsParams {
+    k: usize,
+    log_t: u32,
+}
+
+impl HorsParams {
+    /// Supports `k` of 1 to 256 and `log_t` of 1 to 20.
+    ///
+    /// The digest must have at least `k * log_t` bits, which is checked when
+    /// a key is created.
+    pub fn new(k: usize, log_t: u32) -> LamportResult<Self> {
+        if k == 0 || k > 256 || log_t == 0 || log_t > 20 {
+            return Err(LamportError::InvalidHorsParameters);
+        }
+        Ok(Self { k, log_t })
+    }
+
+    /// The number of secret values revealed by each signature.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The number of secret values in a key.
+    pub fn t(&self) -> usize {
+        1 << self.log_t
+    }
+
+    /// The bits of security left against forging a signature on a message
+    /// after `signatures` signatures with the same key.
+    ///
+    /// Each signature reveals at most `k` of the `t` secret values, so after
+    /// `r` signatures a forger can sign a new message if its `k` indices all
+    /// fall in the at most `r * k` revealed values, with probability
+    /// `(r * k / t)^k`. This is `k * (log2(t) - log2(r * k))` bits, and the
+    /// full `k * log2(t)` bits of preimage resistance before any signature.
+    pub fn security_bits(&self, signatures: u32) -> f64 {
+        let k = f64::from(u16::try_from(self.k).expect("k is at most 256"));
+        if signatures == 0 {
+            return k * f64::from(self.log_t);
+        }
+        let revealed = f64::from(signatures) * k;
+        (k * (f64::from(self.log_t) - revealed.log2())).max(0.0)
+    }
+
+    fn check<T: LamportDigest>(&self) -> LamportResult<usize> {
+        let bits = T::digest_size_in_bits();
+        if self.k * self.log_t as usize > bits {
+            return Err(LamportError::InvalidHorsParameters);
+        }
+        Ok(bits / 8)
+    }
+
+    fn indices(&self, digest: &[u8]) -> Vec<usize> {
+        split_indices(digest, self.k, self.log_t)
+    }
+
+    fn to_bytes(self) -> [u8; 3] {
+        let k = u16::try_from(self.k)
+            .expect("k is at most 256")
+            .to_be_bytes();
+        [k[0], k[1], self.log_t.to_le_bytes()[0]]
+    }
+
+    fn from_bytes<T: LamportDigest>(input: &[u8]) -> Option<(Self, usize)> {
+        let header = input.get(..3)?;
+        let params = Self::new(
+            usize::from(u16::from_be_bytes([header[0], header[1]])),
+            u32::from(header[2]),
+        )
+        .ok()?;
+        let n = params.check::<T>().ok()?;
+        Some((params, n))
+    }
+}
+
+/// A HORS few-time signing key.
+///
+/// Each signature reveals `k` of the `t` secret values chosen by the message
+/// digest, so unlike a one-time key the key can sign a few messages while
+/// keeping a known security level, see [`HorsParams::security_bits`]. The
+/// key refuses to sign more than the `max_uses` messages it was created for.
+///
+/// The key is stateful. Its canonical bytes include the number of uses and
+/// must be persisted after every signature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HorsSigningKey<T: LamportDigest> {
+    pub(crate) params: HorsParams,
+    pub(crate) max_uses: u32,
+    pub(crate) uses: u32,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(HorsSigningKey);
+vec_impl!(HorsSigningKey);
+
+impl<T: LamportDigest> Zeroize for HorsSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> HorsSigningKey<T> {
+    /// Constructs a [`HorsSigningKey`] that signs at most `max_uses` messages.
+    pub fn random(
+        params: HorsParams,
+        max_uses: u32,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let n = params.check::<T>()?;
+        if max_uses == 0 {
+            return Err(LamportError::InvalidHorsParameters);
+        }
+        let mut data = vec![0u8; params.t() * n];
+        rng.fill_bytes(&mut data);
+        Ok(Self {
+            params,
+            max_uses,
+            uses: 0,
+            values: MultiVec {
+                data,
+                axes: [params.t(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The parameters.
+    pub fn params(&self) -> HorsParams {
+        self.params
+    }
+
+    /// The number of messages the key was created to sign.
+    pub fn max_uses(&self) -> u32 {
+        self.max_uses
+    }
+
+    /// The number of messages the key can still sign.
+    pub fn remaining(&self) -> u32 {
+        self.max_uses - self.uses
+    }
+
+    /// The bits of security left once the key has signed `max_uses` messages.
+    pub fn security_bits(&self) -> f64 {
+        self.params.security_bits(self.max_uses)
+    }
+
+    /// Signs the data.
+    ///
+    /// Returns [`LamportError::PrivateKeyReuseError`] once the key has signed
+    /// `max_uses` messages.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<HorsSignature<T>> {
+        if self.uses >= self.max_uses {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        self.uses += 1;
+        let n = self.values.axes[1];
+        let mut signature = Vec::with_capacity(self.params.k * n);
+        for index in self.params.indices(&digest) {
+            signature.extend_from_slice(&self.values.data[index * n..(index + 1) * n]);
+        }
+        Ok(HorsSignature {
+            params: self.params,
+            values: MultiVec {
+                data: signature,
+                axes: [self.params.k, n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`HorsSigningKey`] to canonical bytes.
+    ///
+    /// The format is `k` as a big endian `u16`, `log2(t)`, the maximum and
+    /// current uses as big endian `u32`s, then the secret values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(11 + self.values.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.max_uses.to_be_bytes());
+        bytes.extend_from_slice(&self.uses.to_be_bytes());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`HorsSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let (params, n) =
+            HorsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 11 + params.t() * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let max_uses = u32::from_be_bytes([input[3], input[4], input[5], input[6]]);
+        let uses = u32::from_be_bytes([input[7], input[8], input[9], input[10]]);
+        if max_uses == 0 || uses > max_uses {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            params,
+            max_uses,
+            uses,
+            values: MultiVec {
+                data: input[11..].to_vec(),
+                axes: [params.t(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A HORS verifying key, the digest of each secret value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HorsVerifyingKey<T: LamportDigest> {
+    pub(crate) params: HorsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(HorsVerifyingKey);
+vec_impl!(HorsVerifyingKey);
+
+impl<T: LamportDigest> From<&HorsSigningKey<T>> for HorsVerifyingKey<T> {
+    fn from(value: &HorsSigningKey<T>) -> Self {
+        let data = value
+            .values
+            .data
+            .chunks_exact(value.values.axes[1])
+            .flat_map(T::digest)
+            .collect();
+        Self {
+            params: value.params,
+            values: MultiVec {
+                data,
+                axes: value.values.axes,
+            },
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> HorsVerifyingKey<T> {
+    /// The parameters.
+    pub fn params(&self) -> HorsParams {
+        self.params
+    }
+
+    /// The [`KeyId`] fingerprint of the key.
+    pub fn fingerprint(&self) -> KeyId {
+        KeyId::compute_encoded(b"HORS", T::digest_size_in_bits(), &self.to_bytes())
+    }
+
+    /// Verifies the signature.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &HorsSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let n = self.values.axes[1];
+        if signature.params != self.params || signature.values.axes != [self.params.k, n] {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        let valid = signature
+            .values
+            .data
+            .chunks_exact(n)
+            .zip(self.params.indices(&digest))
+            .all(|(value, index)| T::digest(value) == self.values.data[index * n..(index + 1) * n]);
+        if valid {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the [`HorsVerifyingKey`] to canonical bytes,
+    /// `k` as a big endian `u16`, `log2(t)`, then the digests.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + self.values.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`HorsVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let (params, n) =
+            HorsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 3 + params.t() * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[3..].to_vec(),
+                axes: [params.t(), n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature generated by [`HorsSigningKey`], the `k` revealed secret values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HorsSignature<T: LamportDigest> {
+    pub(crate) params: HorsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(HorsSignature);
+vec_impl!(HorsSignature);
+
+impl<T: LamportDigest> HorsSignature<T> {
+    /// Converts the [`HorsSignature`] to canonical bytes,
+    /// `k` as a big endian `u16`, `log2(t)`, then the revealed values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + self.values.len());
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`HorsSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let (params, n) =
+            HorsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidSignatureBytes)?;
+        if input.len() != 3 + params.k * n {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[3..].to_vec(),
+                axes: [params.k, n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn hors() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let params = HorsParams::new(32, 8).unwrap();
+        assert_eq!(params.t(), 256);
+        assert_eq!(params.security_bits(0), 256.0);
+        assert_eq!(params.security_bits(1), 96.0);
+        assert_eq!(params.security_bits(2), 64.0);
+        assert_eq!(params.security_bits(8), 0.0);
+
+        let mut sk = HorsSigningKey::<D>::random(params, 2, &mut rng).unwrap();
+        let pk = HorsVerifyingKey::from(&sk);
+        assert_eq!(sk.remaining(), 2);
+        assert_eq!(sk.security_bits(), 64.0);
+        let first = sk.sign(b"first").unwrap();
+        assert_eq!(sk.remaining(), 1);
+        let second = sk.sign(b"second").unwrap();
+        assert_eq!(sk.remaining(), 0);
+        assert_eq!(
+            sk.sign(b"third").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert!(pk.verify(&first, b"first").is_ok());
+        assert!(pk.verify(&second, b"second").is_ok());
+        assert!(pk.verify(&first, b"second").is_err());
+
+        let decoded = HorsSignature::<D>::from_bytes(first.to_bytes()).unwrap();
+        assert_eq!(decoded.to_bytes(), first.to_bytes());
+        assert_eq!(first.to_bytes().len(), 3 + 32 * 32);
+        let pk = HorsVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        assert!(pk.verify(&decoded, b"first").is_ok());
+        let restored = HorsSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(restored.remaining(), 0);
+        assert_eq!(restored.max_uses(), 2);
+
+        assert!(HorsParams::new(0, 8).is_err());
+        assert!(HorsParams::new(16, 21).is_err());
+        let too_large = HorsParams::new(33, 8).unwrap();
+        assert_eq!(
+            HorsSigningKey::<D>::random(too_large, 1, &mut rng).unwrap_err(),
+            LamportError::InvalidHorsParameters
+        );
+        assert!(HorsSigningKey::<D>::random(params, 0, &mut rng).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! The Hierarchical Signature System of [RFC 8554 §6](https://www.rfc-editor.org/rfc/rfc8554#section-6).
+use crate::lms::{
+    read_u32, LmOtsAlgorithm, LmsAlgorithm, LmsSignature, LmsSigningKey, LmsVerifyingKey,
+    LMS_HASH_BYTES,
+};
+use crate::{KeyId, LamportError, LamportResult};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The most levels RFC 8554 allows in a hierarchy.
+pub const HSS_MAX_LEVELS: usize = 8;
+
+/// An HSS private key, a hierarchy of [`LmsSigningKey`]s.
+///
+/// The top level key only signs the public keys of the level below it, and
+/// so on down to the bottom level key that signs messages. When the bottom
+/// key is exhausted, [`HssSigningKey::sign`] generates a replacement and
+/// signs it with the next available key above, so the top level key is
+/// used once every `2^h` signatures of the level below and can be kept in
+/// slower, more protected storage.
+///
+/// The key is stateful. Its canonical bytes include every level and must be
+/// persisted after every signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HssSigningKey {
+    pub(crate) levels: Vec<LmsSigningKey>,
+    pub(crate) signed_keys: Vec<(LmsSignature, LmsVerifyingKey)>,
+}
+
+serde_impl!(@concrete HssSigningKey);
+
+impl Zeroize for HssSigningKey {
+    fn zeroize(&mut self) {
+        self.levels.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl HssSigningKey {
+    /// Generates a hierarchy with the LMS and LM-OTS parameter sets of each
+    /// level, top level first.
+    ///
+    /// RFC 8554 allows one to [`HSS_MAX_LEVELS`] levels.
+    pub fn generate(
+        parameters: &[(LmsAlgorithm, LmOtsAlgorithm)],
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        let ((lms, ots), rest) = parameters
+            .split_first()
+            .ok_or(LamportError::InvalidHypertreeParameters)?;
+        if parameters.len() > HSS_MAX_LEVELS {
+            return Err(LamportError::InvalidHypertreeParameters);
+        }
+        let mut key = Self {
+            levels: vec![LmsSigningKey::generate(*lms, *ots, &mut rng)],
+            signed_keys: Vec::with_capacity(rest.len()),
+        };
+        for (lms, ots) in rest {
+            let child = LmsSigningKey::generate(*lms, *ots, &mut rng);
+            key.push_child(child, &mut rng)?;
+        }
+        Ok(key)
+    }
+
+    /// The public key.
+    pub fn verifying_key(&self) -> HssVerifyingKey {
+        HssVerifyingKey {
+            levels: self.levels.len(),
+            root: self.levels[0].verifying_key(),
+        }
+    }
+
+    /// The number of levels.
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// The number of signatures left, saturating at `u64::MAX`.
+    pub fn remaining(&self) -> u64 {
+        // Each unused key on a level can certify a full tree of every level below it
+        let mut remaining = 0u64;
+        let mut below = 1u64;
+        for level in self.levels.iter().rev() {
+            remaining =
+                remaining.saturating_add(below.saturating_mul(u64::from(level.remaining())));
+            below = below.saturating_mul(u64::from(level.lms.capacity()));
+        }
+        remaining
+    }
+
+    /// Signs the data with the bottom level key, first replacing any
+    /// exhausted levels, RFC 8554 §6.2.
+    ///
+    /// Returns [`LamportError::KeyPoolExhausted`] once every level is exhausted.
+    pub fn sign<B: AsRef<[u8]>>(
+        &mut self,
+        data: B,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<HssSignature> {
+        self.refresh(&mut rng)?;
+        let signature = self
+            .levels
+            .last_mut()
+            .expect("there is always a top level")
+            .sign(data, &mut rng)?;
+        Ok(HssSignature {
+            signed_keys: self.signed_keys.clone(),
+            signature,
+        })
+    }
+
+    /// The total number of signatures of the hierarchy, or `None` if it
+    /// does not fit in a `u64`.
+    pub(crate) fn capacity(&self) -> Option<u64> {
+        self.levels.iter().try_fold(1u64, |capacity, level| {
+            capacity.checked_mul(u64::from(level.lms.capacity()))
+        })
+    }
+
+    /// Skips the next `count` signatures, replacing levels as they are exhausted.
+    pub(crate) fn skip(
+        &mut self,
+        mut count: u64,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<()> {
+        while count > 0 {
+            self.refresh(&mut rng)?;
+            let bottom = self.levels.last_mut().expect("there is always a top level");
+            let skipped = count.min(u64::from(bottom.remaining()));
+            bottom.q += u32::try_from(skipped).expect("at most the remaining signatures");
+            count -= skipped;
+        }
+        Ok(())
+    }
+
+    /// Replaces the exhausted levels below the lowest level that can still sign.
+    fn refresh(&mut self, mut rng: impl RngCore + CryptoRng) -> LamportResult<()> {
+        let mut depth = self.levels.len();
+        while self.levels[depth - 1].remaining() == 0 {
+            depth -= 1;
+            if depth == 0 {
+                return Err(LamportError::KeyPoolExhausted);
+            }
+        }
+        while depth < self.levels.len() {
+            let exhausted = &self.levels[depth];
+            let child = LmsSigningKey::generate(exhausted.lms, exhausted.ots, &mut rng);
+            self.levels.truncate(depth);
+            self.signed_keys.truncate(depth - 1);
+            self.push_child(child, &mut rng)?;
+            depth += 1;
+        }
+        Ok(())
+    }
+
+    /// Converts the [`HssSigningKey`] to canonical bytes.
+    ///
+    /// The format is the number of levels as a big endian `u32`, each
+    /// level's [`LmsSigningKey::to_bytes`] top level first, then the
+    /// signature and public key of each level below the top.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(
+            &u32::try_from(self.levels.len())
+                .expect("at most 8 levels")
+                .to_be_bytes(),
+        );
+        for level in &self.levels {
+            bytes.extend_from_slice(&level.to_bytes());
+        }
+        for (signature, key) in &self.signed_keys {
+            bytes.extend_from_slice(&signature.to_bytes());
+            bytes.extend_from_slice(&key.to_bytes());
+        }
+        bytes
+    }
+
+    /// Constructs a [`HssSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let count = read_u32(input, 0).ok_or(LamportError::InvalidPrivateKeyBytes)? as usize;
+        if count == 0 || count > HSS_MAX_LEVELS || input.len() < 4 + count * LmsSigningKey::BYTES {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (levels, mut rest) = input[4..].split_at(count * LmsSigningKey::BYTES);
+        let levels = levels
+            .chunks_exact(LmsSigningKey::BYTES)
+            .map(LmsSigningKey::from_bytes)
+            .collect::<LamportResult<Vec<_>>>()?;
+        let mut signed_keys = Vec::with_capacity(count - 1);
+        for level in &levels[1..] {
+            let (signed_key, tail) = parse_signed_key(rest)?;
+            if signed_key.1 != level.verifying_key() {
+                return Err(LamportError::InvalidPrivateKeyBytes);
+            }
+            signed_keys.push(signed_key);
+            rest = tail;
+        }
+        if !rest.is_empty() {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            levels,
+            signed_keys,
+        })
+    }
+
+    /// Signs the public key of a new bottom level with the current bottom level.
+    fn push_child(
+        &mut self,
+        child: LmsSigningKey,
+        rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<()> {
+        let public = child.verifying_key();
+        let parent = self.levels.last_mut().expect("there is always a top level");
+        let signature = parent.sign(public.to_bytes(), rng)?;
+        self.signed_keys.push((signature, public));
+        self.levels.push(child);
+        Ok(())
+    }
+}
+
+fn parse_signed_key(input: &[u8]) -> LamportResult<((LmsSignature, LmsVerifyingKey), &[u8])> {
+    let (signature, rest) = LmsSignature::parse(input)?;
+    if rest.len() < LmsVerifyingKey::BYTES {
+        return Err(LamportError::InvalidSignatureBytes);
+    }
+    let (key, rest) = rest.split_at(LmsVerifyingKey::BYTES);
+    Ok(((signature, LmsVerifyingKey::from_bytes(key)?), rest))
+}
+
+/// An HSS public key, the number of levels and the top level [`LmsVerifyingKey`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HssVerifyingKey {
+    pub(crate) levels: usize,
+    pub(crate) root: LmsVerifyingKey,
+}
+
+serde_impl!(@concrete HssVerifyingKey);
+
+impl HssVerifyingKey {
+    /// The length of an encoded public key.
+    pub const BYTES: usize = 4 + LmsVerifyingKey::BYTES;
+
+    /// The number of levels.
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+
+    /// The top level key.
+    pub fn root(&self) -> &LmsVerifyingKey {
+        &self.root
+    }
+
+    /// The [`KeyId`] fingerprint of the key.
+    pub fn fingerprint(&self) -> KeyId {
+        KeyId::compute_encoded(b"HSS", LMS_HASH_BYTES * 8, &self.to_bytes())
+    }
+
+    /// Verifies the signature, RFC 8554 §6.3.
+    pub fn verify<B: AsRef<[u8]>>(&self, signature: &HssSignature, data: B) -> LamportResult<()> {
+        if signature.signed_keys.len() + 1 != self.levels {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let mut key = &self.root;
+        for (key_signature, public) in &signature.signed_keys {
+            key.verify(key_signature, public.to_bytes())?;
+            key = public;
+        }
+        key.verify(&signature.signature, data)
+    }
+
+    /// Converts the [`HssVerifyingKey`] to the RFC 8554 §6.1 encoding, `u32str(L) || pub[0]`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.extend_from_slice(
+            &u32::try_from(self.levels)
+                .expect("at most 8 levels")
+                .to_be_bytes(),
+        );
+        bytes.extend_from_slice(&self.root.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`HssVerifyingKey`] from the RFC 8554 §6.1 encoding.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let levels = read_u32(input, 0).ok_or(LamportError::InvalidPrivateKeyBytes)? as usize;
+        if levels == 0 || levels > HSS_MAX_LEVELS {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            levels,
+            root: LmsVerifyingKey::from_bytes(&input[4..])?,
+        })
+    }
+}
+
+/// An HSS signature, the signed public key of each level below the top and
+/// the bottom level [`LmsSignature`] of the message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HssSignature {
+    pub(crate) signed_keys: Vec<(LmsSignature, LmsVerifyingKey)>,
+    pub(crate) signature: LmsSignature,
+}
+
+serde_impl!(@concrete HssSignature);
+
+impl HssSignature {
+    /// The signature of the message by the bottom level key.
+    pub fn lms_signature(&self) -> &LmsSignature {
+        &self.signature
+    }
+
+    /// Converts the [`HssSignature`] to the RFC 8554 §6.4 encoding,
+    /// `u32str(Nspk) || signed_pub_key[0] || ... || sig[Nspk]`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(
+            &u32::try_from(self.signed_keys.len())
+                .expect("at most 7 signed keys")
+                .to_be_bytes(),
+        );
+        for (signature, key) in &self.signed_keys {
+            bytes.extend_from_slice(&signature.to_bytes());
+            bytes.extend_from_slice(&key.to_bytes());
+        }
+        bytes.extend_from_slice(&self.signature.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`HssSignature`] from the RFC 8554 §6.4 encoding.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let count = read_u32(input, 0).ok_or(LamportError::InvalidSignatureBytes)? as usize;
+        if count >= HSS_MAX_LEVELS {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let mut rest = &input[4..];
+        let mut signed_keys = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (signed_key, tail) = parse_signed_key(rest)?;
+            signed_keys.push(signed_key);
+            rest = tail;
+        }
+        let signature = LmsSignature::from_bytes(rest)?;
+        Ok(Self {
+            signed_keys,
+            signature,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn hss() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let level = (LmsAlgorithm::Sha256M32H5, LmOtsAlgorithm::Sha256N32W4);
+        let mut sk = HssSigningKey::generate(&[level, level], &mut rng).unwrap();
+        let pk = sk.verifying_key();
+        assert_eq!(sk.remaining(), 1024);
+        assert_eq!(pk.to_bytes().len(), HssVerifyingKey::BYTES);
+        assert_eq!(&pk.to_bytes()[..4], &[0, 0, 0, 2]);
+        assert_eq!(HssVerifyingKey::from_bytes(pk.to_bytes()).unwrap(), pk);
+
+        // The bottom level is replaced after 32 signatures
+        let mut bottom_keys = Vec::new();
+        for i in 0..34u8 {
+            let signature = sk.sign([i], &mut rng).unwrap();
+            assert!(pk.verify(&signature, [i]).is_ok());
+            assert!(pk.verify(&signature, [i + 1]).is_err());
+            bottom_keys.push(signature.signed_keys[0].1);
+        }
+        assert_eq!(bottom_keys[0], bottom_keys[31]);
+        assert_ne!(bottom_keys[31], bottom_keys[32]);
+        assert_eq!(sk.remaining(), 1024 - 34);
+
+        let signature = sk.sign(b"encoded", &mut rng).unwrap();
+        let bytes = signature.to_bytes();
+        assert_eq!(&bytes[..4], &[0, 0, 0, 1]);
+        let decoded = HssSignature::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), signature.to_bytes());
+        assert!(pk.verify(&decoded, b"encoded").is_ok());
+        assert!(HssSignature::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut one_level = pk;
+        one_level.levels = 1;
+        assert!(one_level.verify(&decoded, b"encoded").is_err());
+
+        let mut restored = HssSigningKey::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(restored.remaining(), sk.remaining());
+        let signature = restored.sign(b"restored", &mut rng).unwrap();
+        assert!(pk.verify(&signature, b"restored").is_ok());
+
+        let mut sk = HssSigningKey::generate(&[level], &mut rng).unwrap();
+        for _ in 0..32 {
+            sk.sign(b"single", &mut rng).unwrap();
+        }
+        assert_eq!(
+            sk.sign(b"single", &mut rng).unwrap_err(),
+            LamportError::KeyPoolExhausted
+        );
+        assert!(HssSigningKey::generate(&[], &mut rng).is_err());
+        assert!(HssSigningKey::generate(&[level; 9], &mut rng).is_err());
+    }
+
+    #[test]
+    fn rfc8554_test_case_2() {
+        // RFC 8554 Appendix F, Test Case 2, a two level hierarchy with
+        // published private key details
+        let key = |lms, ots, identifier: &str, seed: &str| {
+            LmsSigningKey::from_seed(
+                lms,
+                ots,
+                hex::decode(identifier).unwrap().try_into().unwrap(),
+                &hex::decode(seed).unwrap().try_into().unwrap(),
+            )
+        };
+        let mut top = key(
+            LmsAlgorithm::Sha256M32H10,
+            LmOtsAlgorithm::Sha256N32W4,
+            "d08fabd4a2091ff0a8cb4ed834e74534",
+            "558b8966c48ae9cb898b423c83443aae014a72f1b1ab5cc85cf1d892903b5439",
+        );
+        let mut bottom = key(
+            LmsAlgorithm::Sha256M32H5,
+            LmOtsAlgorithm::Sha256N32W8,
+            "215f83b7ccb9acbcd08db97b0d04dc2b",
+            "a1c4696e2608035a886100d05cd99945eb3370731884a8235e2fb3d4d71f2547",
+        );
+        let published = HssVerifyingKey::from_bytes(
+            hex::decode(
+                "00000002\
+                 0000000600000003d08fabd4a2091ff0a8cb4ed834e74534\
+                 32a58885cd9ba0431235466bff9651c6c92124404d45fa53cf161c28f1ad5a8e",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(published.levels(), 2);
+
+        // The top level's signature of the bottom level key is made here
+        // rather than taken from the RFC; the message signature is checked
+        // against the published bytes
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let bottom_public = bottom.verifying_key();
+        top.q = 5;
+        let signed_key = (
+            top.sign(bottom_public.to_bytes(), &mut rng).unwrap(),
+            bottom_public,
+        );
+        bottom.q = 4;
+        let message = b"The enumeration in the Constitution, of certain rights, shall not be \
+                        construed to deny or disparage others retained by the people.\n";
+        let c = hex::decode("0eb1ed54a2460d512388cad533138d240534e97b1e82d33bd927d201dfc24ebb")
+            .unwrap();
+        let signature = HssSignature {
+            signed_keys: vec![signed_key],
+            signature: bottom
+                .sign_with_randomizer(message, c.try_into().unwrap())
+                .unwrap(),
+        };
+        let bytes = signature.to_bytes();
+        let tail = &bytes[bytes.len() - signature.signature.to_bytes().len()..];
+        assert_eq!(
+            hex::encode(&tail[..72]),
+            "00000004000000040eb1ed54a2460d512388cad533138d240534e97b1e82d33bd927d201dfc24ebb\
+             11b3649023696f85150b189e50c00e98850ac343a77b3638319c347d7310269d"
+        );
+        let signature = HssSignature::from_bytes(&bytes).unwrap();
+        assert!(published.verify(&signature, message).is_ok());
+        assert!(published.verify(&signature, &message[1..]).is_err());
+
+        // The same levels loaded as a hierarchy have the published public key
+        let sk = HssSigningKey {
+            levels: vec![top, bottom],
+            signed_keys: signature.signed_keys,
+        };
+        let sk = HssSigningKey::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(sk.verifying_key(), published);
+    }
+}
@@ -0,0 +1,203 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportDigest, LamportError, LamportResult, Signature, SigningKey, VerifyingKey};
+use serde_json::{Number, Value};
+use std::fmt::Write;
+
+/// Encodes the value with the JSON Canonicalization Scheme (RFC 8785).
+///
+/// Object members are sorted by the UTF-16 code units of their names,
+/// strings use the minimal escapes of ECMAScript `JSON.stringify`, and
+/// numbers are written as IEEE 754 doubles in ECMAScript form.
+pub(crate) fn to_canonical_json(value: &Value) -> LamportResult<Vec<u8>> {
+    let mut output = String::new();
+    write_value(value, &mut output)?;
+    Ok(output.into_bytes())
+}
+
+fn write_value(value: &Value, output: &mut String) -> LamportResult<()> {
+    match value {
+        Value::Null => output.push_str("null"),
+        Value::Bool(b) => output.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, output)?,
+        Value::String(s) => write_string(s, output),
+        Value::Array(items) => {
+            output.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_value(item, output)?;
+            }
+            output.push(']');
+        }
+        Value::Object(members) => {
+            let mut members = members.iter().collect::<Vec<_>>();
+            members.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+            output.push('{');
+            for (i, (name, member)) in members.into_iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_string(name, output);
+                output.push(':');
+                write_value(member, output)?;
+            }
+            output.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, output: &mut String) {
+    output.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(output, "\\u{:04x}", u32::from(c));
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
+
+/// Writes the number like ECMAScript `Number.prototype.toString`.
+fn write_number(n: &Number, output: &mut String) -> LamportResult<()> {
+    let value = n
+        .as_f64()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| LamportError::General(format!("{} is not a finite double", n)))?;
+    if value == 0.0 {
+        output.push('0');
+        return Ok(());
+    }
+    // `{:e}` gives the shortest round trip digits, e.g. `-1.2345e-7`
+    let formatted = format!("{:e}", value);
+    let (mantissa, exponent) = formatted
+        .split_once('e')
+        .ok_or_else(|| LamportError::General(format!("unable to format {}", value)))?;
+    let exponent = exponent
+        .parse::<i32>()
+        .map_err(|_| LamportError::General(format!("unable to format {}", value)))?;
+    let (negative, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => (true, m),
+        None => (false, mantissa),
+    };
+    let digits = mantissa.replace('.', "");
+    let k = i32::try_from(digits.len())
+        .map_err(|_| LamportError::General(format!("unable to format {}", value)))?;
+    let n = exponent + 1;
+
+    if negative {
+        output.push('-');
+    }
+    if k <= n && n <= 21 {
+        output.push_str(&digits);
+        output.extend((k..n).map(|_| '0'));
+    } else if 0 < n && n <= 21 {
+        let (integer, fraction) = digits.split_at(n.unsigned_abs() as usize);
+        output.push_str(integer);
+        output.push('.');
+        output.push_str(fraction);
+    } else if -6 < n && n <= 0 {
+        output.push_str("0.");
+        output.extend((n..0).map(|_| '0'));
+        output.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        output.push_str(first);
+        if !rest.is_empty() {
+            output.push('.');
+            output.push_str(rest);
+        }
+        let _ = write!(
+            output,
+            "e{}{}",
+            if n > 0 { "+" } else { "-" },
+            (n - 1).abs()
+        );
+    }
+    Ok(())
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs the RFC 8785 canonical JSON encoding of the value.
+    pub fn sign_json(&mut self, value: &Value) -> LamportResult<Signature<T>> {
+        let bytes = to_canonical_json(value)?;
+        self.sign(bytes)
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies a [`Signature`] over the RFC 8785 canonical JSON encoding of the value.
+    pub fn verify_json(&self, signature: &Signature<T>, value: &Value) -> LamportResult<()> {
+        let bytes = to_canonical_json(value)?;
+        self.verify(signature, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn canonical_json() {
+        // RFC 8785 §3.2.2 example
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let value = serde_json::from_str(input).unwrap();
+        let expected = r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#;
+        assert_eq!(
+            String::from_utf8(to_canonical_json(&value).unwrap()).unwrap(),
+            expected
+        );
+
+        for (number, expected) in [
+            ("0", "0"),
+            ("-0.0", "0"),
+            ("1e21", "1e+21"),
+            ("1e20", "100000000000000000000"),
+            ("-1.5e-7", "-1.5e-7"),
+            ("0.000001", "0.000001"),
+            ("123456789012", "123456789012"),
+            ("9007199254740993", "9007199254740992"),
+        ] {
+            let value = serde_json::from_str(number).unwrap();
+            assert_eq!(to_canonical_json(&value).unwrap(), expected.as_bytes());
+        }
+
+        // Members are sorted by UTF-16 code units, not code points
+        let value = serde_json::json!({ "\u{e000}": 1, "\u{1f600}": 2 });
+        assert_eq!(
+            String::from_utf8(to_canonical_json(&value).unwrap()).unwrap(),
+            "{\"\u{1f600}\":2,\"\u{e000}\":1}"
+        );
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let document = serde_json::json!({ "b": [1, 2.0], "a": "x" });
+        let signature = sk.sign_json(&document).unwrap();
+        let reordered = serde_json::from_str(r#"{ "a": "x", "b": [1.0, 2] }"#).unwrap();
+        assert!(pk.verify_json(&signature, &reordered).is_ok());
+        assert!(pk
+            .verify_json(&signature, &serde_json::json!({ "a": "y", "b": [1, 2] }))
+            .is_err());
+    }
+}
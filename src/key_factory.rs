@@ -0,0 +1,786 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::check_security;
+use crate::key_state::hmac_sha256;
+use crate::signable::possession_message;
+use crate::{
+    KeyId, KeyState, Keypair, LamportDigest, LamportError, LamportResult, Limits, SeedKdf,
+    SeedSigningKey, Signature, SigningKey, VerifyingKey, SEED_BYTES,
+};
+use hmac::Mac;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+use std::ops::Range;
+use zeroize::Zeroize;
+
+const KEY_DOMAIN: &[u8] = b"lamport_signature_plus key factory key v1";
+const CHILD_DOMAIN: &[u8] = b"lamport_signature_plus key factory child v1";
+const ID_DOMAIN: &[u8] = b"lamport_signature_plus key factory id v1";
+const EXPORT_MAGIC: &[u8; 8] = b"LMPTPOOL";
+const EXPORT_VERSION: u8 = 1;
+const EXPORT_HEADER_BYTES: usize = EXPORT_MAGIC.len() + 1 + SEED_BYTES + 1 + 16;
+const TAG_BYTES: usize = 32;
+
+/// Derives independent one-time keys by index from a master seed.
+///
+/// The seed of key `i` is `SHA-256(tag || master || i)` with `i` a big
+/// endian `u64`, expanded with the factory's [`SeedKdf`]. The verifying keys
+/// for a range of indices can be published up front, and the signing key
+/// for an index regenerated when it is needed, so only the master seed has
+/// to be stored. [`KeyFactory::child`] derives a factory of its own from an
+/// index under a separate tag, so a hierarchy of factories, for example one
+/// per device, shares a single master seed.
+///
+/// The factory does not track which indices have signed. The caller must
+/// never derive and use the same index twice, for example by recording them
+/// in a [`KeyState`] for [`KeyFactory::fingerprint`] with
+/// [`KeyFactory::reserve`] or [`KeyFactory::sign_many`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyFactory<T: LamportDigest> {
+    pub(crate) master: [u8; SEED_BYTES],
+    pub(crate) kdf: SeedKdf,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(KeyFactory);
+vec_impl!(KeyFactory);
+
+impl<T: LamportDigest> Zeroize for KeyFactory<T> {
+    fn zeroize(&mut self) {
+        self.master.zeroize();
+    }
+}
+
+impl<T: LamportDigest> KeyFactory<T> {
+    /// The length of the canonical bytes.
+    pub const BYTES: usize = SEED_BYTES + 1;
+
+    /// Constructs a [`KeyFactory`] from a master seed with the default [`SeedKdf`].
+    pub fn new(master: [u8; SEED_BYTES]) -> LamportResult<Self> {
+        Self::with_kdf(master, SeedKdf::default())
+    }
+
+    /// Constructs a [`KeyFactory`] from a master seed with the specified [`SeedKdf`].
+    pub fn with_kdf(master: [u8; SEED_BYTES], kdf: SeedKdf) -> LamportResult<Self> {
+        check_security::<T>()?;
+        Ok(Self {
+            master,
+            kdf,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Constructs a [`KeyFactory`] from a random master seed.
+    pub fn random(mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        let mut master = [0u8; SEED_BYTES];
+        rng.fill_bytes(&mut master);
+        Self::new(master)
+    }
+
+    /// The seed expansion version.
+    pub fn kdf(&self) -> SeedKdf {
+        self.kdf
+    }
+
+    /// Derives the [`Keypair`] at the index.
+    pub fn derive(&self, index: u64) -> Keypair<T> {
+        let mut seed = self.seed(KEY_DOMAIN, index);
+        let signing_key = SigningKey::expand_seed(&seed, self.kdf);
+        seed.zeroize();
+        Keypair::from(signing_key)
+    }
+
+    /// Derives the signing key at the index as a [`SeedSigningKey`], without
+    /// expanding its secret values.
+    pub fn seed_signing_key(&self, index: u64) -> SeedSigningKey<T> {
+        SeedSigningKey {
+            seed: self.seed(KEY_DOMAIN, index),
+            kdf: self.kdf,
+            used: false,
+            algorithm: PhantomData,
+        }
+    }
+
+    /// Derives the [`VerifyingKey`] at the index.
+    pub fn verifying_key(&self, index: u64) -> VerifyingKey<T> {
+        let mut keypair = self.derive(index);
+        keypair.zeroize();
+        keypair.public
+    }
+
+    /// Derives the [`VerifyingKey`]s of the indices to publish ahead of time.
+    pub fn verifying_keys(&self, indices: Range<u64>) -> Vec<VerifyingKey<T>> {
+        indices.map(|index| self.verifying_key(index)).collect()
+    }
+
+    /// The commitment to the key at the index, its [`KeyId`].
+    ///
+    /// Publishing the 32 byte commitment of each key in a pool takes a
+    /// fraction of the space of its [`VerifyingKey`]. The signer sends the
+    /// verifying key along with each signature, and relying parties check it
+    /// against the commitment with [`VerifyingKey::verify_with_fingerprint`].
+    pub fn leaf_commitment(&self, index: u64) -> KeyId {
+        self.verifying_key(index).fingerprint()
+    }
+
+    /// Derives the commitments of the indices to publish ahead of time.
+    pub fn leaf_commitments(&self, indices: Range<u64>) -> Vec<KeyId> {
+        indices.map(|index| self.leaf_commitment(index)).collect()
+    }
+
+    /// Lazily derives the [`VerifyingKey`]s of the indices in the range that
+    /// the factory's [`KeyState`] records as unused.
+    ///
+    /// Keys are only derived as the iterator advances, so upcoming keys can be
+    /// published in batches with [`Iterator::take`] without deriving the rest
+    /// of the pool.
+    pub fn unused_keys<'a>(
+        &'a self,
+        state: &'a KeyState,
+        indices: Range<u64>,
+    ) -> LamportResult<impl Iterator<Item = (u64, VerifyingKey<T>)> + 'a> {
+        self.check_state(state)?;
+        Ok(state
+            .unused_in(indices)
+            .map(move |index| (index, self.verifying_key(index))))
+    }
+
+    /// Lazily derives the [`VerifyingKey`]s of the indices in the range that
+    /// the factory's [`KeyState`] records as used, to audit consumed keys.
+    pub fn used_keys<'a>(
+        &'a self,
+        state: &'a KeyState,
+        indices: Range<u64>,
+    ) -> LamportResult<impl Iterator<Item = (u64, VerifyingKey<T>)> + 'a> {
+        self.check_state(state)?;
+        Ok(state
+            .used_in(indices)
+            .map(move |index| (index, self.verifying_key(index))))
+    }
+
+    /// Derives the child [`KeyFactory`] at the index.
+    ///
+    /// Child factories are independent of each other and of the keys of
+    /// this factory, including the key with the same index.
+    pub fn child(&self, index: u64) -> Self {
+        Self {
+            master: self.seed(CHILD_DOMAIN, index),
+            kdf: self.kdf,
+            algorithm: PhantomData,
+        }
+    }
+
+    /// The [`KeyId`] that identifies the factory in a [`KeyState`].
+    ///
+    /// It is derived from the master seed under a separate tag, so it
+    /// reveals nothing about the seed or the keys.
+    pub fn fingerprint(&self) -> KeyId {
+        let mut seed = self.seed(ID_DOMAIN, 0);
+        let key_id = KeyId::compute_encoded(b"KeyFactory", T::digest_size_in_bits(), &seed);
+        seed.zeroize();
+        key_id
+    }
+
+    /// Proves possession of the key at `leaf` with a statement over its
+    /// [`KeyId`] and `context`, signed by the key at `certifier`.
+    ///
+    /// The certifier's one-time key is consumed but the leaf stays unused, so
+    /// it can still sign once accepted. The certifier index is marked used in
+    /// the factory's [`KeyState`], created with [`KeyState::new`] from
+    /// [`KeyFactory::fingerprint`], and handed to `persist` before the key
+    /// signs, as [`SigningKey::sign_with_state`] does. Relying parties that
+    /// trust the certifier's [`VerifyingKey`] check the proof with
+    /// [`VerifyingKey::check_certified_possession`].
+    pub fn prove_possession<F>(
+        &self,
+        state: &mut KeyState,
+        certifier: u64,
+        leaf: u64,
+        context: &[u8],
+        persist: F,
+    ) -> LamportResult<Signature<T>>
+    where
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        self.check_state(state)?;
+        if certifier == leaf {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        state.mark_used(certifier)?;
+        persist(state)?;
+        let key_id = self.leaf_commitment(leaf);
+        let mut keypair = self.derive(certifier);
+        let proof = keypair.sign(possession_message(
+            &keypair.public.fingerprint(),
+            &key_id,
+            context,
+        ));
+        keypair.zeroize();
+        proof
+    }
+
+    /// Claims the lowest unused index in the factory's [`KeyState`] for a
+    /// later signature.
+    ///
+    /// The index is reserved in a copy of the state, which is handed to
+    /// `persist` before the state is updated, so a failed `persist` claims
+    /// nothing. Workers sharing a pool load the latest state, reserve, and
+    /// store it only if its [`KeyState::generation`] has not changed since it
+    /// was loaded. A worker whose store fails reloads the state and reserves
+    /// again, so no two workers ever hold the same index.
+    pub fn reserve<F>(&self, state: &mut KeyState, persist: F) -> LamportResult<Reservation<'_, T>>
+    where
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        self.check_state(state)?;
+        let mut reserved = state.clone();
+        let index = reserved.reserve()?;
+        persist(&reserved)?;
+        *state = reserved;
+        Ok(Reservation {
+            factory: self,
+            index,
+        })
+    }
+
+    /// Signs each message with a key of its own, recording the whole batch in
+    /// the factory's [`KeyState`] at once.
+    ///
+    /// The lowest unused indices are reserved in a copy of the state, which is
+    /// handed to `persist` once for the batch. Only if that succeeds is the
+    /// state updated and the batch signed, so either every message is signed
+    /// or no index is used. Returns [`LamportError::KeyPoolExhausted`] without
+    /// changing the state if fewer indices remain than there are messages.
+    pub fn sign_many<B, F>(
+        &self,
+        state: &mut KeyState,
+        messages: &[B],
+        persist: F,
+    ) -> LamportResult<Vec<(u64, Signature<T>)>>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        self.check_state(state)?;
+        let mut reserved = state.clone();
+        let indices = messages
+            .iter()
+            .map(|_| reserved.reserve())
+            .collect::<LamportResult<Vec<_>>>()?;
+        persist(&reserved)?;
+        *state = reserved;
+        indices
+            .into_iter()
+            .zip(messages)
+            .map(|(index, message)| {
+                let mut keypair = self.derive(index);
+                let signature = keypair.sign(message);
+                keypair.zeroize();
+                signature.map(|signature| (index, signature))
+            })
+            .collect()
+    }
+
+    /// Converts the [`KeyFactory`] to canonical bytes, the
+    /// [`SeedKdf::version`] and the master seed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.push(self.kdf.version());
+        bytes.extend_from_slice(&self.master);
+        bytes
+    }
+
+    /// Constructs a [`KeyFactory`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        if input.len() != Self::BYTES {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let kdf = SeedKdf::try_from(input[0])?;
+        Self::with_kdf(input[1..].try_into().expect("seed length"), kdf)
+    }
+
+    /// Exports the factory with its [`KeyState`], to move a key pool to
+    /// another host.
+    ///
+    /// The format is a magic tag and version, the factory's canonical bytes,
+    /// the capacity and generation as big endian `u64`s, a bitmap of the used
+    /// indices with index `i` in bit `i % 8` of byte `i / 8`, then the
+    /// HMAC-SHA-256 of everything before it under the integrity key. It holds
+    /// the master seed, so it must be protected like the factory itself.
+    ///
+    /// Returns [`LamportError::InvalidKeyState`] if the bitmap does not fit
+    /// in memory.
+    pub fn export(&self, state: &KeyState, integrity_key: &[u8]) -> LamportResult<Vec<u8>> {
+        self.check_state(state)?;
+        let bitmap_len = usize::try_from(state.capacity().div_ceil(8))
+            .map_err(|_| LamportError::InvalidKeyState)?;
+        let mut bytes = Vec::with_capacity(EXPORT_HEADER_BYTES + bitmap_len + TAG_BYTES);
+        bytes.extend_from_slice(EXPORT_MAGIC);
+        bytes.push(EXPORT_VERSION);
+        bytes.extend_from_slice(&self.to_bytes());
+        bytes.extend_from_slice(&state.capacity().to_be_bytes());
+        bytes.extend_from_slice(&state.generation().to_be_bytes());
+        let bitmap = bytes.len();
+        bytes.resize(bitmap + bitmap_len, 0);
+        for index in state.used_indices() {
+            bytes[bitmap + (index / 8) as usize] |= 1 << (index % 8);
+        }
+        let tag = hmac_sha256(integrity_key).chain_update(&bytes).finalize();
+        bytes.extend_from_slice(&tag.into_bytes());
+        Ok(bytes)
+    }
+
+    /// Imports a factory and its [`KeyState`] from [`KeyFactory::export`],
+    /// checking them with the integrity key.
+    ///
+    /// Every index the export records as used stays used, so the imported
+    /// pool never issues it again. Returns [`LamportError::InvalidKeyState`]
+    /// if the tag does not match or the bytes are malformed.
+    pub fn import<B: AsRef<[u8]>>(
+        input: B,
+        integrity_key: &[u8],
+    ) -> LamportResult<(Self, KeyState)> {
+        Self::import_with_limits(input.as_ref(), integrity_key, &Limits::DEFAULT)
+    }
+
+    /// Imports a factory and its [`KeyState`] from an export no larger than `limits`.
+    pub fn import_with_limits(
+        input: &[u8],
+        integrity_key: &[u8],
+        limits: &Limits,
+    ) -> LamportResult<(Self, KeyState)> {
+        limits.check(input.len())?;
+        if input.len() < EXPORT_HEADER_BYTES + TAG_BYTES {
+            return Err(LamportError::InvalidKeyState);
+        }
+        let (body, tag) = input.split_at(input.len() - TAG_BYTES);
+        hmac_sha256(integrity_key)
+            .chain_update(body)
+            .verify_slice(tag)
+            .map_err(|_| LamportError::InvalidKeyState)?;
+        let (header, bitmap) = body.split_at(EXPORT_HEADER_BYTES);
+        let (magic, rest) = header.split_at(EXPORT_MAGIC.len());
+        if magic != EXPORT_MAGIC || rest[0] != EXPORT_VERSION {
+            return Err(LamportError::InvalidKeyState);
+        }
+        let (factory, words) = rest[1..].split_at(Self::BYTES);
+        let factory = Self::from_bytes(factory)?;
+        let capacity = u64::from_be_bytes(words[..8].try_into().expect("eight bytes"));
+        let generation = u64::from_be_bytes(words[8..].try_into().expect("eight bytes"));
+        if capacity == 0 || capacity.div_ceil(8) != bitmap.len() as u64 {
+            return Err(LamportError::InvalidKeyState);
+        }
+        let mut state = KeyState::new(factory.fingerprint(), capacity)?;
+        for (byte, bits) in bitmap.iter().enumerate() {
+            for bit in 0..8 {
+                if bits & (1 << bit) == 0 {
+                    continue;
+                }
+                let index = byte as u64 * 8 + bit;
+                if index >= capacity {
+                    return Err(LamportError::InvalidKeyState);
+                }
+                state.used.insert(index);
+            }
+        }
+        state.generation = generation;
+        Ok((factory, state))
+    }
+
+    fn check_state(&self, state: &KeyState) -> LamportResult<()> {
+        if state.key_id() != self.fingerprint() {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        Ok(())
+    }
+
+    fn seed(&self, domain: &[u8], index: u64) -> [u8; SEED_BYTES] {
+        let mut hasher = Sha256::new();
+        hasher.update(domain);
+        hasher.update(self.master);
+        hasher.update(index.to_be_bytes());
+        hasher.finalize().into()
+    }
+}
+
+/// An index of a [`KeyFactory`] claimed with [`KeyFactory::reserve`].
+///
+/// A reservation either signs one message or is released, and cannot be
+/// cloned, so its key never signs twice.
+#[derive(Debug)]
+pub struct Reservation<'a, T: LamportDigest> {
+    factory: &'a KeyFactory<T>,
+    index: u64,
+}
+
+impl<T: LamportDigest> Reservation<'_, T> {
+    /// The reserved index.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Signs the data with the key at the reserved index.
+    pub fn sign<B: AsRef<[u8]>>(self, data: B) -> LamportResult<Signature<T>> {
+        let mut keypair = self.factory.derive(self.index);
+        let signature = keypair.sign(data);
+        keypair.zeroize();
+        signature
+    }
+
+    /// Returns the index to the pool without signing.
+    ///
+    /// The index is marked unused in a copy of the current [`KeyState`],
+    /// which is handed to `persist` before the state is updated. If
+    /// `persist` fails, the reservation is handed back with the error so the
+    /// caller can retry.
+    #[allow(clippy::result_large_err)]
+    pub fn release<F>(self, state: &mut KeyState, persist: F) -> Result<(), (Self, LamportError)>
+    where
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        let mut released = state.clone();
+        let result = self
+            .factory
+            .check_state(state)
+            .and_then(|_| released.release(self.index))
+            .and_then(|_| persist(&released));
+        match result {
+            Ok(()) => {
+                *state = released;
+                Ok(())
+            }
+            Err(e) => Err((self, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use std::cell::RefCell;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn key_factory() {
+        type D = LamportFixedDigest<Sha256>;
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let published = factory.verifying_keys(0..4);
+        assert_eq!(published.len(), 4);
+        for (i, key) in published.iter().enumerate() {
+            assert!(published[i + 1..].iter().all(|other| other != key));
+        }
+
+        // Regenerate a signing key later from the master seed alone
+        let restored = KeyFactory::<D>::from_bytes(factory.to_bytes()).unwrap();
+        let mut keypair = restored.derive(2);
+        assert_eq!(keypair.public, published[2]);
+        let signature = keypair.sign(b"indexed").unwrap();
+        assert!(published[2].verify(&signature, b"indexed").is_ok());
+        assert!(published[1].verify(&signature, b"indexed").is_err());
+        let mut lazy = factory.seed_signing_key(2);
+        assert_eq!(
+            lazy.sign(b"indexed").unwrap().to_bytes(),
+            signature.to_bytes()
+        );
+
+        let child = factory.child(2);
+        assert_ne!(child.verifying_key(2), published[2]);
+        assert_eq!(child.verifying_key(0), factory.child(2).verifying_key(0));
+        assert_ne!(child.verifying_key(0), factory.child(3).verifying_key(0));
+        assert!(KeyFactory::<D>::from_bytes([1u8; 32]).is_err());
+    }
+
+    #[test]
+    fn leaf_commitments() {
+        type D = LamportFixedDigest<Sha256>;
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let commitments = factory.leaf_commitments(0..4);
+        assert!(KeyId::BYTES * 100 < factory.verifying_key(0).to_bytes().len());
+        assert_eq!(commitments[2], factory.leaf_commitment(2));
+
+        // The signer sends the full key, which is checked against the commitment
+        let mut keypair = factory.derive(2);
+        let signature = keypair.sign(b"committed").unwrap();
+        assert!(keypair
+            .public
+            .verify_with_fingerprint(&signature, b"committed", &commitments[2])
+            .is_ok());
+        assert_eq!(
+            keypair
+                .public
+                .verify_with_fingerprint(&signature, b"committed", &commitments[1])
+                .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+    }
+
+    #[test]
+    fn key_pages() {
+        type D = LamportFixedDigest<Sha256>;
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let mut state = KeyState::new(factory.fingerprint(), 100).unwrap();
+        for index in [0, 1, 3, 50] {
+            state.mark_used(index).unwrap();
+        }
+
+        // Publish the next batch of unused keys
+        let batch = factory
+            .unused_keys(&state, 0..u64::MAX)
+            .unwrap()
+            .take(3)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            batch.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            [2, 4, 5]
+        );
+        assert!(batch
+            .iter()
+            .all(|(index, key)| *key == factory.verifying_key(*index)));
+        assert_eq!(state.unused_in(45..60).count(), 14);
+        assert_eq!(state.unused_in(98..200).collect::<Vec<_>>(), [98, 99]);
+        assert_eq!(state.unused_indices().count(), 96);
+
+        // Audit the consumed keys in a range
+        let used = factory.used_keys(&state, 2..60).unwrap();
+        assert_eq!(used.map(|(index, _)| index).collect::<Vec<_>>(), [3, 50]);
+        let (start, end) = (60, 2);
+        assert_eq!(state.used_in(start..end).count(), 0);
+        let other = KeyFactory::<D>::new([4u8; 32]).unwrap();
+        assert!(other.unused_keys(&state, 0..10).is_err());
+        assert!(other.used_keys(&state, 0..10).is_err());
+    }
+
+    #[test]
+    fn sign_many() {
+        type D = LamportFixedDigest<Sha256>;
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let mut state = KeyState::new(factory.fingerprint(), 4).unwrap();
+        state.mark_used(1).unwrap();
+        let messages = [&b"first"[..], b"second", b"third"];
+
+        // A failed persist leaves the state untouched and nothing signed
+        let before = state.clone();
+        assert!(factory
+            .sign_many(&mut state, &messages, |_| Err(LamportError::General(
+                "disk full".to_string()
+            )))
+            .is_err());
+        assert_eq!(state, before);
+
+        let mut persisted = None;
+        let signatures = factory
+            .sign_many(&mut state, &messages, |state| {
+                persisted = Some(state.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(persisted.as_ref(), Some(&state));
+        assert_eq!(
+            signatures
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            [0, 2, 3]
+        );
+        for ((index, signature), message) in signatures.iter().zip(messages) {
+            assert!(factory
+                .verifying_key(*index)
+                .verify(signature, message)
+                .is_ok());
+        }
+
+        // A batch larger than the remaining keys uses none of them
+        let mut state = KeyState::new(factory.fingerprint(), 2).unwrap();
+        assert_eq!(
+            factory
+                .sign_many(&mut state, &messages, |_| Ok(()))
+                .unwrap_err(),
+            LamportError::KeyPoolExhausted
+        );
+        assert_eq!(state.remaining(), 2);
+        let other = KeyFactory::<D>::new([4u8; 32]).unwrap();
+        assert_eq!(
+            other
+                .sign_many(&mut state, &messages[..1], |_| Ok(()))
+                .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+    }
+
+    #[test]
+    fn export_and_import() {
+        type D = LamportFixedDigest<Sha256>;
+        const INTEGRITY_KEY: &[u8] = b"pool integrity key";
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let mut state = KeyState::new(factory.fingerprint(), 20).unwrap();
+        for index in [0, 1, 9, 19] {
+            state.mark_used(index).unwrap();
+        }
+        let exported = factory.export(&state, INTEGRITY_KEY).unwrap();
+        assert_eq!(exported.len(), 8 + 1 + KeyFactory::<D>::BYTES + 16 + 3 + 32);
+
+        // The imported pool refuses every index used before the move
+        let (imported, mut moved) = KeyFactory::<D>::import(&exported, INTEGRITY_KEY).unwrap();
+        assert_eq!(imported.to_bytes(), factory.to_bytes());
+        assert_eq!(moved, state);
+        assert_eq!(
+            moved.mark_used(9).unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        let signatures = imported
+            .sign_many(&mut moved, &[b"moved"], |_| Ok(()))
+            .unwrap();
+        assert_eq!(signatures[0].0, 2);
+
+        assert_eq!(
+            KeyFactory::<D>::import(&exported, b"wrong key").unwrap_err(),
+            LamportError::InvalidKeyState
+        );
+        let mut edited = exported.clone();
+        edited[exported.len() - 32 - 3] = 0;
+        assert!(KeyFactory::<D>::import(&edited, INTEGRITY_KEY).is_err());
+        assert!(KeyFactory::<D>::import(&exported[1..], INTEGRITY_KEY).is_err());
+
+        // Bits past the capacity are rejected even under a valid tag
+        let mut padded = exported[..exported.len() - 32].to_vec();
+        *padded.last_mut().unwrap() |= 0x10;
+        let tag = hmac_sha256(INTEGRITY_KEY).chain_update(&padded).finalize();
+        padded.extend_from_slice(&tag.into_bytes());
+        assert!(KeyFactory::<D>::import(&padded, INTEGRITY_KEY).is_err());
+        assert!(matches!(
+            KeyFactory::<D>::import_with_limits(&exported, INTEGRITY_KEY, &Limits::new(64)),
+            Err(LamportError::InputTooLarge { .. })
+        ));
+        let other = KeyFactory::<D>::new([4u8; 32]).unwrap();
+        assert!(other.export(&state, INTEGRITY_KEY).is_err());
+    }
+
+    #[test]
+    fn reservations() {
+        type D = LamportFixedDigest<Sha256>;
+        const INTEGRITY_KEY: &[u8] = b"pool integrity key";
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let state = KeyState::new(factory.fingerprint(), 4).unwrap();
+        let store = RefCell::new(state.to_bytes(INTEGRITY_KEY));
+
+        // Stores only a state derived from the latest one
+        let compare_and_swap = |state: &KeyState| {
+            let stored = KeyState::from_bytes(&*store.borrow(), INTEGRITY_KEY)?;
+            if stored.generation() >= state.generation() {
+                return Err(LamportError::General("the state changed".to_string()));
+            }
+            *store.borrow_mut() = state.to_bytes(INTEGRITY_KEY);
+            Ok(())
+        };
+
+        // Two workers load the same state and race for the next index
+        let mut first = KeyState::from_bytes(&*store.borrow(), INTEGRITY_KEY).unwrap();
+        let mut second = first.clone();
+        let reservation = factory.reserve(&mut first, compare_and_swap).unwrap();
+        assert_eq!(reservation.index(), 0);
+        assert!(factory.reserve(&mut second, compare_and_swap).is_err());
+        assert!(!second.is_used(0));
+        let mut second = KeyState::from_bytes(&*store.borrow(), INTEGRITY_KEY).unwrap();
+        let other = factory.reserve(&mut second, compare_and_swap).unwrap();
+        assert_eq!(other.index(), 1);
+
+        let signature = reservation.sign(b"first").unwrap();
+        assert!(factory
+            .verifying_key(0)
+            .verify(&signature, b"first")
+            .is_ok());
+
+        // A released index is reserved again
+        let (other, _) = other
+            .release(&mut second, |_| Err(LamportError::InvalidKeyState))
+            .unwrap_err();
+        assert!(second.is_used(1));
+        assert!(other.release(&mut second, compare_and_swap).is_ok());
+        assert!(!second.is_used(1));
+        let mut state = KeyState::from_bytes(&*store.borrow(), INTEGRITY_KEY).unwrap();
+        assert_eq!(state, second);
+        assert_eq!(
+            factory
+                .reserve(&mut state, compare_and_swap)
+                .unwrap()
+                .index(),
+            1
+        );
+        let stranger = KeyFactory::<D>::new([4u8; 32]).unwrap();
+        assert_eq!(
+            stranger.reserve(&mut state, |_| Ok(())).unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+    }
+
+    #[test]
+    fn certified_possession() {
+        type D = LamportFixedDigest<Sha256>;
+        let factory = KeyFactory::<D>::new(SEED).unwrap();
+        let certifier = factory.verifying_key(0);
+        let leaf = factory.verifying_key(1);
+        let mut state = KeyState::new(factory.fingerprint(), 8).unwrap();
+        let mut persisted = None;
+        let proof = factory
+            .prove_possession(&mut state, 0, 1, b"registry", |state| {
+                persisted = Some(state.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(persisted.as_ref(), Some(&state));
+        assert!(state.is_used(0));
+        assert!(!state.is_used(1));
+        assert!(certifier
+            .check_certified_possession(&proof, &leaf, b"registry")
+            .is_ok());
+        assert!(certifier
+            .check_certified_possession(&proof, &leaf, b"other registry")
+            .is_err());
+        assert!(certifier
+            .check_certified_possession(&proof, &factory.verifying_key(2), b"registry")
+            .is_err());
+        assert!(leaf
+            .check_certified_possession(&proof, &leaf, b"registry")
+            .is_err());
+        assert!(certifier.check_possession(&proof, b"registry").is_err());
+        assert_eq!(
+            factory
+                .prove_possession(&mut state, 1, 1, b"registry", |_| Ok(()))
+                .unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+
+        // The certifier cannot sign a second proof
+        for (leaf, context) in [(1, &b"registry"[..]), (2, b"registry"), (1, b"other")] {
+            assert_eq!(
+                factory
+                    .prove_possession(&mut state, 0, leaf, context, |_| Ok(()))
+                    .unwrap_err(),
+                LamportError::PrivateKeyReuseError
+            );
+        }
+        let other = KeyFactory::<D>::new([4u8; 32]).unwrap();
+        assert_eq!(
+            other
+                .prove_possession(&mut state, 3, 1, b"registry", |_| Ok(()))
+                .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+
+        // Nothing is signed unless the state was persisted
+        let failed = factory.prove_possession(&mut state, 3, 1, b"registry", |_| {
+            Err(LamportError::General("disk full".to_string()))
+        });
+        assert!(failed.is_err());
+
+        // The leaf still signs once accepted
+        let signature = factory.derive(1).sign(b"accepted").unwrap();
+        assert!(leaf.verify(&signature, b"accepted").is_ok());
+    }
+}
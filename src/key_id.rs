@@ -0,0 +1,96 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display, Formatter};
+
+/// A short, fixed size fingerprint that identifies a [`VerifyingKey`].
+///
+/// The fingerprint is SHA-256 over a domain separation tag, the digest size
+/// of the key, and the canonical key bytes, so keys for different digest
+/// algorithms never share an identifier.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Default)]
+pub struct KeyId(pub(crate) [u8; KeyId::BYTES]);
+
+serde_impl!(@concrete KeyId);
+
+impl KeyId {
+    /// The number of bytes in a [`KeyId`].
+    pub const BYTES: usize = 32;
+
+    const DOMAIN: &'static [u8] = b"lamport_signature_plus key id v1";
+
+    /// Computes the [`KeyId`] of the [`VerifyingKey`].
+    pub fn from_verifying_key<T: LamportDigest>(key: &VerifyingKey<T>) -> Self {
+        key.fingerprint()
+    }
+
+    pub(crate) fn compute(
+        bits: usize,
+        zero_values: &MultiVec<u8, 2>,
+        one_values: &MultiVec<u8, 2>,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(Self::DOMAIN);
+        hasher.update((bits as u64).to_be_bytes());
+        hasher.update(zero_values.as_ref());
+        hasher.update(one_values.as_ref());
+        Self(hasher.finalize().into())
+    }
+
+    /// Computes the [`KeyId`] of a stateful scheme's canonical public key bytes.
+    ///
+    /// The scheme name keeps keys of different schemes with the same bytes apart.
+    pub(crate) fn compute_encoded(scheme: &[u8], bits: usize, key: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(Self::DOMAIN);
+        hasher.update((scheme.len() as u64).to_be_bytes());
+        hasher.update(scheme);
+        hasher.update((bits as u64).to_be_bytes());
+        hasher.update(key);
+        Self(hasher.finalize().into())
+    }
+
+    /// The raw fingerprint bytes.
+    pub fn as_bytes(&self) -> &[u8; Self::BYTES] {
+        &self.0
+    }
+
+    /// Converts the [`KeyId`] to canonical bytes.
+    pub fn to_bytes(&self) -> [u8; Self::BYTES] {
+        self.0
+    }
+
+    /// Constructs a [`KeyId`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let bytes = <[u8; Self::BYTES]>::try_from(input.as_ref())
+            .map_err(|_| LamportError::InvalidKeyIdBytes)?;
+        Ok(Self(bytes))
+    }
+}
+
+impl Display for KeyId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl From<[u8; KeyId::BYTES]> for KeyId {
+    fn from(value: [u8; KeyId::BYTES]) -> Self {
+        Self(value)
+    }
+}
+
+impl From<KeyId> for [u8; KeyId::BYTES] {
+    fn from(value: KeyId) -> Self {
+        value.0
+    }
+}
+
+impl AsRef<[u8]> for KeyId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
@@ -0,0 +1,163 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::merkle::{consistency_path, inclusion_path, leaf_hash, subtree_hash};
+use crate::{
+    ConsistencyProof, InclusionProof, KeyId, LamportDigest, LamportError, LamportResult,
+    VerifyingKey, MERKLE_HASH_BYTES,
+};
+
+/// An append-only, transparency log style record of published [`KeyId`]s.
+///
+/// Each log size has a Merkle root. Monitors that remember an older root can
+/// demand a [`ConsistencyProof`] showing the current log only appended to it,
+/// which detects retroactive substitution of one-time keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct KeyLog {
+    pub(crate) leaves: Vec<KeyId>,
+    pub(crate) hashes: Vec<[u8; MERKLE_HASH_BYTES]>,
+}
+
+serde_impl!(@limits @concrete KeyLog);
+
+impl KeyLog {
+    /// Constructs an empty [`KeyLog`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the [`KeyId`] and returns its index in the log.
+    pub fn append(&mut self, key_id: KeyId) -> u64 {
+        self.hashes.push(leaf_hash(&key_id));
+        self.leaves.push(key_id);
+        (self.leaves.len() - 1) as u64
+    }
+
+    /// Appends the fingerprint of the [`VerifyingKey`] and returns its index in the log.
+    pub fn append_key<T: LamportDigest>(&mut self, key: &VerifyingKey<T>) -> u64 {
+        self.append(key.fingerprint())
+    }
+
+    /// The number of entries in the log.
+    pub fn len(&self) -> u64 {
+        self.leaves.len() as u64
+    }
+
+    /// Returns true if nothing has been appended.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// The entry at `index`.
+    pub fn get(&self, index: u64) -> Option<&KeyId> {
+        usize::try_from(index).ok().and_then(|i| self.leaves.get(i))
+    }
+
+    /// The Merkle root of the whole log.
+    pub fn root(&self) -> LamportResult<[u8; MERKLE_HASH_BYTES]> {
+        self.root_at(self.len())
+    }
+
+    /// The Merkle root of the first `size` entries of the log.
+    pub fn root_at(&self, size: u64) -> LamportResult<[u8; MERKLE_HASH_BYTES]> {
+        let size = self.check_size(size)?;
+        Ok(subtree_hash(&self.hashes[..size]))
+    }
+
+    /// Proves the entry at `index` is included in the first `size` entries of the log.
+    pub fn prove_inclusion(&self, index: u64, size: u64) -> LamportResult<InclusionProof> {
+        let size_usize = self.check_size(size)?;
+        if index >= size {
+            return Err(LamportError::General(format!(
+                "index {} is not in a log of size {}",
+                index, size
+            )));
+        }
+        let mut path = Vec::new();
+        let index_usize = usize::try_from(index)
+            .map_err(|_| LamportError::General(format!("index {} is too large", index)))?;
+        inclusion_path(index_usize, &self.hashes[..size_usize], &mut path);
+        Ok(InclusionProof {
+            index,
+            tree_size: size,
+            path,
+        })
+    }
+
+    /// Proves the log of `old_size` entries is a prefix of the log of `new_size` entries.
+    pub fn prove_consistency(
+        &self,
+        old_size: u64,
+        new_size: u64,
+    ) -> LamportResult<ConsistencyProof> {
+        let new_size_usize = self.check_size(new_size)?;
+        if old_size > new_size {
+            return Err(LamportError::General(format!(
+                "old size {} is larger than new size {}",
+                old_size, new_size
+            )));
+        }
+        let old_size_usize = self.check_size(old_size)?;
+        let mut path = Vec::new();
+        if old_size < new_size {
+            consistency_path(
+                old_size_usize,
+                &self.hashes[..new_size_usize],
+                true,
+                &mut path,
+            );
+        }
+        Ok(ConsistencyProof {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+
+    /// Converts the [`KeyLog`] to canonical bytes, a big endian `u64` count followed by each [`KeyId`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + self.leaves.len() * KeyId::BYTES);
+        bytes.extend_from_slice(&self.len().to_be_bytes());
+        for key_id in &self.leaves {
+            bytes.extend_from_slice(key_id.as_bytes());
+        }
+        bytes
+    }
+
+    /// Constructs a [`KeyLog`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`KeyLog`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        if input.len() < 8 || !(input.len() - 8).is_multiple_of(KeyId::BYTES) {
+            return Err(LamportError::InvalidKeyLogBytes);
+        }
+        let mut count = [0u8; 8];
+        count.copy_from_slice(&input[..8]);
+        if u64::from_be_bytes(count) != ((input.len() - 8) / KeyId::BYTES) as u64 {
+            return Err(LamportError::InvalidKeyLogBytes);
+        }
+        let mut log = Self::new();
+        for chunk in input[8..].chunks_exact(KeyId::BYTES) {
+            log.append(KeyId::from_bytes(chunk)?);
+        }
+        Ok(log)
+    }
+
+    fn check_size(&self, size: u64) -> LamportResult<usize> {
+        if size == 0 || size > self.len() {
+            return Err(LamportError::General(format!(
+                "size {} is not in the range 1..={}",
+                size,
+                self.len()
+            )));
+        }
+        usize::try_from(size)
+            .map_err(|_| LamportError::General(format!("size {} is too large", size)))
+    }
+}
@@ -0,0 +1,662 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+#[cfg(feature = "unstable-hors")]
+use crate::hors::{HorsSignature, HorsSigningKey, HorsVerifyingKey};
+#[cfg(feature = "unstable-hss")]
+use crate::hss::{HssSignature, HssSigningKey};
+use crate::limits::Limits;
+#[cfg(feature = "unstable-lms")]
+use crate::lms::{LmsSignature, LmsSigningKey};
+#[cfg(feature = "unstable-xmss-mt")]
+use crate::xmss_mt::{XmssMtSignature, XmssMtSigningKey};
+use crate::{
+    KeyId, LamportDigest, LamportError, LamportResult, Signature, SigningKey, VerifyingKey,
+};
+use hmac::{Hmac, Mac};
+#[cfg(any(feature = "unstable-lms", feature = "unstable-hss"))]
+use rand::{CryptoRng, RngCore};
+use sha2::Sha256;
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+const STATE_MAGIC: &[u8; 8] = b"LMPTSTAT";
+const STATE_VERSION: u8 = 1;
+const HEADER_BYTES: usize = STATE_MAGIC.len() + 1 + KeyId::BYTES + 24;
+const TAG_BYTES: usize = 32;
+
+/// The record of which signatures of a key have been used.
+///
+/// A stateful key, or a pool of one-time keys, is only safe if a signature
+/// index is never used twice, including after a crash. The safe order is to
+/// [`KeyState::reserve`] an index, persist the state, and only then sign and
+/// release the signature. A crash after persisting wastes the reserved index
+/// but never reuses it. [`SigningKey::sign_with_state`] follows this order
+/// for one-time keys, and the `sign_with_state` methods of the unstable
+/// XMSS^MT, LMS, HSS and HORS keys for stateful keys, whose state is created with
+/// [`KeyState::new`] from the verifying key's fingerprint and the key's
+/// capacity.
+///
+/// The canonical bytes carry an HMAC-SHA-256 tag under a caller supplied
+/// integrity key, so a state file that was corrupted or edited to mark
+/// indices unused is rejected. The generation increases with every change,
+/// so stores can also reject an older state that is replayed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyState {
+    pub(crate) key_id: KeyId,
+    pub(crate) capacity: u64,
+    pub(crate) generation: u64,
+    pub(crate) used: BTreeSet<u64>,
+}
+
+impl KeyState {
+    /// Constructs the state of a fresh key identified by `key_id` that can
+    /// make `capacity` signatures.
+    pub fn new(key_id: KeyId, capacity: u64) -> LamportResult<Self> {
+        if capacity == 0 {
+            return Err(LamportError::InvalidKeyState);
+        }
+        Ok(Self {
+            key_id,
+            capacity,
+            generation: 0,
+            used: BTreeSet::new(),
+        })
+    }
+
+    /// Constructs the state of a one-time [`SigningKey`], with its single
+    /// index used if the key has already signed.
+    pub fn for_signing_key<T: LamportDigest>(key: &SigningKey<T>) -> Self {
+        let mut state = Self {
+            key_id: VerifyingKey::from(key).fingerprint(),
+            capacity: 1,
+            generation: 0,
+            used: BTreeSet::new(),
+        };
+        if key.used {
+            state.used.insert(0);
+        }
+        state
+    }
+
+    /// The fingerprint of the key this state belongs to.
+    pub fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    /// The number of signatures the key can make.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// The number of changes made to the state.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The number of unused indices.
+    pub fn remaining(&self) -> u64 {
+        self.capacity - self.used.len() as u64
+    }
+
+    /// Has the index been used or reserved.
+    pub fn is_used(&self, index: u64) -> bool {
+        self.used.contains(&index)
+    }
+
+    /// The used indices in increasing order.
+    pub fn used_indices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.used.iter().copied()
+    }
+
+    /// The unused indices in increasing order.
+    pub fn unused_indices(&self) -> impl Iterator<Item = u64> + '_ {
+        self.unused_in(0..self.capacity)
+    }
+
+    /// The used indices within `indices` in increasing order.
+    pub fn used_in(&self, indices: Range<u64>) -> impl Iterator<Item = u64> + '_ {
+        let end = indices.end.max(indices.start);
+        self.used.range(indices.start..end).copied()
+    }
+
+    /// The unused indices within `indices` in increasing order.
+    ///
+    /// Indices at or above the capacity are never returned.
+    pub fn unused_in(&self, indices: Range<u64>) -> impl Iterator<Item = u64> + '_ {
+        (indices.start..indices.end.min(self.capacity))
+            .filter(move |index| !self.used.contains(index))
+    }
+
+    /// Marks the lowest unused index as used and returns it.
+    ///
+    /// Returns [`LamportError::KeyPoolExhausted`] if every index is used.
+    pub fn reserve(&mut self) -> LamportResult<u64> {
+        let index = (0..self.capacity)
+            .zip(self.used.iter().copied().chain(std::iter::once(u64::MAX)))
+            .find(|(index, used)| index != used)
+            .map(|(index, _)| index)
+            .ok_or(LamportError::KeyPoolExhausted)?;
+        self.mark_used(index)?;
+        Ok(index)
+    }
+
+    /// Marks the index as used.
+    ///
+    /// Returns [`LamportError::PrivateKeyReuseError`] if it already was.
+    pub fn mark_used(&mut self, index: u64) -> LamportResult<()> {
+        if index >= self.capacity {
+            return Err(LamportError::InvalidKeyState);
+        }
+        if !self.used.insert(index) {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Marks a reserved index that never signed as unused again.
+    pub(crate) fn release(&mut self, index: u64) -> LamportResult<()> {
+        if !self.used.remove(&index) {
+            return Err(LamportError::InvalidKeyState);
+        }
+        self.generation += 1;
+        Ok(())
+    }
+
+    /// Reserves the lowest unused index from `next` on for the key and hands
+    /// the state to `persist` before the key may sign with the index.
+    ///
+    /// Indices below `next` were already used by the key, and used indices
+    /// above it were reserved by a signature that was lost in a crash, so
+    /// both are skipped.
+    #[cfg(any(
+        feature = "unstable-xmss-mt",
+        feature = "unstable-lms",
+        feature = "unstable-hors"
+    ))]
+    fn reserve_for<F>(
+        &mut self,
+        key_id: KeyId,
+        capacity: u64,
+        next: u64,
+        persist: F,
+    ) -> LamportResult<u64>
+    where
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        if self.capacity != capacity || self.key_id != key_id {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        let index = (next..self.capacity)
+            .find(|index| !self.used.contains(index))
+            .ok_or(LamportError::KeyPoolExhausted)?;
+        self.mark_used(index)?;
+        persist(self)?;
+        Ok(index)
+    }
+
+    /// Converts the [`KeyState`] to canonical bytes protected by the integrity key.
+    ///
+    /// The format is a magic tag and version, the [`KeyId`], the capacity,
+    /// generation and number of used indices as big endian `u64`s, each used
+    /// index as a big endian `u64`, then the HMAC-SHA-256 of everything before it.
+    pub fn to_bytes(&self, integrity_key: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES + self.used.len() * 8 + TAG_BYTES);
+        bytes.extend_from_slice(STATE_MAGIC);
+        bytes.push(STATE_VERSION);
+        bytes.extend_from_slice(self.key_id.as_bytes());
+        bytes.extend_from_slice(&self.capacity.to_be_bytes());
+        bytes.extend_from_slice(&self.generation.to_be_bytes());
+        bytes.extend_from_slice(&(self.used.len() as u64).to_be_bytes());
+        for index in &self.used {
+            bytes.extend_from_slice(&index.to_be_bytes());
+        }
+        let tag = hmac_sha256(integrity_key).chain_update(&bytes).finalize();
+        bytes.extend_from_slice(&tag.into_bytes());
+        bytes
+    }
+
+    /// Constructs a [`KeyState`] from canonical bytes, checking them with the integrity key.
+    ///
+    /// Returns [`LamportError::InvalidKeyState`] if the tag does not match
+    /// or the bytes are malformed.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B, integrity_key: &[u8]) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), integrity_key, &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`KeyState`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(
+        input: &[u8],
+        integrity_key: &[u8],
+        limits: &Limits,
+    ) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        if input.len() < HEADER_BYTES + TAG_BYTES
+            || !(input.len() - HEADER_BYTES - TAG_BYTES).is_multiple_of(8)
+        {
+            return Err(LamportError::InvalidKeyState);
+        }
+        let (body, tag) = input.split_at(input.len() - TAG_BYTES);
+        hmac_sha256(integrity_key)
+            .chain_update(body)
+            .verify_slice(tag)
+            .map_err(|_| LamportError::InvalidKeyState)?;
+        if &body[..STATE_MAGIC.len()] != STATE_MAGIC || body[STATE_MAGIC.len()] != STATE_VERSION {
+            return Err(LamportError::InvalidKeyState);
+        }
+        let key_id =
+            KeyId::from_bytes(&body[STATE_MAGIC.len() + 1..STATE_MAGIC.len() + 1 + KeyId::BYTES])?;
+        let mut words = body[STATE_MAGIC.len() + 1 + KeyId::BYTES..]
+            .chunks_exact(8)
+            .map(|word| u64::from_be_bytes(word.try_into().expect("eight bytes")));
+        let mut next = || words.next().ok_or(LamportError::InvalidKeyState);
+        let capacity = next()?;
+        let generation = next()?;
+        let count = next()?;
+        let mut used = BTreeSet::new();
+        let mut previous = None;
+        for _ in 0..count {
+            let index = next()?;
+            if index >= capacity || previous.is_some_and(|previous| index <= previous) {
+                return Err(LamportError::InvalidKeyState);
+            }
+            used.insert(index);
+            previous = Some(index);
+        }
+        if capacity == 0 || next().is_ok() {
+            return Err(LamportError::InvalidKeyState);
+        }
+        Ok(Self {
+            key_id,
+            capacity,
+            generation,
+            used,
+        })
+    }
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs the data only after recording the signature in the [`KeyState`].
+    ///
+    /// The state's single index is reserved and handed to `persist`, which
+    /// must durably store it, before the key signs. If `persist` fails the
+    /// error is returned and nothing is signed. A key whose state records it
+    /// as used refuses to sign even if the key itself was restored from an
+    /// older copy that is not marked used.
+    pub fn sign_with_state<B, F>(
+        &mut self,
+        state: &mut KeyState,
+        data: B,
+        persist: F,
+    ) -> LamportResult<Signature<T>>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        if state.capacity != 1 || state.key_id != VerifyingKey::from(&*self).fingerprint() {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        if self.used || state.is_used(0) {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        state.mark_used(0)?;
+        persist(state)?;
+        self.sign(data)
+    }
+}
+
+#[cfg(feature = "unstable-xmss-mt")]
+impl<T: LamportDigest> XmssMtSigningKey<T> {
+    /// Signs the data only after recording the signature index in the [`KeyState`].
+    ///
+    /// The index is reserved and handed to `persist`, which must durably
+    /// store it, before the key signs. A key restored from an older copy
+    /// skips every index the state records as used instead of signing with
+    /// it again.
+    pub fn sign_with_state<B, F>(
+        &mut self,
+        state: &mut KeyState,
+        data: B,
+        persist: F,
+    ) -> LamportResult<XmssMtSignature<T>>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        let key_id = self.verifying_key().fingerprint();
+        let index = state.reserve_for(key_id, self.params.capacity(), self.next_index, persist)?;
+        self.next_index = index;
+        self.sign(data)
+    }
+}
+
+#[cfg(feature = "unstable-lms")]
+impl LmsSigningKey {
+    /// Signs the data only after recording the LM-OTS index in the [`KeyState`].
+    ///
+    /// The index is reserved and handed to `persist`, which must durably
+    /// store it, before the key signs. A key restored from an older copy
+    /// skips every index the state records as used instead of signing with
+    /// it again.
+    pub fn sign_with_state<B, F>(
+        &mut self,
+        state: &mut KeyState,
+        data: B,
+        rng: impl RngCore + CryptoRng,
+        persist: F,
+    ) -> LamportResult<LmsSignature>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        let key_id = self.verifying_key().fingerprint();
+        let capacity = u64::from(self.lms.capacity());
+        let index = state.reserve_for(key_id, capacity, u64::from(self.q), persist)?;
+        self.q = u32::try_from(index).expect("below the LMS capacity");
+        self.sign(data, rng)
+    }
+}
+
+#[cfg(feature = "unstable-hss")]
+impl HssSigningKey {
+    /// Signs the data only after recording the signature number in the [`KeyState`].
+    ///
+    /// Signatures are numbered across the whole hierarchy, so the state's
+    /// capacity is the product of every level's capacity. The number is
+    /// reserved and handed to `persist`, which must durably store it, before
+    /// the key signs. A key restored from an older copy skips every number
+    /// the state records as used, replacing levels as needed, instead of
+    /// signing with it again.
+    ///
+    /// Returns [`LamportError::InvalidKeyState`] if the hierarchy has more
+    /// than `u64::MAX` signatures.
+    pub fn sign_with_state<B, F>(
+        &mut self,
+        state: &mut KeyState,
+        data: B,
+        mut rng: impl RngCore + CryptoRng,
+        persist: F,
+    ) -> LamportResult<HssSignature>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        let capacity = self.capacity().ok_or(LamportError::InvalidKeyState)?;
+        let next = capacity - self.remaining();
+        let key_id = self.verifying_key().fingerprint();
+        let index = state.reserve_for(key_id, capacity, next, persist)?;
+        self.skip(index - next, &mut rng)?;
+        self.sign(data, rng)
+    }
+}
+
+#[cfg(feature = "unstable-hors")]
+impl<T: LamportDigest> HorsSigningKey<T> {
+    /// Signs the data only after recording the use in the [`KeyState`].
+    ///
+    /// The state's capacity is the key's maximum number of uses. The use is
+    /// reserved and handed to `persist`, which must durably store it, before
+    /// the key signs. A key restored from an older copy counts every use the
+    /// state records instead of exceeding its maximum.
+    pub fn sign_with_state<B, F>(
+        &mut self,
+        state: &mut KeyState,
+        data: B,
+        persist: F,
+    ) -> LamportResult<HorsSignature<T>>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(&KeyState) -> LamportResult<()>,
+    {
+        let key_id = HorsVerifyingKey::from(&*self).fingerprint();
+        let capacity = u64::from(self.max_uses);
+        let index = state.reserve_for(key_id, capacity, u64::from(self.uses), persist)?;
+        self.uses = u32::try_from(index).expect("below the maximum uses");
+        self.sign(data)
+    }
+}
+
+/// HMAC-SHA-256 keyed with the integrity key.
+pub(crate) fn hmac_sha256(key: &[u8]) -> Hmac<Sha256> {
+    Hmac::new_from_slice(key).expect("HMAC accepts keys of any length")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn key_state_hmac_rfc4231() {
+        use hmac::Mac;
+
+        // RFC 4231 test cases 1, 2 and 6
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ];
+        for (key, data, tag) in cases {
+            let mac = hmac_sha256(key).chain_update(data);
+            assert_eq!(hex::encode(mac.clone().finalize().into_bytes()), tag);
+            assert!(mac.verify_slice(&hex::decode(tag).unwrap()).is_ok());
+        }
+
+        // The state tag is the HMAC of every byte before it
+        let state = KeyState::new(KeyId::default(), 3).unwrap();
+        let bytes = state.to_bytes(b"Jefe");
+        let (body, tag) = bytes.split_at(bytes.len() - 32);
+        assert!(hmac_sha256(b"Jefe")
+            .chain_update(body)
+            .verify_slice(tag)
+            .is_ok());
+    }
+
+    #[test]
+    fn key_state() {
+        type D = LamportFixedDigest<Sha256>;
+        const INTEGRITY_KEY: &[u8] = b"state integrity key";
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+        let backup = sk.clone();
+        let mut state = KeyState::for_signing_key(&sk);
+        assert_eq!(state.remaining(), 1);
+
+        // A failed persist leaves nothing signed
+        let mut failing = state.clone();
+        assert!(sk
+            .sign_with_state(&mut failing, b"first", |_| Err(
+                LamportError::InvalidKeyState
+            ))
+            .is_err());
+        assert!(!sk.used());
+
+        let mut stored = Vec::new();
+        let signature = sk
+            .sign_with_state(&mut state, b"first", |state| {
+                stored = state.to_bytes(INTEGRITY_KEY);
+                Ok(())
+            })
+            .unwrap();
+        assert!(pk.verify(&signature, b"first").is_ok());
+
+        // After a crash the restored state still blocks an old copy of the key
+        let mut recovered = KeyState::from_bytes(&stored, INTEGRITY_KEY).unwrap();
+        assert_eq!(recovered, state);
+        assert!(recovered.is_used(0));
+        let mut old_copy = backup;
+        assert_eq!(
+            old_copy
+                .sign_with_state(&mut recovered, b"second", |_| Ok(()))
+                .unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        let (mut other, _) = generate_keys::<D, _>(&mut rng).unwrap();
+        assert_eq!(
+            other
+                .sign_with_state(&mut recovered, b"second", |_| Ok(()))
+                .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+
+        assert_eq!(
+            KeyState::from_bytes(&stored, b"wrong key").unwrap_err(),
+            LamportError::InvalidKeyState
+        );
+        let mut edited = stored.clone();
+        let count = edited.len() - 32 - 16;
+        edited[count] ^= 1;
+        assert!(KeyState::from_bytes(&edited, INTEGRITY_KEY).is_err());
+        assert!(KeyState::from_bytes(&stored[1..], INTEGRITY_KEY).is_err());
+
+        let mut pool = KeyState::new(KeyId::default(), 4).unwrap();
+        pool.mark_used(1).unwrap();
+        assert_eq!(pool.reserve().unwrap(), 0);
+        assert_eq!(pool.reserve().unwrap(), 2);
+        assert_eq!(
+            pool.mark_used(2).unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert_eq!(pool.reserve().unwrap(), 3);
+        assert_eq!(pool.reserve().unwrap_err(), LamportError::KeyPoolExhausted);
+        assert_eq!(pool.generation(), 4);
+        assert_eq!(pool.used_indices().collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(pool.unused_indices().count(), 0);
+        let restored = KeyState::from_bytes(pool.to_bytes(&[0x0b; 80]), &[0x0b; 80]).unwrap();
+        assert_eq!(restored, pool);
+        assert!(KeyState::new(KeyId::default(), 0).is_err());
+    }
+
+    #[cfg(all(
+        feature = "unstable-xmss-mt",
+        feature = "unstable-hss",
+        feature = "unstable-hors"
+    ))]
+    #[test]
+    fn stateful_keys_skip_reserved_indices() {
+        use crate::hors::HorsParams;
+        use crate::lms::{LmOtsAlgorithm, LmsAlgorithm};
+        use crate::xmss_mt::XmssMtParams;
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+
+        // A crash after persisting index 0 leaves a stale copy of the key
+        let mut sk =
+            XmssMtSigningKey::<D>::generate(XmssMtParams::new(4, 2, 16).unwrap(), &mut rng)
+                .unwrap();
+        let pk = sk.verifying_key();
+        let stale = sk.clone();
+        let mut state = KeyState::new(pk.fingerprint(), sk.params().capacity()).unwrap();
+        assert_eq!(
+            sk.sign_with_state(&mut state, b"lost", |_| Err(LamportError::InvalidKeyState))
+                .unwrap_err(),
+            LamportError::InvalidKeyState
+        );
+        assert!(state.is_used(0));
+        let mut sk = stale.clone();
+        let signature = sk
+            .sign_with_state(&mut state, b"first", |_| Ok(()))
+            .unwrap();
+        assert_eq!(signature.index(), 1);
+        assert!(pk.verify(&signature, b"first").is_ok());
+
+        // Restoring the stale copy again refuses both used indices
+        let mut sk = stale;
+        let signature = sk
+            .sign_with_state(&mut state, b"second", |_| Ok(()))
+            .unwrap();
+        assert_eq!(signature.index(), 2);
+        assert_eq!(sk.index(), 3);
+        let mut other = KeyState::new(KeyId::default(), 16).unwrap();
+        assert_eq!(
+            sk.sign_with_state(&mut other, b"third", |_| Ok(()))
+                .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+
+        let mut sk = LmsSigningKey::generate(
+            LmsAlgorithm::Sha256M32H5,
+            LmOtsAlgorithm::Sha256N32W8,
+            &mut rng,
+        );
+        let pk = sk.verifying_key();
+        let stale = sk.clone();
+        let mut state = KeyState::new(pk.fingerprint(), 32).unwrap();
+        state.mark_used(0).unwrap();
+        let signature = sk
+            .sign_with_state(&mut state, b"first", &mut rng, |_| Ok(()))
+            .unwrap();
+        assert_eq!(signature.index(), 1);
+        assert!(pk.verify(&signature, b"first").is_ok());
+        let mut sk = stale;
+        let signature = sk
+            .sign_with_state(&mut state, b"second", &mut rng, |_| Ok(()))
+            .unwrap();
+        assert_eq!(signature.index(), 2);
+
+        // HSS numbers signatures across levels and replaces exhausted ones
+        let level = (LmsAlgorithm::Sha256M32H5, LmOtsAlgorithm::Sha256N32W8);
+        let mut sk = HssSigningKey::generate(&[level, level], &mut rng).unwrap();
+        let pk = sk.verifying_key();
+        let stale = sk.clone();
+        let mut state = KeyState::new(pk.fingerprint(), 1024).unwrap();
+        for index in 0..33 {
+            state.mark_used(index).unwrap();
+        }
+        let signature = sk
+            .sign_with_state(&mut state, b"first", &mut rng, |_| Ok(()))
+            .unwrap();
+        assert!(pk.verify(&signature, b"first").is_ok());
+        assert_eq!(signature.signature.index(), 1);
+        assert_eq!(sk.remaining(), 1024 - 34);
+        let mut sk = stale;
+        assert_eq!(
+            sk.sign_with_state(
+                &mut KeyState::new(pk.fingerprint(), 32).unwrap(),
+                b"x",
+                &mut rng,
+                |_| Ok(())
+            )
+            .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+
+        let mut sk =
+            HorsSigningKey::<D>::random(HorsParams::new(32, 8).unwrap(), 2, &mut rng).unwrap();
+        let pk = HorsVerifyingKey::from(&sk);
+        let stale = sk.clone();
+        let mut state = KeyState::new(pk.fingerprint(), 2).unwrap();
+        let signature = sk
+            .sign_with_state(&mut state, b"first", |_| Ok(()))
+            .unwrap();
+        assert!(pk.verify(&signature, b"first").is_ok());
+        let mut sk = stale;
+        assert!(sk
+            .sign_with_state(&mut state, b"second", |_| Ok(()))
+            .is_ok());
+        assert_eq!(sk.remaining(), 0);
+        let mut sk = HorsSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        sk.uses = 0;
+        assert_eq!(
+            sk.sign_with_state(&mut state, b"third", |_| Ok(()))
+                .unwrap_err(),
+            LamportError::KeyPoolExhausted
+        );
+    }
+}
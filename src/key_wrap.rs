@@ -0,0 +1,181 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportDigest, LamportError, LamportResult, SigningKey};
+use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes192, Aes256};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// The alternative initial value prefix from RFC 5649.
+const AIV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Wraps the canonical key bytes with AES-KWP (RFC 5649) under a 16, 24 or
+    /// 32 byte key encryption key, for export to an HSM or KMS.
+    pub fn wrap(&self, kek: &[u8]) -> LamportResult<Vec<u8>> {
+        let mut plaintext = self.to_bytes();
+        let wrapped = kwp_wrap(kek, &plaintext);
+        plaintext.zeroize();
+        wrapped
+    }
+
+    /// Unwraps a key created by [`SigningKey::wrap`] or by an HSM using AES-KWP.
+    pub fn unwrap(kek: &[u8], wrapped: &[u8]) -> LamportResult<Self> {
+        let mut plaintext = kwp_unwrap(kek, wrapped)?;
+        let signing_key = Self::from_bytes(&plaintext);
+        plaintext.zeroize();
+        signing_key
+    }
+}
+
+enum Kek {
+    Aes128(Aes128),
+    Aes192(Aes192),
+    Aes256(Aes256),
+}
+
+impl Kek {
+    fn new(kek: &[u8]) -> LamportResult<Self> {
+        match kek.len() {
+            16 => Ok(Self::Aes128(Aes128::new(GenericArray::from_slice(kek)))),
+            24 => Ok(Self::Aes192(Aes192::new(GenericArray::from_slice(kek)))),
+            32 => Ok(Self::Aes256(Aes256::new(GenericArray::from_slice(kek)))),
+            _ => Err(LamportError::InvalidKeyWrap),
+        }
+    }
+
+    fn encrypt(&self, block: &mut [u8; 16]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Self::Aes128(c) => c.encrypt_block(block),
+            Self::Aes192(c) => c.encrypt_block(block),
+            Self::Aes256(c) => c.encrypt_block(block),
+        }
+    }
+
+    fn decrypt(&self, block: &mut [u8; 16]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Self::Aes128(c) => c.decrypt_block(block),
+            Self::Aes192(c) => c.decrypt_block(block),
+            Self::Aes256(c) => c.decrypt_block(block),
+        }
+    }
+}
+
+pub(crate) fn kwp_wrap(kek: &[u8], plaintext: &[u8]) -> LamportResult<Vec<u8>> {
+    let kek = Kek::new(kek)?;
+    let length = u32::try_from(plaintext.len()).map_err(|_| LamportError::InvalidKeyWrap)?;
+    if plaintext.is_empty() {
+        return Err(LamportError::InvalidKeyWrap);
+    }
+    let mut a = [0u8; 8];
+    a[..4].copy_from_slice(&AIV_PREFIX);
+    a[4..].copy_from_slice(&length.to_be_bytes());
+
+    let padded = plaintext.len().div_ceil(8) * 8;
+    let mut output = vec![0u8; 8 + padded];
+    output[8..8 + plaintext.len()].copy_from_slice(plaintext);
+    let mut block = [0u8; 16];
+    if padded == 8 {
+        block[..8].copy_from_slice(&a);
+        block[8..].copy_from_slice(&output[8..]);
+        kek.encrypt(&mut block);
+        output.copy_from_slice(&block);
+    } else {
+        let n = padded / 8;
+        for j in 0..6 {
+            for i in 1..=n {
+                block[..8].copy_from_slice(&a);
+                block[8..].copy_from_slice(&output[i * 8..i * 8 + 8]);
+                kek.encrypt(&mut block);
+                a.copy_from_slice(&block[..8]);
+                xor_counter(&mut a, (n * j + i) as u64);
+                output[i * 8..i * 8 + 8].copy_from_slice(&block[8..]);
+            }
+        }
+        output[..8].copy_from_slice(&a);
+    }
+    block.zeroize();
+    Ok(output)
+}
+
+pub(crate) fn kwp_unwrap(kek: &[u8], wrapped: &[u8]) -> LamportResult<Vec<u8>> {
+    let kek = Kek::new(kek)?;
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(LamportError::InvalidKeyWrap);
+    }
+    let mut a = [0u8; 8];
+    let mut output = vec![0u8; wrapped.len() - 8];
+    let mut block = [0u8; 16];
+    if wrapped.len() == 16 {
+        block.copy_from_slice(wrapped);
+        kek.decrypt(&mut block);
+        a.copy_from_slice(&block[..8]);
+        output.copy_from_slice(&block[8..]);
+    } else {
+        let n = output.len() / 8;
+        a.copy_from_slice(&wrapped[..8]);
+        output.copy_from_slice(&wrapped[8..]);
+        for j in (0..6).rev() {
+            for i in (1..=n).rev() {
+                xor_counter(&mut a, (n * j + i) as u64);
+                block[..8].copy_from_slice(&a);
+                block[8..].copy_from_slice(&output[(i - 1) * 8..i * 8]);
+                kek.decrypt(&mut block);
+                a.copy_from_slice(&block[..8]);
+                output[(i - 1) * 8..i * 8].copy_from_slice(&block[8..]);
+            }
+        }
+    }
+    block.zeroize();
+
+    let mut length = [0u8; 4];
+    length.copy_from_slice(&a[4..]);
+    let length = usize::try_from(u32::from_be_bytes(length)).unwrap_or(usize::MAX);
+    let valid_length = length > output.len().saturating_sub(8) && length <= output.len();
+    let padding_ok =
+        valid_length && bool::from(output[length..].ct_eq(&vec![0u8; output.len() - length]));
+    if !bool::from(a[..4].ct_eq(&AIV_PREFIX)) || !padding_ok {
+        output.zeroize();
+        return Err(LamportError::InvalidKeyWrap);
+    }
+    output.truncate(length);
+    Ok(output)
+}
+
+fn xor_counter(a: &mut [u8; 8], t: u64) {
+    for (a, t) in a.iter_mut().zip(t.to_be_bytes()) {
+        *a ^= t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aes_kwp_vectors() {
+        // RFC 5649 section 6
+        let kek = hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+        let vectors = [
+            (
+                "c37b7e6492584340bed12207808941155068f738",
+                "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+            ),
+            ("466f7250617369", "afbeb0f07dfbf5419200f2ccb50bb24f"),
+        ];
+        for (key, wrapped) in vectors {
+            let key = hex::decode(key).unwrap();
+            let wrapped = hex::decode(wrapped).unwrap();
+            assert_eq!(kwp_wrap(&kek, &key).unwrap(), wrapped);
+            assert_eq!(kwp_unwrap(&kek, &wrapped).unwrap(), key);
+            let mut tampered = wrapped.clone();
+            tampered[0] ^= 1;
+            assert!(kwp_unwrap(&kek, &tampered).is_err());
+        }
+        assert!(kwp_wrap(&kek[..20], b"key").is_err());
+    }
+}
@@ -0,0 +1,137 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportDigest, LamportError, LamportResult, Signature, SigningKey, VerifyingKey};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// A signing key together with its verifying key.
+///
+/// The API follows the shape of `ed25519_dalek::Keypair` to ease migrating
+/// call sites, except that [`Keypair::sign`] takes `&mut self` and returns a
+/// [`LamportResult`], since the key can only sign once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Keypair<T: LamportDigest> {
+    /// The one-time signing key.
+    pub secret: SigningKey<T>,
+    /// The verifying key of `secret`.
+    pub public: VerifyingKey<T>,
+}
+
+serde_impl!(Keypair);
+vec_impl!(Keypair);
+
+impl<T: LamportDigest> Zeroize for Keypair<T> {
+    fn zeroize(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl<T: LamportDigest> From<SigningKey<T>> for Keypair<T> {
+    fn from(secret: SigningKey<T>) -> Self {
+        let public = VerifyingKey::from(&secret);
+        Self { secret, public }
+    }
+}
+
+impl<T: LamportDigest> AsRef<VerifyingKey<T>> for Keypair<T> {
+    fn as_ref(&self) -> &VerifyingKey<T> {
+        &self.public
+    }
+}
+
+impl<T: LamportDigest> Keypair<T> {
+    /// Generates a new [`Keypair`] with the specified RNG.
+    ///
+    /// Returns [`LamportError::InsecureDigest`] if the digest is below the
+    /// minimum security level, see [`LamportDigest`].
+    pub fn generate<R: RngCore + CryptoRng>(csprng: &mut R) -> LamportResult<Self> {
+        SigningKey::random(csprng).map(Self::from)
+    }
+
+    /// Signs the message, returning [`LamportError::PrivateKeyReuseError`]
+    /// if the key has already signed.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, message: B) -> LamportResult<Signature<T>> {
+        self.secret.sign(message)
+    }
+
+    /// Verifies the signature over the message with the public key.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        message: B,
+        signature: &Signature<T>,
+    ) -> LamportResult<()> {
+        self.public.verify(signature, message)
+    }
+
+    /// Has the key been used.
+    pub fn used(&self) -> bool {
+        self.secret.used()
+    }
+
+    /// The secret key bytes followed by the public key bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.secret.to_bytes();
+        bytes.extend_from_slice(&self.public.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`Keypair`] from the bytes of [`Keypair::to_bytes`].
+    ///
+    /// Returns [`LamportError::InvalidPrivateKeyBytes`] if the public key
+    /// does not belong to the secret key.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let secret_length = 1 + bits * (bits / 8) * 2;
+        if input.len() < secret_length {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (secret, public) = input.split_at(secret_length);
+        let secret = SigningKey::from_bytes(secret)?;
+        let public = VerifyingKey::from_bytes(public)?;
+        if VerifyingKey::from(&secret) != public {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self { secret, public })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn keypair() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut keypair = Keypair::<LamportFixedDigest<Sha256>>::generate(&mut rng).unwrap();
+        assert!(!keypair.used());
+        let signature = keypair.sign(b"message").unwrap();
+        assert!(keypair.used());
+        assert!(keypair.verify(b"message", &signature).is_ok());
+        assert!(keypair.verify(b"other", &signature).is_err());
+        assert_eq!(
+            keypair.sign(b"other").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+
+        let bytes = keypair.to_bytes();
+        let decoded = Keypair::<LamportFixedDigest<Sha256>>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(decoded.used());
+
+        let other = Keypair::<LamportFixedDigest<Sha256>>::generate(&mut rng).unwrap();
+        let mut mismatched = keypair.secret.to_bytes();
+        mismatched.extend_from_slice(&other.public.to_bytes());
+        assert_eq!(
+            Keypair::<LamportFixedDigest<Sha256>>::from_bytes(&mismatched).unwrap_err(),
+            LamportError::InvalidPrivateKeyBytes
+        );
+        assert!(Keypair::<LamportFixedDigest<Sha256>>::from_bytes(&bytes[..100]).is_err());
+    }
+}
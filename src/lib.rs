@@ -13,7 +13,7 @@
 //! use rand_chacha::ChaChaRng;
 //!
 //! let mut rng = ChaChaRng::from_entropy();
-//! let mut signing_key = SigningKey::<LamportFixedDigest<Sha256>>::random(rng);
+//! let mut signing_key = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
 //! let verifying_key = VerifyingKey::from(&signing_key);
 //! let signature = signing_key.sign(b"Hello, World!").expect("signing failed");
 //! assert!(verifying_key.verify(&signature, b"Hello, World!").is_ok());
@@ -33,7 +33,7 @@
 //! use rand_chacha::ChaChaRng;
 //!
 //! let mut rng = ChaChaRng::from_entropy();
-//! let mut signing = SigningKey::<LamportExtendableDigest<Shake128>>::random(rng);
+//! let mut signing = SigningKey::<LamportExtendableDigest<Shake128>>::random(rng).unwrap();
 //! let verifying = VerifyingKey::from(&signing);
 //! let signature = signing.sign(b"Hello, World!").expect("signing failed");
 //! assert!(verifying.verify(&signature, b"Hello, World!").is_ok());
@@ -44,6 +44,27 @@
 //! [`SigningKey`] takes the cryptographically secure RNG implemented in [rust-lang-nursery/rand](https://github.com/rust-lang-nursery/rand) as an argument to construct,
 //! i.e. RNG must implement the `RngCore` and `CryptoRng` traits.
 //!
+//! # Serialization
+//!
+//! Human-readable serde formats encode values as hex. Fields annotated with
+//! `#[serde(with = "lamport_signature_plus::base64url")]` use unpadded
+//! base64url instead. Each field only decodes the encoding it is written in.
+//! Binary formats use the canonical bytes.
+//! Keys, shares and signatures can also be exchanged as ASCII armor, see
+//! [`armor`].
+//!
+//! # Features
+//!
+//! The `std` feature is on by default. It enables the `std::io` based APIs,
+//! [`SigningKey::split_into_writers`] and [`LamportError::IoError`], and the
+//! `std` features of the dependencies. The error type builds with only
+//! `core` and `alloc`, but the crate is not `no_std` yet and still links
+//! `std` without the feature.
+//!
+//! Features named `unstable-*` gate experimental subsystems in the
+//! `unstable` module, which is exempt from semantic versioning. Their APIs
+//! may change in any release until they move to the crate root.
+//!
 //! # Note
 //! [`SigningKey`] can only be used once to securely sign a message. If an attempt is made to sign a message with a used key, an error returns.
 
@@ -73,33 +94,165 @@
     rust_2018_idioms
 )]
 
+extern crate alloc;
+
 #[macro_use]
 mod utils;
+pub mod armor;
+#[cfg(feature = "async")]
+mod async_verify;
+pub mod base64url;
+#[cfg(feature = "unstable-biba")]
+mod biba;
+mod bundle;
+#[cfg(feature = "cbor")]
+mod cbor;
+#[cfg(feature = "unstable-ceremony")]
+mod ceremony;
+#[cfg(feature = "unstable-cert-chain")]
+mod cert_chain;
+mod codec;
+mod combine;
+mod compact_key;
+mod compact_signature;
+#[cfg(feature = "dsse")]
+pub mod dsse;
+#[cfg(feature = "dudect")]
+pub mod dudect;
 mod error;
+#[cfg(feature = "escrow")]
+mod escrow;
+mod fail_stop;
+#[cfg(feature = "unstable-flash")]
+mod flash;
+#[cfg(feature = "unstable-fors")]
+mod fors;
+mod forward_secure;
+mod gf256;
 mod hash;
+#[cfg(feature = "hazmat")]
+pub mod hazmat;
+#[cfg(feature = "unstable-hors")]
+mod hors;
+#[cfg(feature = "unstable-hss")]
+mod hss;
+#[cfg(feature = "json")]
+mod json;
+mod key_factory;
+mod key_id;
+mod key_log;
+mod key_state;
+#[cfg(feature = "key_wrap")]
+mod key_wrap;
+mod keypair;
+mod limits;
+#[cfg(feature = "unstable-lms")]
+mod lms;
+mod merkle;
+mod messages;
+mod multi_sig;
 mod multi_vec;
+mod personalized;
+mod progress;
+mod registry;
+mod seed_kdf;
+mod seed_key;
+#[cfg(feature = "unstable-threshold-session")]
+mod session;
+mod signable;
 mod signature;
+#[cfg(feature = "signature")]
+mod signature_traits;
 mod signing;
+#[cfg(feature = "unstable-sphincs")]
+mod sphincs;
+mod state;
+mod subtree;
+#[cfg(feature = "test_utils")]
+pub mod test_utils;
+mod transcript;
+mod tree_sign;
+mod tweakable;
+mod two_party;
+#[cfg(feature = "unstable")]
+pub mod unstable;
 mod verifying;
+#[cfg(feature = "unstable-wots")]
+mod wots;
+#[cfg(feature = "unstable-wots")]
+mod wots_plus;
+#[cfg(feature = "unstable-xmss-mt")]
+mod xmss_mt;
 
+pub use bundle::ShareBundle;
+pub use combine::CombineContext;
+pub use compact_key::{CompactKeySignature, CompactVerifyingKey};
+pub use compact_signature::CompactSignature;
 pub use error::{LamportError, LamportResult};
-pub use hash::{LamportDigest, LamportExtendableDigest, LamportFixedDigest};
-pub use multi_vec::MultiVec;
+#[cfg(feature = "escrow")]
+pub use escrow::{HpkePublicKey, HpkeSecretKey, KeyEscrow, SeedEscrow};
+pub use fail_stop::{FailStopSignature, FailStopSigningKey, ForgeryProof};
+pub use forward_secure::{ForwardSecureKey, ForwardSecurePublicKey, ForwardSecureSignature};
+pub use hash::{
+    LamportDigest, LamportExtendableDigest, LamportFixedDigest, LamportIncrementalDigest,
+    LamportReducedDigest, LamportTweakableDigest, MIN_DIGEST_BITS, RECOMMENDED_DIGEST_BITS,
+};
+pub use key_factory::{KeyFactory, Reservation};
+pub use key_id::KeyId;
+pub use key_log::KeyLog;
+pub use key_state::KeyState;
+pub use keypair::Keypair;
+pub use limits::{Bounded, DecodeWithLimits, Limits, DEFAULT_MAX_INPUT_BYTES};
+pub use merkle::{ConsistencyProof, InclusionProof, MerkleTree, MERKLE_HASH_BYTES};
+pub use messages::{
+    CombineResult, PartialSignatureMsg, RefreshMsg, SignRequest, MAX_SIGN_REQUEST_MESSAGE_BYTES,
+    MESSAGE_VERSION, SESSION_ID_BYTES,
+};
+pub use multi_sig::{MultiSigDocument, SignerPolicy};
+pub use multi_vec::{ConstMultiVec, MultiVec};
+pub use personalized::{
+    PersonalizedSigningKey, PersonalizedVerifyingKey, MAX_PERSONALIZATION_BYTES,
+};
+pub use progress::{CancellationToken, Progress};
 use rand::{CryptoRng, RngCore};
-pub use signature::{Signature, SignatureShare};
-pub use signing::{SigningKey, SigningKeyShare};
-pub use verifying::VerifyingKey;
+pub use registry::KeyRegistry;
+pub use seed_kdf::SeedKdf;
+pub use seed_key::SeedSigningKey;
+pub use signable::{CanonicalEncoder, SignableMessage};
+pub use signature::{
+    AttachedSignature, Signature, SignatureDecoder, SignatureShare, SignatureWithKey,
+};
+#[cfg(feature = "signature")]
+pub use signature_traits::SharedSigningKey;
+pub use signing::{SigningKey, SigningKeyShare, SEED_BYTES};
+pub use state::{MemoryStateStore, StateStore, Tombstone};
+pub use subtree::{MerkleSubtree, SubtreeCache};
+pub use transcript::KeygenTranscript;
+pub use tree_sign::TreeSignature;
+pub use tweakable::{TweakableSigningKey, TweakableVerifyingKey, TWEAK_IDENTIFIER_BYTES};
+pub use two_party::{
+    TwoPartyCommitment, TwoPartyKeyShare, TwoPartyRole, TwoPartySignature, TwoPartySignatureShare,
+    TwoPartyVerifyingKey,
+};
+pub use verifying::{
+    ChunkedVerifier, CommitmentChunk, CommitmentChunks, PreparedVerifyingKey, VerifyContext,
+    VerifyingKey,
+};
 
 /// Generate a new pair of keys.
+///
+/// Returns [`LamportError::InsecureDigest`] if the digest is below the
+/// minimum security level, see [`LamportDigest`].
 pub fn generate_keys<T: LamportDigest, R: RngCore + CryptoRng>(
     rng: R,
-) -> (SigningKey<T>, VerifyingKey<T>) {
-    let sk = SigningKey::<T>::random(rng);
+) -> LamportResult<(SigningKey<T>, VerifyingKey<T>)> {
+    let sk = SigningKey::<T>::random(rng)?;
     let pk = VerifyingKey::from(&sk);
-    (sk, pk)
+    Ok((sk, pk))
 }
 
 #[cfg(test)]
+#[allow(clippy::nonminimal_bool)]
 mod tests {
     use super::*;
     use rand::SeedableRng;
@@ -110,7 +263,8 @@ mod tests {
     #[test]
     fn key_bytes_round_trip() {
         let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let (mut sk, original_public_key) = generate_keys::<LamportFixedDigest<Sha3_256>, _>(rng);
+        let (mut sk, original_public_key) =
+            generate_keys::<LamportFixedDigest<Sha3_256>, _>(rng).unwrap();
 
         let bytes = original_public_key.to_bytes();
         let res = VerifyingKey::<LamportFixedDigest<Sha3_256>>::from_bytes(&bytes);
@@ -135,10 +289,76 @@ mod tests {
         assert_eq!(restored_signature.to_bytes(), signature.to_bytes());
     }
 
+    #[test]
+    fn verifying_key_commitments() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (_, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+
+        assert_eq!(pk.rows(), 256);
+        let bytes = pk.to_bytes();
+        assert_eq!(pk.zero_commitment(0).unwrap(), &bytes[..32]);
+        assert_eq!(pk.zero_commitment(255).unwrap(), &bytes[255 * 32..256 * 32]);
+        assert_eq!(pk.one_commitment(0).unwrap(), &bytes[256 * 32..257 * 32]);
+        assert!(pk.zero_commitment(256).is_none());
+        assert!(pk.one_commitment(256).is_none());
+    }
+
+    #[test]
+    fn attached_signature() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let (_, other_pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        assert_ne!(pk.fingerprint(), other_pk.fingerprint());
+
+        let message = b"hello, world!";
+        let signature = sk.sign_attached(message).unwrap();
+        assert_eq!(signature.key_id(), pk.fingerprint());
+        assert!(pk.verify_attached(&signature, message).is_ok());
+        assert_eq!(
+            sk.sign_attached(message).unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert!(matches!(
+            other_pk.verify_attached(&signature, message),
+            Err(LamportError::KeyIdMismatch)
+        ));
+
+        let restored = AttachedSignature::from_bytes(signature.to_bytes()).unwrap();
+        assert_eq!(restored.to_bytes(), signature.to_bytes());
+        assert!(pk
+            .verify_with_fingerprint(restored.signature(), message, &pk.fingerprint())
+            .is_ok());
+    }
+
+    #[test]
+    fn signature_with_key() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut sk = SigningKey::<LamportFixedDigest<Sha3_256>>::random(rng).unwrap();
+
+        let message = b"hello, world!";
+        let bundle = sk.sign_with_key(message).unwrap();
+        assert!(bundle.verify(message).is_ok());
+        assert_eq!(
+            sk.sign_with_key(message).unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert!(bundle.verify(b"hello, world").is_err());
+
+        let restored =
+            SignatureWithKey::<LamportFixedDigest<Sha3_256>>::from_bytes(bundle.to_bytes())
+                .unwrap();
+        assert_eq!(restored.to_bytes(), bundle.to_bytes());
+        assert!(restored.verify(message).is_ok());
+        assert!(
+            SignatureWithKey::<LamportFixedDigest<Sha3_256>>::from_bytes(&bundle.to_bytes()[1..])
+                .is_err()
+        );
+    }
+
     #[test]
     fn generate_sha3_256_private_key() {
         let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let private_key = SigningKey::<LamportFixedDigest<Sha3_256>>::random(rng);
+        let private_key = SigningKey::<LamportFixedDigest<Sha3_256>>::random(rng).unwrap();
 
         assert!(!private_key.used());
         assert_eq!(private_key.zero_values.len(), 256 * 32);
@@ -148,7 +368,7 @@ mod tests {
     #[test]
     fn generate_sha3_512_private_key() {
         let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let private_key = SigningKey::<LamportFixedDigest<Sha3_512>>::random(rng);
+        let private_key = SigningKey::<LamportFixedDigest<Sha3_512>>::random(rng).unwrap();
 
         assert!(!private_key.used());
         assert_eq!(private_key.zero_values.len(), 512 * 64);
@@ -158,7 +378,7 @@ mod tests {
     #[test]
     fn sign_fixed() {
         let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha3_256>, _>(rng);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha3_256>, _>(rng).unwrap();
 
         let message = b"hello, world!";
         let signature = sk.sign(message).unwrap();
@@ -169,7 +389,7 @@ mod tests {
     #[test]
     fn sign_xof() {
         let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let (mut sk, pk) = generate_keys::<LamportExtendableDigest<Shake128>, _>(rng);
+        let (mut sk, pk) = generate_keys::<LamportExtendableDigest<Shake128>, _>(rng).unwrap();
 
         let message = b"hello, world!";
         let signature = sk.sign(message).unwrap();
@@ -180,7 +400,7 @@ mod tests {
     #[test]
     fn vsss_key_round_trip() {
         let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let sk = SigningKey::<LamportFixedDigest<Sha256>>::random(&mut rng);
+        let sk = SigningKey::<LamportFixedDigest<Sha256>>::random(&mut rng).unwrap();
         let res = sk.split(3, 5, &mut rng);
         assert!(res.is_ok());
         let shares = res.unwrap();
@@ -202,7 +422,7 @@ mod tests {
     #[test]
     fn partial_sign() {
         let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
-        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng);
+        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
         let message = b"hello, world!";
         let mut shares = sk.split(3, 5, &mut rng).unwrap();
         let signatures = shares
@@ -214,5 +434,381 @@ mod tests {
         assert!(res.is_ok());
         let signature = res.unwrap();
         assert!(pk.verify(&signature, message).is_ok());
+
+        let res = Signature::combine(&[signatures[0].clone(), signatures[4].clone()]);
+        assert!(res.is_err());
+        let mut truncated = signatures[1].clone();
+        truncated.data = MultiVec::fill([16, 16], 0u8);
+        let res = Signature::combine(&[signatures[0].clone(), truncated, signatures[2].clone()]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn verify_strict() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let message = b"hello, world!";
+        let signature = sk.sign(message).unwrap();
+        assert!(signature.validate().is_ok());
+        assert!(pk.verify_strict(&signature, message).is_ok());
+
+        let mut mangled = signature.clone();
+        mangled.data.iso([1, 256 * 32]);
+        assert_eq!(
+            pk.verify_strict(&mangled, message),
+            Err(LamportError::InvalidSignatureShape {
+                expected: [256, 32],
+                found: [1, 256 * 32],
+                length: 256 * 32,
+            })
+        );
+
+        let mut truncated = signature;
+        truncated.data.data.truncate(32);
+        assert_eq!(
+            pk.verify_strict(&truncated, message),
+            Err(LamportError::InvalidSignatureShape {
+                expected: [256, 32],
+                found: [256, 32],
+                length: 32,
+            })
+        );
+    }
+
+    #[test]
+    fn prepared_verifying_key() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha3_512>, _>(&mut rng).unwrap();
+        let prepared = PreparedVerifyingKey::from(&pk);
+        assert_eq!(prepared.to_bytes(), pk.to_bytes());
+        let prepared =
+            PreparedVerifyingKey::<LamportFixedDigest<Sha3_512>>::from_bytes(pk.to_bytes())
+                .unwrap();
+
+        let signature = sk.sign(b"hello, world!").unwrap();
+        assert!(prepared.verify(&signature, b"hello, world!").is_ok());
+        assert!(prepared.verify(&signature, b"hello, world").is_err());
+    }
+
+    #[test]
+    fn random_pair_matches_sequential_draws() {
+        type D = LamportFixedDigest<Sha3_256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let zero = D::random(&mut rng);
+        let one = D::random(&mut rng);
+        let (pair_zero, pair_one) = D::random_pair(rand_chacha::ChaCha8Rng::from_seed(SEED));
+        assert_eq!(zero, pair_zero);
+        assert_eq!(one, pair_one);
+    }
+
+    #[test]
+    fn verify_many_keys() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut keys = (0..5)
+            .map(|_| generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let candidates = keys.iter().map(|(_, pk)| pk.clone()).collect::<Vec<_>>();
+        let signature = keys[3].0.sign(b"rotate").unwrap();
+
+        assert_eq!(
+            VerifyingKey::verify_many_keys(&candidates, &signature, b"rotate"),
+            Some(3)
+        );
+        assert_eq!(
+            VerifyingKey::verify_many_keys(&candidates[..3], &signature, b"rotate"),
+            None
+        );
+        assert_eq!(
+            VerifyingKey::verify_many_keys(&candidates, &signature, b"other"),
+            None
+        );
+    }
+
+    #[test]
+    fn commitment_chunks() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (_, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+        let pairs = pk.commitments().collect::<Vec<_>>();
+        assert_eq!(pairs.len(), 256);
+        assert_eq!(pairs[5], (&pk.zero_values[5], &pk.one_values[5]));
+
+        let chunks = pk.commitment_chunks(100).collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2].first_row, 200);
+        assert_eq!(chunks[2].data.len(), 56 * 64);
+        let streamed = chunks
+            .iter()
+            .flat_map(|c| c.data.clone())
+            .collect::<Vec<_>>();
+        let expected = pairs
+            .iter()
+            .flat_map(|(z, o)| z.iter().chain(o.iter()).copied())
+            .collect::<Vec<_>>();
+        assert_eq!(streamed, expected);
+
+        // The running digest covers the commitments so far, however they are chunked
+        let single = pk.commitment_chunks(256).collect::<Vec<_>>();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].running_digest, chunks[2].running_digest);
+        assert_eq!(single[0].running_digest, pk.commitment_stream_digest());
+        let small = pk.commitment_chunks(50).collect::<Vec<_>>();
+        assert_eq!(small[1].running_digest, chunks[0].running_digest);
+        assert_ne!(small[0].running_digest, chunks[0].running_digest);
+    }
+
+    #[test]
+    fn chunked_verifier() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let message = b"verified in small pieces";
+        let signature = sk.sign(message).unwrap().to_bytes();
+        let trusted = pk.commitment_stream_digest();
+        assert_eq!(
+            trusted,
+            pk.commitment_chunks(16).last().unwrap().running_digest
+        );
+
+        let verify_chunked = |message: &[u8], signature: &[u8], trusted, rows| {
+            let mut verifier =
+                ChunkedVerifier::<LamportFixedDigest<Sha256>>::new(message, trusted).unwrap();
+            for chunk in pk.commitment_chunks(rows) {
+                let start = chunk.first_row * 32;
+                let end = start + chunk.data.len() / 2;
+                verifier.update(&chunk, &signature[start..end])?;
+            }
+            verifier.finish()
+        };
+        let verify = |message: &[u8], signature: &[u8], trusted| {
+            verify_chunked(message, signature, trusted, 16)
+        };
+        assert!(verify(message, &signature, trusted).is_ok());
+        for rows in [1, 7, 100, 256] {
+            assert!(verify_chunked(message, &signature, trusted, rows).is_ok());
+        }
+        assert!(verify(b"another message", &signature, trusted).is_err());
+        let (_, other) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        assert!(verify(message, &signature, other.commitment_stream_digest()).is_err());
+        let mut tampered = signature.clone();
+        tampered[4000] ^= 1;
+        assert!(verify(message, &tampered, trusted).is_err());
+
+        // Missing or out of order chunks are rejected
+        let mut verifier =
+            ChunkedVerifier::<LamportFixedDigest<Sha256>>::new(message, trusted).unwrap();
+        let chunks = pk.commitment_chunks(16).collect::<Vec<_>>();
+        assert!(verifier.update(&chunks[1], &signature[512..1024]).is_err());
+        assert!(verifier.update(&chunks[0], &signature[..256]).is_err());
+        verifier.update(&chunks[0], &signature[..512]).unwrap();
+        assert_eq!(verifier.remaining(), 240);
+        assert!(verifier.finish().is_err());
+    }
+
+    #[test]
+    fn to_array() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let pk_array = pk.to_array::<{ 256 * 32 * 2 }>().unwrap();
+        assert_eq!(&pk_array[..], &pk.to_bytes()[..]);
+        assert!(pk.to_array::<32>().is_err());
+
+        let mut shares = sk.split(2, 3, &mut rng).unwrap();
+        let share = shares[0].sign(b"array").unwrap();
+        let share_array = share.to_array::<{ 256 * 32 + 2 }>().unwrap();
+        assert_eq!(&share_array[..], &share.to_bytes()[..]);
+        assert!(share.to_array::<{ 256 * 32 }>().is_err());
+
+        let signature = sk.sign(b"array").unwrap();
+        let array = signature.to_array::<{ 256 * 32 }>().unwrap();
+        assert!(pk
+            .verify(&Signature::from_bytes(array).unwrap(), b"array")
+            .is_ok());
+    }
+
+    #[test]
+    fn errors_are_comparable() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, _) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        sk.sign(b"once").unwrap();
+        let err = sk.sign(b"twice").unwrap_err();
+        assert_eq!(err, LamportError::PrivateKeyReuseError);
+        assert_eq!(err.clone(), err);
+
+        let io = LamportError::from(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "short read",
+        ));
+        assert_eq!(
+            io,
+            LamportError::IoError {
+                kind: std::io::ErrorKind::UnexpectedEof,
+                message: "short read".to_string(),
+            }
+        );
+        assert_ne!(io, LamportError::General("short read".to_string()));
+    }
+
+    #[test]
+    fn mark_used() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, _) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let mut shares = sk.split(2, 3, &mut rng).unwrap();
+        let mut first =
+            TwoPartyKeyShare::<LamportFixedDigest<Sha256>>::generate(TwoPartyRole::First, &mut rng)
+                .unwrap();
+
+        sk.mark_used();
+        assert!(sk.used());
+        assert_eq!(
+            sk.sign(b"no").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        shares[0].mark_used();
+        assert!(shares[0].sign(b"no").is_err());
+        assert!(shares[1].sign(b"yes").is_ok());
+        first.mark_used();
+        assert!(first.sign(b"no").is_err());
+    }
+
+    #[test]
+    fn cached_fingerprint() {
+        use std::collections::HashSet;
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (_, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let (_, other) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let copy = VerifyingKey::<LamportFixedDigest<Sha256>>::from_bytes(pk.to_bytes()).unwrap();
+        assert_eq!(pk, copy);
+        assert_eq!(pk.fingerprint(), KeyId::from_verifying_key(&copy));
+        assert_eq!(pk, copy);
+        assert_ne!(pk, other);
+
+        let set = [pk.clone(), copy, other]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&pk));
+    }
+
+    #[test]
+    fn verify_context() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+        let (_, other) = generate_keys::<D, _>(&mut rng).unwrap();
+        let context = pk.precompute();
+        assert_eq!(context.fingerprint(), pk.fingerprint());
+
+        let bytes = context.to_bytes();
+        let context = VerifyContext::<D>::from_bytes(&bytes).unwrap();
+        let signature = sk.sign_attached(b"context").unwrap();
+        assert!(context.verify_attached(&signature, b"context").is_ok());
+        assert!(context.verify(signature.signature(), b"context").is_ok());
+        assert_eq!(
+            other
+                .precompute()
+                .verify_attached(&signature, b"context")
+                .unwrap_err(),
+            LamportError::KeyIdMismatch
+        );
+
+        let mut tampered = bytes.clone();
+        tampered[0] ^= 1;
+        assert!(VerifyContext::<D>::from_bytes(&tampered).is_err());
+
+        #[derive(Debug)]
+        struct EmptyDigest;
+
+        impl LamportDigest for EmptyDigest {
+            const ALLOW_INSECURE: bool = true;
+
+            fn digest_size_in_bits() -> usize {
+                0
+            }
+
+            fn digest(_: &[u8]) -> Vec<u8> {
+                Vec::new()
+            }
+        }
+
+        assert_eq!(
+            VerifyContext::<EmptyDigest>::from_bytes([0u8; KeyId::BYTES]).unwrap_err(),
+            LamportError::InvalidDigest
+        );
+    }
+
+    #[test]
+    fn signature_verify_with() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let signature = sk.sign(b"verify with").unwrap();
+        let signature =
+            Signature::<LamportFixedDigest<Sha256>>::from_bytes(signature.to_bytes()).unwrap();
+        assert!(signature.verify_with(&pk, b"verify with").is_ok());
+        assert!(signature.verify_with(&pk, b"other").is_err());
+    }
+
+    #[test]
+    fn vectored() {
+        type D = LamportExtendableDigest<Shake128>;
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let signature = sk.sign_vectored(&[b"header", b"payload"]).unwrap();
+        assert!(pk
+            .verify_vectored(&signature, &[b"header", b"payload"])
+            .is_ok());
+        assert!(pk
+            .verify_vectored(&signature, &[b"headerp", b"ayload"])
+            .is_err());
+        let encoded = CanonicalEncoder::default()
+            .write_bytes(b"header")
+            .write_bytes(b"payload")
+            .clone()
+            .finish();
+        assert!(pk.verify(&signature, encoded).is_ok());
+
+        let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+        let signature = sk.sign_vectored(&[b"a", b"", b"bc"]).unwrap();
+        assert!(pk.verify_vectored(&signature, &[b"a", b"", b"bc"]).is_ok());
+        assert!(pk.verify_vectored(&signature, &[b"a", b"bc"]).is_err());
+    }
+
+    #[test]
+    fn signing_key_from_seed() {
+        let a = SigningKey::<LamportFixedDigest<Sha256>>::from_seed(&[7u8; SEED_BYTES]).unwrap();
+        let b = SigningKey::<LamportFixedDigest<Sha256>>::from_seed(&[7u8; SEED_BYTES]).unwrap();
+        let c = SigningKey::<LamportFixedDigest<Sha256>>::from_seed(&[8u8; SEED_BYTES]).unwrap();
+        assert_eq!(a.to_bytes(), b.to_bytes());
+        assert_ne!(a.to_bytes(), c.to_bytes());
+        assert_ne!(a.zero_values, a.one_values);
+    }
+
+    #[test]
+    fn from_vec_round_trip() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+
+        let loaded = SigningKey::<LamportFixedDigest<Sha256>>::from_vec(sk.to_bytes()).unwrap();
+        assert_eq!(loaded.to_bytes(), sk.to_bytes());
+        let loaded = VerifyingKey::<LamportFixedDigest<Sha256>>::try_from(pk.to_bytes()).unwrap();
+        assert_eq!(loaded.to_bytes(), pk.to_bytes());
+        assert_eq!(loaded.fingerprint(), pk.fingerprint());
+
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let shares = sk.split(2, 3, rng).unwrap();
+        let share =
+            SigningKeyShare::<LamportFixedDigest<Sha256>>::from_vec(shares[1].to_bytes()).unwrap();
+        assert_eq!(share.to_bytes(), shares[1].to_bytes());
+
+        let mut bytes = sk.to_bytes();
+        bytes.pop();
+        assert!(SigningKey::<LamportFixedDigest<Sha256>>::from_vec(bytes).is_err());
+        let mut bytes = shares[0].to_bytes();
+        bytes[0] = 0;
+        assert!(SigningKeyShare::<LamportFixedDigest<Sha256>>::from_vec(bytes).is_err());
+
+        sk.mark_used();
+        let loaded = SigningKey::<LamportFixedDigest<Sha256>>::try_from(sk.to_bytes()).unwrap();
+        assert!(loaded.used());
     }
 }
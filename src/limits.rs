@@ -0,0 +1,236 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Size limits for decoding untrusted input.
+//!
+//! Every variable length decoder in the crate, including serde, the ASCII
+//! armor and DSSE parsers, rejects inputs larger than its [`Limits`] with
+//! [`LamportError::InputTooLarge`] before allocating for them. The plain
+//! decoders use [`Limits::default`]; each has a `_with_limits` variant that
+//! takes the limit as a parameter, and serde fields can carry their own limit
+//! with [`Bounded`].
+use crate::codec::TextEncoding;
+use crate::{LamportError, LamportResult};
+use core::fmt::{self, Formatter};
+use core::ops::{Deref, DerefMut};
+use serde::de::{Deserializer, Error, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use zeroize::Zeroizing;
+
+/// The default limit of 64 MiB, enough for registries of thousands of keys.
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 64 << 20;
+
+/// The largest inputs a decoder accepts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Limits {
+    max_input_bytes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Limits {
+    /// The limits used by the plain decoders, [`DEFAULT_MAX_INPUT_BYTES`].
+    pub const DEFAULT: Self = Self::new(DEFAULT_MAX_INPUT_BYTES);
+
+    /// Limits decoded inputs to `max_input_bytes`.
+    pub const fn new(max_input_bytes: usize) -> Self {
+        Self { max_input_bytes }
+    }
+
+    /// The largest decoded input, in bytes, that is accepted.
+    pub const fn max_input_bytes(&self) -> usize {
+        self.max_input_bytes
+    }
+
+    /// Returns [`LamportError::InputTooLarge`] if `length` exceeds the limit.
+    pub fn check(&self, length: usize) -> LamportResult<()> {
+        if length > self.max_input_bytes {
+            return Err(LamportError::InputTooLarge {
+                length,
+                limit: self.max_input_bytes,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Decoding canonical bytes under caller supplied [`Limits`].
+///
+/// Implemented by every type with a serde implementation. Fixed size types
+/// only check the length of the input, variable length types also bound what
+/// they allocate while parsing it.
+pub trait DecodeWithLimits: Sized {
+    /// The limits serde decodes the value with.
+    const LIMITS: Limits = Limits::DEFAULT;
+
+    /// Decodes the canonical bytes, rejecting input larger than `limits`.
+    fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self>;
+}
+
+/// A serde wrapper that decodes the value with a limit of `MAX` bytes
+/// instead of [`DEFAULT_MAX_INPUT_BYTES`].
+///
+/// ```
+/// use lamport_signature_plus::{Bounded, KeyRegistry, LamportFixedDigest};
+/// use sha2::Sha256;
+///
+/// let registry = KeyRegistry::<LamportFixedDigest<Sha256>>::new();
+/// let json = serde_json::to_string(&registry).unwrap();
+/// let small: Bounded<KeyRegistry<LamportFixedDigest<Sha256>>, 1024> =
+///     serde_json::from_str(&json).unwrap();
+/// assert!(small.is_empty());
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bounded<V, const MAX: usize>(pub V);
+
+impl<V, const MAX: usize> Bounded<V, MAX> {
+    /// The limits the value is decoded with.
+    pub const LIMITS: Limits = Limits::new(MAX);
+
+    /// Unwraps the value.
+    pub fn into_inner(self) -> V {
+        self.0
+    }
+}
+
+impl<V, const MAX: usize> From<V> for Bounded<V, MAX> {
+    fn from(value: V) -> Self {
+        Self(value)
+    }
+}
+
+impl<V, const MAX: usize> Deref for Bounded<V, MAX> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        &self.0
+    }
+}
+
+impl<V, const MAX: usize> DerefMut for Bounded<V, MAX> {
+    fn deref_mut(&mut self) -> &mut V {
+        &mut self.0
+    }
+}
+
+impl<V: Serialize, const MAX: usize> Serialize for Bounded<V, MAX> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(s)
+    }
+}
+
+impl<'de, V: DecodeWithLimits, const MAX: usize> Deserialize<'de> for Bounded<V, MAX> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize_with_limits(d, &Self::LIMITS).map(Self)
+    }
+}
+
+impl<V: DecodeWithLimits, const MAX: usize> DecodeWithLimits for Bounded<V, MAX> {
+    const LIMITS: Limits = Limits::new(MAX);
+
+    fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        V::from_bytes_with_limits(input, limits).map(Self)
+    }
+}
+
+/// Deserializes hex or bytes no larger than the limits.
+pub(crate) fn deserialize_with_limits<'de, V, D>(d: D, limits: &Limits) -> Result<V, D::Error>
+where
+    V: DecodeWithLimits,
+    D: Deserializer<'de>,
+{
+    deserialize_encoded(d, limits, TextEncoding::Hex)
+}
+
+/// Deserializes text in the encoding, or bytes, no larger than the limits.
+pub(crate) fn deserialize_encoded<'de, V, D>(
+    d: D,
+    limits: &Limits,
+    encoding: TextEncoding,
+) -> Result<V, D::Error>
+where
+    V: DecodeWithLimits,
+    D: Deserializer<'de>,
+{
+    if d.is_human_readable() {
+        let text = d.deserialize_str(BoundedText(*limits, encoding))?;
+        let bytes = encoding.decode(&text).map_err(D::Error::custom)?;
+        V::from_bytes_with_limits(&bytes, limits).map_err(D::Error::custom)
+    } else {
+        let bytes = d.deserialize_byte_buf(BoundedBytes(*limits))?;
+        V::from_bytes_with_limits(&bytes, limits).map_err(D::Error::custom)
+    }
+}
+
+/// Deserializes a byte buffer no larger than the limit.
+pub(crate) struct BoundedBytes(pub(crate) Limits);
+
+impl<'de> Visitor<'de> for BoundedBytes {
+    type Value = Zeroizing<Vec<u8>>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "at most {} bytes", self.0.max_input_bytes())
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        self.0.check(v.len()).map_err(E::custom)?;
+        Ok(Zeroizing::new(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        let v = Zeroizing::new(v);
+        self.0.check(v.len()).map_err(E::custom)?;
+        Ok(v)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let limit = self.0.max_input_bytes();
+        let hint = seq.size_hint().unwrap_or_default();
+        self.0.check(hint).map_err(A::Error::custom)?;
+        let mut bytes = Zeroizing::new(Vec::with_capacity(hint));
+        while let Some(byte) = seq.next_element()? {
+            if bytes.len() == limit {
+                return Err(A::Error::custom(LamportError::InputTooLarge {
+                    length: limit + 1,
+                    limit,
+                }));
+            }
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Deserializes a string whose decoded form fits within the limit.
+///
+/// The decoded size depends on the encoding: hex is half the length of the
+/// text and base64url three quarters.
+pub(crate) struct BoundedText(pub(crate) Limits, pub(crate) TextEncoding);
+
+impl<'de> Visitor<'de> for BoundedText {
+    type Value = Zeroizing<String>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a string decoding to at most {} bytes",
+            self.0.max_input_bytes()
+        )
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.0.check(self.1.decoded_len(v)).map_err(E::custom)?;
+        Ok(Zeroizing::new(v.to_string()))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        let v = Zeroizing::new(v);
+        self.0.check(self.1.decoded_len(&v)).map_err(E::custom)?;
+        Ok(v)
+    }
+}
@@ -0,0 +1,853 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Leighton-Micali Signatures as specified by [RFC 8554](https://www.rfc-editor.org/rfc/rfc8554).
+//!
+//! Public keys and signatures use the RFC wire formats, so they interoperate
+//! with other LMS implementations. Private keys have no standard encoding and
+//! use the format of [`LmsSigningKey::to_bytes`].
+use crate::{KeyId, LamportError, LamportResult, MerkleSubtree, SubtreeCache};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const D_PBLC: u16 = 0x8080;
+const D_MESG: u16 = 0x8181;
+const D_LEAF: u16 = 0x8282;
+const D_INTR: u16 = 0x8383;
+
+/// The number of bytes in the key pair identifier `I`.
+pub const LMS_IDENTIFIER_BYTES: usize = 16;
+/// The number of bytes in a hash, `n` and `m` in RFC 8554.
+pub const LMS_HASH_BYTES: usize = 32;
+const SEED_BYTES: usize = 32;
+
+type HashValue = [u8; LMS_HASH_BYTES];
+
+fn hash(parts: &[&[u8]]) -> HashValue {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+pub(crate) fn read_u32(input: &[u8], offset: usize) -> Option<u32> {
+    let bytes = input.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// An LM-OTS parameter set, RFC 8554 §4.1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum LmOtsAlgorithm {
+    /// `LMOTS_SHA256_N32_W1`
+    Sha256N32W1,
+    /// `LMOTS_SHA256_N32_W2`
+    Sha256N32W2,
+    /// `LMOTS_SHA256_N32_W4`
+    Sha256N32W4,
+    /// `LMOTS_SHA256_N32_W8`
+    Sha256N32W8,
+}
+
+impl LmOtsAlgorithm {
+    /// The registered typecode.
+    pub fn typecode(self) -> u32 {
+        match self {
+            Self::Sha256N32W1 => 1,
+            Self::Sha256N32W2 => 2,
+            Self::Sha256N32W4 => 3,
+            Self::Sha256N32W8 => 4,
+        }
+    }
+
+    /// The number of bits encoded by each hash chain.
+    pub fn w(self) -> usize {
+        match self {
+            Self::Sha256N32W1 => 1,
+            Self::Sha256N32W2 => 2,
+            Self::Sha256N32W4 => 4,
+            Self::Sha256N32W8 => 8,
+        }
+    }
+
+    /// The number of hash chains.
+    pub fn p(self) -> usize {
+        match self {
+            Self::Sha256N32W1 => 265,
+            Self::Sha256N32W2 => 133,
+            Self::Sha256N32W4 => 67,
+            Self::Sha256N32W8 => 34,
+        }
+    }
+
+    /// The left shift applied to the checksum.
+    fn ls(self) -> u32 {
+        match self {
+            Self::Sha256N32W1 => 7,
+            Self::Sha256N32W2 => 6,
+            Self::Sha256N32W4 => 4,
+            Self::Sha256N32W8 => 0,
+        }
+    }
+
+    /// The length of an LM-OTS signature.
+    pub fn signature_len(self) -> usize {
+        4 + LMS_HASH_BYTES * (self.p() + 1)
+    }
+
+    fn max_digit(self) -> usize {
+        (1 << self.w()) - 1
+    }
+
+    /// Digit `i` of `s` in base `2^w`, `coef` in RFC 8554 §3.1.3.
+    fn coef(self, s: &[u8], i: usize) -> usize {
+        let w = self.w();
+        let byte = usize::from(s[i * w / 8]);
+        (byte >> (8 - (w * (i % (8 / w)) + w))) & self.max_digit()
+    }
+
+    /// The message hash followed by its checksum, `Q || Cksm(Q)`.
+    fn digits(self, q: &HashValue) -> Vec<usize> {
+        let sum = (0..LMS_HASH_BYTES * 8 / self.w())
+            .map(|i| self.max_digit() - self.coef(q, i))
+            .sum::<usize>();
+        let checksum = u16::try_from(sum << self.ls()).expect("the checksum fits in 16 bits");
+        let mut encoded = q.to_vec();
+        encoded.extend_from_slice(&checksum.to_be_bytes());
+        (0..self.p()).map(|i| self.coef(&encoded, i)).collect()
+    }
+}
+
+impl TryFrom<u32> for LmOtsAlgorithm {
+    type Error = LamportError;
+
+    fn try_from(typecode: u32) -> LamportResult<Self> {
+        match typecode {
+            1 => Ok(Self::Sha256N32W1),
+            2 => Ok(Self::Sha256N32W2),
+            3 => Ok(Self::Sha256N32W4),
+            4 => Ok(Self::Sha256N32W8),
+            _ => Err(LamportError::UnsupportedLmsTypecode(typecode)),
+        }
+    }
+}
+
+/// An LMS parameter set, RFC 8554 §5.1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum LmsAlgorithm {
+    /// `LMS_SHA256_M32_H5`
+    Sha256M32H5,
+    /// `LMS_SHA256_M32_H10`
+    Sha256M32H10,
+    /// `LMS_SHA256_M32_H15`
+    Sha256M32H15,
+    /// `LMS_SHA256_M32_H20`
+    Sha256M32H20,
+    /// `LMS_SHA256_M32_H25`
+    Sha256M32H25,
+}
+
+impl LmsAlgorithm {
+    /// The registered typecode.
+    pub fn typecode(self) -> u32 {
+        match self {
+            Self::Sha256M32H5 => 5,
+            Self::Sha256M32H10 => 6,
+            Self::Sha256M32H15 => 7,
+            Self::Sha256M32H20 => 8,
+            Self::Sha256M32H25 => 9,
+        }
+    }
+
+    /// The height of the tree.
+    pub fn height(self) -> u32 {
+        match self {
+            Self::Sha256M32H5 => 5,
+            Self::Sha256M32H10 => 10,
+            Self::Sha256M32H15 => 15,
+            Self::Sha256M32H20 => 20,
+            Self::Sha256M32H25 => 25,
+        }
+    }
+
+    /// The number of signatures a key can make, `2^h`.
+    pub fn capacity(self) -> u32 {
+        1 << self.height()
+    }
+}
+
+impl TryFrom<u32> for LmsAlgorithm {
+    type Error = LamportError;
+
+    fn try_from(typecode: u32) -> LamportResult<Self> {
+        match typecode {
+            5 => Ok(Self::Sha256M32H5),
+            6 => Ok(Self::Sha256M32H10),
+            7 => Ok(Self::Sha256M32H15),
+            8 => Ok(Self::Sha256M32H20),
+            9 => Ok(Self::Sha256M32H25),
+            _ => Err(LamportError::UnsupportedLmsTypecode(typecode)),
+        }
+    }
+}
+
+/// Applies the chain steps `start..end` of chain `i` of key `q`, RFC 8554 §4.3.
+fn chain(
+    identifier: &[u8],
+    q: u32,
+    i: usize,
+    value: &HashValue,
+    start: usize,
+    end: usize,
+) -> HashValue {
+    let i = u16::try_from(i).expect("fewer than 2^16 chains");
+    let mut value = *value;
+    for j in start..end {
+        let j = u8::try_from(j).expect("w is at most 8");
+        let next = hash(&[identifier, &q.to_be_bytes(), &i.to_be_bytes(), &[j], &value]);
+        value.zeroize();
+        value = next;
+    }
+    value
+}
+
+fn message_hash(identifier: &[u8], q: u32, c: &[u8], message: &[u8]) -> HashValue {
+    hash(&[
+        identifier,
+        &q.to_be_bytes(),
+        &D_MESG.to_be_bytes(),
+        c,
+        message,
+    ])
+}
+
+fn ots_public_key(identifier: &[u8], q: u32, ends: &[HashValue]) -> HashValue {
+    let mut hasher = Sha256::new();
+    hasher.update(identifier);
+    hasher.update(q.to_be_bytes());
+    hasher.update(D_PBLC.to_be_bytes());
+    for end in ends {
+        hasher.update(end);
+    }
+    hasher.finalize().into()
+}
+
+fn leaf_hash(identifier: &[u8], node: u32, ots_public_key: &[u8]) -> Vec<u8> {
+    hash(&[
+        identifier,
+        &node.to_be_bytes(),
+        &D_LEAF.to_be_bytes(),
+        ots_public_key,
+    ])
+    .to_vec()
+}
+
+fn interior_hash(identifier: &[u8], node: u32, left: &[u8], right: &[u8]) -> Vec<u8> {
+    hash(&[
+        identifier,
+        &node.to_be_bytes(),
+        &D_INTR.to_be_bytes(),
+        left,
+        right,
+    ])
+    .to_vec()
+}
+
+/// An LMS private key, a Merkle tree of `2^h` LM-OTS keys.
+///
+/// The LM-OTS private keys are derived from a secret seed as in RFC 8554
+/// Appendix A. The tree is built when the key is generated and kept in a
+/// [`SubtreeCache`] for computing authentication paths. A tree of height
+/// `h` holds `2^(h + 1)` hashes, so the taller parameter sets need a lot of
+/// memory and time to generate; use `HssSigningKey` for large
+/// signature capacities.
+///
+/// The key is stateful. Its canonical bytes include the index `q` of the
+/// next LM-OTS key and must be persisted after every signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LmsSigningKey {
+    pub(crate) lms: LmsAlgorithm,
+    pub(crate) ots: LmOtsAlgorithm,
+    pub(crate) identifier: [u8; LMS_IDENTIFIER_BYTES],
+    pub(crate) seed: [u8; SEED_BYTES],
+    pub(crate) q: u32,
+    pub(crate) root: Vec<u8>,
+    pub(crate) cache: SubtreeCache,
+}
+
+serde_impl!(@concrete LmsSigningKey);
+
+impl Zeroize for LmsSigningKey {
+    fn zeroize(&mut self) {
+        self.seed.zeroize();
+        self.cache.clear();
+    }
+}
+
+impl LmsSigningKey {
+    /// Generates a key with a random identifier and seed.
+    pub fn generate(
+        lms: LmsAlgorithm,
+        ots: LmOtsAlgorithm,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> Self {
+        let mut identifier = [0u8; LMS_IDENTIFIER_BYTES];
+        rng.fill_bytes(&mut identifier);
+        let mut seed = [0u8; SEED_BYTES];
+        rng.fill_bytes(&mut seed);
+        let key = Self::from_seed(lms, ots, identifier, &seed);
+        seed.zeroize();
+        key
+    }
+
+    /// Builds the key for an identifier and secret seed.
+    pub(crate) fn from_seed(
+        lms: LmsAlgorithm,
+        ots: LmOtsAlgorithm,
+        identifier: [u8; LMS_IDENTIFIER_BYTES],
+        seed: &[u8; SEED_BYTES],
+    ) -> Self {
+        let mut key = Self {
+            lms,
+            ots,
+            identifier,
+            seed: *seed,
+            q: 0,
+            root: Vec::new(),
+            cache: SubtreeCache::new(),
+        };
+        key.root = key.tree().root().to_vec();
+        key
+    }
+
+    /// The public key.
+    pub fn verifying_key(&self) -> LmsVerifyingKey {
+        let mut root = [0u8; LMS_HASH_BYTES];
+        root.copy_from_slice(&self.root);
+        LmsVerifyingKey {
+            lms: self.lms,
+            ots: self.ots,
+            identifier: self.identifier,
+            root,
+        }
+    }
+
+    /// The LMS parameter set.
+    pub fn lms_algorithm(&self) -> LmsAlgorithm {
+        self.lms
+    }
+
+    /// The LM-OTS parameter set.
+    pub fn ots_algorithm(&self) -> LmOtsAlgorithm {
+        self.ots
+    }
+
+    /// The index `q` of the next LM-OTS key.
+    pub fn index(&self) -> u32 {
+        self.q
+    }
+
+    /// The number of signatures left.
+    pub fn remaining(&self) -> u32 {
+        self.lms.capacity() - self.q
+    }
+
+    /// Signs the data with the next LM-OTS key.
+    ///
+    /// Returns [`LamportError::KeyPoolExhausted`] once every LM-OTS key has been used.
+    pub fn sign<B: AsRef<[u8]>>(
+        &mut self,
+        data: B,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<LmsSignature> {
+        let mut c = [0u8; LMS_HASH_BYTES];
+        rng.fill_bytes(&mut c);
+        self.sign_with_randomizer(data, c)
+    }
+
+    /// Signs the data with the next LM-OTS key and the randomizer `C`.
+    pub(crate) fn sign_with_randomizer<B: AsRef<[u8]>>(
+        &mut self,
+        data: B,
+        c: HashValue,
+    ) -> LamportResult<LmsSignature> {
+        if self.q >= self.lms.capacity() {
+            return Err(LamportError::KeyPoolExhausted);
+        }
+        let q = self.q;
+        self.q += 1;
+
+        let digest = message_hash(&self.identifier, q, &c, data.as_ref());
+        let y = self
+            .ots
+            .digits(&digest)
+            .into_iter()
+            .enumerate()
+            .map(|(i, digit)| {
+                let mut x = self.ots_private_value(q, i);
+                let y = chain(&self.identifier, q, i, &x, 0, digit);
+                x.zeroize();
+                y
+            })
+            .collect();
+        let expected = self.root.clone();
+        let tree = self.tree();
+        if tree.root() != expected.as_slice() {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let path = tree.auth_path(q)?;
+        Ok(LmsSignature {
+            q,
+            ots: self.ots,
+            c,
+            y,
+            lms: self.lms,
+            path,
+        })
+    }
+
+    /// Converts the [`LmsSigningKey`] to canonical bytes.
+    ///
+    /// The format is the LMS and LM-OTS typecodes and `q` as big endian
+    /// `u32`s, then `I`, the secret seed and the root. The tree is rebuilt
+    /// when needed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.extend_from_slice(&self.lms.typecode().to_be_bytes());
+        bytes.extend_from_slice(&self.ots.typecode().to_be_bytes());
+        bytes.extend_from_slice(&self.q.to_be_bytes());
+        bytes.extend_from_slice(&self.identifier);
+        bytes.extend_from_slice(&self.seed);
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    /// Constructs a [`LmsSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        if input.len() != Self::BYTES {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let lms = LmsAlgorithm::try_from(read_u32(input, 0).unwrap_or_default())?;
+        let ots = LmOtsAlgorithm::try_from(read_u32(input, 4).unwrap_or_default())?;
+        let q = read_u32(input, 8).unwrap_or_default();
+        if q > lms.capacity() {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let mut identifier = [0u8; LMS_IDENTIFIER_BYTES];
+        identifier.copy_from_slice(&input[12..28]);
+        let mut seed = [0u8; SEED_BYTES];
+        seed.copy_from_slice(&input[28..60]);
+        Ok(Self {
+            lms,
+            ots,
+            identifier,
+            seed,
+            q,
+            root: input[60..].to_vec(),
+            cache: SubtreeCache::new(),
+        })
+    }
+
+    pub(crate) const BYTES: usize = 12 + LMS_IDENTIFIER_BYTES + SEED_BYTES + LMS_HASH_BYTES;
+
+    /// `x_q[i] = H(I || u32str(q) || u16str(i) || u8str(0xff) || SEED)`
+    fn ots_private_value(&self, q: u32, i: usize) -> HashValue {
+        let i = u16::try_from(i).expect("fewer than 2^16 chains");
+        hash(&[
+            &self.identifier,
+            &q.to_be_bytes(),
+            &i.to_be_bytes(),
+            &[0xff],
+            &self.seed,
+        ])
+    }
+
+    fn ots_public_key(&self, q: u32) -> HashValue {
+        let last = self.ots.max_digit();
+        let ends = (0..self.ots.p())
+            .map(|i| {
+                let mut x = self.ots_private_value(q, i);
+                let y = chain(&self.identifier, q, i, &x, 0, last);
+                x.zeroize();
+                y
+            })
+            .collect::<Vec<_>>();
+        ots_public_key(&self.identifier, q, &ends)
+    }
+
+    fn tree(&mut self) -> &MerkleSubtree {
+        let height = self.lms.height();
+        let identifier = self.identifier;
+        let mut leaves = Vec::with_capacity(1 << height);
+        if !self.cache.contains(0, 0) {
+            leaves.extend((0..self.lms.capacity()).map(|q| self.ots_public_key(q)));
+        }
+        self.cache.get_or_build(0, 0, || {
+            MerkleSubtree::build(
+                height,
+                |q| leaf_hash(&identifier, (1 << height) + q, &leaves[q as usize]),
+                |level, index, left, right| {
+                    interior_hash(&identifier, (1 << (height - level)) + index, left, right)
+                },
+            )
+        })
+    }
+}
+
+/// An LMS public key.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LmsVerifyingKey {
+    pub(crate) lms: LmsAlgorithm,
+    pub(crate) ots: LmOtsAlgorithm,
+    pub(crate) identifier: [u8; LMS_IDENTIFIER_BYTES],
+    pub(crate) root: HashValue,
+}
+
+serde_impl!(@concrete LmsVerifyingKey);
+
+impl LmsVerifyingKey {
+    /// The length of an encoded public key.
+    pub const BYTES: usize = 8 + LMS_IDENTIFIER_BYTES + LMS_HASH_BYTES;
+
+    /// The LMS parameter set.
+    pub fn lms_algorithm(&self) -> LmsAlgorithm {
+        self.lms
+    }
+
+    /// The LM-OTS parameter set.
+    pub fn ots_algorithm(&self) -> LmOtsAlgorithm {
+        self.ots
+    }
+
+    /// The key pair identifier `I`.
+    pub fn identifier(&self) -> &[u8; LMS_IDENTIFIER_BYTES] {
+        &self.identifier
+    }
+
+    /// The [`KeyId`] fingerprint of the key.
+    pub fn fingerprint(&self) -> KeyId {
+        KeyId::compute_encoded(b"LMS", LMS_HASH_BYTES * 8, &self.to_bytes())
+    }
+
+    /// Verifies the signature, RFC 8554 §5.4.2.
+    pub fn verify<B: AsRef<[u8]>>(&self, signature: &LmsSignature, data: B) -> LamportResult<()> {
+        if signature.lms != self.lms || signature.ots != self.ots {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let root = signature.recover_root(&self.identifier, data.as_ref())?;
+        if root.ct_eq(&self.root).into() {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the [`LmsVerifyingKey`] to the RFC 8554 §5.3 encoding,
+    /// `u32str(type) || u32str(otstype) || I || T[1]`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.extend_from_slice(&self.lms.typecode().to_be_bytes());
+        bytes.extend_from_slice(&self.ots.typecode().to_be_bytes());
+        bytes.extend_from_slice(&self.identifier);
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    /// Constructs a [`LmsVerifyingKey`] from the RFC 8554 §5.3 encoding.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let lms = LmsAlgorithm::try_from(
+            read_u32(input, 0).ok_or(LamportError::InvalidPrivateKeyBytes)?,
+        )?;
+        let ots = LmOtsAlgorithm::try_from(
+            read_u32(input, 4).ok_or(LamportError::InvalidPrivateKeyBytes)?,
+        )?;
+        if input.len() != Self::BYTES {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let mut identifier = [0u8; LMS_IDENTIFIER_BYTES];
+        identifier.copy_from_slice(&input[8..24]);
+        let mut root = [0u8; LMS_HASH_BYTES];
+        root.copy_from_slice(&input[24..]);
+        Ok(Self {
+            lms,
+            ots,
+            identifier,
+            root,
+        })
+    }
+}
+
+/// An LMS signature, the index `q`, an LM-OTS signature and an authentication path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LmsSignature {
+    pub(crate) q: u32,
+    pub(crate) ots: LmOtsAlgorithm,
+    pub(crate) c: HashValue,
+    pub(crate) y: Vec<HashValue>,
+    pub(crate) lms: LmsAlgorithm,
+    pub(crate) path: Vec<Vec<u8>>,
+}
+
+serde_impl!(@concrete LmsSignature);
+
+impl LmsSignature {
+    /// The index of the LM-OTS key that made the signature.
+    pub fn index(&self) -> u32 {
+        self.q
+    }
+
+    /// The LMS parameter set.
+    pub fn lms_algorithm(&self) -> LmsAlgorithm {
+        self.lms
+    }
+
+    /// The LM-OTS parameter set.
+    pub fn ots_algorithm(&self) -> LmOtsAlgorithm {
+        self.ots
+    }
+
+    /// Computes the candidate root `Tc` for key pair identifier `I`, RFC 8554 §5.4.2.
+    pub(crate) fn recover_root(&self, identifier: &[u8], data: &[u8]) -> LamportResult<HashValue> {
+        let height = self.lms.height();
+        if self.q >= self.lms.capacity()
+            || self.y.len() != self.ots.p()
+            || self.path.len() != height as usize
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let digest = message_hash(identifier, self.q, &self.c, data);
+        let last = self.ots.max_digit();
+        let ends = self
+            .ots
+            .digits(&digest)
+            .into_iter()
+            .zip(&self.y)
+            .enumerate()
+            .map(|(i, (digit, y))| chain(identifier, self.q, i, y, digit, last))
+            .collect::<Vec<_>>();
+        let candidate = ots_public_key(identifier, self.q, &ends);
+        let leaf = leaf_hash(identifier, (1 << height) + self.q, &candidate);
+        let root = MerkleSubtree::root_from_path(
+            self.q,
+            &leaf,
+            &self.path,
+            |level, index, left, right| {
+                interior_hash(identifier, (1 << (height - level)) + index, left, right)
+            },
+        );
+        let mut output = [0u8; LMS_HASH_BYTES];
+        output.copy_from_slice(&root);
+        Ok(output)
+    }
+
+    /// The length of the encoded signature.
+    pub fn len(&self) -> usize {
+        8 + self.ots.signature_len() + self.path.len() * LMS_HASH_BYTES
+    }
+
+    /// Always false, an encoded signature has at least its typecodes.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Converts the [`LmsSignature`] to the RFC 8554 §5.4 encoding,
+    /// `u32str(q) || lmots_signature || u32str(type) || path`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.len());
+        bytes.extend_from_slice(&self.q.to_be_bytes());
+        bytes.extend_from_slice(&self.ots.typecode().to_be_bytes());
+        bytes.extend_from_slice(&self.c);
+        self.y.iter().for_each(|y| bytes.extend_from_slice(y));
+        bytes.extend_from_slice(&self.lms.typecode().to_be_bytes());
+        self.path.iter().for_each(|p| bytes.extend_from_slice(p));
+        bytes
+    }
+
+    /// Constructs a [`LmsSignature`] from the RFC 8554 §5.4 encoding.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (signature, rest) = Self::parse(input.as_ref())?;
+        if !rest.is_empty() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(signature)
+    }
+
+    /// Parses a signature from the front of the input, returning the remaining bytes.
+    pub(crate) fn parse(input: &[u8]) -> LamportResult<(Self, &[u8])> {
+        let q = read_u32(input, 0).ok_or(LamportError::InvalidSignatureBytes)?;
+        let ots = LmOtsAlgorithm::try_from(
+            read_u32(input, 4).ok_or(LamportError::InvalidSignatureBytes)?,
+        )?;
+        let lms_offset = 4 + ots.signature_len();
+        let lms = LmsAlgorithm::try_from(
+            read_u32(input, lms_offset).ok_or(LamportError::InvalidSignatureBytes)?,
+        )?;
+        let length = lms_offset + 4 + lms.height() as usize * LMS_HASH_BYTES;
+        if input.len() < length || q >= lms.capacity() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let hashes = |bytes: &[u8]| {
+            bytes
+                .chunks_exact(LMS_HASH_BYTES)
+                .map(|chunk| {
+                    let mut value = [0u8; LMS_HASH_BYTES];
+                    value.copy_from_slice(chunk);
+                    value
+                })
+                .collect::<Vec<_>>()
+        };
+        let c = hashes(&input[8..8 + LMS_HASH_BYTES])[0];
+        let y = hashes(&input[8 + LMS_HASH_BYTES..lms_offset]);
+        let path = input[lms_offset + 4..length]
+            .chunks_exact(LMS_HASH_BYTES)
+            .map(<[u8]>::to_vec)
+            .collect();
+        Ok((
+            Self {
+                q,
+                ots,
+                c,
+                y,
+                lms,
+                path,
+            },
+            &input[length..],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn lms() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut sk = LmsSigningKey::generate(
+            LmsAlgorithm::Sha256M32H5,
+            LmOtsAlgorithm::Sha256N32W8,
+            &mut rng,
+        );
+        let pk = sk.verifying_key();
+        assert_eq!(pk.to_bytes().len(), LmsVerifyingKey::BYTES);
+        assert_eq!(&pk.to_bytes()[..8], &[0, 0, 0, 5, 0, 0, 0, 4]);
+        assert_eq!(LmsVerifyingKey::from_bytes(pk.to_bytes()).unwrap(), pk);
+
+        let signature = sk.sign(b"first", &mut rng).unwrap();
+        assert_eq!(signature.index(), 0);
+        assert_eq!(signature.to_bytes().len(), 1292);
+        assert!(pk.verify(&signature, b"first").is_ok());
+        assert!(pk.verify(&signature, b"second").is_err());
+        let mut bytes = signature.to_bytes();
+        let decoded = LmsSignature::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), signature.to_bytes());
+        bytes[100] ^= 1;
+        let tampered = LmsSignature::from_bytes(&bytes).unwrap();
+        assert!(pk.verify(&tampered, b"first").is_err());
+        assert!(LmsSignature::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        bytes[7] = 9;
+        assert_eq!(
+            LmsSignature::from_bytes(&bytes).unwrap_err(),
+            LamportError::UnsupportedLmsTypecode(9)
+        );
+
+        // A restored key rebuilds its tree and continues from q
+        let mut restored = LmsSigningKey::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(restored.index(), 1);
+        let signature = restored.sign(b"second", &mut rng).unwrap();
+        assert_eq!(signature.index(), 1);
+        assert!(pk.verify(&signature, b"second").is_ok());
+
+        while restored.remaining() > 0 {
+            let signature = restored.sign(b"more", &mut rng).unwrap();
+            assert!(pk.verify(&signature, b"more").is_ok());
+        }
+        assert_eq!(
+            restored.sign(b"more", &mut rng).unwrap_err(),
+            LamportError::KeyPoolExhausted
+        );
+
+        let mut sk = LmsSigningKey::generate(
+            LmsAlgorithm::Sha256M32H5,
+            LmOtsAlgorithm::Sha256N32W1,
+            &mut rng,
+        );
+        let signature = sk.sign(b"w1", &mut rng).unwrap();
+        assert_eq!(signature.to_bytes().len(), 8 + 4 + 32 * 266 + 5 * 32);
+        assert!(sk.verifying_key().verify(&signature, b"w1").is_ok());
+        assert!(pk.verify(&signature, b"w1").is_err());
+    }
+
+    #[test]
+    fn rfc8554_test_case_2() {
+        // RFC 8554 Appendix F, Test Case 2, which publishes the private key
+        // details of both levels
+        let key = |lms, ots, identifier: &str, seed: &str| {
+            LmsSigningKey::from_seed(
+                lms,
+                ots,
+                hex::decode(identifier).unwrap().try_into().unwrap(),
+                &hex::decode(seed).unwrap().try_into().unwrap(),
+            )
+        };
+
+        // Top level, LMS_SHA256_M32_H10 with LMOTS_SHA256_N32_W4
+        let top = key(
+            LmsAlgorithm::Sha256M32H10,
+            LmOtsAlgorithm::Sha256N32W4,
+            "d08fabd4a2091ff0a8cb4ed834e74534",
+            "558b8966c48ae9cb898b423c83443aae014a72f1b1ab5cc85cf1d892903b5439",
+        );
+        let top_public = hex::decode(
+            "0000000600000003d08fabd4a2091ff0a8cb4ed834e74534\
+             32a58885cd9ba0431235466bff9651c6c92124404d45fa53cf161c28f1ad5a8e",
+        )
+        .unwrap();
+        assert_eq!(top.verifying_key().to_bytes(), top_public);
+
+        // Bottom level, LMS_SHA256_M32_H5 with LMOTS_SHA256_N32_W8
+        let mut bottom = key(
+            LmsAlgorithm::Sha256M32H5,
+            LmOtsAlgorithm::Sha256N32W8,
+            "215f83b7ccb9acbcd08db97b0d04dc2b",
+            "a1c4696e2608035a886100d05cd99945eb3370731884a8235e2fb3d4d71f2547",
+        );
+        let bottom_public = LmsVerifyingKey::from_bytes(
+            hex::decode(
+                "0000000500000004215f83b7ccb9acbcd08db97b0d04dc2b\
+                 a1cd035833e0e90059603f26e07ad2aad152338e7a5e5984bcd5f7bb4eba40b7",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(bottom.verifying_key(), bottom_public);
+
+        // The message signature uses q = 4 and the published randomizer
+        let message = b"The enumeration in the Constitution, of certain rights, shall not be \
+                        construed to deny or disparage others retained by the people.\n";
+        bottom.q = 4;
+        let c = hex::decode("0eb1ed54a2460d512388cad533138d240534e97b1e82d33bd927d201dfc24ebb")
+            .unwrap();
+        let signature = bottom
+            .sign_with_randomizer(message, c.try_into().unwrap())
+            .unwrap();
+        let bytes = signature.to_bytes();
+        assert_eq!(
+            hex::encode(&bytes[..72]),
+            "00000004000000040eb1ed54a2460d512388cad533138d240534e97b1e82d33bd927d201dfc24ebb\
+             11b3649023696f85150b189e50c00e98850ac343a77b3638319c347d7310269d"
+        );
+        let signature = LmsSignature::from_bytes(&bytes).unwrap();
+        assert!(bottom_public.verify(&signature, message).is_ok());
+        assert!(bottom_public.verify(&signature, &message[1..]).is_err());
+    }
+}
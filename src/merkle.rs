@@ -0,0 +1,432 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::{KeyId, LamportDigest, LamportError, LamportResult, Signature, VerifyingKey};
+use sha2::{Digest, Sha256};
+
+/// The size in bytes of a Merkle tree node and root.
+pub const MERKLE_HASH_BYTES: usize = 32;
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// A Merkle tree over a set of [`KeyId`] fingerprints.
+///
+/// The tree uses the SHA-256 based construction from RFC 6962 with distinct
+/// leaf and interior node prefixes, so verifiers can pin a single root hash
+/// instead of a directory of full public keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MerkleTree {
+    pub(crate) leaves: Vec<KeyId>,
+    pub(crate) root: [u8; MERKLE_HASH_BYTES],
+}
+
+impl MerkleTree {
+    /// Constructs a [`MerkleTree`] over the fingerprints in the given order.
+    pub fn new(leaves: Vec<KeyId>) -> LamportResult<Self> {
+        if leaves.is_empty() {
+            return Err(LamportError::General(
+                "a merkle tree requires at least one leaf".to_string(),
+            ));
+        }
+        let hashes = leaves.iter().map(leaf_hash).collect::<Vec<_>>();
+        let root = subtree_hash(&hashes);
+        Ok(Self { leaves, root })
+    }
+
+    /// Constructs a [`MerkleTree`] over the fingerprints of the [`VerifyingKey`]s.
+    pub fn from_verifying_keys<T: LamportDigest>(keys: &[VerifyingKey<T>]) -> LamportResult<Self> {
+        Self::new(keys.iter().map(VerifyingKey::fingerprint).collect())
+    }
+
+    /// The root hash committing to every leaf.
+    pub fn root(&self) -> [u8; MERKLE_HASH_BYTES] {
+        self.root
+    }
+
+    /// The leaves of the tree.
+    pub fn leaves(&self) -> &[KeyId] {
+        &self.leaves
+    }
+
+    /// The number of leaves in the tree.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Returns true if the tree has no leaves. Always false for a constructed tree.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// The position of the [`KeyId`] in the tree, if present.
+    pub fn position(&self, key_id: &KeyId) -> Option<usize> {
+        self.leaves.iter().position(|leaf| leaf == key_id)
+    }
+
+    /// Creates a proof that the leaf at `index` is included in this tree.
+    pub fn prove(&self, index: usize) -> LamportResult<InclusionProof> {
+        if index >= self.leaves.len() {
+            return Err(LamportError::General(format!(
+                "leaf index {} is out of range for a tree of {} leaves",
+                index,
+                self.leaves.len()
+            )));
+        }
+        let hashes = self.leaves.iter().map(leaf_hash).collect::<Vec<_>>();
+        let mut path = Vec::new();
+        inclusion_path(index, &hashes, &mut path);
+        Ok(InclusionProof {
+            index: index as u64,
+            tree_size: hashes.len() as u64,
+            path,
+        })
+    }
+
+    /// Creates a proof that the [`VerifyingKey`] is included in this tree,
+    /// the authentication path to publish alongside its signatures.
+    pub fn prove_key<T: LamportDigest>(
+        &self,
+        key: &VerifyingKey<T>,
+    ) -> LamportResult<InclusionProof> {
+        let index = self.position(&key.fingerprint()).ok_or_else(|| {
+            LamportError::General("the verifying key is not a leaf of the tree".to_string())
+        })?;
+        self.prove(index)
+    }
+}
+
+/// A proof that a [`KeyId`] is a leaf of a [`MerkleTree`] with a known root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InclusionProof {
+    pub(crate) index: u64,
+    pub(crate) tree_size: u64,
+    pub(crate) path: Vec<[u8; MERKLE_HASH_BYTES]>,
+}
+
+serde_impl!(@limits @concrete InclusionProof);
+
+impl InclusionProof {
+    /// The position of the proven leaf.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// The number of leaves in the tree the proof was created for.
+    pub fn tree_size(&self) -> u64 {
+        self.tree_size
+    }
+
+    /// The sibling hashes from the leaf to the root.
+    pub fn path(&self) -> &[[u8; MERKLE_HASH_BYTES]] {
+        &self.path
+    }
+
+    /// Checks the [`KeyId`] is included in the tree with the specified `root`.
+    pub fn verify(&self, key_id: &KeyId, root: &[u8; MERKLE_HASH_BYTES]) -> LamportResult<()> {
+        self.verify_leaf_hash(leaf_hash(key_id), root)
+    }
+
+    /// Checks an already hashed leaf is included in the tree with the specified `root`.
+    pub(crate) fn verify_leaf_hash(
+        &self,
+        leaf: [u8; MERKLE_HASH_BYTES],
+        root: &[u8; MERKLE_HASH_BYTES],
+    ) -> LamportResult<()> {
+        if self.index >= self.tree_size {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+        let mut f_n = self.index;
+        let mut s_n = self.tree_size - 1;
+        let mut r = leaf;
+        for p in &self.path {
+            if s_n == 0 {
+                return Err(LamportError::InvalidMerkleProof);
+            }
+            if f_n & 1 == 1 || f_n == s_n {
+                r = node_hash(p, &r);
+                if f_n & 1 == 0 {
+                    while f_n & 1 == 0 && f_n != 0 {
+                        f_n >>= 1;
+                        s_n >>= 1;
+                    }
+                }
+            } else {
+                r = node_hash(&r, p);
+            }
+            f_n >>= 1;
+            s_n >>= 1;
+        }
+        if s_n == 0 && r == *root {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidMerkleProof)
+        }
+    }
+
+    /// Checks the [`VerifyingKey`] is included in the tree with the specified `root`.
+    pub fn verify_key<T: LamportDigest>(
+        &self,
+        key: &VerifyingKey<T>,
+        root: &[u8; MERKLE_HASH_BYTES],
+    ) -> LamportResult<()> {
+        self.verify(&key.fingerprint(), root)
+    }
+
+    /// Checks the [`VerifyingKey`] is included in the tree with the
+    /// specified `root` and verifies its [`Signature`] over the data.
+    ///
+    /// Relying parties only need to know the root to check signatures from
+    /// every key in the tree.
+    pub fn verify_signature<T: LamportDigest, B: AsRef<[u8]>>(
+        &self,
+        key: &VerifyingKey<T>,
+        signature: &Signature<T>,
+        data: B,
+        root: &[u8; MERKLE_HASH_BYTES],
+    ) -> LamportResult<()> {
+        self.verify_key(key, root)?;
+        key.verify(signature, data)
+    }
+
+    /// Converts the proof to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.path.len() * MERKLE_HASH_BYTES);
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        bytes.extend_from_slice(&self.tree_size.to_be_bytes());
+        for p in &self.path {
+            bytes.extend_from_slice(p);
+        }
+        bytes
+    }
+
+    /// Constructs an [`InclusionProof`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs an [`InclusionProof`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        if input.len() < 16 || !(input.len() - 16).is_multiple_of(MERKLE_HASH_BYTES) {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+        let index = u64::from_be_bytes(read_array(&input[..8])?);
+        let tree_size = u64::from_be_bytes(read_array(&input[8..16])?);
+        let path = input[16..]
+            .chunks_exact(MERKLE_HASH_BYTES)
+            .map(read_array)
+            .collect::<LamportResult<Vec<_>>>()?;
+        Ok(Self {
+            index,
+            tree_size,
+            path,
+        })
+    }
+}
+
+/// A proof that a Merkle tree of `old_size` leaves is a prefix of a tree of `new_size` leaves.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConsistencyProof {
+    pub(crate) old_size: u64,
+    pub(crate) new_size: u64,
+    pub(crate) path: Vec<[u8; MERKLE_HASH_BYTES]>,
+}
+
+serde_impl!(@limits @concrete ConsistencyProof);
+
+impl ConsistencyProof {
+    /// The size of the older tree.
+    pub fn old_size(&self) -> u64 {
+        self.old_size
+    }
+
+    /// The size of the newer tree.
+    pub fn new_size(&self) -> u64 {
+        self.new_size
+    }
+
+    /// The intermediate hashes needed to recompute both roots.
+    pub fn path(&self) -> &[[u8; MERKLE_HASH_BYTES]] {
+        &self.path
+    }
+
+    /// Checks that the tree with `old_root` is a prefix of the tree with `new_root`.
+    pub fn verify(
+        &self,
+        old_root: &[u8; MERKLE_HASH_BYTES],
+        new_root: &[u8; MERKLE_HASH_BYTES],
+    ) -> LamportResult<()> {
+        if self.old_size == 0 || self.old_size > self.new_size {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+        if self.old_size == self.new_size {
+            return if self.path.is_empty() && old_root == new_root {
+                Ok(())
+            } else {
+                Err(LamportError::InvalidMerkleProof)
+            };
+        }
+        if self.path.is_empty() {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+
+        let mut path = self.path.iter();
+        let first = if self.old_size.is_power_of_two() {
+            *old_root
+        } else {
+            *path.next().ok_or(LamportError::InvalidMerkleProof)?
+        };
+        let mut f_n = self.old_size - 1;
+        let mut s_n = self.new_size - 1;
+        while f_n & 1 == 1 {
+            f_n >>= 1;
+            s_n >>= 1;
+        }
+        let mut f_r = first;
+        let mut s_r = first;
+        for c in path {
+            if s_n == 0 {
+                return Err(LamportError::InvalidMerkleProof);
+            }
+            if f_n & 1 == 1 || f_n == s_n {
+                f_r = node_hash(c, &f_r);
+                s_r = node_hash(c, &s_r);
+                while f_n & 1 == 0 && f_n != 0 {
+                    f_n >>= 1;
+                    s_n >>= 1;
+                }
+            } else {
+                s_r = node_hash(&s_r, c);
+            }
+            f_n >>= 1;
+            s_n >>= 1;
+        }
+        if s_n == 0 && f_r == *old_root && s_r == *new_root {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidMerkleProof)
+        }
+    }
+
+    /// Converts the proof to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.path.len() * MERKLE_HASH_BYTES);
+        bytes.extend_from_slice(&self.old_size.to_be_bytes());
+        bytes.extend_from_slice(&self.new_size.to_be_bytes());
+        for p in &self.path {
+            bytes.extend_from_slice(p);
+        }
+        bytes
+    }
+
+    /// Constructs a [`ConsistencyProof`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`ConsistencyProof`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        if input.len() < 16 || !(input.len() - 16).is_multiple_of(MERKLE_HASH_BYTES) {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+        let old_size = u64::from_be_bytes(read_array(&input[..8])?);
+        let new_size = u64::from_be_bytes(read_array(&input[8..16])?);
+        let path = input[16..]
+            .chunks_exact(MERKLE_HASH_BYTES)
+            .map(read_array)
+            .collect::<LamportResult<Vec<_>>>()?;
+        Ok(Self {
+            old_size,
+            new_size,
+            path,
+        })
+    }
+}
+
+fn read_array<const N: usize>(input: &[u8]) -> LamportResult<[u8; N]> {
+    <[u8; N]>::try_from(input).map_err(|_| LamportError::InvalidMerkleProof)
+}
+
+pub(crate) fn leaf_hash(key_id: &KeyId) -> [u8; MERKLE_HASH_BYTES] {
+    data_leaf_hash(key_id.as_bytes())
+}
+
+pub(crate) fn data_leaf_hash(data: &[u8]) -> [u8; MERKLE_HASH_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub(crate) fn node_hash(
+    left: &[u8; MERKLE_HASH_BYTES],
+    right: &[u8; MERKLE_HASH_BYTES],
+) -> [u8; MERKLE_HASH_BYTES] {
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The largest power of two strictly less than `n`, `n` must be greater than 1.
+pub(crate) fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Computes the RFC 6962 Merkle tree hash over already hashed leaves.
+pub(crate) fn subtree_hash(hashes: &[[u8; MERKLE_HASH_BYTES]]) -> [u8; MERKLE_HASH_BYTES] {
+    if hashes.len() == 1 {
+        return hashes[0];
+    }
+    let k = split_point(hashes.len());
+    node_hash(&subtree_hash(&hashes[..k]), &subtree_hash(&hashes[k..]))
+}
+
+pub(crate) fn inclusion_path(
+    index: usize,
+    hashes: &[[u8; MERKLE_HASH_BYTES]],
+    path: &mut Vec<[u8; MERKLE_HASH_BYTES]>,
+) {
+    if hashes.len() <= 1 {
+        return;
+    }
+    let k = split_point(hashes.len());
+    if index < k {
+        inclusion_path(index, &hashes[..k], path);
+        path.push(subtree_hash(&hashes[k..]));
+    } else {
+        inclusion_path(index - k, &hashes[k..], path);
+        path.push(subtree_hash(&hashes[..k]));
+    }
+}
+
+pub(crate) fn consistency_path(
+    old_size: usize,
+    hashes: &[[u8; MERKLE_HASH_BYTES]],
+    complete: bool,
+    path: &mut Vec<[u8; MERKLE_HASH_BYTES]>,
+) {
+    if old_size == hashes.len() {
+        if !complete {
+            path.push(subtree_hash(hashes));
+        }
+        return;
+    }
+    let k = split_point(hashes.len());
+    if old_size <= k {
+        consistency_path(old_size, &hashes[..k], complete, path);
+        path.push(subtree_hash(&hashes[k..]));
+    } else {
+        consistency_path(old_size - k, &hashes[k..], false, path);
+        path.push(subtree_hash(&hashes[..k]));
+    }
+}
@@ -0,0 +1,276 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Wire formats for running the threshold protocol over an arbitrary transport.
+//!
+//! Every message starts with a [`MESSAGE_VERSION`] byte so peers can reject
+//! formats they do not understand instead of misparsing them.
+use crate::gf256::ShareSplitter;
+use crate::utils::separate_one_and_zero_values;
+use crate::{
+    LamportDigest, LamportError, LamportResult, MultiVec, Signature, SignatureShare,
+    SigningKeyShare,
+};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The wire format version written by this crate.
+pub const MESSAGE_VERSION: u8 = 1;
+
+/// The number of bytes in a session identifier.
+pub const SESSION_ID_BYTES: usize = 32;
+
+/// The largest message a [`SignRequest`] may carry.
+///
+/// Every other message has a fixed length determined by the digest.
+pub const MAX_SIGN_REQUEST_MESSAGE_BYTES: usize = 1 << 20;
+
+/// Asks the share holders to sign a message in a session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SignRequest {
+    /// The session the signature is for.
+    pub session_id: [u8; SESSION_ID_BYTES],
+    /// The message to sign.
+    pub message: Vec<u8>,
+}
+
+serde_impl!(@concrete SignRequest);
+
+impl SignRequest {
+    /// Converts the [`SignRequest`] to canonical bytes.
+    ///
+    /// The format is the version byte, the session id, a big endian `u32`
+    /// message length and the message. Messages longer than
+    /// [`MAX_SIGN_REQUEST_MESSAGE_BYTES`] are encoded but rejected when decoded.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + SESSION_ID_BYTES + 4 + self.message.len());
+        bytes.push(MESSAGE_VERSION);
+        bytes.extend_from_slice(&self.session_id);
+        let length = u32::try_from(self.message.len()).unwrap_or(u32::MAX);
+        bytes.extend_from_slice(&length.to_be_bytes());
+        bytes.extend_from_slice(&self.message);
+        bytes
+    }
+
+    /// Constructs a [`SignRequest`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = check_version(input.as_ref())?;
+        if input.len() < SESSION_ID_BYTES + 4 {
+            return Err(LamportError::InvalidMessageBytes);
+        }
+        let (session_id, rest) = read_session_id(input)?;
+        let mut length = [0u8; 4];
+        length.copy_from_slice(&rest[..4]);
+        let length = usize::try_from(u32::from_be_bytes(length))
+            .map_err(|_| LamportError::InvalidMessageBytes)?;
+        if length > MAX_SIGN_REQUEST_MESSAGE_BYTES || rest.len() != 4 + length {
+            return Err(LamportError::InvalidMessageBytes);
+        }
+        Ok(Self {
+            session_id,
+            message: rest[4..].to_vec(),
+        })
+    }
+}
+
+/// A participant's [`SignatureShare`] for a session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PartialSignatureMsg<T: LamportDigest> {
+    /// The session the share is for.
+    pub session_id: [u8; SESSION_ID_BYTES],
+    /// The signature share.
+    pub share: SignatureShare<T>,
+}
+
+serde_impl!(PartialSignatureMsg);
+vec_impl!(PartialSignatureMsg);
+
+impl<T: LamportDigest> PartialSignatureMsg<T> {
+    /// Converts the [`PartialSignatureMsg`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let share = self.share.to_bytes();
+        let mut bytes = Vec::with_capacity(1 + SESSION_ID_BYTES + share.len());
+        bytes.push(MESSAGE_VERSION);
+        bytes.extend_from_slice(&self.session_id);
+        bytes.extend_from_slice(&share);
+        bytes
+    }
+
+    /// Constructs a [`PartialSignatureMsg`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (session_id, rest) = read_session_id(check_version(input.as_ref())?)?;
+        Ok(Self {
+            session_id,
+            share: SignatureShare::from_bytes(rest)?,
+        })
+    }
+}
+
+/// The combined [`Signature`] announced at the end of a session.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CombineResult<T: LamportDigest> {
+    /// The session the signature is for.
+    pub session_id: [u8; SESSION_ID_BYTES],
+    /// The combined signature.
+    pub signature: Signature<T>,
+}
+
+serde_impl!(CombineResult);
+vec_impl!(CombineResult);
+
+impl<T: LamportDigest> CombineResult<T> {
+    /// Converts the [`CombineResult`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + SESSION_ID_BYTES + self.signature.data.len());
+        bytes.push(MESSAGE_VERSION);
+        bytes.extend_from_slice(&self.session_id);
+        bytes.extend_from_slice(&self.signature.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`CombineResult`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (session_id, rest) = read_session_id(check_version(input.as_ref())?)?;
+        Ok(Self {
+            session_id,
+            signature: Signature::from_bytes(rest)?,
+        })
+    }
+}
+
+/// A share of zero sent from one share holder to another to refresh their [`SigningKeyShare`]s.
+///
+/// Adding shares of zero leaves the shared key unchanged while making old
+/// shares useless in combination with new ones. Each holder creates messages
+/// with [`SigningKeyShare::refresh_messages`] and every recipient applies them
+/// with [`SigningKeyShare::apply_refresh`]. Each message must be applied
+/// exactly once, and only over a confidential channel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefreshMsg<T: LamportDigest> {
+    pub(crate) sender: u8,
+    pub(crate) recipient: u8,
+    pub(crate) zero_values: MultiVec<u8, 2>,
+    pub(crate) one_values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(RefreshMsg);
+vec_impl!(RefreshMsg);
+
+impl<T: LamportDigest> Zeroize for RefreshMsg<T> {
+    fn zeroize(&mut self) {
+        self.zero_values.zeroize();
+        self.one_values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> RefreshMsg<T> {
+    /// The identifier of the share holder that created the message.
+    pub fn sender(&self) -> u8 {
+        self.sender
+    }
+
+    /// The identifier of the share holder the message is for.
+    pub fn recipient(&self) -> u8 {
+        self.recipient
+    }
+
+    /// Converts the [`RefreshMsg`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + self.zero_values.len() * 2);
+        bytes.push(MESSAGE_VERSION);
+        bytes.push(self.sender);
+        bytes.push(self.recipient);
+        bytes.extend_from_slice(self.zero_values.as_ref());
+        bytes.extend_from_slice(self.one_values.as_ref());
+        bytes
+    }
+
+    /// Constructs a [`RefreshMsg`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = check_version(input.as_ref())?;
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != 2 + bits * bytes * 2 || input[0] == 0 || input[1] == 0 {
+            return Err(LamportError::InvalidMessageBytes);
+        }
+        let (zero_values, one_values) = separate_one_and_zero_values(&input[2..], bytes);
+        Ok(Self {
+            sender: input[0],
+            recipient: input[1],
+            zero_values,
+            one_values,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+impl<T: LamportDigest> SigningKeyShare<T> {
+    /// Creates one [`RefreshMsg`] for each of the `shares` holders, including this one.
+    pub fn refresh_messages(
+        &self,
+        shares: usize,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Vec<RefreshMsg<T>>> {
+        let mut output = Vec::with_capacity(shares);
+        for i in 1..=shares {
+            output.push(RefreshMsg {
+                sender: self.identifier,
+                recipient: u8::try_from(i).map_err(|_| {
+                    LamportError::General(format!("unable to create identifier for {}", i))
+                })?,
+                zero_values: MultiVec::fill(self.zero_values.axes, 0u8),
+                one_values: MultiVec::fill(self.one_values.axes, 0u8),
+                algorithm: PhantomData,
+            });
+        }
+        let mut splitter = ShareSplitter::new(self.threshold as usize, shares)?;
+        let mut zero = Zeroizing::new(vec![0u8; shares]);
+        let mut one = Zeroizing::new(vec![0u8; shares]);
+        for i in 0..self.zero_values.len() {
+            splitter.split(0, &mut rng, &mut zero);
+            splitter.split(0, &mut rng, &mut one);
+            for ((o, z), n) in output.iter_mut().zip(zero.iter()).zip(one.iter()) {
+                o.zero_values.data[i] = *z;
+                o.one_values.data[i] = *n;
+            }
+        }
+        Ok(output)
+    }
+
+    /// Adds the share of zero in the [`RefreshMsg`] to this share.
+    pub fn apply_refresh(&mut self, message: &RefreshMsg<T>) -> LamportResult<()> {
+        if message.recipient != self.identifier
+            || message.zero_values.axes != self.zero_values.axes
+            || message.one_values.axes != self.one_values.axes
+        {
+            return Err(LamportError::InvalidMessageBytes);
+        }
+        for (s, m) in self.zero_values.iter_mut().zip(message.zero_values.iter()) {
+            *s ^= *m;
+        }
+        for (s, m) in self.one_values.iter_mut().zip(message.one_values.iter()) {
+            *s ^= *m;
+        }
+        Ok(())
+    }
+}
+
+fn check_version(input: &[u8]) -> LamportResult<&[u8]> {
+    match input.first() {
+        Some(&MESSAGE_VERSION) => Ok(&input[1..]),
+        Some(&version) => Err(LamportError::UnsupportedMessageVersion(version)),
+        None => Err(LamportError::InvalidMessageBytes),
+    }
+}
+
+fn read_session_id(input: &[u8]) -> LamportResult<([u8; SESSION_ID_BYTES], &[u8])> {
+    if input.len() < SESSION_ID_BYTES {
+        return Err(LamportError::InvalidMessageBytes);
+    }
+    let mut session_id = [0u8; SESSION_ID_BYTES];
+    session_id.copy_from_slice(&input[..SESSION_ID_BYTES]);
+    Ok((session_id, &input[SESSION_ID_BYTES..]))
+}
@@ -0,0 +1,232 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::{
+    AttachedSignature, KeyId, KeyRegistry, LamportDigest, LamportError, LamportResult, SigningKey,
+};
+
+/// How many signers must approve a [`MultiSigDocument`].
+///
+/// Signers are the unrevoked keys of the [`KeyRegistry`] the document is
+/// verified against.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SignerPolicy {
+    /// Every unrevoked key in the registry must have signed.
+    All,
+    /// At least one registered key must have signed.
+    Any,
+    /// At least this many distinct registered keys must have signed.
+    Threshold(usize),
+}
+
+impl SignerPolicy {
+    /// Checks that `valid` distinct registered signers satisfy the policy.
+    pub(crate) fn check<T: LamportDigest>(
+        self,
+        valid: usize,
+        registry: &KeyRegistry<T>,
+    ) -> LamportResult<()> {
+        let required = match self {
+            Self::All => registry.iter().filter(|(_, _, revoked)| !revoked).count(),
+            Self::Any => 1,
+            Self::Threshold(threshold) => threshold,
+        };
+        if required == 0 || valid < required {
+            return Err(LamportError::PolicyNotSatisfied { valid, required });
+        }
+        Ok(())
+    }
+}
+
+/// One payload with signatures from any number of signers.
+///
+/// Each signature is bound to its signer's [`KeyId`], so approvals can be
+/// collected in any order and checked against a [`KeyRegistry`] with a
+/// [`SignerPolicy`]. Signatures from unknown, revoked or repeated keys are
+/// kept but never count towards the policy.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MultiSigDocument<T: LamportDigest> {
+    pub(crate) payload: Vec<u8>,
+    pub(crate) signatures: Vec<AttachedSignature<T>>,
+}
+
+serde_impl!(@limits MultiSigDocument);
+vec_impl!(MultiSigDocument);
+
+impl<T: LamportDigest> MultiSigDocument<T> {
+    /// Constructs a [`MultiSigDocument`] over the payload with no signatures.
+    pub fn new<B: Into<Vec<u8>>>(payload: B) -> Self {
+        Self {
+            payload: payload.into(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// The signed payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The signatures in the order they were added.
+    pub fn signatures(&self) -> &[AttachedSignature<T>] {
+        &self.signatures
+    }
+
+    /// Signs the payload with the one-time key and adds the signature.
+    pub fn sign(&mut self, signing_key: &mut SigningKey<T>) -> LamportResult<KeyId> {
+        let signature = signing_key.sign_attached(&self.payload)?;
+        let key_id = signature.key_id();
+        self.signatures.push(signature);
+        Ok(key_id)
+    }
+
+    /// Adds a signature over the payload created elsewhere.
+    pub fn add_signature(&mut self, signature: AttachedSignature<T>) {
+        self.signatures.push(signature);
+    }
+
+    /// Returns the [`KeyId`]s of the registered, unrevoked keys with a valid
+    /// signature over the payload, each listed once.
+    pub fn valid_signers(&self, registry: &KeyRegistry<T>) -> Vec<KeyId> {
+        let mut signers = Vec::with_capacity(self.signatures.len());
+        for signature in &self.signatures {
+            if !signers.contains(&signature.key_id)
+                && registry.verify_attached(signature, &self.payload).is_ok()
+            {
+                signers.push(signature.key_id);
+            }
+        }
+        signers
+    }
+
+    /// Verifies the signatures against the registry and checks the policy.
+    ///
+    /// Returns [`LamportError::PolicyNotSatisfied`] with the number of valid
+    /// signers found and the number required.
+    pub fn verify(&self, registry: &KeyRegistry<T>, policy: SignerPolicy) -> LamportResult<()> {
+        policy.check(self.valid_signers(registry).len(), registry)
+    }
+
+    /// Converts the [`MultiSigDocument`] to canonical bytes.
+    ///
+    /// The format is a big endian `u64` payload length, the payload, a big
+    /// endian `u32` signature count and the canonical bytes of each
+    /// [`AttachedSignature`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let signature_length = Self::signature_length();
+        let mut bytes =
+            Vec::with_capacity(12 + self.payload.len() + self.signatures.len() * signature_length);
+        bytes.extend_from_slice(&(self.payload.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        let count = u32::try_from(self.signatures.len()).unwrap_or(u32::MAX);
+        bytes.extend_from_slice(&count.to_be_bytes());
+        for signature in &self.signatures {
+            bytes.extend_from_slice(&signature.to_bytes());
+        }
+        bytes
+    }
+
+    /// Constructs a [`MultiSigDocument`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`MultiSigDocument`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        if input.len() < 8 {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (length, rest) = input.split_at(8);
+        let length = usize::try_from(u64::from_be_bytes(
+            <[u8; 8]>::try_from(length).map_err(|_| LamportError::InvalidSignatureBytes)?,
+        ))
+        .map_err(|_| LamportError::InvalidSignatureBytes)?;
+        if rest.len() < length.saturating_add(4) {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (payload, rest) = rest.split_at(length);
+        let (count, rest) = rest.split_at(4);
+        let count = usize::try_from(u32::from_be_bytes(
+            <[u8; 4]>::try_from(count).map_err(|_| LamportError::InvalidSignatureBytes)?,
+        ))
+        .map_err(|_| LamportError::InvalidSignatureBytes)?;
+        let signature_length = Self::signature_length();
+        if rest.len() != count.saturating_mul(signature_length) {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let signatures = rest
+            .chunks_exact(signature_length)
+            .map(AttachedSignature::from_bytes)
+            .collect::<LamportResult<Vec<_>>>()?;
+        Ok(Self {
+            payload: payload.to_vec(),
+            signatures,
+        })
+    }
+
+    fn signature_length() -> usize {
+        let bits = T::digest_size_in_bits();
+        KeyId::BYTES + bits * (bits / 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn multi_sig_document() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut registry = KeyRegistry::new();
+        let mut signing_keys = Vec::new();
+        for _ in 0..3 {
+            let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+            registry.insert(pk);
+            signing_keys.push(sk);
+        }
+        let (mut outsider, _) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+
+        let mut document = MultiSigDocument::new(b"release v1.2.3".to_vec());
+        assert!(document.verify(&registry, SignerPolicy::Any).is_err());
+        let first = document.sign(&mut signing_keys[0]).unwrap();
+        document.sign(&mut outsider).unwrap();
+        document.add_signature(document.signatures()[0].clone());
+        assert_eq!(document.valid_signers(&registry), vec![first]);
+        assert!(document.verify(&registry, SignerPolicy::Any).is_ok());
+        assert_eq!(
+            document.verify(&registry, SignerPolicy::Threshold(2)),
+            Err(LamportError::PolicyNotSatisfied {
+                valid: 1,
+                required: 2
+            })
+        );
+
+        let second = document.sign(&mut signing_keys[1]).unwrap();
+        assert!(document
+            .verify(&registry, SignerPolicy::Threshold(2))
+            .is_ok());
+        assert!(document.verify(&registry, SignerPolicy::All).is_err());
+        registry.revoke(&second).unwrap();
+        assert!(document
+            .verify(&registry, SignerPolicy::Threshold(2))
+            .is_err());
+        document.sign(&mut signing_keys[2]).unwrap();
+        assert!(document.verify(&registry, SignerPolicy::All).is_ok());
+
+        let bytes = document.to_bytes();
+        let restored = MultiSigDocument::<LamportFixedDigest<Sha256>>::from_bytes(&bytes).unwrap();
+        assert_eq!(restored.payload(), document.payload());
+        assert_eq!(restored.to_bytes(), bytes);
+        assert!(MultiSigDocument::<LamportFixedDigest<Sha256>>::from_bytes(
+            &bytes[..bytes.len() - 1]
+        )
+        .is_err());
+    }
+}
@@ -144,6 +144,37 @@ impl<T> MultiVec<T, 2> {
     }
 }
 
+impl<T: Copy> MultiVec<T, 2> {
+    /// Interleaves the rows of two [`MultiVec`]s with the same axes so that row `i`
+    /// of both is adjacent in memory. The result has axes `[rows, 2, columns]`.
+    pub fn interleave(&self, other: &Self) -> MultiVec<T, 3> {
+        assert_eq!(self.axes, other.axes);
+        let columns = self.axes[1];
+        let mut data = Vec::with_capacity(self.data.len() * 2);
+        for (a, b) in self
+            .data
+            .chunks_exact(columns)
+            .zip(other.data.chunks_exact(columns))
+        {
+            data.extend_from_slice(a);
+            data.extend_from_slice(b);
+        }
+        MultiVec {
+            data,
+            axes: [self.axes[0], 2, columns],
+        }
+    }
+}
+
+impl<T> Index<(usize, usize)> for MultiVec<T, 3> {
+    type Output = [T];
+
+    fn index(&self, (i, j): (usize, usize)) -> &[T] {
+        let b = (i * self.axes[1] + j) * self.axes[2];
+        &self.data[b..b + self.axes[2]]
+    }
+}
+
 impl<T> Index<usize> for MultiVec<T, 2> {
     type Output = [T];
 
@@ -272,3 +303,141 @@ impl<T> IndexMut<(usize, RangeToInclusive<usize>)> for MultiVec<T, 2> {
         &mut self.data[b..b + j.end + 1]
     }
 }
+
+/// A two dimensional vector with `R` rows of `C` columns fixed at compile time.
+///
+/// Rows are arrays, so row lengths are checked by the type system and loops
+/// over a row have a constant trip count the compiler can unroll.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConstMultiVec<T, const R: usize, const C: usize> {
+    pub(crate) data: Box<[[T; C]; R]>,
+}
+
+impl<T: Copy, const R: usize, const C: usize> ConstMultiVec<T, R, C> {
+    /// Constructs a [`ConstMultiVec`] filled with the specified value.
+    pub fn fill(value: T) -> Self {
+        let rows = vec![[value; C]; R].into_boxed_slice();
+        Self {
+            data: rows.try_into().ok().expect("exactly R rows"),
+        }
+    }
+}
+
+impl<T, const R: usize, const C: usize> ConstMultiVec<T, R, C> {
+    /// The axes as they would appear on a [`MultiVec`].
+    pub const AXES: [usize; 2] = [R, C];
+
+    /// Returns an iterator over the rows.
+    pub fn rows(&self) -> impl Iterator<Item = &[T; C]> {
+        self.data.iter()
+    }
+
+    /// Returns an iterator over mutable references to the rows.
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [T; C]> {
+        self.data.iter_mut()
+    }
+
+    /// Returns the elements in row-major order.
+    pub fn as_slice(&self) -> &[T] {
+        self.data.as_flattened()
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        R * C
+    }
+
+    /// Returns true if the [`ConstMultiVec`] is empty.
+    pub fn is_empty(&self) -> bool {
+        R * C == 0
+    }
+}
+
+impl<T, const R: usize, const C: usize> Index<usize> for ConstMultiVec<T, R, C> {
+    type Output = [T; C];
+
+    fn index(&self, i: usize) -> &[T; C] {
+        &self.data[i]
+    }
+}
+
+impl<T, const R: usize, const C: usize> IndexMut<usize> for ConstMultiVec<T, R, C> {
+    fn index_mut(&mut self, i: usize) -> &mut [T; C] {
+        &mut self.data[i]
+    }
+}
+
+impl<T, const R: usize, const C: usize> AsRef<[T]> for ConstMultiVec<T, R, C> {
+    fn as_ref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T: Zeroize, const R: usize, const C: usize> Zeroize for ConstMultiVec<T, R, C> {
+    fn zeroize(&mut self) {
+        self.data.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl<T: Copy, const R: usize, const C: usize> TryFrom<&MultiVec<T, 2>> for ConstMultiVec<T, R, C> {
+    type Error = crate::LamportError;
+
+    fn try_from(value: &MultiVec<T, 2>) -> crate::LamportResult<Self> {
+        if value.axes != Self::AXES || value.data.len() != R * C {
+            return Err(crate::LamportError::General(format!(
+                "expected axes {:?}, found {:?}",
+                Self::AXES,
+                value.axes
+            )));
+        }
+        let rows = value
+            .data
+            .chunks_exact(C)
+            .map(|row| {
+                let mut out = [row[0]; C];
+                out.copy_from_slice(row);
+                out
+            })
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+        Ok(Self {
+            data: rows.try_into().ok().expect("exactly R rows"),
+        })
+    }
+}
+
+impl<T: Copy, const R: usize, const C: usize> From<&ConstMultiVec<T, R, C>> for MultiVec<T, 2> {
+    fn from(value: &ConstMultiVec<T, R, C>) -> Self {
+        MultiVec {
+            data: value.as_slice().to_vec(),
+            axes: [R, C],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn const_multi_vec() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (sk, _) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+        let zero = ConstMultiVec::<u8, 256, 32>::try_from(&sk.zero_values).unwrap();
+        assert_eq!(zero.as_slice(), sk.zero_values.as_ref());
+        assert_eq!(&zero[3][..], &sk.zero_values[3]);
+        assert_eq!(MultiVec::from(&zero), sk.zero_values);
+        assert!(ConstMultiVec::<u8, 128, 64>::try_from(&sk.zero_values).is_err());
+
+        let mut filled = ConstMultiVec::<u8, 4, 2>::fill(7);
+        assert_eq!(filled.len(), 8);
+        filled[1][1] = 9;
+        assert_eq!(filled.as_slice(), &[7, 7, 7, 9, 7, 7, 7, 7]);
+        Zeroize::zeroize(&mut filled);
+        assert!(filled.rows().all(|row| row == &[0, 0]));
+    }
+}
@@ -0,0 +1,288 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::check_digest;
+use crate::{
+    LamportDigest, LamportError, LamportResult, MultiVec, Signature, SigningKey, VerifyingKey,
+};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// The version byte of the personalized (v2) key format.
+const KEY_FORMAT_V2: u8 = 2;
+
+/// The longest personalization tag a key can carry.
+pub const MAX_PERSONALIZATION_BYTES: usize = 255;
+
+/// A [`SigningKey`] with a personalization tag mixed into every hash.
+///
+/// Both the commitments of the verifying key and the message digest are
+/// prefixed with the tag, so two organizations using the same digest and
+/// message never produce signatures that verify under each other's keys.
+/// The tag is stored in the key bytes (the v2 key format), so the
+/// [`PersonalizedVerifyingKey`] needs no out of band agreement.
+///
+/// # Example
+///
+/// ```
+/// use lamport_signature_plus::{LamportFixedDigest, PersonalizedSigningKey};
+/// use rand::SeedableRng;
+/// use sha2::Sha256;
+///
+/// let rng = rand_chacha::ChaCha8Rng::from_seed([0u8; 32]);
+/// let mut sk =
+///     PersonalizedSigningKey::<LamportFixedDigest<Sha256>>::random(b"acme corp signing v1", rng)
+///         .unwrap();
+/// let pk = sk.verifying_key();
+/// let signature = sk.sign(b"hello").unwrap();
+/// assert!(pk.verify(&signature, b"hello").is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PersonalizedSigningKey<T: LamportDigest> {
+    pub(crate) personalization: Vec<u8>,
+    pub(crate) key: SigningKey<T>,
+}
+
+serde_impl!(PersonalizedSigningKey);
+vec_impl!(PersonalizedSigningKey);
+
+impl<T: LamportDigest> Zeroize for PersonalizedSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl<T: LamportDigest> PersonalizedSigningKey<T> {
+    /// Personalizes an unused [`SigningKey`] with the tag.
+    ///
+    /// The tag must be between 1 and [`MAX_PERSONALIZATION_BYTES`] long.
+    pub fn new(personalization: &[u8], key: SigningKey<T>) -> LamportResult<Self> {
+        check_personalization(personalization)?;
+        if key.used() {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        Ok(Self {
+            personalization: personalization.to_vec(),
+            key,
+        })
+    }
+
+    /// Constructs a random key personalized with the tag.
+    pub fn random(personalization: &[u8], rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        Self::new(personalization, SigningKey::random(rng)?)
+    }
+
+    /// The personalization tag.
+    pub fn personalization(&self) -> &[u8] {
+        &self.personalization
+    }
+
+    /// Has the key already signed a message.
+    pub fn used(&self) -> bool {
+        self.key.used()
+    }
+
+    /// The [`PersonalizedVerifyingKey`] with the same tag.
+    pub fn verifying_key(&self) -> PersonalizedVerifyingKey<T> {
+        let tag = &self.personalization;
+        let hash = |values: &MultiVec<u8, 2>| {
+            let mut hashed = MultiVec::fill(values.axes, 0u8);
+            let rows = hashed.data.chunks_exact_mut(values.axes[1]);
+            for (out, row) in rows.zip(values.data.chunks_exact(values.axes[1])) {
+                out.copy_from_slice(&personalized_digest::<T>(tag, row));
+            }
+            hashed
+        };
+        PersonalizedVerifyingKey {
+            personalization: self.personalization.clone(),
+            key: VerifyingKey::new(hash(&self.key.zero_values), hash(&self.key.one_values)),
+        }
+    }
+
+    /// Signs the personalized digest of the data.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<Signature<T>> {
+        let digest = personalized_digest::<T>(&self.personalization, data.as_ref());
+        self.key.sign_digest(&digest)
+    }
+
+    /// Converts the key to the v2 key format: the version byte `2`, the
+    /// length of the tag as a byte, the tag, and the [`SigningKey`] bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut key = self.key.to_bytes();
+        let bytes = v2_bytes(&self.personalization, &key);
+        key.zeroize();
+        bytes
+    }
+
+    /// Constructs a [`PersonalizedSigningKey`] from the v2 key format.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (personalization, key) =
+            split_v2(input.as_ref()).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        Ok(Self {
+            personalization: personalization.to_vec(),
+            key: SigningKey::from_bytes(key)?,
+        })
+    }
+}
+
+/// The verifying key of a [`PersonalizedSigningKey`].
+///
+/// The commitments are personalized, so a plain [`VerifyingKey`] with the
+/// same bytes rejects every signature, as does a key with another tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PersonalizedVerifyingKey<T: LamportDigest> {
+    pub(crate) personalization: Vec<u8>,
+    pub(crate) key: VerifyingKey<T>,
+}
+
+serde_impl!(PersonalizedVerifyingKey);
+vec_impl!(PersonalizedVerifyingKey);
+
+impl<T: LamportDigest> PersonalizedVerifyingKey<T> {
+    /// The personalization tag.
+    pub fn personalization(&self) -> &[u8] {
+        &self.personalization
+    }
+
+    /// Verifies the [`Signature`] with the key's personalization tag.
+    pub fn verify<B: AsRef<[u8]>>(&self, signature: &Signature<T>, data: B) -> LamportResult<()> {
+        let tag = &self.personalization;
+        let data_digest = personalized_digest::<T>(tag, data.as_ref());
+        check_digest::<T>(&data_digest)?;
+        if signature.data.len() != self.key.one_values.len() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+
+        let res = data_digest.iter().enumerate().all(|(i, byte)| {
+            (0..8).all(|j| {
+                let offset = i * 8 + j;
+                let hashed_value = personalized_digest::<T>(tag, &signature.data[offset]);
+                let cmp = if (byte >> j) & 1 == 1 {
+                    &self.key.one_values[offset]
+                } else {
+                    &self.key.zero_values[offset]
+                };
+                hashed_value == cmp
+            })
+        });
+        if res {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the key to the v2 key format: the version byte `2`, the
+    /// length of the tag as a byte, the tag, and the [`VerifyingKey`] bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        v2_bytes(&self.personalization, &self.key.to_bytes())
+    }
+
+    /// Constructs a [`PersonalizedVerifyingKey`] from the v2 key format.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (personalization, key) =
+            split_v2(input.as_ref()).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        Ok(Self {
+            personalization: personalization.to_vec(),
+            key: VerifyingKey::from_bytes(key)?,
+        })
+    }
+}
+
+/// Hashes the data prefixed with the length of the tag and the tag.
+fn personalized_digest<T: LamportDigest>(personalization: &[u8], data: &[u8]) -> Vec<u8> {
+    let length = (personalization.len() as u64).to_be_bytes();
+    T::digest_concat(&[&length, personalization, data])
+}
+
+fn check_personalization(personalization: &[u8]) -> LamportResult<()> {
+    if personalization.is_empty() || personalization.len() > MAX_PERSONALIZATION_BYTES {
+        return Err(LamportError::InvalidPersonalization);
+    }
+    Ok(())
+}
+
+fn v2_bytes(personalization: &[u8], key: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(2 + personalization.len() + key.len());
+    bytes.push(KEY_FORMAT_V2);
+    bytes.push(u8::try_from(personalization.len()).expect("checked when the key was created"));
+    bytes.extend_from_slice(personalization);
+    bytes.extend_from_slice(key);
+    bytes
+}
+
+/// Splits the v2 key format into the tag and the key bytes.
+fn split_v2(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (&[version, length], rest) = input.split_first_chunk::<2>()?;
+    if version != KEY_FORMAT_V2 || length == 0 {
+        return None;
+    }
+    let personalization = rest.get(..usize::from(length))?;
+    Some((personalization, &rest[personalization.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn personalized_keys() {
+        type D = LamportFixedDigest<Sha256>;
+        let rng = || rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut sk = PersonalizedSigningKey::<D>::random(b"org a", rng()).unwrap();
+        let pk = sk.verifying_key();
+        let (_, plain) = generate_keys::<D, _>(rng()).unwrap();
+        assert_eq!(pk.personalization(), b"org a");
+        assert_ne!(pk.key, plain);
+
+        let signature = sk.sign(b"hello, world!").unwrap();
+        assert!(pk.verify(&signature, b"hello, world!").is_ok());
+        assert!(pk.verify(&signature, b"hello, world?").is_err());
+        assert!(sk.sign(b"again").is_err());
+
+        // The same secret values under another tag, or none, never cross-verify
+        let mut other = PersonalizedSigningKey::<D>::random(b"org b", rng()).unwrap();
+        let other_signature = other.sign(b"hello, world!").unwrap();
+        assert_eq!(other.key.zero_values, sk.key.zero_values);
+        assert!(pk.verify(&other_signature, b"hello, world!").is_err());
+        assert!(other
+            .verifying_key()
+            .verify(&signature, b"hello, world!")
+            .is_err());
+        assert!(pk.key.verify(&signature, b"hello, world!").is_err());
+
+        // The tag is carried in the v2 key format
+        let bytes = pk.to_bytes();
+        assert_eq!(&bytes[..7], b"\x02\x05org a");
+        assert_eq!(
+            PersonalizedVerifyingKey::<D>::from_bytes(&bytes)
+                .unwrap()
+                .to_bytes(),
+            bytes
+        );
+        let sk_bytes = sk.to_bytes();
+        let decoded = PersonalizedSigningKey::<D>::from_bytes(&sk_bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), sk_bytes);
+        assert!(decoded.used());
+        let mut tampered = bytes.clone();
+        tampered[6] = b'b';
+        let tampered = PersonalizedVerifyingKey::<D>::from_bytes(&tampered).unwrap();
+        assert!(tampered.verify(&signature, b"hello, world!").is_err());
+        assert!(PersonalizedVerifyingKey::<D>::from_bytes(&bytes[1..]).is_err());
+        assert!(PersonalizedVerifyingKey::<D>::from_bytes(plain.to_bytes()).is_err());
+
+        assert_eq!(
+            PersonalizedSigningKey::<D>::random(b"", rng()).unwrap_err(),
+            LamportError::InvalidPersonalization
+        );
+        assert_eq!(
+            PersonalizedSigningKey::<D>::random(&[0u8; 256], rng()).unwrap_err(),
+            LamportError::InvalidPersonalization
+        );
+    }
+}
@@ -0,0 +1,147 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportError, LamportResult};
+use std::fmt::{self, Debug, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag that asks a long-running operation to stop.
+///
+/// Clones share the flag, so one can be kept by the caller, e.g. a UI thread,
+/// and another passed to the operation in a [`Progress`].
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Constructs a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Asks every operation holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true once [`CancellationToken::cancel`] has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress reporting and cancellation for long-running operations.
+///
+/// The callback receives the items done and the total after each item. The
+/// token is checked before each item, and a cancelled operation returns
+/// [`LamportError::Cancelled`] without producing a partial result.
+#[derive(Default)]
+pub struct Progress<'a> {
+    callback: Option<Box<dyn FnMut(u64, u64) + 'a>>,
+    token: Option<CancellationToken>,
+}
+
+impl Debug for Progress<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("callback", &self.callback.is_some())
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl<'a> Progress<'a> {
+    /// Constructs a [`Progress`] that neither reports nor cancels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` with the items done and the total as the operation advances.
+    pub fn with_callback<F: FnMut(u64, u64) + 'a>(mut self, callback: F) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Stops the operation at the next item once `token` is cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    /// Returns [`LamportError::Cancelled`] if the operation should stop.
+    pub(crate) fn check(&self) -> LamportResult<()> {
+        match &self.token {
+            Some(token) if token.is_cancelled() => Err(LamportError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reports that `done` of `total` items are complete.
+    pub(crate) fn report(&mut self, done: u64, total: u64) {
+        if let Some(callback) = self.callback.as_mut() {
+            callback(done, total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, ForwardSecureKey, LamportFixedDigest, SEED_BYTES};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn progress_and_cancellation() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut reports = Vec::new();
+        let mut progress = Progress::new().with_callback(|done, total| reports.push((done, total)));
+        let sk =
+            ForwardSecureKey::<D>::new_with_progress([3u8; SEED_BYTES], 3, &mut progress).unwrap();
+        drop(progress);
+        assert_eq!(reports, vec![(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(
+            sk.public_key().to_bytes(),
+            ForwardSecureKey::<D>::new([3u8; SEED_BYTES], 3)
+                .unwrap()
+                .public_key()
+                .to_bytes()
+        );
+
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        let mut progress = Progress::new()
+            .with_cancellation(token)
+            .with_callback(|done, _| {
+                if done == 2 {
+                    cancel.cancel();
+                }
+            });
+        assert_eq!(
+            ForwardSecureKey::<D>::new_with_progress([3u8; SEED_BYTES], 5, &mut progress)
+                .unwrap_err(),
+            LamportError::Cancelled
+        );
+
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (sk, _) = generate_keys::<D, _>(&mut rng).unwrap();
+        let mut last = (0, 0);
+        let mut progress = Progress::new().with_callback(|done, total| last = (done, total));
+        let mut writers = vec![Vec::new(); 3];
+        sk.split_into_writers_with_progress(2, &mut writers, &mut rng, &mut progress)
+            .unwrap();
+        drop(progress);
+        assert_eq!(last, (512, 512));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut progress = Progress::new().with_cancellation(token);
+        let mut writers = vec![Vec::new(); 3];
+        assert_eq!(
+            sk.split_into_writers_with_progress(2, &mut writers, &mut rng, &mut progress),
+            Err(LamportError::Cancelled)
+        );
+    }
+}
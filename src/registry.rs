@@ -0,0 +1,188 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::{
+    AttachedSignature, KeyId, LamportDigest, LamportError, LamportResult, Signature, VerifyingKey,
+};
+use std::collections::BTreeMap;
+
+/// A collection of [`VerifyingKey`]s indexed by their [`KeyId`] fingerprint.
+///
+/// Keys can be revoked, after which they are retained for auditing but no
+/// longer verify signatures.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyRegistry<T: LamportDigest> {
+    pub(crate) entries: BTreeMap<KeyId, RegistryEntry<T>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct RegistryEntry<T: LamportDigest> {
+    pub(crate) key: VerifyingKey<T>,
+    pub(crate) revoked: bool,
+}
+
+serde_impl!(@limits KeyRegistry);
+vec_impl!(KeyRegistry);
+
+impl<T: LamportDigest> Default for KeyRegistry<T> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: LamportDigest> KeyRegistry<T> {
+    /// Constructs an empty [`KeyRegistry`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the [`VerifyingKey`] and returns its [`KeyId`].
+    ///
+    /// Inserting a key that is already present leaves its revocation status unchanged.
+    pub fn insert(&mut self, key: VerifyingKey<T>) -> KeyId {
+        let key_id = key.fingerprint();
+        self.entries.entry(key_id).or_insert(RegistryEntry {
+            key,
+            revoked: false,
+        });
+        key_id
+    }
+
+    /// Returns the [`VerifyingKey`] with the [`KeyId`], revoked or not.
+    pub fn get(&self, key_id: &KeyId) -> Option<&VerifyingKey<T>> {
+        self.entries.get(key_id).map(|entry| &entry.key)
+    }
+
+    /// Returns true if the registry holds a key with the [`KeyId`].
+    pub fn contains(&self, key_id: &KeyId) -> bool {
+        self.entries.contains_key(key_id)
+    }
+
+    /// Marks the key as revoked so it no longer verifies signatures.
+    pub fn revoke(&mut self, key_id: &KeyId) -> LamportResult<()> {
+        let entry = self
+            .entries
+            .get_mut(key_id)
+            .ok_or(LamportError::UnknownKeyId)?;
+        entry.revoked = true;
+        Ok(())
+    }
+
+    /// Returns true if the key is present and has been revoked.
+    pub fn is_revoked(&self, key_id: &KeyId) -> bool {
+        self.entries
+            .get(key_id)
+            .map(|entry| entry.revoked)
+            .unwrap_or(false)
+    }
+
+    /// Removes the key from the registry entirely.
+    pub fn remove(&mut self, key_id: &KeyId) -> Option<VerifyingKey<T>> {
+        self.entries.remove(key_id).map(|entry| entry.key)
+    }
+
+    /// The number of keys in the registry, including revoked keys.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the registry has no keys.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the keys in [`KeyId`] order along with their revocation status.
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyId, &VerifyingKey<T>, bool)> {
+        self.entries
+            .iter()
+            .map(|(key_id, entry)| (key_id, &entry.key, entry.revoked))
+    }
+
+    /// Verifies the [`Signature`] with the registered key identified by `key_id`.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &Signature<T>,
+        data: B,
+        key_id: &KeyId,
+    ) -> LamportResult<()> {
+        let entry = self.entries.get(key_id).ok_or(LamportError::UnknownKeyId)?;
+        if entry.revoked {
+            return Err(LamportError::RevokedKey);
+        }
+        entry.key.verify(signature, data)
+    }
+
+    /// Verifies the [`AttachedSignature`] with the registered key it is bound to.
+    pub fn verify_attached<B: AsRef<[u8]>>(
+        &self,
+        signature: &AttachedSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        self.verify(&signature.signature, data, &signature.key_id)
+    }
+
+    /// Converts the [`KeyRegistry`] to canonical bytes.
+    ///
+    /// The format is a big endian `u64` key count followed by each entry as a
+    /// revocation flag byte and the canonical [`VerifyingKey`] bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let key_length = Self::key_length();
+        let mut bytes = Vec::with_capacity(8 + self.entries.len() * (1 + key_length));
+        bytes.extend_from_slice(&(self.entries.len() as u64).to_be_bytes());
+        for entry in self.entries.values() {
+            bytes.push(entry.revoked as u8);
+            bytes.extend_from_slice(&entry.key.to_bytes());
+        }
+        bytes
+    }
+
+    /// Constructs a [`KeyRegistry`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`KeyRegistry`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let key_length = Self::key_length();
+        if input.len() < 8 {
+            return Err(LamportError::InvalidRegistryBytes);
+        }
+        let mut count = [0u8; 8];
+        count.copy_from_slice(&input[..8]);
+        let count = u64::from_be_bytes(count);
+        let body = &input[8..];
+        if (body.len() / (1 + key_length)) as u64 != count
+            || !body.len().is_multiple_of(1 + key_length)
+        {
+            return Err(LamportError::InvalidRegistryBytes);
+        }
+        let mut registry = Self::new();
+        for chunk in body.chunks_exact(1 + key_length) {
+            let revoked = match chunk[0] {
+                0 => false,
+                1 => true,
+                _ => return Err(LamportError::InvalidRegistryBytes),
+            };
+            let key = VerifyingKey::from_bytes(&chunk[1..])?;
+            let key_id = key.fingerprint();
+            if registry
+                .entries
+                .insert(key_id, RegistryEntry { key, revoked })
+                .is_some()
+            {
+                return Err(LamportError::InvalidRegistryBytes);
+            }
+        }
+        Ok(registry)
+    }
+
+    fn key_length() -> usize {
+        let bits = T::digest_size_in_bits();
+        bits * (bits / 8) * 2
+    }
+}
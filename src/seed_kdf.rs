@@ -0,0 +1,158 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Versioned expansion of a seed into the secret values of a [`crate::SigningKey`].
+//!
+//! Each [`SeedKdf`] version names a fixed algorithm whose output never
+//! changes, so a seed backed up together with its version regenerates the
+//! same key in every future release. Improvements are added as new versions
+//! instead of changing an existing one.
+use crate::{LamportError, LamportResult, SEED_BYTES};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, Zeroizing};
+
+/// A seed expansion algorithm and version.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum SeedKdf {
+    /// SHA-256 in counter mode.
+    ///
+    /// Block `i` is `SHA-256("lamport_signature_plus seed v1" || seed || i)`
+    /// with `i` a big endian `u64` starting at zero. The blocks are
+    /// concatenated and truncated, zero values first.
+    #[default]
+    V1,
+}
+
+impl SeedKdf {
+    /// The newest version this crate supports.
+    pub const LATEST: Self = Self::V1;
+
+    /// Every supported version, oldest first.
+    pub const SUPPORTED: &'static [Self] = &[Self::V1];
+
+    /// The version number.
+    pub fn version(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+        }
+    }
+
+    /// The name of the expansion algorithm.
+    pub fn algorithm(self) -> &'static str {
+        match self {
+            Self::V1 => "SHA-256-CTR",
+        }
+    }
+
+    /// Picks the newest version supported by both this crate and the peer.
+    ///
+    /// Returns [`LamportError::UnsupportedSeedKdf`] with the newest peer
+    /// version if there is no common version.
+    pub fn negotiate(peer_versions: &[u8]) -> LamportResult<Self> {
+        Self::SUPPORTED
+            .iter()
+            .rev()
+            .find(|kdf| peer_versions.contains(&kdf.version()))
+            .copied()
+            .ok_or_else(|| {
+                LamportError::UnsupportedSeedKdf(
+                    peer_versions.iter().max().copied().unwrap_or_default(),
+                )
+            })
+    }
+
+    /// Expands the seed into `length` secret bytes.
+    pub(crate) fn expand(self, seed: &[u8; SEED_BYTES], length: usize) -> Zeroizing<Vec<u8>> {
+        self.expand_range(seed, 0, length)
+    }
+
+    /// The `length` bytes of the expansion starting at `offset`, without
+    /// computing the bytes before them.
+    pub(crate) fn expand_range(
+        self,
+        seed: &[u8; SEED_BYTES],
+        offset: usize,
+        length: usize,
+    ) -> Zeroizing<Vec<u8>> {
+        match self {
+            Self::V1 => {
+                let skip = offset % 32;
+                let mut data = Zeroizing::new(Vec::with_capacity(skip + length + 32));
+                let mut counter = (offset / 32) as u64;
+                while data.len() < skip + length {
+                    let mut hasher = Sha256::new();
+                    hasher.update(b"lamport_signature_plus seed v1");
+                    hasher.update(seed);
+                    hasher.update(counter.to_be_bytes());
+                    let mut block: [u8; 32] = hasher.finalize().into();
+                    data.extend_from_slice(&block);
+                    block.zeroize();
+                    counter += 1;
+                }
+                data.truncate(skip + length);
+                data.drain(..skip);
+                data
+            }
+        }
+    }
+}
+
+impl TryFrom<u8> for SeedKdf {
+    type Error = LamportError;
+
+    fn try_from(version: u8) -> LamportResult<Self> {
+        Self::SUPPORTED
+            .iter()
+            .find(|kdf| kdf.version() == version)
+            .copied()
+            .ok_or(LamportError::UnsupportedSeedKdf(version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LamportFixedDigest, SigningKey};
+
+    #[test]
+    fn seed_kdf_frozen() {
+        // These digests must never change, or seed backups stop regenerating their keys
+        for (version, bytes, expected) in [
+            (
+                1,
+                SigningKey::<LamportFixedDigest<Sha256>>::from_seed(&[7u8; SEED_BYTES])
+                    .unwrap()
+                    .to_bytes(),
+                "1cb7e280f4111b9515b867721d46495bbc23359ed40030b7104568ac9c095f56",
+            ),
+            (
+                1,
+                SigningKey::<LamportFixedDigest<sha2::Sha512>>::from_seed_with_kdf(
+                    &[7u8; SEED_BYTES],
+                    SeedKdf::V1,
+                )
+                .unwrap()
+                .to_bytes(),
+                "971f7601c654789296706ff81837e03ce6ffedcb93dda6285ba06a838c3d4fb2",
+            ),
+        ] {
+            let kdf = SeedKdf::try_from(version).unwrap();
+            assert_eq!(kdf.version(), version);
+            assert_eq!(hex::encode(<Sha256 as Digest>::digest(&bytes)), expected);
+        }
+
+        assert_eq!(SeedKdf::default(), SeedKdf::V1);
+        assert_eq!(SeedKdf::V1.algorithm(), "SHA-256-CTR");
+        assert_eq!(
+            SeedKdf::try_from(0).unwrap_err(),
+            LamportError::UnsupportedSeedKdf(0)
+        );
+        assert_eq!(SeedKdf::negotiate(&[1, 9]).unwrap(), SeedKdf::V1);
+        assert_eq!(
+            SeedKdf::negotiate(&[2, 9]).unwrap_err(),
+            LamportError::UnsupportedSeedKdf(9)
+        );
+    }
+}
@@ -0,0 +1,185 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_security};
+use crate::{
+    LamportDigest, LamportError, LamportResult, MultiVec, SeedKdf, Signature, SigningKey,
+    VerifyingKey, SEED_BYTES,
+};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+/// A one-time signing key that holds only its seed.
+///
+/// It is the same key as [`SigningKey::from_seed_with_kdf`] of the seed,
+/// and makes the same signatures, but derives each revealed value from the
+/// seed while signing instead of keeping every secret value in memory. A
+/// SHA-256 key is 32 secret bytes rather than 16KB.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeedSigningKey<T: LamportDigest> {
+    pub(crate) seed: [u8; SEED_BYTES],
+    pub(crate) kdf: SeedKdf,
+    pub(crate) used: bool,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(SeedSigningKey);
+vec_impl!(SeedSigningKey);
+
+impl<T: LamportDigest> Zeroize for SeedSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.seed.zeroize();
+    }
+}
+
+impl<T: LamportDigest> SeedSigningKey<T> {
+    /// The length of the canonical bytes.
+    pub const BYTES: usize = SEED_BYTES + 2;
+
+    /// Constructs a [`SeedSigningKey`] from a seed with the default [`SeedKdf`].
+    pub fn new(seed: [u8; SEED_BYTES]) -> LamportResult<Self> {
+        Self::with_kdf(seed, SeedKdf::default())
+    }
+
+    /// Constructs a [`SeedSigningKey`] from a seed with the specified [`SeedKdf`].
+    pub fn with_kdf(seed: [u8; SEED_BYTES], kdf: SeedKdf) -> LamportResult<Self> {
+        check_security::<T>()?;
+        Ok(Self {
+            seed,
+            kdf,
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Constructs a [`SeedSigningKey`] from a random seed.
+    pub fn random(mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        let mut seed = [0u8; SEED_BYTES];
+        rng.fill_bytes(&mut seed);
+        Self::new(seed)
+    }
+
+    /// The seed expansion version.
+    pub fn kdf(&self) -> SeedKdf {
+        self.kdf
+    }
+
+    /// Has the key already signed a message.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// The [`VerifyingKey`], which needs every secret value to be derived once.
+    pub fn verifying_key(&self) -> VerifyingKey<T> {
+        let mut signing_key = self.to_signing_key();
+        let verifying_key = VerifyingKey::from(&signing_key);
+        signing_key.zeroize();
+        verifying_key
+    }
+
+    /// Derives the full [`SigningKey`], with the same used state.
+    pub fn to_signing_key(&self) -> SigningKey<T> {
+        let mut signing_key = SigningKey::expand_seed(&self.seed, self.kdf);
+        signing_key.used = self.used;
+        signing_key
+    }
+
+    /// Signs the data, deriving only the revealed secret values.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<Signature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let data_hash = T::digest(data.as_ref());
+        check_digest::<T>(&data_hash)?;
+        self.used = true;
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        let mut values = Vec::with_capacity(bits * bytes);
+        for (i, byte) in data_hash.iter().enumerate() {
+            for j in 0..8 {
+                let row = i * 8 + j;
+                // The expansion holds every zero value followed by every one value
+                let side = usize::from((byte >> j) & 1);
+                let value = self
+                    .kdf
+                    .expand_range(&self.seed, (side * bits + row) * bytes, bytes);
+                values.extend_from_slice(&value);
+            }
+        }
+        Ok(Signature {
+            data: MultiVec {
+                data: values,
+                axes: [bits, bytes],
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`SeedSigningKey`] to canonical bytes, the
+    /// [`SeedKdf::version`], the used flag and the seed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.push(self.kdf.version());
+        bytes.push(self.used as u8);
+        bytes.extend_from_slice(&self.seed);
+        bytes
+    }
+
+    /// Constructs a [`SeedSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        if input.len() != Self::BYTES {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let kdf = SeedKdf::try_from(input[0])?;
+        let used = match input[1] {
+            0 => false,
+            1 => true,
+            _ => return Err(LamportError::InvalidPrivateKeyBytes),
+        };
+        let mut key = Self::with_kdf(input[2..].try_into().expect("seed length"), kdf)?;
+        key.used = used;
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn seed_signing_key() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut sk = SeedSigningKey::<D>::new(SEED).unwrap();
+        let mut full = SigningKey::<D>::from_seed(&SEED).unwrap();
+        let pk = sk.verifying_key();
+        assert_eq!(pk, VerifyingKey::from(&full));
+        let signature = sk.sign(b"lazy").unwrap();
+        assert_eq!(signature.to_bytes(), full.sign(b"lazy").unwrap().to_bytes());
+        assert!(pk.verify(&signature, b"lazy").is_ok());
+        assert!(sk.used());
+        assert!(sk.to_signing_key().used());
+        assert_eq!(
+            sk.sign(b"again").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+
+        let bytes = sk.to_bytes();
+        assert_eq!(bytes.len(), SeedSigningKey::<D>::BYTES);
+        let restored = SeedSigningKey::<D>::from_bytes(&bytes).unwrap();
+        assert!(restored.used());
+        assert_eq!(restored.verifying_key(), pk);
+        let mut unknown = bytes;
+        unknown[0] = 0;
+        assert!(SeedSigningKey::<D>::from_bytes(&unknown).is_err());
+
+        let data = SeedKdf::V1.expand(&SEED, 100);
+        assert_eq!(*SeedKdf::V1.expand_range(&SEED, 45, 40), data[45..85]);
+    }
+}
@@ -0,0 +1,317 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{
+    LamportDigest, LamportError, LamportResult, SignRequest, Signature, SignatureShare,
+    SigningKeyShare, VerifyingKey, SESSION_ID_BYTES,
+};
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+const COMMITMENT_DOMAIN: &[u8] = b"lamport_signature_plus threshold commitment v1";
+
+/// The phase a [`ThresholdSession`] is in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SessionState {
+    /// The message was announced and participants are committing to their shares.
+    CollectingCommitments,
+    /// A quorum has committed and participants are revealing their shares.
+    CollectingSignatures,
+    /// The signature was combined and verified.
+    Complete,
+    /// The session was abandoned and accepts no more messages.
+    Aborted,
+}
+
+/// The announcement sent by the coordinator to every participant.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SessionRequest {
+    /// Random identifier binding all messages to this session.
+    pub session_id: [u8; SESSION_ID_BYTES],
+    /// The message to sign.
+    pub message: Vec<u8>,
+}
+
+impl From<SessionRequest> for SignRequest {
+    fn from(request: SessionRequest) -> Self {
+        Self {
+            session_id: request.session_id,
+            message: request.message,
+        }
+    }
+}
+
+impl From<&SessionRequest> for SignRequest {
+    fn from(request: &SessionRequest) -> Self {
+        Self::from(request.clone())
+    }
+}
+
+impl From<SignRequest> for SessionRequest {
+    fn from(request: SignRequest) -> Self {
+        Self {
+            session_id: request.session_id,
+            message: request.message,
+        }
+    }
+}
+
+/// A participant's binding commitment to its [`SignatureShare`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SessionCommitment {
+    /// The session this commitment belongs to.
+    pub session_id: [u8; SESSION_ID_BYTES],
+    /// The identifier of the committing share.
+    pub identifier: u8,
+    /// Hash of the session, identifier and signature share.
+    pub commitment: [u8; 32],
+}
+
+/// Coordinates a threshold signing round from announcement to a verified [`Signature`].
+///
+/// Participants first commit to their signature shares and only reveal them
+/// once a quorum has committed, so no participant can choose its share after
+/// seeing the others. Every transition checks the current [`SessionState`].
+///
+/// A participant that stops responding or sends a bad share can be
+/// [excluded](ThresholdSession::exclude) so another participant can take its
+/// place, or the whole session can be [aborted](ThresholdSession::abort).
+#[derive(Debug, Clone)]
+pub struct ThresholdSession<T: LamportDigest> {
+    verifying_key: VerifyingKey<T>,
+    threshold: usize,
+    request: SessionRequest,
+    state: SessionState,
+    commitments: BTreeMap<u8, [u8; 32]>,
+    shares: Vec<SignatureShare<T>>,
+    excluded: BTreeSet<u8>,
+}
+
+impl<T: LamportDigest> ThresholdSession<T> {
+    /// Starts a session to sign `message` with the shares of `verifying_key`.
+    pub fn new<B: AsRef<[u8]>>(
+        verifying_key: VerifyingKey<T>,
+        threshold: usize,
+        message: B,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        if threshold < 2 {
+            return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
+        }
+        let mut session_id = [0u8; SESSION_ID_BYTES];
+        rng.fill_bytes(&mut session_id);
+        Ok(Self {
+            verifying_key,
+            threshold,
+            request: SessionRequest {
+                session_id,
+                message: message.as_ref().to_vec(),
+            },
+            state: SessionState::CollectingCommitments,
+            commitments: BTreeMap::new(),
+            shares: Vec::with_capacity(threshold),
+            excluded: BTreeSet::new(),
+        })
+    }
+
+    /// The announcement to send to participants.
+    pub fn request(&self) -> &SessionRequest {
+        &self.request
+    }
+
+    /// The current phase of the session.
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// The identifiers of the participants whose commitments were accepted.
+    pub fn quorum(&self) -> impl Iterator<Item = u8> + '_ {
+        self.commitments.keys().copied()
+    }
+
+    /// The identifiers of the excluded participants.
+    pub fn excluded(&self) -> impl Iterator<Item = u8> + '_ {
+        self.excluded.iter().copied()
+    }
+
+    /// Records a participant's commitment.
+    ///
+    /// Once `threshold` distinct participants have committed the session moves
+    /// to [`SessionState::CollectingSignatures`] and later commitments are rejected.
+    pub fn add_commitment(&mut self, commitment: &SessionCommitment) -> LamportResult<()> {
+        self.expect_state(SessionState::CollectingCommitments)?;
+        self.check_session(&commitment.session_id)?;
+        if commitment.identifier == 0
+            || self.commitments.contains_key(&commitment.identifier)
+            || self.excluded.contains(&commitment.identifier)
+        {
+            return Err(LamportError::InvalidParticipant(commitment.identifier));
+        }
+        self.commitments
+            .insert(commitment.identifier, commitment.commitment);
+        if self.commitments.len() == self.threshold {
+            self.state = SessionState::CollectingSignatures;
+        }
+        Ok(())
+    }
+
+    /// Records a revealed [`SignatureShare`] from a member of the quorum.
+    ///
+    /// The share must match the commitment made earlier by the same participant
+    /// and be made for the session's threshold.
+    pub fn add_signature_share(&mut self, share: SignatureShare<T>) -> LamportResult<()> {
+        self.expect_state(SessionState::CollectingSignatures)?;
+        let identifier = share.identifier;
+        let expected = self
+            .commitments
+            .get(&identifier)
+            .ok_or(LamportError::InvalidParticipant(identifier))?;
+        if commit(&self.request.session_id, &share) != *expected
+            || self.shares.iter().any(|s| s.identifier == identifier)
+        {
+            return Err(LamportError::InvalidParticipant(identifier));
+        }
+        if usize::from(share.threshold) != self.threshold {
+            return Err(LamportError::ThresholdMismatch {
+                identifier,
+                expected: self.threshold,
+                found: share.threshold,
+            });
+        }
+        self.shares.push(share);
+        Ok(())
+    }
+
+    /// Removes the participant from the quorum and rejects its later messages.
+    ///
+    /// If the quorum was complete the session returns to
+    /// [`SessionState::CollectingCommitments`] so another participant can
+    /// commit in its place. The shares already revealed are kept, since
+    /// their one-time key shares have signed the message.
+    pub fn exclude(&mut self, identifier: u8) -> LamportResult<()> {
+        if matches!(self.state, SessionState::Complete | SessionState::Aborted) {
+            return Err(LamportError::InvalidSessionState);
+        }
+        self.excluded.insert(identifier);
+        self.commitments.remove(&identifier);
+        self.shares.retain(|share| share.identifier != identifier);
+        if self.commitments.len() < self.threshold {
+            self.state = SessionState::CollectingCommitments;
+        }
+        Ok(())
+    }
+
+    /// Abandons the session so it accepts no more commitments or shares.
+    pub fn abort(&mut self) -> LamportResult<()> {
+        if self.state == SessionState::Complete {
+            return Err(LamportError::InvalidSessionState);
+        }
+        self.state = SessionState::Aborted;
+        Ok(())
+    }
+
+    /// Returns true once every member of the quorum has revealed its share.
+    pub fn is_ready(&self) -> bool {
+        self.state == SessionState::CollectingSignatures && self.shares.len() == self.threshold
+    }
+
+    /// Combines the revealed shares and verifies the result against the verifying key.
+    pub fn finalize(&mut self) -> LamportResult<Signature<T>> {
+        self.expect_state(SessionState::CollectingSignatures)?;
+        if !self.is_ready() {
+            return Err(LamportError::InvalidSessionState);
+        }
+        let signature = Signature::combine(&self.shares)?;
+        self.verifying_key
+            .verify(&signature, &self.request.message)?;
+        self.state = SessionState::Complete;
+        Ok(signature)
+    }
+
+    fn expect_state(&self, state: SessionState) -> LamportResult<()> {
+        if self.state == state {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSessionState)
+        }
+    }
+
+    fn check_session(&self, session_id: &[u8; SESSION_ID_BYTES]) -> LamportResult<()> {
+        if *session_id == self.request.session_id {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSessionState)
+        }
+    }
+}
+
+/// A share holder taking part in a [`ThresholdSession`].
+#[derive(Debug, Clone)]
+pub struct ThresholdParticipant<T: LamportDigest> {
+    share: SigningKeyShare<T>,
+    pending: Option<([u8; SESSION_ID_BYTES], SignatureShare<T>)>,
+}
+
+impl<T: LamportDigest> ThresholdParticipant<T> {
+    /// Wraps the [`SigningKeyShare`] held by this participant.
+    pub fn new(share: SigningKeyShare<T>) -> Self {
+        Self {
+            share,
+            pending: None,
+        }
+    }
+
+    /// The identifier of the wrapped share.
+    pub fn identifier(&self) -> u8 {
+        self.share.identifier
+    }
+
+    /// Signs the announced message and returns a commitment to the resulting share.
+    ///
+    /// This consumes the one-time share even if the participant is not chosen for the quorum.
+    pub fn commit(&mut self, request: &SessionRequest) -> LamportResult<SessionCommitment> {
+        if self.pending.is_some() {
+            return Err(LamportError::InvalidSessionState);
+        }
+        let share = self.share.sign(&request.message)?;
+        let commitment = SessionCommitment {
+            session_id: request.session_id,
+            identifier: share.identifier,
+            commitment: commit(&request.session_id, &share),
+        };
+        self.pending = Some((request.session_id, share));
+        Ok(commitment)
+    }
+
+    /// Reveals the committed share for the session.
+    pub fn reveal(
+        &mut self,
+        session_id: &[u8; SESSION_ID_BYTES],
+    ) -> LamportResult<SignatureShare<T>> {
+        match self.pending.take() {
+            Some((id, share)) if id == *session_id => Ok(share),
+            other => {
+                self.pending = other;
+                Err(LamportError::InvalidSessionState)
+            }
+        }
+    }
+
+    /// The wrapped [`SigningKeyShare`].
+    pub fn share(&self) -> &SigningKeyShare<T> {
+        &self.share
+    }
+}
+
+fn commit<T: LamportDigest>(
+    session_id: &[u8; SESSION_ID_BYTES],
+    share: &SignatureShare<T>,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITMENT_DOMAIN);
+    hasher.update(session_id);
+    hasher.update(share.to_bytes());
+    hasher.finalize().into()
+}
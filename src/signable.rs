@@ -0,0 +1,232 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{KeyId, LamportDigest, LamportResult, Signature, SigningKey, VerifyingKey};
+use zeroize::Zeroize;
+
+const POSSESSION_DOMAIN: &[u8] = b"lamport_signature_plus proof of possession v1";
+
+/// Structured data with a canonical, unambiguous byte encoding for signing.
+///
+/// The encoding starts with the type's [`SignableMessage::DOMAIN`] and every
+/// field written through the [`CanonicalEncoder`] is length prefixed, so two
+/// different values, even of different types, never encode to the same bytes.
+///
+/// ```
+/// use lamport_signature_plus::{CanonicalEncoder, SignableMessage};
+///
+/// struct Transfer {
+///     to: String,
+///     amount: u64,
+/// }
+///
+/// impl SignableMessage for Transfer {
+///     const DOMAIN: &'static [u8] = b"example transfer v1";
+///
+///     fn encode(&self, encoder: &mut CanonicalEncoder) {
+///         encoder.write_str(&self.to).write_u64(self.amount);
+///     }
+/// }
+/// ```
+pub trait SignableMessage {
+    /// Separates the encodings of different message types.
+    const DOMAIN: &'static [u8];
+
+    /// Writes each field of the message to the encoder.
+    fn encode(&self, encoder: &mut CanonicalEncoder);
+
+    /// The canonical bytes that are hashed and signed.
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = CanonicalEncoder::new(Self::DOMAIN);
+        self.encode(&mut encoder);
+        encoder.finish()
+    }
+}
+
+/// Builds the canonical encoding of a [`SignableMessage`].
+///
+/// Integers are written big endian with a fixed width and variable length
+/// fields are prefixed with their length as a big endian `u64`.
+#[derive(Debug, Clone, Default)]
+pub struct CanonicalEncoder {
+    bytes: Vec<u8>,
+}
+
+impl CanonicalEncoder {
+    /// Starts an encoding with the domain separator.
+    pub fn new(domain: &[u8]) -> Self {
+        let mut encoder = Self::default();
+        encoder.write_bytes(domain);
+        encoder
+    }
+
+    /// Writes a length prefixed byte string.
+    pub fn write_bytes(&mut self, value: &[u8]) -> &mut Self {
+        self.write_u64(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+        self
+    }
+
+    /// Writes a length prefixed UTF-8 string.
+    pub fn write_str(&mut self, value: &str) -> &mut Self {
+        self.write_bytes(value.as_bytes())
+    }
+
+    /// Writes a single byte.
+    pub fn write_u8(&mut self, value: u8) -> &mut Self {
+        self.bytes.push(value);
+        self
+    }
+
+    /// Writes a boolean as a single `0` or `1` byte.
+    pub fn write_bool(&mut self, value: bool) -> &mut Self {
+        self.write_u8(value as u8)
+    }
+
+    /// Writes a big endian `u32`.
+    pub fn write_u32(&mut self, value: u32) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Writes a big endian `u64`.
+    pub fn write_u64(&mut self, value: u64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Writes a nested message, including its own domain, as a length prefixed field.
+    pub fn write_message<M: SignableMessage>(&mut self, value: &M) -> &mut Self {
+        self.write_bytes(&value.canonical_bytes())
+    }
+
+    /// Returns the encoded bytes.
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs the canonical encoding of the [`SignableMessage`].
+    pub fn sign_message<M: SignableMessage>(&mut self, message: &M) -> LamportResult<Signature<T>> {
+        self.sign(message.canonical_bytes())
+    }
+
+    /// Signs a statement over this key's own [`KeyId`] and `context`, proving
+    /// possession of the secret key to a registry before it accepts the
+    /// [`VerifyingKey`].
+    ///
+    /// The proof is the one-time key's only signature, so the key is consumed.
+    /// Keys that must stay usable can be certified by another key instead,
+    /// with [`KeyFactory::prove_possession`](crate::KeyFactory::prove_possession)
+    /// or a certificate chain.
+    pub fn prove_possession(mut self, context: &[u8]) -> LamportResult<Signature<T>> {
+        let key_id = VerifyingKey::from(&self).fingerprint();
+        let proof = self.sign(possession_message(&key_id, &key_id, context));
+        self.zeroize();
+        proof
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies a [`Signature`] over the canonical encoding of the [`SignableMessage`].
+    pub fn verify_message<M: SignableMessage>(
+        &self,
+        signature: &Signature<T>,
+        message: &M,
+    ) -> LamportResult<()> {
+        self.verify(signature, message.canonical_bytes())
+    }
+
+    /// Checks a proof of possession created with [`SigningKey::prove_possession`].
+    pub fn check_possession(&self, proof: &Signature<T>, context: &[u8]) -> LamportResult<()> {
+        let key_id = self.fingerprint();
+        self.verify(proof, possession_message(&key_id, &key_id, context))
+    }
+
+    /// Checks a proof of possession of `key` certified by this key, created
+    /// with [`KeyFactory::prove_possession`](crate::KeyFactory::prove_possession).
+    pub fn check_certified_possession(
+        &self,
+        proof: &Signature<T>,
+        key: &VerifyingKey<T>,
+        context: &[u8],
+    ) -> LamportResult<()> {
+        self.verify(
+            proof,
+            possession_message(&self.fingerprint(), &key.fingerprint(), context),
+        )
+    }
+}
+
+/// The statement that the holder of `certifier` also holds `key_id`.
+///
+/// A key proving its own possession is its own certifier.
+pub(crate) fn possession_message(certifier: &KeyId, key_id: &KeyId, context: &[u8]) -> Vec<u8> {
+    let mut encoder = CanonicalEncoder::new(POSSESSION_DOMAIN);
+    encoder
+        .write_bytes(certifier.as_bytes())
+        .write_bytes(key_id.as_bytes())
+        .write_bytes(context);
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    struct Transfer {
+        to: String,
+        memo: String,
+        amount: u64,
+    }
+
+    impl SignableMessage for Transfer {
+        const DOMAIN: &'static [u8] = b"lamport test transfer";
+
+        fn encode(&self, encoder: &mut CanonicalEncoder) {
+            encoder
+                .write_str(&self.to)
+                .write_str(&self.memo)
+                .write_u64(self.amount);
+        }
+    }
+
+    #[test]
+    fn sign_message() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let transfer = Transfer {
+            to: "alice".to_string(),
+            memo: "rent".to_string(),
+            amount: 10,
+        };
+        let signature = sk.sign_message(&transfer).unwrap();
+        assert!(pk.verify_message(&signature, &transfer).is_ok());
+
+        let shifted = Transfer {
+            to: "alicer".to_string(),
+            memo: "ent".to_string(),
+            amount: 10,
+        };
+        assert_ne!(transfer.canonical_bytes(), shifted.canonical_bytes());
+        assert!(pk.verify_message(&signature, &shifted).is_err());
+    }
+
+    #[test]
+    fn proof_of_possession() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let (_, other) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let proof = sk.prove_possession(b"registry").unwrap();
+        assert!(pk.check_possession(&proof, b"registry").is_ok());
+        assert!(pk.check_possession(&proof, b"other registry").is_err());
+        assert!(other.check_possession(&proof, b"registry").is_err());
+        assert!(pk.verify(&proof, b"registry").is_err());
+    }
+}
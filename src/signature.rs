@@ -2,9 +2,10 @@
     Copyright Michael Lodder. All Rights Reserved.
     SPDX-License-Identifier: Apache-2.0
 */
-use crate::{LamportDigest, LamportError, LamportResult, MultiVec};
+use crate::{
+    CombineContext, KeyId, LamportDigest, LamportError, LamportResult, MultiVec, VerifyingKey,
+};
 use std::marker::PhantomData;
-use vsss_rs::{combine_shares, Gf256};
 
 /// A signature data generated by [`SigningKey`].
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -15,6 +16,7 @@ pub struct Signature<T: LamportDigest> {
 
 serde_impl!(Signature);
 vec_impl!(Signature);
+armor_impl!(Signature, "LAMPORT SIGNATURE");
 
 impl<T: LamportDigest> Signature<T> {
     /// Constructs a [`Signature`] from a byte sequence
@@ -41,28 +43,258 @@ impl<T: LamportDigest> Signature<T> {
         self.data.data.clone()
     }
 
+    /// Checks that the axes and length of the signature match the digest.
+    ///
+    /// Returns [`LamportError::InvalidSignatureShape`] for signatures whose
+    /// layout was changed or truncated after they were created.
+    pub fn validate(&self) -> LamportResult<()> {
+        let digest_size_in_bits = T::digest_size_in_bits();
+        let expected = [digest_size_in_bits, digest_size_in_bits / 8];
+        if self.data.axes != expected
+            || self.data.data.len() != expected[0].saturating_mul(expected[1])
+        {
+            return Err(LamportError::InvalidSignatureShape {
+                expected,
+                found: self.data.axes,
+                length: self.data.data.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies this signature over `data` with the [`VerifyingKey`].
+    ///
+    /// Equivalent to [`VerifyingKey::verify`].
+    pub fn verify_with<B: AsRef<[u8]>>(&self, key: &VerifyingKey<T>, data: B) -> LamportResult<()> {
+        key.verify(self, data)
+    }
+
+    /// Copies the signature data into a fixed size array without allocating.
+    ///
+    /// `N` must equal the signature length, `bits * bits / 8` for a `bits`
+    /// sized digest, or [`LamportError::InvalidSignatureBytes`] is returned.
+    ///
+    /// ```
+    /// use lamport_signature_plus::{generate_keys, LamportFixedDigest};
+    /// use rand::SeedableRng;
+    /// use sha2::Sha256;
+    ///
+    /// let rng = rand_chacha::ChaCha8Rng::from_seed([0u8; 32]);
+    /// let (mut sk, _) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+    /// let signature = sk.sign(b"hello").unwrap();
+    /// let array: [u8; 256 * 32] = signature.to_array().unwrap();
+    /// assert!(signature.to_array::<32>().is_err());
+    /// ```
+    pub fn to_array<const N: usize>(&self) -> LamportResult<[u8; N]> {
+        self.data
+            .data
+            .as_slice()
+            .try_into()
+            .map_err(|_| LamportError::InvalidSignatureBytes)
+    }
+
     /// Combines multiple signature shares into a single signature.
+    ///
+    /// Use a [`CombineContext`] to reuse the interpolation coefficients when
+    /// combining many signatures from the same signers.
     pub fn combine(shares: &[SignatureShare<T>]) -> LamportResult<Self> {
-        if shares.is_empty() {
+        CombineContext::new().combine_signature(shares)
+    }
+}
+
+/// Incrementally decodes a [`Signature`] from bytes as they arrive.
+///
+/// The buffer is sized for exactly one signature when the decoder is created,
+/// so excess input is rejected as soon as it is seen rather than after the
+/// whole blob has been collected.
+#[derive(Debug, Clone)]
+pub struct SignatureDecoder<T: LamportDigest> {
+    buffer: Vec<u8>,
+    length: usize,
+    algorithm: PhantomData<T>,
+}
+
+impl<T: LamportDigest> Default for SignatureDecoder<T> {
+    fn default() -> Self {
+        let bits = T::digest_size_in_bits();
+        let length = bits * (bits / 8);
+        Self {
+            buffer: Vec::with_capacity(length),
+            length,
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> SignatureDecoder<T> {
+    /// Creates a decoder for one [`Signature`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of bytes still needed to complete the signature.
+    pub fn remaining(&self) -> usize {
+        self.length - self.buffer.len()
+    }
+
+    /// Returns true once every byte of the signature has been received.
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Buffers as much of `input` as the signature needs and returns how many
+    /// bytes were consumed, leaving the rest for whatever follows in the stream.
+    pub fn update(&mut self, input: &[u8]) -> usize {
+        let consumed = input.len().min(self.remaining());
+        self.buffer.extend_from_slice(&input[..consumed]);
+        consumed
+    }
+
+    /// Buffers `input` and returns the [`Signature`] once it is complete.
+    ///
+    /// Input beyond the end of the signature is an error. After a signature
+    /// is returned the decoder is ready for the next one.
+    pub fn decode(&mut self, input: &[u8]) -> LamportResult<Option<Signature<T>>> {
+        if input.len() > self.remaining() {
             return Err(LamportError::InvalidSignatureBytes);
         }
-        if shares.len() < shares[0].threshold as usize {
-            return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
+        self.update(input);
+        if !self.is_complete() {
+            return Ok(None);
         }
+        let signature = Signature::from_bytes(&self.buffer)?;
+        self.buffer.clear();
+        Ok(Some(signature))
+    }
 
-        let mut out = Self {
-            data: MultiVec::fill(shares[0].data.axes, 0u8),
-            algorithm: PhantomData,
-        };
-        let mut share_bytes = vec![[0u8; 2]; shares.len()];
-        for i in 0..shares[0].data.len() {
-            for (j, share) in shares.iter().enumerate() {
-                share_bytes[j][0] = share.identifier;
-                share_bytes[j][1] = share.data.data[i];
-            }
-            out.data.data[i] = combine_shares::<Gf256, u8, [u8; 2]>(&share_bytes)?.0;
+    /// Returns the [`Signature`], or an error if bytes are still missing.
+    pub fn finish(self) -> LamportResult<Signature<T>> {
+        if !self.is_complete() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Signature::from_bytes(&self.buffer)
+    }
+}
+
+/// A [`Signature`] bound to the [`KeyId`] of the key that created it.
+///
+/// Relying parties can reject signatures presented against the wrong
+/// [`VerifyingKey`] by comparing fingerprints before doing the per-bit checks.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AttachedSignature<T: LamportDigest> {
+    pub(crate) key_id: KeyId,
+    pub(crate) signature: Signature<T>,
+}
+
+serde_impl!(AttachedSignature);
+vec_impl!(AttachedSignature);
+
+impl<T: LamportDigest> AttachedSignature<T> {
+    /// Binds the [`Signature`] to the [`KeyId`] of the signer.
+    pub fn new(signature: Signature<T>, key_id: KeyId) -> Self {
+        Self { key_id, signature }
+    }
+
+    /// The fingerprint of the key that created the signature.
+    pub fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    /// The detached [`Signature`].
+    pub fn signature(&self) -> &Signature<T> {
+        &self.signature
+    }
+
+    /// Splits into the detached [`Signature`] and the signer [`KeyId`].
+    pub fn into_parts(self) -> (Signature<T>, KeyId) {
+        (self.signature, self.key_id)
+    }
+
+    /// Constructs an [`AttachedSignature`] from a byte sequence
+    pub fn from_bytes<B: AsRef<[u8]>>(bytes: B) -> LamportResult<Self> {
+        let bytes = bytes.as_ref();
+        if bytes.len() < KeyId::BYTES {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let key_id = KeyId::from_bytes(&bytes[..KeyId::BYTES])?;
+        let signature = Signature::from_bytes(&bytes[KeyId::BYTES..])?;
+        Ok(Self { key_id, signature })
+    }
+
+    /// Converts the key id and signature data into a linearized vector.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(KeyId::BYTES + self.signature.data.len());
+        bytes.extend_from_slice(self.key_id.as_bytes());
+        bytes.extend_from_slice(&self.signature.data.data);
+        bytes
+    }
+}
+
+/// A [`Signature`] bundled with the full [`VerifyingKey`] that checks it.
+///
+/// This is useful for self-contained attestations where the verifier does not
+/// have the key in advance. A successful [`SignatureWithKey::verify`] only proves
+/// the bundled key signed the message, callers must still decide whether to trust
+/// that key, e.g. by comparing its [`KeyId`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SignatureWithKey<T: LamportDigest> {
+    pub(crate) verifying_key: VerifyingKey<T>,
+    pub(crate) signature: Signature<T>,
+}
+
+serde_impl!(SignatureWithKey);
+vec_impl!(SignatureWithKey);
+
+impl<T: LamportDigest> SignatureWithKey<T> {
+    /// Bundles the [`Signature`] with the [`VerifyingKey`] that checks it.
+    pub fn new(signature: Signature<T>, verifying_key: VerifyingKey<T>) -> Self {
+        Self {
+            verifying_key,
+            signature,
+        }
+    }
+
+    /// The bundled [`VerifyingKey`].
+    pub fn verifying_key(&self) -> &VerifyingKey<T> {
+        &self.verifying_key
+    }
+
+    /// The bundled [`Signature`].
+    pub fn signature(&self) -> &Signature<T> {
+        &self.signature
+    }
+
+    /// Splits into the [`Signature`] and [`VerifyingKey`].
+    pub fn into_parts(self) -> (Signature<T>, VerifyingKey<T>) {
+        (self.signature, self.verifying_key)
+    }
+
+    /// Verifies the bundled [`Signature`] with the bundled [`VerifyingKey`].
+    pub fn verify<B: AsRef<[u8]>>(&self, data: B) -> LamportResult<()> {
+        self.verifying_key.verify(&self.signature, data)
+    }
+
+    /// Constructs a [`SignatureWithKey`] from a byte sequence
+    pub fn from_bytes<B: AsRef<[u8]>>(bytes: B) -> LamportResult<Self> {
+        let bytes = bytes.as_ref();
+        let bits = T::digest_size_in_bits();
+        let key_length = bits * (bits / 8) * 2;
+        if bytes.len() < key_length {
+            return Err(LamportError::InvalidSignatureBytes);
         }
-        Ok(out)
+        let verifying_key = VerifyingKey::from_bytes(&bytes[..key_length])?;
+        let signature = Signature::from_bytes(&bytes[key_length..])?;
+        Ok(Self {
+            verifying_key,
+            signature,
+        })
+    }
+
+    /// Converts the key and signature data into a linearized vector.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.verifying_key.to_bytes();
+        bytes.extend_from_slice(&self.signature.data.data);
+        bytes
     }
 }
 
@@ -77,8 +309,19 @@ pub struct SignatureShare<T: LamportDigest> {
 
 serde_impl!(SignatureShare);
 vec_impl!(SignatureShare);
+armor_impl!(SignatureShare, "LAMPORT SIGNATURE SHARE");
 
 impl<T: LamportDigest> SignatureShare<T> {
+    /// The identifier of the [`SigningKeyShare`] that created this share.
+    pub fn identifier(&self) -> u8 {
+        self.identifier
+    }
+
+    /// The number of shares required to combine a [`Signature`].
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
     /// Constructs a [`SignatureShare`] from a byte sequence
     pub fn from_bytes<B: AsRef<[u8]>>(bytes: B) -> LamportResult<Self> {
         let bytes = bytes.as_ref();
@@ -106,6 +349,20 @@ impl<T: LamportDigest> SignatureShare<T> {
         })
     }
 
+    /// Converts the share into a fixed size array without allocating.
+    ///
+    /// `N` must be two more than the [`Signature`] length.
+    pub fn to_array<const N: usize>(&self) -> LamportResult<[u8; N]> {
+        if N != self.data.data.len() + 2 {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let mut bytes = [0u8; N];
+        bytes[0] = self.identifier;
+        bytes[1] = self.threshold;
+        bytes[2..].copy_from_slice(&self.data.data);
+        Ok(bytes)
+    }
+
     /// Converts the inner signature data into a linearized vector.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = vec![0u8; self.data.data.len() + 2];
@@ -115,3 +372,42 @@ impl<T: LamportDigest> SignatureShare<T> {
         bytes
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn signature_decoder() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+        let bytes = sk.sign(b"stream").unwrap().to_bytes();
+
+        let mut decoder = SignatureDecoder::<D>::new();
+        let mut chunks = bytes.chunks(1000).peekable();
+        let mut decoded = None;
+        while let Some(chunk) = chunks.next() {
+            decoded = decoder.decode(chunk).unwrap();
+            assert_eq!(decoded.is_some(), chunks.peek().is_none());
+        }
+        assert!(pk.verify(&decoded.unwrap(), b"stream").is_ok());
+        assert_eq!(decoder.remaining(), bytes.len());
+
+        let mut stream = bytes.clone();
+        stream.extend_from_slice(b"next");
+        let mut decoder = SignatureDecoder::<D>::new();
+        assert_eq!(decoder.update(&stream), bytes.len());
+        assert!(decoder.is_complete());
+        assert!(pk.verify(&decoder.finish().unwrap(), b"stream").is_ok());
+
+        let mut decoder = SignatureDecoder::<D>::new();
+        assert!(decoder.decode(&stream).is_err());
+        assert_eq!(decoder.update(&bytes[..10]), 10);
+        assert!(decoder.finish().is_err());
+    }
+}
@@ -0,0 +1,166 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{
+    Keypair, LamportDigest, LamportError, LamportIncrementalDigest, Signature, SigningKey,
+    VerifyingKey,
+};
+use signature::digest::Digest;
+use signature::{
+    DigestSigner, DigestVerifier, Error, KeypairRef, SignatureEncoding, Signer, SignerMut, Verifier,
+};
+use std::sync::{Mutex, PoisonError};
+
+/// A [`SigningKey`] that implements [`Signer`] for generic code.
+///
+/// [`Signer::try_sign`] takes `&self`, but a one-time key must record that
+/// it has signed. The key is held behind a lock so the first signature marks
+/// it used and every later call fails, even across threads. Where `&mut`
+/// access is available, [`SigningKey`] implements [`SignerMut`] directly.
+#[derive(Debug)]
+pub struct SharedSigningKey<T: LamportDigest> {
+    key: Mutex<SigningKey<T>>,
+}
+
+impl<T: LamportDigest> From<SigningKey<T>> for SharedSigningKey<T> {
+    fn from(key: SigningKey<T>) -> Self {
+        Self::new(key)
+    }
+}
+
+impl<T: LamportDigest> SharedSigningKey<T> {
+    /// Wraps the [`SigningKey`].
+    pub fn new(key: SigningKey<T>) -> Self {
+        Self {
+            key: Mutex::new(key),
+        }
+    }
+
+    /// Has the key already signed a message.
+    ///
+    /// A key whose lock was poisoned is reported as used.
+    pub fn used(&self) -> bool {
+        self.key.lock().map_or(true, |key| key.used())
+    }
+
+    /// The [`VerifyingKey`] of the wrapped key.
+    pub fn verifying_key(&self) -> VerifyingKey<T> {
+        let key = self.key.lock().unwrap_or_else(PoisonError::into_inner);
+        VerifyingKey::from(&*key)
+    }
+
+    /// Unwraps the [`SigningKey`], with its used state.
+    pub fn into_inner(self) -> Result<SigningKey<T>, Error> {
+        self.key.into_inner().map_err(|_| Error::new())
+    }
+}
+
+impl<T: LamportDigest> signature::Keypair for SharedSigningKey<T>
+where
+    VerifyingKey<T>: Clone,
+{
+    type VerifyingKey = VerifyingKey<T>;
+
+    fn verifying_key(&self) -> VerifyingKey<T> {
+        SharedSigningKey::verifying_key(self)
+    }
+}
+
+impl<T: LamportDigest> Signer<Signature<T>> for SharedSigningKey<T> {
+    fn try_sign(&self, msg: &[u8]) -> Result<Signature<T>, Error> {
+        let mut key = self.key.lock().map_err(|_| Error::new())?;
+        key.sign(msg).map_err(signature_error)
+    }
+}
+
+/// Signs a message hashed incrementally into `D`, so it never has to be
+/// held in memory at once.
+///
+/// The digest must be started with [`LamportIncrementalDigest::hasher`],
+/// which for [`LamportFixedDigest`](crate::LamportFixedDigest) is `D::new()`,
+/// for the signature to match [`SigningKey::sign`] of the whole message.
+impl<T, D> DigestSigner<D, Signature<T>> for SharedSigningKey<T>
+where
+    T: LamportIncrementalDigest<Hasher = D>,
+    D: Digest,
+{
+    fn try_sign_digest(&self, digest: D) -> Result<Signature<T>, Error> {
+        let mut key = self.key.lock().map_err(|_| Error::new())?;
+        key.sign_digest(&T::finalize(digest))
+            .map_err(signature_error)
+    }
+}
+
+impl<T: LamportDigest> SignerMut<Signature<T>> for SigningKey<T> {
+    fn try_sign(&mut self, msg: &[u8]) -> Result<Signature<T>, Error> {
+        self.sign(msg).map_err(signature_error)
+    }
+}
+
+/// Computes the [`VerifyingKey`] from the secret values on every call.
+/// Keep a [`Keypair`] to avoid hashing them again.
+impl<T: LamportDigest> signature::Keypair for SigningKey<T>
+where
+    VerifyingKey<T>: Clone,
+{
+    type VerifyingKey = VerifyingKey<T>;
+
+    fn verifying_key(&self) -> VerifyingKey<T> {
+        VerifyingKey::from(self)
+    }
+}
+
+impl<T: LamportDigest> KeypairRef for Keypair<T>
+where
+    VerifyingKey<T>: Clone,
+{
+    type VerifyingKey = VerifyingKey<T>;
+}
+
+impl<T: LamportDigest> Verifier<Signature<T>> for VerifyingKey<T> {
+    fn verify(&self, msg: &[u8], signature: &Signature<T>) -> Result<(), Error> {
+        VerifyingKey::verify(self, signature, msg).map_err(signature_error)
+    }
+}
+
+/// Verifies a message hashed incrementally into `D`, see
+/// [`SharedSigningKey`]'s [`DigestSigner`] implementation.
+impl<T, D> DigestVerifier<D, Signature<T>> for VerifyingKey<T>
+where
+    T: LamportIncrementalDigest<Hasher = D>,
+    D: Digest,
+{
+    fn verify_digest(&self, digest: D, signature: &Signature<T>) -> Result<(), Error> {
+        VerifyingKey::verify_digest(self, signature, &T::finalize(digest)).map_err(signature_error)
+    }
+}
+
+/// Encodes the [`Signature`] as the bytes of [`Signature::to_bytes`].
+impl<T: LamportDigest> SignatureEncoding for Signature<T>
+where
+    Signature<T>: Clone,
+{
+    type Repr = Vec<u8>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        Signature::to_bytes(self)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.data.data.len()
+    }
+}
+
+/// Keeps the [`LamportError`] as the source when `std` is available.
+pub(crate) fn signature_error(error: LamportError) -> Error {
+    #[cfg(feature = "std")]
+    {
+        Error::from_source(error)
+    }
+    #[cfg(not(feature = "std"))]
+    {
+        let _ = error;
+        Error::new()
+    }
+}
@@ -2,14 +2,25 @@
     Copyright Michael Lodder. All Rights Reserved.
     SPDX-License-Identifier: Apache-2.0
 */
+use crate::gf256::ShareSplitter;
+use crate::hash::{check_digest, check_random, check_security, digest_vectored};
 use crate::signature::SignatureShare;
-use crate::utils::separate_one_and_zero_values;
-use crate::{LamportDigest, LamportError, LamportResult, MultiVec, Signature};
+use crate::utils::{separate_one_and_zero_values, separate_owned_one_and_zero_values};
+#[cfg(feature = "std")]
+use crate::Progress;
+use crate::{
+    AttachedSignature, CombineContext, LamportDigest, LamportError, LamportResult, MultiVec,
+    SeedKdf, Signature, SignatureWithKey, VerifyingKey,
+};
 use rand::{CryptoRng, RngCore};
+#[cfg(feature = "std")]
+use std::io::Write;
 use std::marker::PhantomData;
 use subtle::{Choice, ConditionallySelectable};
-use vsss_rs::{combine_shares, shamir, Gf256};
-use zeroize::Zeroize;
+use zeroize::{Zeroize, Zeroizing};
+
+/// The number of bytes in a seed for [`SigningKey::from_seed`].
+pub const SEED_BYTES: usize = 32;
 
 /// A one-time signing private key.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
@@ -21,7 +32,8 @@ pub struct SigningKey<T: LamportDigest> {
 }
 
 serde_impl!(SigningKey);
-vec_impl!(SigningKey);
+vec_impl!(@owned SigningKey);
+armor_impl!(SigningKey, "LAMPORT PRIVATE KEY");
 
 impl<T: LamportDigest> Zeroize for SigningKey<T> {
     fn zeroize(&mut self) {
@@ -36,11 +48,68 @@ impl<T: LamportDigest> SigningKey<T> {
         self.used
     }
 
+    /// Marks the key as used so it refuses to sign.
+    ///
+    /// Use this to synchronize with an external record that the key has
+    /// already signed. There is no way to clear the flag again.
+    pub fn mark_used(&mut self) {
+        self.used = true;
+    }
+
     /// Constructs a [`SigningKey`] with Digest algorithm type and the specified RNG.
-    pub fn random(mut rng: impl RngCore + CryptoRng) -> SigningKey<T> {
+    ///
+    /// Returns [`LamportError::InsecureDigest`] if the digest is below the
+    /// minimum security level, see [`LamportDigest`], and
+    /// [`LamportError::InvalidDigest`] if [`LamportDigest::random_pair`]
+    /// returns values of the wrong size.
+    pub fn random(rng: impl RngCore + CryptoRng) -> LamportResult<SigningKey<T>> {
+        check_security::<T>()?;
+        let (zero_values, one_values) = T::random_pair(rng);
+        check_random::<T>(&zero_values)?;
+        check_random::<T>(&one_values)?;
+        Ok(SigningKey {
+            zero_values,
+            one_values,
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Deterministically derives a [`SigningKey`] from a seed.
+    ///
+    /// The secret values are expanded with [`SeedKdf::V1`], whose output is
+    /// frozen, so the same seed always gives the same key. Use
+    /// [`SigningKey::from_seed_with_kdf`] to pick another version. The seed
+    /// must be protected exactly like the key.
+    ///
+    /// Returns [`LamportError::InsecureDigest`] if the digest is below the
+    /// minimum security level, see [`LamportDigest`].
+    pub fn from_seed(seed: &[u8; SEED_BYTES]) -> LamportResult<SigningKey<T>> {
+        Self::from_seed_with_kdf(seed, SeedKdf::V1)
+    }
+
+    /// Deterministically derives a [`SigningKey`] from a seed with the specified [`SeedKdf`].
+    ///
+    /// Returns [`LamportError::InsecureDigest`] if the digest is below the
+    /// minimum security level, see [`LamportDigest`].
+    pub fn from_seed_with_kdf(
+        seed: &[u8; SEED_BYTES],
+        kdf: SeedKdf,
+    ) -> LamportResult<SigningKey<T>> {
+        check_security::<T>()?;
+        Ok(Self::expand_seed(seed, kdf))
+    }
+
+    /// Expands a seed without checking the security level, for types that
+    /// already checked it when they were constructed.
+    pub(crate) fn expand_seed(seed: &[u8; SEED_BYTES], kdf: SeedKdf) -> SigningKey<T> {
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        let data = kdf.expand(seed, bits * bytes * 2);
+        let (zero_values, one_values) = separate_one_and_zero_values(&data, bytes);
         SigningKey {
-            zero_values: T::random(&mut rng),
-            one_values: T::random(&mut rng),
+            zero_values,
+            one_values,
             used: false,
             algorithm: PhantomData,
         }
@@ -58,43 +127,52 @@ impl<T: LamportDigest> SigningKey<T> {
     ///
     /// const SEED: [u8; 32] = [0; 32];
     /// let rng = ChaCha12Rng::from_seed(SEED);
-    /// let mut private_key = SigningKey::<LamportFixedDigest<Sha256>>::random(rng);
+    /// let mut private_key = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
     /// const MESSAGE: &[u8] = b"hello, world!";
     /// assert!(private_key.sign(MESSAGE).is_ok());
     /// ```
     pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<Signature<T>> {
+        let data_hash = T::digest(data.as_ref());
+        self.sign_digest(&data_hash)
+    }
+
+    /// Signs a message given as several parts without copying them into one buffer.
+    ///
+    /// Each part is prefixed with its length as a big endian `u64` before
+    /// hashing, so the boundaries between parts are part of what is signed.
+    /// The signature is the same as signing that encoding with [`SigningKey::sign`].
+    pub fn sign_vectored(&mut self, parts: &[&[u8]]) -> LamportResult<Signature<T>> {
+        let data_hash = digest_vectored::<T>(parts);
+        self.sign_digest(&data_hash)
+    }
+
+    /// Signs the data and binds the signature to the fingerprint of this key's [`VerifyingKey`].
+    pub fn sign_attached<B: AsRef<[u8]>>(
+        &mut self,
+        data: B,
+    ) -> LamportResult<AttachedSignature<T>> {
         if self.used {
             return Err(LamportError::PrivateKeyReuseError);
         }
-        let data = data.as_ref();
-
-        let data_hash = T::digest(data);
+        let key_id = VerifyingKey::from(&*self).fingerprint();
+        let signature = self.sign(data)?;
+        Ok(AttachedSignature::new(signature, key_id))
+    }
 
-        let bits = T::digest_size_in_bits();
-        let bytes = bits / 8;
-        let mut data = MultiVec::fill([bits, bytes], 0);
-        // Ensure runtime is independent of secret keys
-        // Ensure code access patterns are independent of secret keys
-        // Ensure data access patterns are independent of secret keys
-        {
-            let mut signature_iter = data.iter_mut();
-            let mut zero_iter = self.zero_values.iter();
-            let mut one_iter = self.one_values.iter();
-            for byte in data_hash.iter() {
-                for j in 0..8 {
-                    let b = (*byte >> j) & 1;
-                    let choice = Choice::from(b);
-                    for _ in 0..bytes {
-                        *signature_iter.next().expect("more values") = u8::conditional_select(
-                            zero_iter.next().expect("more values"),
-                            one_iter.next().expect("more values"),
-                            choice,
-                        );
-                    }
-                }
-            }
+    /// Signs the data and bundles the signature with this key's [`VerifyingKey`].
+    pub fn sign_with_key<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<SignatureWithKey<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
         }
+        let verifying_key = VerifyingKey::from(&*self);
+        let signature = self.sign(data)?;
+        Ok(SignatureWithKey::new(signature, verifying_key))
+    }
 
+    /// Signs a pre-computed message digest. The caller must ensure
+    /// `data_hash` is exactly the digest length.
+    pub(crate) fn sign_digest(&mut self, data_hash: &[u8]) -> LamportResult<Signature<T>> {
+        let data = sign_values::<T>(&self.zero_values, &self.one_values, self.used, data_hash)?;
         self.used = true;
         Ok(Signature {
             data,
@@ -117,6 +195,7 @@ impl<T: LamportDigest> SigningKey<T> {
 
     /// Constructs a [`SigningKey`] from canonical bytes.
     pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
         let input = input.as_ref();
         let bits = T::digest_size_in_bits();
         let bytes = bits / 8;
@@ -134,6 +213,30 @@ impl<T: LamportDigest> SigningKey<T> {
         })
     }
 
+    /// Constructs a [`SigningKey`] from canonical bytes, taking ownership of the buffer.
+    ///
+    /// The secret values are split in place, so loading does not leave an
+    /// extra copy of the key behind. The buffer is zeroized on error.
+    pub fn from_vec(input: Vec<u8>) -> LamportResult<Self> {
+        let mut input = Zeroizing::new(input);
+        check_security::<T>()?;
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+
+        if input.len() != bits * bytes * 2 + 1 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let used = input[0] == 1;
+        let (zero_values, one_values) =
+            separate_owned_one_and_zero_values(std::mem::take(&mut *input), 1, bytes);
+        Ok(Self {
+            used,
+            zero_values,
+            one_values,
+            algorithm: PhantomData,
+        })
+    }
+
     /// Create secret shares of the signing key where `threshold` are required
     /// to combine back into this secret.
     pub fn split(
@@ -158,57 +261,93 @@ impl<T: LamportDigest> SigningKey<T> {
             })
         }
 
+        let mut splitter = ShareSplitter::new(threshold, shares)?;
+        let mut temp = Zeroizing::new(vec![0u8; shares]);
         for (i, b) in self.zero_values.data.iter().enumerate() {
-            let temp =
-                shamir::split_secret::<Gf256, u8, [u8; 2]>(threshold, shares, Gf256(*b), &mut rng)?;
-            for (o, t) in output.iter_mut().zip(temp) {
-                debug_assert_eq!(t[0], o.identifier);
-                o.zero_values.data[i] = t[1];
+            splitter.split(*b, &mut rng, &mut temp);
+            for (o, t) in output.iter_mut().zip(temp.iter()) {
+                o.zero_values.data[i] = *t;
             }
         }
         for (i, b) in self.one_values.data.iter().enumerate() {
-            let temp =
-                shamir::split_secret::<Gf256, u8, [u8; 2]>(threshold, shares, Gf256(*b), &mut rng)?;
-            for (o, t) in output.iter_mut().zip(temp) {
-                debug_assert_eq!(t[0], o.identifier);
-                o.one_values.data[i] = t[1];
+            splitter.split(*b, &mut rng, &mut temp);
+            for (o, t) in output.iter_mut().zip(temp.iter()) {
+                o.one_values.data[i] = *t;
             }
         }
 
         Ok(output)
     }
 
-    /// Reconstruct the signing key from the secret shares created by `split`
-    pub fn combine(shares: &[SigningKeyShare<T>]) -> LamportResult<Self> {
-        if shares.is_empty() {
-            return Err(LamportError::InvalidPrivateKeyBytes);
-        }
-        if shares.len() < shares[0].threshold as usize {
+    /// Create secret shares of the signing key like [`SigningKey::split`], streaming
+    /// share `i + 1` to `writers[i]` in the [`SigningKeyShare`] canonical byte format.
+    ///
+    /// Only one row of each share is held in memory at a time. Each row is
+    /// written with a separate call, so slow writers should be buffered.
+    /// Requires the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn split_into_writers<W: Write>(
+        &self,
+        threshold: usize,
+        writers: &mut [W],
+        rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<()> {
+        self.split_into_writers_with_progress(threshold, writers, rng, &mut Progress::new())
+    }
+
+    /// Streams secret shares like [`SigningKey::split_into_writers`], reporting
+    /// each key row written and stopping between rows if cancelled.
+    ///
+    /// A cancelled split leaves the writers with truncated shares that must be discarded.
+    #[cfg(feature = "std")]
+    pub fn split_into_writers_with_progress<W: Write>(
+        &self,
+        threshold: usize,
+        writers: &mut [W],
+        mut rng: impl RngCore + CryptoRng,
+        progress: &mut Progress<'_>,
+    ) -> LamportResult<()> {
+        let shares = writers.len();
+        if threshold < 2 || threshold > shares {
             return Err(LamportError::VsssError(vsss_rs::Error::SharingMinThreshold));
         }
-        let mut out = Self {
-            zero_values: MultiVec::fill(shares[0].zero_values.axes, 0u8),
-            one_values: MultiVec::fill(shares[0].one_values.axes, 0u8),
-            used: false,
-            algorithm: PhantomData,
-        };
-        let mut share_bytes = vec![[0u8; 2]; shares.len()];
-        for i in 0..shares[0].zero_values.len() {
-            for (j, share) in shares.iter().enumerate() {
-                share_bytes[j][0] = share.identifier;
-                share_bytes[j][1] = share.zero_values.data[i];
-                out.used |= share.used;
-            }
-            out.zero_values.data[i] = combine_shares::<Gf256, u8, [u8; 2]>(&share_bytes)?.0;
+        // Everything that can fail is checked before the first write, so an
+        // invalid split never leaves partial shares in the writers
+        let threshold_byte = u8::try_from(threshold).map_err(|_| {
+            LamportError::General(format!("unable to create identifier for {}", threshold))
+        })?;
+        u8::try_from(shares).map_err(|_| {
+            LamportError::General(format!("unable to create identifier for {}", shares))
+        })?;
+        let mut splitter = ShareSplitter::new(threshold, shares)?;
+        for (identifier, writer) in (1u8..).zip(writers.iter_mut()) {
+            writer.write_all(&[identifier, threshold_byte, self.used as u8])?;
         }
-        for i in 0..shares[0].one_values.len() {
-            for (j, share) in shares.iter().enumerate() {
-                share_bytes[j][0] = share.identifier;
-                share_bytes[j][1] = share.one_values.data[i];
+
+        let mut temp = Zeroizing::new(vec![0u8; shares]);
+        let row_length = self.zero_values.axes[1];
+        let mut rows = Zeroizing::new(vec![vec![0u8; row_length]; shares]);
+        let total = (self.zero_values.axes[0] + self.one_values.axes[0]) as u64;
+        let values = self.zero_values.data.chunks(row_length);
+        for (done, row) in (1..).zip(values.chain(self.one_values.data.chunks(row_length))) {
+            progress.check()?;
+            for (j, b) in row.iter().enumerate() {
+                splitter.split(*b, &mut rng, &mut temp);
+                for (o, t) in rows.iter_mut().zip(temp.iter()) {
+                    o[j] = *t;
+                }
+            }
+            for (writer, o) in writers.iter_mut().zip(rows.iter()) {
+                writer.write_all(o)?;
             }
-            out.one_values.data[i] = combine_shares::<Gf256, u8, [u8; 2]>(&share_bytes)?.0;
+            progress.report(done, total);
         }
-        Ok(out)
+        Ok(())
+    }
+
+    /// Reconstruct the signing key from the secret shares created by `split`
+    pub fn combine(shares: &[SigningKeyShare<T>]) -> LamportResult<Self> {
+        CombineContext::new().combine_signing_key(shares)
     }
 }
 
@@ -225,7 +364,8 @@ pub struct SigningKeyShare<T: LamportDigest> {
 }
 
 serde_impl!(SigningKeyShare);
-vec_impl!(SigningKeyShare);
+vec_impl!(@owned SigningKeyShare);
+armor_impl!(SigningKeyShare, "LAMPORT PRIVATE KEY SHARE");
 
 impl<T: LamportDigest> Zeroize for SigningKeyShare<T> {
     fn zeroize(&mut self) {
@@ -235,21 +375,40 @@ impl<T: LamportDigest> Zeroize for SigningKeyShare<T> {
 }
 
 impl<T: LamportDigest> SigningKeyShare<T> {
+    /// The identifier of this share, which is its x-coordinate.
+    pub fn identifier(&self) -> u8 {
+        self.identifier
+    }
+
+    /// The number of shares required to sign or recombine the key.
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Has this share been used.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Marks the share as used so it refuses to sign.
+    ///
+    /// Use this to synchronize with an external record that the share has
+    /// already signed. There is no way to clear the flag again.
+    pub fn mark_used(&mut self) {
+        self.used = true;
+    }
+
     /// Signs the data to create a [`SignatureShare`].
     pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<SignatureShare<T>> {
-        let mut s = SigningKey::<T> {
-            zero_values: self.zero_values.clone(),
-            one_values: self.one_values.clone(),
-            used: self.used,
-            algorithm: PhantomData,
-        };
-        let signature = s.sign(data)?;
+        let data_hash = T::digest(data.as_ref());
+        let signature =
+            sign_values::<T>(&self.zero_values, &self.one_values, self.used, &data_hash)?;
 
         self.used = true;
         Ok(SignatureShare {
             identifier: self.identifier,
             threshold: self.threshold,
-            data: signature.data,
+            data: signature,
             algorithm: PhantomData,
         })
     }
@@ -271,6 +430,7 @@ impl<T: LamportDigest> SigningKeyShare<T> {
 
     /// Constructs a [`SigningKeyShare`] from canonical bytes.
     pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
         let input = input.as_ref();
         let bits = T::digest_size_in_bits();
         let bytes = bits / 8;
@@ -297,4 +457,73 @@ impl<T: LamportDigest> SigningKeyShare<T> {
             algorithm: PhantomData,
         })
     }
+
+    /// Constructs a [`SigningKeyShare`] from canonical bytes, taking ownership of the buffer.
+    ///
+    /// Like [`SigningKey::from_vec`], the values are split in place and the
+    /// buffer is zeroized on error.
+    pub fn from_vec(input: Vec<u8>) -> LamportResult<Self> {
+        let mut input = Zeroizing::new(input);
+        check_security::<T>()?;
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+
+        if input.len() != bits * bytes * 2 + 3 || input[0] == 0 || input[1] < 2 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let identifier = input[0];
+        let threshold = input[1];
+        let used = input[2] == 1;
+        let (zero_values, one_values) =
+            separate_owned_one_and_zero_values(std::mem::take(&mut *input), 3, bytes);
+        Ok(Self {
+            identifier,
+            used,
+            threshold,
+            zero_values,
+            one_values,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// Selects the secret value for each bit of `data_hash` from borrowed key values.
+///
+/// Shared by every key type so signing never copies the secret values.
+pub(crate) fn sign_values<T: LamportDigest>(
+    zero_values: &MultiVec<u8, 2>,
+    one_values: &MultiVec<u8, 2>,
+    used: bool,
+    data_hash: &[u8],
+) -> LamportResult<MultiVec<u8, 2>> {
+    if used {
+        return Err(LamportError::PrivateKeyReuseError);
+    }
+    check_digest::<T>(data_hash)?;
+
+    let bits = T::digest_size_in_bits();
+    let bytes = bits / 8;
+    let mut data = MultiVec::fill([bits, bytes], 0);
+    // Ensure runtime is independent of secret keys
+    // Ensure code access patterns are independent of secret keys
+    // Ensure data access patterns are independent of secret keys
+    {
+        let mut signature_iter = data.iter_mut();
+        let mut zero_iter = zero_values.iter();
+        let mut one_iter = one_values.iter();
+        for byte in data_hash.iter() {
+            for j in 0..8 {
+                let b = (*byte >> j) & 1;
+                let choice = Choice::from(b);
+                for _ in 0..bytes {
+                    *signature_iter.next().expect("more values") = u8::conditional_select(
+                        zero_iter.next().expect("more values"),
+                        one_iter.next().expect("more values"),
+                        choice,
+                    );
+                }
+            }
+        }
+    }
+    Ok(data)
 }
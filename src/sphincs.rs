@@ -0,0 +1,419 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::fors::{ForsParams, ForsSignature, ForsSigningKey};
+use crate::hash::{check_digest_size, check_security};
+use crate::limits::Limits;
+use crate::xmss_mt::{XmssMtParams, XmssMtSignature, XmssMtSigningKey, XmssMtVerifyingKey};
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec, SubtreeCache};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const PRF_DOMAIN: &[u8] = b"lamport_signature_plus sphincs prf v1";
+const DIGEST_DOMAIN: &[u8] = b"lamport_signature_plus sphincs digest v1";
+const INDEX_DOMAIN: &[u8] = b"lamport_signature_plus sphincs index v1";
+const FORS_SECRET_DOMAIN: &[u8] = b"lamport_signature_plus sphincs fors secret v1";
+const FORS_SEED_DOMAIN: &[u8] = b"lamport_signature_plus sphincs fors seed v1";
+
+/// The hypertree and FORS parameters of a [`SphincsSigningKey`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SphincsParams {
+    hypertree: XmssMtParams,
+    fors: ForsParams,
+}
+
+impl SphincsParams {
+    /// Combines a hypertree of `2^height` FORS keys with the FORS parameters.
+    ///
+    /// Each signature uses the FORS key at a pseudorandom index, so the
+    /// height should leave few repeats over the key's lifetime, e.g. a
+    /// height of 60 with FORS `k = 14, a = 12` for `2^40` signatures.
+    pub fn new(hypertree: XmssMtParams, fors: ForsParams) -> Self {
+        Self { hypertree, fors }
+    }
+
+    /// The hypertree parameters.
+    pub fn hypertree(&self) -> XmssMtParams {
+        self.hypertree
+    }
+
+    /// The FORS parameters.
+    pub fn fors(&self) -> ForsParams {
+        self.fors
+    }
+
+    fn to_bytes(self) -> [u8; 5] {
+        let hypertree = self.hypertree.to_bytes();
+        let fors = self.fors.to_bytes();
+        [hypertree[0], hypertree[1], hypertree[2], fors[0], fors[1]]
+    }
+
+    fn from_bytes<T: LamportDigest>(input: &[u8]) -> Option<Self> {
+        let hypertree = XmssMtParams::from_bytes(input.get(..3)?)?;
+        let (fors, _) = ForsParams::from_bytes::<T>(input.get(3..5)?)?;
+        Some(Self { hypertree, fors })
+    }
+}
+
+/// A stateless many-time signing key in the style of SPHINCS+.
+///
+/// Each message is signed by a FORS few-time key chosen pseudorandomly from
+/// the message and a secret PRF key, and the FORS public key is certified
+/// by a hypertree of WOTS+ keys as in [`XmssMtSigningKey`]. Every secret is
+/// derived from seeds, so the key never changes and there is no index to
+/// persist: restoring an old copy of the key is safe. A FORS key that is
+/// picked for several messages only loses security gradually, see
+/// [`ForsParams::security_bits`].
+///
+/// Signing takes `&mut self` only to reuse cached hypertree subtrees.
+///
+/// The construction follows SPHINCS+ but does not produce FIPS 205
+/// SLH-DSA signatures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SphincsSigningKey<T: LamportDigest> {
+    pub(crate) fors: ForsParams,
+    pub(crate) sk_prf: Vec<u8>,
+    pub(crate) hypertree: XmssMtSigningKey<T>,
+}
+
+serde_impl!(SphincsSigningKey);
+vec_impl!(SphincsSigningKey);
+
+impl<T: LamportDigest> Zeroize for SphincsSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.sk_prf.zeroize();
+        self.hypertree.zeroize();
+    }
+}
+
+impl<T: LamportDigest> SphincsSigningKey<T> {
+    /// Generates a key by building the top subtree of the hypertree.
+    pub fn generate(
+        params: SphincsParams,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let n = params.fors.check::<T>()?;
+        let hypertree = XmssMtSigningKey::generate(params.hypertree, &mut rng)?;
+        let mut sk_prf = vec![0u8; n];
+        rng.fill_bytes(&mut sk_prf);
+        Ok(Self {
+            fors: params.fors,
+            sk_prf,
+            hypertree,
+        })
+    }
+
+    /// The parameters.
+    pub fn params(&self) -> SphincsParams {
+        SphincsParams::new(self.hypertree.params, self.fors)
+    }
+
+    /// The public key.
+    pub fn verifying_key(&self) -> SphincsVerifyingKey<T> {
+        SphincsVerifyingKey {
+            fors: self.fors,
+            hypertree: self.hypertree.verifying_key(),
+        }
+    }
+
+    /// Signs the data.
+    ///
+    /// Signatures are deterministic: signing the same data twice gives the
+    /// same signature.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<SphincsSignature<T>> {
+        let data = data.as_ref();
+        let randomizer = T::digest_concat(&[PRF_DOMAIN, &self.sk_prf, data]);
+        let verifying_key = self.verifying_key();
+        let (digest, index) = verifying_key.digest_and_index(&randomizer, data);
+        let fors_key = self.fors_key(index);
+        let fors = fors_key.sign_digest(&digest)?;
+        let hypertree = self.hypertree.sign_hash(index, fors_key.root())?;
+        Ok(SphincsSignature {
+            randomizer,
+            fors,
+            hypertree,
+        })
+    }
+
+    /// Derives the FORS key at the hypertree leaf `index` from the secret seed.
+    fn fors_key(&self, index: u64) -> ForsSigningKey<T> {
+        let n = self.sk_prf.len();
+        let rows = self.fors.k() * self.fors.t();
+        let data = (0..rows)
+            .flat_map(|row| {
+                let row = u32::try_from(row).expect("at most 2^22 FORS values");
+                T::digest_concat(&[
+                    FORS_SECRET_DOMAIN,
+                    &self.hypertree.sk_seed,
+                    &index.to_be_bytes(),
+                    &row.to_be_bytes(),
+                ])
+            })
+            .collect();
+        ForsSigningKey {
+            params: self.fors,
+            max_uses: 1,
+            uses: 0,
+            pub_seed: fors_seed::<T>(&self.hypertree.pub_seed, index),
+            values: MultiVec {
+                data,
+                axes: [rows, n],
+            },
+            algorithm: PhantomData,
+        }
+    }
+
+    /// Converts the [`SphincsSigningKey`] to canonical bytes.
+    ///
+    /// The format is the hypertree height, layers and `log2(w)`, and the FORS
+    /// `k` and `a`, as single bytes, then the secret seed, PRF key, public
+    /// seed and root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let n = self.sk_prf.len();
+        let mut bytes = Vec::with_capacity(5 + 4 * n);
+        bytes.extend_from_slice(&self.params().to_bytes());
+        bytes.extend_from_slice(&self.hypertree.sk_seed);
+        bytes.extend_from_slice(&self.sk_prf);
+        bytes.extend_from_slice(&self.hypertree.pub_seed);
+        bytes.extend_from_slice(&self.hypertree.root);
+        bytes
+    }
+
+    /// Constructs a [`SphincsSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let n = T::digest_size_in_bits() / 8;
+        let params =
+            SphincsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 5 + 4 * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let mut parts = input[5..].chunks_exact(n).map(<[u8]>::to_vec);
+        let mut next = || parts.next().ok_or(LamportError::InvalidPrivateKeyBytes);
+        let sk_seed = next()?;
+        let sk_prf = next()?;
+        let pub_seed = next()?;
+        let root = next()?;
+        Ok(Self {
+            fors: params.fors,
+            sk_prf,
+            hypertree: XmssMtSigningKey {
+                params: params.hypertree,
+                sk_seed,
+                pub_seed,
+                root,
+                next_index: 0,
+                cache: SubtreeCache::new(),
+                algorithm: PhantomData,
+            },
+        })
+    }
+}
+
+/// The public seed of the FORS key at the hypertree leaf `index`.
+fn fors_seed<T: LamportDigest>(pub_seed: &[u8], index: u64) -> Vec<u8> {
+    T::digest_concat(&[FORS_SEED_DOMAIN, pub_seed, &index.to_be_bytes()])
+}
+
+/// The public key of a [`SphincsSigningKey`], the hypertree public seed and root.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SphincsVerifyingKey<T: LamportDigest> {
+    pub(crate) fors: ForsParams,
+    pub(crate) hypertree: XmssMtVerifyingKey<T>,
+}
+
+serde_impl!(SphincsVerifyingKey);
+vec_impl!(SphincsVerifyingKey);
+
+impl<T: LamportDigest> SphincsVerifyingKey<T> {
+    /// The parameters.
+    pub fn params(&self) -> SphincsParams {
+        SphincsParams::new(self.hypertree.params, self.fors)
+    }
+
+    /// Verifies the signature.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &SphincsSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        if signature.fors.params != self.fors {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (digest, index) = self.digest_and_index(&signature.randomizer, data.as_ref());
+        if signature.hypertree.index != index {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let fors_root = signature
+            .fors
+            .recover_root(&fors_seed::<T>(&self.hypertree.pub_seed, index), &digest);
+        let root = self
+            .hypertree
+            .recover_root(&signature.hypertree, fors_root)?;
+        if root.ct_eq(&self.hypertree.root).into() {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// The FORS message digest and the hypertree leaf of the FORS key.
+    fn digest_and_index(&self, randomizer: &[u8], data: &[u8]) -> (Vec<u8>, u64) {
+        let key = [&self.hypertree.pub_seed[..], &self.hypertree.root].concat();
+        let digest = T::digest_concat(&[DIGEST_DOMAIN, randomizer, &key, data]);
+        let index_digest = T::digest_concat(&[INDEX_DOMAIN, randomizer, &key, data]);
+        let mut index = [0u8; 8];
+        index.copy_from_slice(&index_digest[..8]);
+        let index = u64::from_be_bytes(index) & (self.hypertree.params.capacity() - 1);
+        (digest, index)
+    }
+
+    /// Converts the [`SphincsVerifyingKey`] to canonical bytes, the
+    /// parameters as in [`SphincsSigningKey::to_bytes`], then the public
+    /// seed and root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(5 + 2 * self.hypertree.root.len());
+        bytes.extend_from_slice(&self.params().to_bytes());
+        bytes.extend_from_slice(&self.hypertree.pub_seed);
+        bytes.extend_from_slice(&self.hypertree.root);
+        bytes
+    }
+
+    /// Constructs a [`SphincsVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let n = T::digest_size_in_bits() / 8;
+        let params =
+            SphincsParams::from_bytes::<T>(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 5 + 2 * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (pub_seed, root) = input[5..].split_at(n);
+        Ok(Self {
+            fors: params.fors,
+            hypertree: XmssMtVerifyingKey {
+                params: params.hypertree,
+                pub_seed: pub_seed.to_vec(),
+                root: root.to_vec(),
+                algorithm: PhantomData,
+            },
+        })
+    }
+}
+
+/// A signature generated by [`SphincsSigningKey`].
+///
+/// It holds the randomizer that selects the FORS key, the FORS signature of
+/// the message digest, and the hypertree signature of the FORS public key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SphincsSignature<T: LamportDigest> {
+    pub(crate) randomizer: Vec<u8>,
+    pub(crate) fors: ForsSignature<T>,
+    pub(crate) hypertree: XmssMtSignature<T>,
+}
+
+serde_impl!(@limits SphincsSignature);
+vec_impl!(SphincsSignature);
+
+impl<T: LamportDigest> SphincsSignature<T> {
+    /// The hypertree leaf of the FORS key that made the signature.
+    pub fn index(&self) -> u64 {
+        self.hypertree.index
+    }
+
+    /// Converts the [`SphincsSignature`] to canonical bytes, the randomizer,
+    /// then [`ForsSignature::to_bytes`] and [`XmssMtSignature::to_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.randomizer.clone();
+        bytes.extend_from_slice(&self.fors.to_bytes());
+        bytes.extend_from_slice(&self.hypertree.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`SphincsSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`SphincsSignature`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let n = T::digest_size_in_bits() / 8;
+        if input.len() < n {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (randomizer, rest) = input.split_at(n);
+        let (params, _) =
+            ForsParams::from_bytes::<T>(rest).ok_or(LamportError::InvalidSignatureBytes)?;
+        let fors_length = 2 + params.k() * (params.a() as usize + 1) * n;
+        if rest.len() < fors_length {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (fors, hypertree) = rest.split_at(fors_length);
+        Ok(Self {
+            randomizer: randomizer.to_vec(),
+            fors: ForsSignature::from_bytes(fors)?,
+            hypertree: XmssMtSignature::from_bytes(hypertree)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn sphincs() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let params = SphincsParams::new(
+            XmssMtParams::new(8, 2, 16).unwrap(),
+            ForsParams::new(8, 4).unwrap(),
+        );
+        let mut sk = SphincsSigningKey::<D>::generate(params, &mut rng).unwrap();
+        let pk = sk.verifying_key();
+        let key_bytes = sk.to_bytes();
+
+        let mut indices = Vec::new();
+        for i in 0..4u8 {
+            let signature = sk.sign([i]).unwrap();
+            assert!(pk.verify(&signature, [i]).is_ok());
+            assert!(pk.verify(&signature, [i + 1]).is_err());
+            indices.push(signature.index());
+        }
+        indices.dedup();
+        assert!(indices.len() > 1);
+        // Signing has no state, so the key bytes never change
+        assert_eq!(sk.to_bytes(), key_bytes);
+
+        let signature = sk.sign(b"stateless").unwrap();
+        let mut restored = SphincsSigningKey::<D>::from_bytes(&key_bytes).unwrap();
+        assert_eq!(
+            restored.sign(b"stateless").unwrap().to_bytes(),
+            signature.to_bytes()
+        );
+
+        let mut bytes = signature.to_bytes();
+        let pk = SphincsVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        let decoded = SphincsSignature::<D>::from_bytes(&bytes).unwrap();
+        assert!(pk.verify(&decoded, b"stateless").is_ok());
+        assert!(SphincsSignature::<D>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        bytes[0] ^= 1;
+        let tampered = SphincsSignature::<D>::from_bytes(&bytes).unwrap();
+        assert!(pk.verify(&tampered, b"stateless").is_err());
+        bytes[0] ^= 1;
+        bytes[40] ^= 1;
+        let tampered = SphincsSignature::<D>::from_bytes(&bytes).unwrap();
+        assert!(pk.verify(&tampered, b"stateless").is_err());
+    }
+}
@@ -0,0 +1,123 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{KeyId, LamportDigest, LamportError, LamportResult, SigningKey, VerifyingKey};
+use std::collections::BTreeMap;
+use zeroize::Zeroize;
+
+/// The record left behind when a [`SigningKey`] is destroyed with [`SigningKey::burn`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tombstone {
+    /// The fingerprint of the destroyed key.
+    pub key_id: KeyId,
+    /// When the key was destroyed, in seconds since the Unix epoch.
+    pub burned_at: u64,
+}
+
+/// Persistent storage for the lifecycle records of signing keys.
+///
+/// Implement this over a database or file to keep an auditable history of
+/// decommissioned keys.
+pub trait StateStore {
+    /// Records that a key was destroyed.
+    fn record_tombstone(&mut self, tombstone: Tombstone) -> LamportResult<()>;
+
+    /// Returns the tombstone for the key, if it was destroyed.
+    fn tombstone(&self, key_id: &KeyId) -> LamportResult<Option<Tombstone>>;
+}
+
+/// A [`StateStore`] kept in memory, useful for tests and short lived processes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStateStore {
+    tombstones: BTreeMap<KeyId, Tombstone>,
+}
+
+impl MemoryStateStore {
+    /// Constructs an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over the recorded tombstones in [`KeyId`] order.
+    pub fn tombstones(&self) -> impl Iterator<Item = &Tombstone> {
+        self.tombstones.values()
+    }
+}
+
+impl StateStore for MemoryStateStore {
+    fn record_tombstone(&mut self, tombstone: Tombstone) -> LamportResult<()> {
+        self.tombstones.entry(tombstone.key_id).or_insert(tombstone);
+        Ok(())
+    }
+
+    fn tombstone(&self, key_id: &KeyId) -> LamportResult<Option<Tombstone>> {
+        Ok(self.tombstones.get(key_id).copied())
+    }
+}
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Destroys the key and records a [`Tombstone`] for it in the store.
+    ///
+    /// `burned_at` is the destruction time in seconds since the Unix epoch.
+    /// The key material is zeroized only once the store has recorded the
+    /// tombstone. If the store fails, the key is handed back untouched with
+    /// the store's error so the caller can retry.
+    #[allow(clippy::result_large_err)]
+    pub fn burn<S: StateStore + ?Sized>(
+        mut self,
+        store: &mut S,
+        burned_at: u64,
+    ) -> Result<Tombstone, (Self, LamportError)> {
+        let tombstone = Tombstone {
+            key_id: VerifyingKey::from(&self).fingerprint(),
+            burned_at,
+        };
+        if let Err(e) = store.record_tombstone(tombstone) {
+            return Err((self, e));
+        }
+        self.zeroize();
+        Ok(tombstone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[derive(Debug)]
+    struct FailingStore;
+
+    impl StateStore for FailingStore {
+        fn record_tombstone(&mut self, _tombstone: Tombstone) -> LamportResult<()> {
+            Err(LamportError::General("store is offline".to_string()))
+        }
+
+        fn tombstone(&self, _key_id: &KeyId) -> LamportResult<Option<Tombstone>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn burn() {
+        let rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+        let original = sk.to_bytes();
+
+        // A store failure hands the key back intact
+        let (sk, error) = sk.burn(&mut FailingStore, 1_700_000_000).unwrap_err();
+        assert_eq!(error, LamportError::General("store is offline".to_string()));
+        assert_eq!(sk.to_bytes(), original);
+
+        let mut store = MemoryStateStore::new();
+        let tombstone = sk.burn(&mut store, 1_700_000_000).unwrap();
+        assert_eq!(tombstone.key_id, pk.fingerprint());
+        assert_eq!(tombstone.burned_at, 1_700_000_000);
+        assert_eq!(store.tombstone(&pk.fingerprint()).unwrap(), Some(tombstone));
+        assert_eq!(store.tombstones().count(), 1);
+    }
+}
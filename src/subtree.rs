@@ -0,0 +1,185 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{LamportError, LamportResult};
+
+/// A complete binary hash tree of `2^height` leaves held in memory.
+///
+/// The leaf and node hashes are supplied by the caller, so the same tree
+/// backs every many-time scheme built from one-time keys. Node hashes
+/// receive their level, counted from 1 just above the leaves, and their
+/// index within that level for domain separation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MerkleSubtree {
+    levels: Vec<Vec<Vec<u8>>>,
+}
+
+impl MerkleSubtree {
+    /// Builds the tree, calling `leaf` for each leaf index in order.
+    pub fn build<L, N>(height: u32, mut leaf: L, mut node: N) -> Self
+    where
+        L: FnMut(u32) -> Vec<u8>,
+        N: FnMut(u32, u32, &[u8], &[u8]) -> Vec<u8>,
+    {
+        let mut levels = Vec::with_capacity(height as usize + 1);
+        levels.push((0..1u32 << height).map(&mut leaf).collect::<Vec<_>>());
+        for level in 1..=height {
+            let below = &levels[level as usize - 1];
+            let nodes = below
+                .chunks_exact(2)
+                .zip(0u32..)
+                .map(|(pair, index)| node(level, index, &pair[0], &pair[1]))
+                .collect();
+            levels.push(nodes);
+        }
+        Self { levels }
+    }
+
+    /// The number of levels above the leaves.
+    pub fn height(&self) -> u32 {
+        u32::try_from(self.levels.len() - 1).expect("height fits in u32")
+    }
+
+    /// The root hash.
+    pub fn root(&self) -> &[u8] {
+        &self.levels[self.levels.len() - 1][0]
+    }
+
+    /// The leaf hash at `index`.
+    pub fn leaf(&self, index: u32) -> Option<&[u8]> {
+        self.levels[0].get(index as usize).map(Vec::as_slice)
+    }
+
+    /// The sibling of each node on the path from the leaf to the root, leaf level first.
+    pub fn auth_path(&self, index: u32) -> LamportResult<Vec<Vec<u8>>> {
+        if index as usize >= self.levels[0].len() {
+            return Err(LamportError::General(format!(
+                "leaf {} is outside of the subtree",
+                index
+            )));
+        }
+        Ok(self.levels[..self.levels.len() - 1]
+            .iter()
+            .zip(0..)
+            .map(|(level, depth)| level[((index >> depth) ^ 1) as usize].clone())
+            .collect())
+    }
+
+    /// Recomputes the root from a leaf hash and its [`MerkleSubtree::auth_path`].
+    pub fn root_from_path<N>(index: u32, leaf: &[u8], path: &[Vec<u8>], mut node: N) -> Vec<u8>
+    where
+        N: FnMut(u32, u32, &[u8], &[u8]) -> Vec<u8>,
+    {
+        let mut current = leaf.to_vec();
+        for (sibling, level) in path.iter().zip(1u32..) {
+            let position = index >> (level - 1);
+            current = if position & 1 == 0 {
+                node(level, position >> 1, &current, sibling)
+            } else {
+                node(level, position >> 1, sibling, &current)
+            };
+        }
+        current
+    }
+}
+
+/// Keeps the subtree in use on each layer of a hypertree.
+///
+/// Consecutive signatures of a multi-tree scheme walk the leaves of the
+/// bottom subtree in order and only move to the next subtree of a higher
+/// layer every `2^height` signatures, so one cached subtree per layer
+/// avoids rebuilding trees on every signature.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtreeCache {
+    layers: Vec<Option<(u64, MerkleSubtree)>>,
+}
+
+impl SubtreeCache {
+    /// Constructs an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cached subtree `tree` of `layer`, building and caching it
+    /// in place of that layer's previous subtree if needed.
+    pub fn get_or_build<F>(&mut self, layer: usize, tree: u64, build: F) -> &MerkleSubtree
+    where
+        F: FnOnce() -> MerkleSubtree,
+    {
+        if self.layers.len() <= layer {
+            self.layers.resize(layer + 1, None);
+        }
+        if !self.contains(layer, tree) {
+            self.layers[layer] = Some((tree, build()));
+        }
+        &self.layers[layer]
+            .as_ref()
+            .expect("the slot was just filled")
+            .1
+    }
+
+    /// Returns true if subtree `tree` of `layer` is cached.
+    pub fn contains(&self, layer: usize, tree: u64) -> bool {
+        matches!(self.layers.get(layer), Some(Some((cached, _))) if *cached == tree)
+    }
+
+    /// The number of cached subtrees.
+    pub fn len(&self) -> usize {
+        self.layers.iter().flatten().count()
+    }
+
+    /// Returns true if no subtree is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops every cached subtree.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Sha256;
+
+    #[test]
+    fn merkle_subtree() {
+        let node = |level: u32, index: u32, left: &[u8], right: &[u8]| {
+            <Sha256 as sha2::Digest>::digest(
+                [&level.to_be_bytes(), &index.to_be_bytes(), left, right].concat(),
+            )
+            .to_vec()
+        };
+        let tree = MerkleSubtree::build(3, |leaf| vec![leaf.to_le_bytes()[0]; 32], node);
+        assert_eq!(tree.height(), 3);
+        assert_eq!(tree.leaf(5), Some(&[5u8; 32][..]));
+        for index in 0..8 {
+            let path = tree.auth_path(index).unwrap();
+            assert_eq!(path.len(), 3);
+            let root = MerkleSubtree::root_from_path(index, tree.leaf(index).unwrap(), &path, node);
+            assert_eq!(root, tree.root());
+            let wrong =
+                MerkleSubtree::root_from_path(index ^ 1, tree.leaf(index).unwrap(), &path, node);
+            assert_ne!(wrong, tree.root());
+        }
+        assert!(tree.auth_path(8).is_err());
+
+        let mut cache = SubtreeCache::new();
+        let mut builds = 0;
+        for tree in [0, 0, 1, 1] {
+            cache.get_or_build(2, tree, || {
+                builds += 1;
+                MerkleSubtree::build(1, |leaf| vec![leaf.to_le_bytes()[0]; 32], node)
+            });
+        }
+        assert_eq!(builds, 2);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(2, 1));
+        assert!(!cache.contains(2, 0));
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Deterministic keys and randomness for tests.
+//!
+//! These helpers are only available with the `test_utils` feature. Every
+//! value is derived from a caller supplied tag, so tests get reproducible
+//! fixtures without choosing their own seeds or RNG crates. The derivation is
+//! stable across releases with the same major version.
+//!
+//! Nothing here is suitable for production keys.
+use crate::{LamportDigest, LamportResult, SigningKey, SigningKeyShare, VerifyingKey};
+use rand::SeedableRng;
+use sha2::{Digest, Sha256};
+
+/// The deterministic RNG returned by [`test_rng`].
+pub type TestRng = rand_chacha::ChaCha20Rng;
+
+const TEST_RNG_DOMAIN: &[u8] = b"lamport_signature_plus test rng v1";
+
+/// Returns a [`TestRng`] seeded with SHA-256 of a domain separator and `tag`.
+pub fn test_rng<B: AsRef<[u8]>>(tag: B) -> TestRng {
+    let mut hasher = Sha256::new();
+    hasher.update(TEST_RNG_DOMAIN);
+    hasher.update(tag.as_ref());
+    TestRng::from_seed(hasher.finalize().into())
+}
+
+/// Returns the [`SigningKey`] for `tag`.
+///
+/// # Panics
+///
+/// Panics if the digest is below the minimum security level, see [`LamportDigest`].
+pub fn test_signing_key<T: LamportDigest, B: AsRef<[u8]>>(tag: B) -> SigningKey<T> {
+    SigningKey::random(test_rng(tag)).expect("digest is below the minimum security level")
+}
+
+/// Returns the [`SigningKey`] for `tag` and its [`VerifyingKey`].
+pub fn test_keypair<T: LamportDigest, B: AsRef<[u8]>>(tag: B) -> (SigningKey<T>, VerifyingKey<T>) {
+    let signing_key = test_signing_key(tag);
+    let verifying_key = VerifyingKey::from(&signing_key);
+    (signing_key, verifying_key)
+}
+
+/// Returns [`SigningKeyShare`]s of the [`test_signing_key`] for `tag`.
+///
+/// The shares use their own RNG stream, so the key is the same as the one
+/// returned by [`test_signing_key`] for the same tag.
+pub fn test_key_shares<T: LamportDigest, B: AsRef<[u8]>>(
+    tag: B,
+    threshold: usize,
+    shares: usize,
+) -> LamportResult<Vec<SigningKeyShare<T>>> {
+    let tag = tag.as_ref();
+    let signing_key = test_signing_key::<T, _>(tag);
+    let mut share_tag = tag.to_vec();
+    share_tag.extend_from_slice(b"/shares");
+    signing_key.split(threshold, shares, test_rng(share_tag))
+}
@@ -0,0 +1,169 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::{
+    KeyId, LamportDigest, LamportError, LamportResult, SigningKey, VerifyingKey, SEED_BYTES,
+};
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+
+const SEED_COMMITMENT_DOMAIN: &[u8] = b"lamport_signature_plus keygen seed commitment v1";
+const DIGEST_ID_DOMAIN: &[u8] = b"lamport_signature_plus keygen digest id v1";
+
+/// A record of how a [`SigningKey`] was derived with [`SigningKey::from_seed`].
+///
+/// The transcript commits to the seed, identifies the digest by its output
+/// on a fixed input and records the [`KeyId`] of the resulting key. It does
+/// not reveal the seed, so it can be published next to the [`VerifyingKey`].
+/// An auditor later given the seed can re-run the derivation with
+/// [`KeygenTranscript::audit`] to confirm the published key was honestly derived.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeygenTranscript<T: LamportDigest> {
+    pub(crate) seed_commitment: [u8; 32],
+    pub(crate) digest_id: Vec<u8>,
+    pub(crate) key_id: KeyId,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(KeygenTranscript);
+vec_impl!(KeygenTranscript);
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Derives a [`SigningKey`] like [`SigningKey::from_seed`] and records a
+    /// [`KeygenTranscript`] of the derivation.
+    ///
+    /// To start from an RNG, fill a seed from it and keep the seed for the auditor.
+    pub fn from_seed_with_transcript(
+        seed: &[u8; SEED_BYTES],
+    ) -> LamportResult<(SigningKey<T>, KeygenTranscript<T>)> {
+        let signing_key = Self::from_seed(seed)?;
+        let transcript = KeygenTranscript {
+            seed_commitment: seed_commitment(seed),
+            digest_id: digest_id::<T>(),
+            key_id: VerifyingKey::from(&signing_key).fingerprint(),
+            algorithm: PhantomData,
+        };
+        Ok((signing_key, transcript))
+    }
+}
+
+impl<T: LamportDigest> KeygenTranscript<T> {
+    /// The commitment to the seed.
+    pub fn seed_commitment(&self) -> [u8; 32] {
+        self.seed_commitment
+    }
+
+    /// The [`KeyId`] of the derived key.
+    pub fn key_id(&self) -> KeyId {
+        self.key_id
+    }
+
+    /// Checks the published [`VerifyingKey`] is the one this transcript records.
+    pub fn matches(&self, verifying_key: &VerifyingKey<T>) -> LamportResult<()> {
+        if self.digest_id != digest_id::<T>() {
+            return Err(LamportError::General(
+                "transcript was recorded with a different digest".to_string(),
+            ));
+        }
+        if verifying_key.fingerprint() != self.key_id {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        Ok(())
+    }
+
+    /// Re-runs the derivation from `seed` and checks it produces the
+    /// published [`VerifyingKey`] recorded by this transcript.
+    pub fn audit(
+        &self,
+        seed: &[u8; SEED_BYTES],
+        verifying_key: &VerifyingKey<T>,
+    ) -> LamportResult<()> {
+        self.matches(verifying_key)?;
+        if seed_commitment(seed) != self.seed_commitment {
+            return Err(LamportError::InvalidCommitment);
+        }
+        let (_, transcript) = SigningKey::<T>::from_seed_with_transcript(seed)?;
+        if transcript.key_id != self.key_id {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        Ok(())
+    }
+
+    /// Converts the transcript to canonical bytes.
+    ///
+    /// The format is the seed commitment, the digest identifier and the [`KeyId`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32 + self.digest_id.len() + KeyId::BYTES);
+        bytes.extend_from_slice(&self.seed_commitment);
+        bytes.extend_from_slice(&self.digest_id);
+        bytes.extend_from_slice(self.key_id.as_bytes());
+        bytes
+    }
+
+    /// Constructs a [`KeygenTranscript`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let digest_length = T::digest_size_in_bits() / 8;
+        if input.len() != 32 + digest_length + KeyId::BYTES {
+            return Err(LamportError::InvalidKeyIdBytes);
+        }
+        let mut seed_commitment = [0u8; 32];
+        seed_commitment.copy_from_slice(&input[..32]);
+        Ok(Self {
+            seed_commitment,
+            digest_id: input[32..32 + digest_length].to_vec(),
+            key_id: KeyId::from_bytes(&input[32 + digest_length..])?,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+fn seed_commitment(seed: &[u8; SEED_BYTES]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(SEED_COMMITMENT_DOMAIN);
+    hasher.update(seed);
+    hasher.finalize().into()
+}
+
+/// Identifies the digest by its output on a fixed input, since digests have no names.
+fn digest_id<T: LamportDigest>() -> Vec<u8> {
+    T::digest(DIGEST_ID_DOMAIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use sha3::Sha3_256;
+
+    #[test]
+    fn keygen_transcript() {
+        type D = LamportFixedDigest<Sha256>;
+        let seed = [9u8; SEED_BYTES];
+        let (sk, transcript) = SigningKey::<D>::from_seed_with_transcript(&seed).unwrap();
+        assert_eq!(
+            sk.to_bytes(),
+            SigningKey::<D>::from_seed(&seed).unwrap().to_bytes()
+        );
+        let pk = VerifyingKey::from(&sk);
+        assert_eq!(transcript.key_id(), pk.fingerprint());
+
+        let transcript = KeygenTranscript::<D>::from_bytes(transcript.to_bytes()).unwrap();
+        assert!(transcript.matches(&pk).is_ok());
+        assert!(transcript.audit(&seed, &pk).is_ok());
+        assert_eq!(
+            transcript.audit(&[8u8; SEED_BYTES], &pk).unwrap_err(),
+            LamportError::InvalidCommitment
+        );
+        let other = VerifyingKey::from(&SigningKey::<D>::from_seed(&[8u8; SEED_BYTES]).unwrap());
+        assert!(transcript.audit(&seed, &other).is_err());
+
+        let sha3 =
+            KeygenTranscript::<LamportFixedDigest<Sha3_256>>::from_bytes(transcript.to_bytes())
+                .unwrap();
+        let (sha3_sk, _) =
+            SigningKey::<LamportFixedDigest<Sha3_256>>::from_seed_with_transcript(&seed).unwrap();
+        assert!(sha3.matches(&VerifyingKey::from(&sha3_sk)).is_err());
+    }
+}
@@ -0,0 +1,242 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::limits::Limits;
+use crate::merkle::{data_leaf_hash, inclusion_path, subtree_hash};
+use crate::{
+    InclusionProof, LamportDigest, LamportError, LamportResult, Signature, SigningKey,
+    VerifyingKey, MERKLE_HASH_BYTES,
+};
+
+const TREE_DOMAIN: &[u8] = b"lamport_signature_plus tree root v1";
+
+/// One signature covering a batch of messages.
+///
+/// The messages are the leaves of an RFC 6962 Merkle tree and the one-time
+/// key signs only the root. Each message can later be checked on its own with
+/// the signature, the root and that message's [`InclusionProof`], so a single
+/// key can cover every file in a release manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TreeSignature<T: LamportDigest> {
+    pub(crate) root: [u8; MERKLE_HASH_BYTES],
+    pub(crate) signature: Signature<T>,
+    pub(crate) proofs: Vec<InclusionProof>,
+}
+
+serde_impl!(@limits TreeSignature);
+vec_impl!(TreeSignature);
+
+impl<T: LamportDigest> SigningKey<T> {
+    /// Signs every message in `messages` with this one-time key.
+    pub fn sign_tree(&mut self, messages: &[&[u8]]) -> LamportResult<TreeSignature<T>> {
+        if messages.is_empty() {
+            return Err(LamportError::General(
+                "a tree signature requires at least one message".to_string(),
+            ));
+        }
+        let hashes = messages
+            .iter()
+            .map(|m| data_leaf_hash(m))
+            .collect::<Vec<_>>();
+        let root = subtree_hash(&hashes);
+        let signature = self.sign(tree_message(&root))?;
+        let proofs = (0..hashes.len())
+            .map(|index| {
+                let mut path = Vec::new();
+                inclusion_path(index, &hashes, &mut path);
+                InclusionProof {
+                    index: index as u64,
+                    tree_size: hashes.len() as u64,
+                    path,
+                }
+            })
+            .collect();
+        Ok(TreeSignature {
+            root,
+            signature,
+            proofs,
+        })
+    }
+}
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Verifies one message of a [`TreeSignature`] from the signature, root and
+    /// the message's [`InclusionProof`].
+    pub fn verify_tree_message<B: AsRef<[u8]>>(
+        &self,
+        signature: &Signature<T>,
+        root: &[u8; MERKLE_HASH_BYTES],
+        proof: &InclusionProof,
+        message: B,
+    ) -> LamportResult<()> {
+        proof.verify_leaf_hash(data_leaf_hash(message.as_ref()), root)?;
+        self.verify(signature, tree_message(root))
+    }
+}
+
+impl<T: LamportDigest> TreeSignature<T> {
+    /// The Merkle root signed by the key.
+    pub fn root(&self) -> [u8; MERKLE_HASH_BYTES] {
+        self.root
+    }
+
+    /// The signature over the root.
+    pub fn signature(&self) -> &Signature<T> {
+        &self.signature
+    }
+
+    /// The inclusion proof of every message, in signing order.
+    pub fn proofs(&self) -> &[InclusionProof] {
+        &self.proofs
+    }
+
+    /// The inclusion proof of the message at `index`.
+    pub fn proof(&self, index: usize) -> Option<&InclusionProof> {
+        self.proofs.get(index)
+    }
+
+    /// Verifies the message at `index`.
+    pub fn verify_one<B: AsRef<[u8]>>(
+        &self,
+        key: &VerifyingKey<T>,
+        index: usize,
+        message: B,
+    ) -> LamportResult<()> {
+        let proof = self.proof(index).ok_or(LamportError::InvalidMerkleProof)?;
+        key.verify_tree_message(&self.signature, &self.root, proof, message)
+    }
+
+    /// Verifies every message, which must be given in signing order.
+    pub fn verify(&self, key: &VerifyingKey<T>, messages: &[&[u8]]) -> LamportResult<()> {
+        if messages.len() != self.proofs.len() {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+        let hashes = messages
+            .iter()
+            .map(|m| data_leaf_hash(m))
+            .collect::<Vec<_>>();
+        if subtree_hash(&hashes) != self.root {
+            return Err(LamportError::InvalidMerkleProof);
+        }
+        key.verify(&self.signature, tree_message(&self.root))
+    }
+
+    /// Converts the [`TreeSignature`] to canonical bytes.
+    ///
+    /// The format is the root, the signature, a big endian `u32` proof count
+    /// and each proof as a big endian `u32` length and its canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let signature = self.signature.to_bytes();
+        let mut bytes = Vec::with_capacity(MERKLE_HASH_BYTES + signature.len() + 4);
+        bytes.extend_from_slice(&self.root);
+        bytes.extend_from_slice(&signature);
+        let count = u32::try_from(self.proofs.len()).unwrap_or(u32::MAX);
+        bytes.extend_from_slice(&count.to_be_bytes());
+        for proof in &self.proofs {
+            let proof = proof.to_bytes();
+            let length = u32::try_from(proof.len()).unwrap_or(u32::MAX);
+            bytes.extend_from_slice(&length.to_be_bytes());
+            bytes.extend_from_slice(&proof);
+        }
+        bytes
+    }
+
+    /// Constructs a [`TreeSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`TreeSignature`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let bits = T::digest_size_in_bits();
+        let signature_length = bits * (bits / 8);
+        if input.len() < MERKLE_HASH_BYTES + signature_length + 4 {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (root, rest) = input.split_at(MERKLE_HASH_BYTES);
+        let (signature, rest) = rest.split_at(signature_length);
+        let (count, mut rest) = rest.split_at(4);
+        let root = <[u8; MERKLE_HASH_BYTES]>::try_from(root)
+            .map_err(|_| LamportError::InvalidSignatureBytes)?;
+        let signature = Signature::from_bytes(signature)?;
+        let count = u32::from_be_bytes(
+            <[u8; 4]>::try_from(count).map_err(|_| LamportError::InvalidSignatureBytes)?,
+        );
+
+        let mut proofs = Vec::new();
+        for _ in 0..count {
+            if rest.len() < 4 {
+                return Err(LamportError::InvalidSignatureBytes);
+            }
+            let (length, tail) = rest.split_at(4);
+            let length = usize::try_from(u32::from_be_bytes(
+                <[u8; 4]>::try_from(length).map_err(|_| LamportError::InvalidSignatureBytes)?,
+            ))
+            .map_err(|_| LamportError::InvalidSignatureBytes)?;
+            if tail.len() < length {
+                return Err(LamportError::InvalidSignatureBytes);
+            }
+            let (proof, tail) = tail.split_at(length);
+            proofs.push(InclusionProof::from_bytes(proof)?);
+            rest = tail;
+        }
+        if !rest.is_empty() || proofs.is_empty() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            root,
+            signature,
+            proofs,
+        })
+    }
+}
+
+fn tree_message(root: &[u8; MERKLE_HASH_BYTES]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(TREE_DOMAIN.len() + MERKLE_HASH_BYTES);
+    message.extend_from_slice(TREE_DOMAIN);
+    message.extend_from_slice(root);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generate_keys, LamportFixedDigest};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn tree_signature() {
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+        let messages: [&[u8]; 5] = [b"a.tar.gz", b"b.tar.gz", b"c.tar.gz", b"d.tar.gz", b"e"];
+        let tree = sk.sign_tree(&messages).unwrap();
+        assert!(sk.sign_tree(&messages).is_err());
+        assert!(tree.verify(&pk, &messages).is_ok());
+        assert!(tree.verify(&pk, &messages[..4]).is_err());
+        for (i, message) in messages.iter().enumerate() {
+            assert!(tree.verify_one(&pk, i, message).is_ok());
+            assert!(pk
+                .verify_tree_message(
+                    tree.signature(),
+                    &tree.root(),
+                    tree.proof(i).unwrap(),
+                    message
+                )
+                .is_ok());
+        }
+        assert!(tree.verify_one(&pk, 0, b"b.tar.gz").is_err());
+        assert!(tree.verify_one(&pk, 5, b"e").is_err());
+
+        let bytes = tree.to_bytes();
+        let decoded = TreeSignature::<LamportFixedDigest<Sha256>>::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert!(
+            TreeSignature::<LamportFixedDigest<Sha256>>::from_bytes(&bytes[..bytes.len() - 1])
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,284 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size, check_security};
+use crate::signing::sign_values;
+use crate::utils::separate_one_and_zero_values;
+use crate::{
+    LamportDigest, LamportError, LamportResult, LamportTweakableDigest, MultiVec, Signature,
+};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+/// The number of bytes in the identifier of a [`TweakableSigningKey`].
+pub const TWEAK_IDENTIFIER_BYTES: usize = 32;
+
+/// A one-time signing key whose commitments use [`LamportTweakableDigest::tweak_hash`].
+///
+/// Each key has a random public identifier, and the secret value for row
+/// `i` and bit `b` is committed to at position `2 * i + b`. Signatures are
+/// ordinary [`Signature`]s, but only verify with a [`TweakableVerifyingKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TweakableSigningKey<T: LamportDigest> {
+    pub(crate) identifier: [u8; TWEAK_IDENTIFIER_BYTES],
+    pub(crate) zero_values: MultiVec<u8, 2>,
+    pub(crate) one_values: MultiVec<u8, 2>,
+    pub(crate) used: bool,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(TweakableSigningKey);
+vec_impl!(TweakableSigningKey);
+
+impl<T: LamportDigest> Zeroize for TweakableSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.zero_values.zeroize();
+        self.one_values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> TweakableSigningKey<T> {
+    /// Constructs a random [`TweakableSigningKey`] with a random identifier.
+    ///
+    /// Returns [`LamportError::InsecureDigest`] if the digest is below the
+    /// minimum security level, see [`LamportDigest`].
+    pub fn random(mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let mut identifier = [0u8; TWEAK_IDENTIFIER_BYTES];
+        rng.fill_bytes(&mut identifier);
+        let (zero_values, one_values) = T::random_pair(rng);
+        Ok(Self {
+            identifier,
+            zero_values,
+            one_values,
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The public identifier mixed into every commitment.
+    pub fn identifier(&self) -> [u8; TWEAK_IDENTIFIER_BYTES] {
+        self.identifier
+    }
+
+    /// Has the key already signed a message.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs the data.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<Signature<T>> {
+        let data_hash = T::digest(data.as_ref());
+        let data = sign_values::<T>(&self.zero_values, &self.one_values, self.used, &data_hash)?;
+        self.used = true;
+        Ok(Signature {
+            data,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`TweakableSigningKey`] to canonical bytes, the
+    /// identifier, the used flag, then the zero and one values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            TWEAK_IDENTIFIER_BYTES + 1 + self.zero_values.len() + self.one_values.len(),
+        );
+        bytes.extend_from_slice(&self.identifier);
+        bytes.push(self.used as u8);
+        bytes.extend_from_slice(&self.zero_values.data);
+        bytes.extend_from_slice(&self.one_values.data);
+        bytes
+    }
+
+    /// Constructs a [`TweakableSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != TWEAK_IDENTIFIER_BYTES + 1 + bits * bytes * 2 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (identifier, rest) = input.split_at(TWEAK_IDENTIFIER_BYTES);
+        let used = match rest[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(LamportError::InvalidPrivateKeyBytes),
+        };
+        let (zero_values, one_values) = separate_one_and_zero_values(&rest[1..], bytes);
+        Ok(Self {
+            identifier: identifier.try_into().expect("identifier length"),
+            zero_values,
+            one_values,
+            used,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// The public key of a [`TweakableSigningKey`], its identifier and the tweaked commitments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TweakableVerifyingKey<T: LamportDigest> {
+    pub(crate) identifier: [u8; TWEAK_IDENTIFIER_BYTES],
+    pub(crate) zero_values: MultiVec<u8, 2>,
+    pub(crate) one_values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(TweakableVerifyingKey);
+vec_impl!(TweakableVerifyingKey);
+
+impl<T: LamportTweakableDigest> From<&TweakableSigningKey<T>> for TweakableVerifyingKey<T> {
+    fn from(value: &TweakableSigningKey<T>) -> Self {
+        Self {
+            identifier: value.identifier,
+            zero_values: commitments::<T>(&value.identifier, &value.zero_values, 0),
+            one_values: commitments::<T>(&value.identifier, &value.one_values, 1),
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportTweakableDigest> TweakableVerifyingKey<T> {
+    /// Verifies the [`Signature`].
+    pub fn verify<B: AsRef<[u8]>>(&self, signature: &Signature<T>, data: B) -> LamportResult<()> {
+        let data_digest = T::digest(data.as_ref());
+        check_digest::<T>(&data_digest)?;
+        let bits = data_digest.len() * 8;
+        if signature.data.axes != [bits, data_digest.len()]
+            || signature.data.data.len() != self.one_values.len()
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let res = (0..bits).all(|offset| {
+            let side = u32::from((data_digest[offset / 8] >> (offset % 8)) & 1);
+            let hashed_value = T::tweak_hash(
+                &self.identifier,
+                position(offset, side),
+                &signature.data[offset],
+            );
+            let cmp = if side == 1 {
+                &self.one_values[offset]
+            } else {
+                &self.zero_values[offset]
+            };
+            hashed_value == cmp
+        });
+        if res {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+}
+
+impl<T: LamportDigest> TweakableVerifyingKey<T> {
+    /// The public identifier mixed into every commitment.
+    pub fn identifier(&self) -> [u8; TWEAK_IDENTIFIER_BYTES] {
+        self.identifier
+    }
+
+    /// Converts the [`TweakableVerifyingKey`] to canonical bytes, the
+    /// identifier, then the zero and one commitments.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            TWEAK_IDENTIFIER_BYTES + self.zero_values.len() + self.one_values.len(),
+        );
+        bytes.extend_from_slice(&self.identifier);
+        bytes.extend_from_slice(&self.zero_values.data);
+        bytes.extend_from_slice(&self.one_values.data);
+        bytes
+    }
+
+    /// Constructs a [`TweakableVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != TWEAK_IDENTIFIER_BYTES + bits * bytes * 2 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (identifier, rest) = input.split_at(TWEAK_IDENTIFIER_BYTES);
+        let (zero_values, one_values) = separate_one_and_zero_values(rest, bytes);
+        Ok(Self {
+            identifier: identifier.try_into().expect("identifier length"),
+            zero_values,
+            one_values,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// The position of the value for `row` and bit `side`.
+fn position(row: usize, side: u32) -> u32 {
+    u32::try_from(row).expect("at most 2^31 rows") * 2 + side
+}
+
+fn commitments<T: LamportTweakableDigest>(
+    identifier: &[u8],
+    values: &MultiVec<u8, 2>,
+    side: u32,
+) -> MultiVec<u8, 2> {
+    MultiVec {
+        data: values
+            .data
+            .chunks_exact(values.axes[1])
+            .enumerate()
+            .flat_map(|(row, value)| T::tweak_hash(identifier, position(row, side), value))
+            .collect(),
+        axes: values.axes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LamportFixedDigest, SigningKey, VerifyingKey};
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn tweakable() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut sk = TweakableSigningKey::<D>::random(&mut rng).unwrap();
+        let pk = TweakableVerifyingKey::from(&sk);
+        assert_eq!(pk.identifier(), sk.identifier());
+        let signature = sk.sign(b"tweaked").unwrap();
+        assert!(sk.used());
+        assert_eq!(
+            sk.sign(b"again").unwrap_err(),
+            LamportError::PrivateKeyReuseError
+        );
+        assert!(pk.verify(&signature, b"tweaked").is_ok());
+        assert!(pk.verify(&signature, b"other").is_err());
+
+        // The same secret values under another identifier commit differently
+        let mut other = sk.clone();
+        other.identifier[0] ^= 1;
+        let other = TweakableVerifyingKey::from(&other);
+        assert_ne!(other.one_values, pk.one_values);
+        let mut relabeled = pk.clone();
+        relabeled.identifier = other.identifier();
+        assert!(relabeled.verify(&signature, b"tweaked").is_err());
+        // and never match the untweaked commitments
+        let plain = VerifyingKey::from(&SigningKey::<D> {
+            zero_values: sk.zero_values.clone(),
+            one_values: sk.one_values.clone(),
+            used: false,
+            algorithm: PhantomData,
+        });
+        assert_ne!(plain.one_values, pk.one_values);
+
+        let pk = TweakableVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        assert!(pk.verify(&signature, b"tweaked").is_ok());
+        let restored = TweakableSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        assert!(restored.used());
+        assert_eq!(restored.identifier(), sk.identifier());
+        assert!(TweakableVerifyingKey::<D>::from_bytes(&pk.to_bytes()[1..]).is_err());
+    }
+}
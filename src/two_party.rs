@@ -0,0 +1,511 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size, check_random, check_security};
+use crate::signing::sign_values;
+use crate::utils::separate_one_and_zero_values;
+use crate::{KeyId, LamportDigest, LamportError, LamportResult, MultiVec, Signature, VerifyingKey};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+const TWO_PARTY_DOMAIN: &[u8] = b"lamport_signature_plus two party v1";
+
+/// The position of a party in the two-party co-signing protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub enum TwoPartyRole {
+    /// The first party, e.g. the user device.
+    First = 1,
+    /// The second party, e.g. the custody server.
+    Second = 2,
+}
+
+impl TryFrom<u8> for TwoPartyRole {
+    type Error = LamportError;
+
+    fn try_from(value: u8) -> LamportResult<Self> {
+        match value {
+            1 => Ok(Self::First),
+            2 => Ok(Self::Second),
+            _ => Err(LamportError::General(format!(
+                "invalid two party role {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// One party's half of a two-party key, where both parties must sign.
+///
+/// There is no dealer: each party generates its own share with
+/// [`TwoPartyKeyShare::generate`] and publishes only its
+/// [`TwoPartyCommitment`], the hashes of its secret values. The joint
+/// [`TwoPartyVerifyingKey`] commits to each pair of hashes, so a signature
+/// must reveal a preimage from both parties for every bit, and neither party
+/// ever sees the other's secret values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct TwoPartyKeyShare<T: LamportDigest> {
+    pub(crate) role: TwoPartyRole,
+    pub(crate) zero_values: MultiVec<u8, 2>,
+    pub(crate) one_values: MultiVec<u8, 2>,
+    pub(crate) used: bool,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(TwoPartyKeyShare);
+vec_impl!(TwoPartyKeyShare);
+
+impl<T: LamportDigest> Zeroize for TwoPartyKeyShare<T> {
+    fn zeroize(&mut self) {
+        self.zero_values.zeroize();
+        self.one_values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> TwoPartyKeyShare<T> {
+    /// Generates this party's share from its own randomness.
+    pub fn generate(role: TwoPartyRole, rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let (zero_values, one_values) = T::random_pair(rng);
+        check_random::<T>(&zero_values)?;
+        check_random::<T>(&one_values)?;
+        Ok(Self {
+            role,
+            zero_values,
+            one_values,
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The role of the party holding this share.
+    pub fn role(&self) -> TwoPartyRole {
+        self.role
+    }
+
+    /// Has this share been used.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Marks the share as used so it refuses to sign.
+    pub fn mark_used(&mut self) {
+        self.used = true;
+    }
+
+    /// The commitment to send to the other party during key generation.
+    pub fn commitment(&self) -> LamportResult<TwoPartyCommitment<T>> {
+        Ok(TwoPartyCommitment {
+            role: self.role,
+            key: VerifyingKey::new(T::hash(&self.zero_values)?, T::hash(&self.one_values)?),
+        })
+    }
+
+    /// The joint [`TwoPartyVerifyingKey`] from this share and the other party's commitment.
+    pub fn verifying_key(
+        &self,
+        other: &TwoPartyCommitment<T>,
+    ) -> LamportResult<TwoPartyVerifyingKey<T>> {
+        TwoPartyVerifyingKey::new(&self.commitment()?, other)
+    }
+
+    /// Signs the data to create this party's half of the signature.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<TwoPartySignatureShare<T>> {
+        let data_hash = T::digest(data.as_ref());
+        let signature =
+            sign_values::<T>(&self.zero_values, &self.one_values, self.used, &data_hash)?;
+
+        self.used = true;
+        Ok(TwoPartySignatureShare {
+            role: self.role,
+            message_digest: data_hash,
+            data: signature,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`TwoPartyKeyShare`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.zero_values.len() * 2);
+        bytes.push(self.role as u8);
+        bytes.push(self.used as u8);
+        bytes.extend_from_slice(self.zero_values.as_ref());
+        bytes.extend_from_slice(self.one_values.as_ref());
+        bytes
+    }
+
+    /// Constructs a [`TwoPartyKeyShare`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != bits * bytes * 2 + 2 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let role = TwoPartyRole::try_from(input[0])?;
+        let used = input[1] == 1;
+        let (zero_values, one_values) = separate_one_and_zero_values(&input[2..], bytes);
+        Ok(Self {
+            role,
+            zero_values,
+            one_values,
+            used,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// The hashes of one party's secret values, exchanged during key generation.
+///
+/// It is the [`VerifyingKey`] of the party's share on its own, so it can
+/// also check that party's [`TwoPartySignatureShare`]s before they are combined.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TwoPartyCommitment<T: LamportDigest> {
+    pub(crate) role: TwoPartyRole,
+    pub(crate) key: VerifyingKey<T>,
+}
+
+serde_impl!(TwoPartyCommitment);
+vec_impl!(TwoPartyCommitment);
+
+impl<T: LamportDigest> TwoPartyCommitment<T> {
+    /// The role of the party that made this commitment.
+    pub fn role(&self) -> TwoPartyRole {
+        self.role
+    }
+
+    /// Checks the party's half of a signature over the data.
+    pub fn verify_share<B: AsRef<[u8]>>(
+        &self,
+        share: &TwoPartySignatureShare<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        if share.role != self.role {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let signature = Signature {
+            data: share.data.clone(),
+            algorithm: PhantomData,
+        };
+        self.key.verify(&signature, data)
+    }
+
+    /// Converts the [`TwoPartyCommitment`] to canonical bytes, the role
+    /// followed by the [`VerifyingKey`] bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.role as u8];
+        bytes.extend_from_slice(&self.key.to_bytes());
+        bytes
+    }
+
+    /// Constructs a [`TwoPartyCommitment`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let (role, key) = input
+            .as_ref()
+            .split_first()
+            .ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        Ok(Self {
+            role: TwoPartyRole::try_from(*role)?,
+            key: VerifyingKey::from_bytes(key)?,
+        })
+    }
+}
+
+/// The joint public key of a two-party key.
+///
+/// Each commitment is the digest of the first party's and the second
+/// party's hash for that bit, so the key is the size of a [`VerifyingKey`]
+/// and verifiers never learn the individual commitments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TwoPartyVerifyingKey<T: LamportDigest> {
+    pub(crate) key: VerifyingKey<T>,
+}
+
+serde_impl!(TwoPartyVerifyingKey);
+vec_impl!(TwoPartyVerifyingKey);
+
+impl<T: LamportDigest> TwoPartyVerifyingKey<T> {
+    /// Combines the commitments of both parties, in either order.
+    pub fn new(a: &TwoPartyCommitment<T>, b: &TwoPartyCommitment<T>) -> LamportResult<Self> {
+        let (first, second) = match (a.role, b.role) {
+            (TwoPartyRole::First, TwoPartyRole::Second) => (&a.key, &b.key),
+            (TwoPartyRole::Second, TwoPartyRole::First) => (&b.key, &a.key),
+            _ => {
+                return Err(LamportError::General(
+                    "two party commitments must have different roles".to_string(),
+                ))
+            }
+        };
+        let joint = |first: &MultiVec<u8, 2>, second: &MultiVec<u8, 2>| {
+            let mut values = MultiVec::fill(first.axes, 0u8);
+            let bytes = first.axes[1];
+            let rows = first
+                .data
+                .chunks_exact(bytes)
+                .zip(second.data.chunks_exact(bytes));
+            for (out, (f, s)) in values.data.chunks_exact_mut(bytes).zip(rows) {
+                out.copy_from_slice(&joint_commitment::<T>(f, s));
+            }
+            values
+        };
+        Ok(Self {
+            key: VerifyingKey::new(
+                joint(&first.zero_values, &second.zero_values),
+                joint(&first.one_values, &second.one_values),
+            ),
+        })
+    }
+
+    /// The [`KeyId`] fingerprint of the joint key.
+    pub fn fingerprint(&self) -> KeyId {
+        self.key.fingerprint()
+    }
+
+    /// Verifies a [`TwoPartySignature`] over the data.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &TwoPartySignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let data_digest = T::digest(data.as_ref());
+        check_digest::<T>(&data_digest)?;
+        if signature.first.len() != self.key.one_values.len()
+            || signature.second.len() != self.key.one_values.len()
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+
+        let res = data_digest.iter().enumerate().all(|(i, byte)| {
+            (0..8).all(|j| {
+                let offset = i * 8 + j;
+                let hashed_value = joint_commitment::<T>(
+                    &T::digest(&signature.first[offset]),
+                    &T::digest(&signature.second[offset]),
+                );
+                let cmp = if (byte >> j) & 1 == 1 {
+                    &self.key.one_values[offset]
+                } else {
+                    &self.key.zero_values[offset]
+                };
+                hashed_value == cmp
+            })
+        });
+        if res {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the joint key to canonical bytes, in the [`VerifyingKey`] format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.key.to_bytes()
+    }
+
+    /// Constructs a [`TwoPartyVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Ok(Self {
+            key: VerifyingKey::from_bytes(input)?,
+        })
+    }
+}
+
+/// One party's half of a [`TwoPartySignature`], sent to whoever combines them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+pub struct TwoPartySignatureShare<T: LamportDigest> {
+    pub(crate) role: TwoPartyRole,
+    pub(crate) message_digest: Vec<u8>,
+    pub(crate) data: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(TwoPartySignatureShare);
+vec_impl!(TwoPartySignatureShare);
+
+impl<T: LamportDigest> TwoPartySignatureShare<T> {
+    /// The role of the party that created this share.
+    pub fn role(&self) -> TwoPartyRole {
+        self.role
+    }
+
+    /// The digest of the message that was signed.
+    pub fn message_digest(&self) -> &[u8] {
+        &self.message_digest
+    }
+
+    /// Combines both halves into a [`TwoPartySignature`], in either order.
+    ///
+    /// Fails if the halves come from the same party or were made over different messages.
+    pub fn combine(&self, other: &Self) -> LamportResult<TwoPartySignature<T>> {
+        let (first, second) = match (self.role, other.role) {
+            (TwoPartyRole::First, TwoPartyRole::Second) => (self, other),
+            (TwoPartyRole::Second, TwoPartyRole::First) => (other, self),
+            _ => {
+                return Err(LamportError::General(
+                    "two party signature shares must have different roles".to_string(),
+                ))
+            }
+        };
+        if self.message_digest.ct_eq(&other.message_digest).unwrap_u8() == 0 {
+            return Err(LamportError::General(
+                "two party signature shares are for different messages".to_string(),
+            ));
+        }
+        Ok(TwoPartySignature {
+            first: first.data.clone(),
+            second: second.data.clone(),
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`TwoPartySignatureShare`] to canonical bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.message_digest.len() + self.data.len());
+        bytes.push(self.role as u8);
+        bytes.extend_from_slice(&self.message_digest);
+        bytes.extend_from_slice(self.data.as_ref());
+        bytes
+    }
+
+    /// Constructs a [`TwoPartySignatureShare`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != 1 + bytes + bits * bytes {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let role = TwoPartyRole::try_from(input[0])?;
+        Ok(Self {
+            role,
+            message_digest: input[1..1 + bytes].to_vec(),
+            data: MultiVec {
+                data: input[1 + bytes..].to_vec(),
+                axes: [bits, bytes],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature by both parties, verified with a [`TwoPartyVerifyingKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TwoPartySignature<T: LamportDigest> {
+    pub(crate) first: MultiVec<u8, 2>,
+    pub(crate) second: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(TwoPartySignature);
+vec_impl!(TwoPartySignature);
+
+impl<T: LamportDigest> TwoPartySignature<T> {
+    /// Converts the [`TwoPartySignature`] to canonical bytes, the first
+    /// party's revealed values followed by the second party's.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.first.len() * 2);
+        bytes.extend_from_slice(self.first.as_ref());
+        bytes.extend_from_slice(self.second.as_ref());
+        bytes
+    }
+
+    /// Constructs a [`TwoPartySignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != bits * bytes * 2 {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let (first, second) = input.split_at(bits * bytes);
+        let values = |data: &[u8]| MultiVec {
+            data: data.to_vec(),
+            axes: [bits, bytes],
+        };
+        Ok(Self {
+            first: values(first),
+            second: values(second),
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// The joint commitment to the first and second party's hashes for one bit.
+fn joint_commitment<T: LamportDigest>(first: &[u8], second: &[u8]) -> Vec<u8> {
+    T::digest_concat(&[TWO_PARTY_DOMAIN, first, second])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+
+    #[test]
+    fn two_party_sign() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut device_rng = rand_chacha::ChaCha8Rng::from_seed([1u8; 32]);
+        let mut server_rng = rand_chacha::ChaCha8Rng::from_seed([2u8; 32]);
+        let mut first =
+            TwoPartyKeyShare::<D>::generate(TwoPartyRole::First, &mut device_rng).unwrap();
+        let mut second =
+            TwoPartyKeyShare::<D>::generate(TwoPartyRole::Second, &mut server_rng).unwrap();
+
+        // Only the commitments are exchanged, and both sides derive the same key
+        let first_commitment =
+            TwoPartyCommitment::from_bytes(first.commitment().unwrap().to_bytes()).unwrap();
+        let second_commitment = second.commitment().unwrap();
+        let pk = first.verifying_key(&second_commitment).unwrap();
+        assert_eq!(
+            second.verifying_key(&first_commitment).unwrap().to_bytes(),
+            pk.to_bytes()
+        );
+        assert!(first.verifying_key(&first_commitment).is_err());
+        let pk = TwoPartyVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+
+        let message = b"hello, world!";
+        let first_signature = first.sign(message).unwrap();
+        let second_signature = second.sign(message).unwrap();
+        assert!(first.sign(message).is_err());
+        assert!(first_signature.combine(&first_signature).is_err());
+        assert!(first_commitment
+            .verify_share(&first_signature, message)
+            .is_ok());
+        assert!(first_commitment
+            .verify_share(&second_signature, message)
+            .is_err());
+        assert!(second_commitment
+            .verify_share(&second_signature, message)
+            .is_ok());
+
+        let second_signature =
+            TwoPartySignatureShare::from_bytes(second_signature.to_bytes()).unwrap();
+        let signature = second_signature.combine(&first_signature).unwrap();
+        let signature = TwoPartySignature::from_bytes(signature.to_bytes()).unwrap();
+        assert!(pk.verify(&signature, message).is_ok());
+        assert!(pk.verify(&signature, b"hello, world?").is_err());
+
+        // One party cannot sign alone, even with a share of its own for the other role
+        let mut rogue =
+            TwoPartyKeyShare::<D>::generate(TwoPartyRole::Second, &mut device_rng).unwrap();
+        let mut rogue_first =
+            TwoPartyKeyShare::<D>::generate(TwoPartyRole::First, &mut device_rng).unwrap();
+        let forged = rogue_first
+            .sign(b"transfer")
+            .unwrap()
+            .combine(&rogue.sign(b"transfer").unwrap())
+            .unwrap();
+        assert!(pk.verify(&forged, b"transfer").is_err());
+
+        let mut other =
+            TwoPartyKeyShare::<D>::generate(TwoPartyRole::Second, &mut server_rng).unwrap();
+        let other_signature = other.sign(b"hello, world").unwrap();
+        assert!(first_signature.combine(&other_signature).is_err());
+    }
+}
@@ -0,0 +1,102 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+//! Experimental subsystems that are not covered by semantic versioning.
+//!
+//! Everything under this module may change or be removed in any release,
+//! including patch releases. Each subsystem lives in its own submodule behind
+//! an `unstable-<name>` cargo feature, for example `lms` behind
+//! `unstable-lms`, and every `unstable-*` feature enables the `unstable`
+//! feature that exposes this module. Depend on an exact version (`=x.y.z`) when enabling one.
+//!
+//! A subsystem graduates by moving to the crate root under a regular feature,
+//! at which point the usual compatibility guarantees apply.
+
+/// Winternitz one-time signatures, WOTS and WOTS+. Requires `unstable-wots`.
+#[cfg(feature = "unstable-wots")]
+pub mod wots {
+    pub use crate::wots::{WotsSignature, WotsSigningKey, WotsVerifyingKey};
+    pub use crate::wots_plus::{WotsPlusSignature, WotsPlusSigningKey, WotsPlusVerifyingKey};
+}
+
+/// XMSS^MT style hypertree signatures. Requires `unstable-xmss-mt`.
+#[cfg(feature = "unstable-xmss-mt")]
+pub mod xmss_mt {
+    pub use crate::xmss_mt::{
+        XmssMtParams, XmssMtSignature, XmssMtSigningKey, XmssMtVerifyingKey, XMSS_MT_MAX_HEIGHT,
+        XMSS_MT_MAX_SUBTREE_HEIGHT,
+    };
+}
+
+/// RFC 8554 Leighton-Micali signatures. Requires `unstable-lms`.
+#[cfg(feature = "unstable-lms")]
+pub mod lms {
+    pub use crate::lms::{
+        LmOtsAlgorithm, LmsAlgorithm, LmsSignature, LmsSigningKey, LmsVerifyingKey, LMS_HASH_BYTES,
+        LMS_IDENTIFIER_BYTES,
+    };
+}
+
+/// The RFC 8554 Hierarchical Signature System. Requires `unstable-hss`.
+#[cfg(feature = "unstable-hss")]
+pub mod hss {
+    pub use crate::hss::{HssSignature, HssSigningKey, HssVerifyingKey, HSS_MAX_LEVELS};
+}
+
+/// HORS few-time signatures. Requires `unstable-hors`.
+#[cfg(feature = "unstable-hors")]
+pub mod hors {
+    pub use crate::hors::{HorsParams, HorsSignature, HorsSigningKey, HorsVerifyingKey};
+}
+
+/// FORS few-time signatures. Requires `unstable-fors`.
+#[cfg(feature = "unstable-fors")]
+pub mod fors {
+    pub use crate::fors::{ForsParams, ForsSignature, ForsSigningKey, ForsVerifyingKey};
+}
+
+/// SPHINCS style stateless signatures. Requires `unstable-sphincs`.
+#[cfg(feature = "unstable-sphincs")]
+pub mod sphincs {
+    pub use crate::sphincs::{
+        SphincsParams, SphincsSignature, SphincsSigningKey, SphincsVerifyingKey,
+    };
+}
+
+/// BiBa few-time signatures. Requires `unstable-biba`.
+#[cfg(feature = "unstable-biba")]
+pub mod biba {
+    pub use crate::biba::{
+        BibaParams, BibaSignature, BibaSigningKey, BibaVerifyingKey, BIBA_MAX_COUNTER,
+    };
+}
+
+/// Chains of one-time keys that each certify the next. Requires `unstable-cert-chain`.
+#[cfg(feature = "unstable-cert-chain")]
+pub mod cert_chain {
+    pub use crate::cert_chain::{CertChain, ChainLink, ChainSignature};
+}
+
+/// One-time key pools kept in flash storage. Requires `unstable-flash`.
+#[cfg(feature = "unstable-flash")]
+pub mod flash {
+    pub use crate::flash::{BlockStore, FlashKeyPool, MemoryBlockStore};
+}
+
+/// Multi-party seed generation ceremonies. Requires `unstable-ceremony`.
+#[cfg(feature = "unstable-ceremony")]
+pub mod ceremony {
+    pub use crate::ceremony::{
+        CeremonyParticipant, CeremonyTranscript, EntropyCommitment, EntropyReveal, SeedCeremony,
+        CEREMONY_ID_BYTES, ENTROPY_BYTES,
+    };
+}
+
+/// Coordinated threshold signing rounds. Requires `unstable-threshold-session`.
+#[cfg(feature = "unstable-threshold-session")]
+pub mod threshold_session {
+    pub use crate::session::{
+        SessionCommitment, SessionRequest, SessionState, ThresholdParticipant, ThresholdSession,
+    };
+}
@@ -3,35 +3,90 @@
     SPDX-License-Identifier: Apache-2.0
 */
 use crate::MultiVec;
+use zeroize::Zeroize;
 
 macro_rules! serde_impl {
     ($name:ident) => {
-        impl<T: LamportDigest> serde::Serialize for $name<T> {
+        serde_impl!(@impl [T: LamportDigest] $name<T>, from_bytes);
+    };
+    (@concrete $name:ident) => {
+        serde_impl!(@impl [] $name, from_bytes);
+    };
+    (@limits $name:ident) => {
+        serde_impl!(@impl [T: LamportDigest] $name<T>, from_bytes_with_limits);
+    };
+    (@limits @concrete $name:ident) => {
+        serde_impl!(@impl [] $name, from_bytes_with_limits);
+    };
+    (@decode from_bytes, $input:ident, $limits:ident) => {
+        $limits
+            .check($input.len())
+            .and_then(|_| Self::from_bytes($input))
+    };
+    (@decode from_bytes_with_limits, $input:ident, $limits:ident) => {
+        Self::from_bytes_with_limits($input, $limits)
+    };
+    (@impl [$($generics:tt)*] $ty:ty, $decode:ident) => {
+        impl<$($generics)*> $crate::DecodeWithLimits for $ty {
+            fn from_bytes_with_limits(
+                input: &[u8],
+                limits: &$crate::Limits,
+            ) -> $crate::LamportResult<Self> {
+                serde_impl!(@decode $decode, input, limits)
+            }
+        }
+
+        impl<$($generics)*> serde::Serialize for $ty {
             fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
             where
                 S: serde::ser::Serializer,
             {
-                let bytes = self.to_bytes();
-                if s.is_human_readable() {
-                    hex::encode(&bytes).serialize(s)
+                let mut bytes = self.to_bytes();
+                let result = if s.is_human_readable() {
+                    let mut text = $crate::codec::text_encode(bytes.as_ref());
+                    let result = text.serialize(s);
+                    zeroize::Zeroize::zeroize(&mut text);
+                    result
                 } else {
-                    s.serialize_bytes(&bytes)
-                }
+                    s.serialize_bytes(bytes.as_ref())
+                };
+                zeroize::Zeroize::zeroize(&mut bytes);
+                result
             }
         }
 
-        impl<'de, T: LamportDigest> serde::Deserialize<'de> for $name<T> {
+        impl<'de, $($generics)*> serde::Deserialize<'de> for $ty {
             fn deserialize<D>(d: D) -> Result<Self, D::Error>
             where
                 D: serde::de::Deserializer<'de>,
             {
-                let bytes = if d.is_human_readable() {
-                    let hex_str = String::deserialize(d)?;
-                    hex::decode(hex_str).map_err(serde::de::Error::custom)?
-                } else {
-                    Vec::<u8>::deserialize(d)?
-                };
-                Self::from_bytes(bytes).map_err(serde::de::Error::custom)
+                $crate::limits::deserialize_with_limits(d, &$crate::Limits::DEFAULT)
+            }
+        }
+    };
+}
+
+macro_rules! armor_impl {
+    ($name:ident, $label:literal) => {
+        impl<T: LamportDigest> $name<T> {
+            #[doc = concat!("Encodes the canonical bytes as ASCII armor labelled `", $label, "`.")]
+            pub fn to_armor(&self) -> String {
+                let mut bytes = self.to_bytes();
+                let armored = $crate::armor::encode($label, &[], &bytes);
+                zeroize::Zeroize::zeroize(&mut bytes);
+                armored
+            }
+
+            #[doc = concat!("Decodes ASCII armor labelled `", $label, "`.")]
+            ///
+            /// Headers are ignored. [`LamportError::InvalidArmor`] is returned for
+            /// another label or a checksum mismatch.
+            pub fn from_armor(input: &str) -> LamportResult<Self> {
+                let armored = $crate::armor::decode(input)?;
+                if armored.label != $label {
+                    return Err(LamportError::InvalidArmor);
+                }
+                Self::from_bytes(&armored.data)
             }
         }
     };
@@ -39,6 +94,28 @@ macro_rules! serde_impl {
 
 macro_rules! vec_impl {
     ($name:ident) => {
+        vec_impl!(@common $name);
+
+        impl<T: LamportDigest> TryFrom<Vec<u8>> for $name<T> {
+            type Error = LamportError;
+
+            fn try_from(value: Vec<u8>) -> LamportResult<Self> {
+                Self::try_from(value.as_slice())
+            }
+        }
+    };
+    (@owned $name:ident) => {
+        vec_impl!(@common $name);
+
+        impl<T: LamportDigest> TryFrom<Vec<u8>> for $name<T> {
+            type Error = LamportError;
+
+            fn try_from(value: Vec<u8>) -> LamportResult<Self> {
+                Self::from_vec(value)
+            }
+        }
+    };
+    (@common $name:ident) => {
         impl<T: LamportDigest> From<$name<T>> for Vec<u8> {
             fn from(value: $name<T>) -> Vec<u8> {
                 Self::from(&value)
@@ -51,14 +128,6 @@ macro_rules! vec_impl {
             }
         }
 
-        impl<T: LamportDigest> TryFrom<Vec<u8>> for $name<T> {
-            type Error = LamportError;
-
-            fn try_from(value: Vec<u8>) -> LamportResult<Self> {
-                Self::try_from(value.as_slice())
-            }
-        }
-
         impl<T: LamportDigest> TryFrom<&Vec<u8>> for $name<T> {
             type Error = LamportError;
 
@@ -90,10 +159,45 @@ pub fn separate_one_and_zero_values(
     bytes: usize,
 ) -> (MultiVec<u8, 2>, MultiVec<u8, 2>) {
     let bits = bytes * 8;
-    let mut zero_values = MultiVec::fill([bits, bytes], 0);
-    let mut one_values = MultiVec::fill([bits, bytes], 0);
+    let (zero, one) = input.split_at(bits * bytes);
+    (
+        MultiVec {
+            data: zero.to_vec(),
+            axes: [bits, bytes],
+        },
+        MultiVec {
+            data: one.to_vec(),
+            axes: [bits, bytes],
+        },
+    )
+}
 
-    zero_values.data = input[..bits * bytes].to_vec();
-    one_values.data = input[bits * bytes..].to_vec();
-    (zero_values, one_values)
+/// Like [`separate_one_and_zero_values`] for an owned buffer that starts with
+/// `header` bytes.
+///
+/// The zero values are moved to the front of `input` and keep its
+/// allocation. Only the one values are copied out, and every byte left
+/// behind in the buffer, including the header, is zeroized.
+pub fn separate_owned_one_and_zero_values(
+    mut input: Vec<u8>,
+    header: usize,
+    bytes: usize,
+) -> (MultiVec<u8, 2>, MultiVec<u8, 2>) {
+    let bits = bytes * 8;
+    let split = header + bits * bytes;
+    let one = input[split..].to_vec();
+    input[split..].zeroize();
+    input.copy_within(header..split, 0);
+    input[split - header..split].zeroize();
+    input.truncate(bits * bytes);
+    (
+        MultiVec {
+            data: input,
+            axes: [bits, bytes],
+        },
+        MultiVec {
+            data: one,
+            axes: [bits, bytes],
+        },
+    )
 }
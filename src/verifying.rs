@@ -2,26 +2,72 @@
     Copyright Michael Lodder. All Rights Reserved.
     SPDX-License-Identifier: Apache-2.0
 */
-use crate::utils::separate_one_and_zero_values;
-use crate::{LamportDigest, LamportError, LamportResult, MultiVec, Signature, SigningKey};
+use crate::hash::{check_digest, check_digest_size, digest_vectored};
+use crate::utils::{separate_one_and_zero_values, separate_owned_one_and_zero_values};
+use crate::{
+    AttachedSignature, KeyId, LamportDigest, LamportError, LamportResult, MultiVec, Signature,
+    SigningKey,
+};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
 
+const CHUNK_DOMAIN: &[u8] = b"lamport_signature_plus commitment stream v2";
+
 /// A one-time signing public key.
 ///
 /// In general, a public key is generated by the paired [SigningKey] or [generate_keys] function.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+///
+/// The [`KeyId`] fingerprint is computed once when the key is constructed.
+/// [`Hash`] uses the fingerprint, and equality rejects keys with different
+/// fingerprints without comparing their commitments.
+#[derive(Debug, Clone)]
 pub struct VerifyingKey<T: LamportDigest> {
     pub(crate) zero_values: MultiVec<u8, 2>,
     pub(crate) one_values: MultiVec<u8, 2>,
+    pub(crate) fingerprint: KeyId,
     pub(crate) algorithm: PhantomData<T>,
 }
 
+impl<T: LamportDigest> PartialEq for VerifyingKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.fingerprint == other.fingerprint
+            && self.zero_values == other.zero_values
+            && self.one_values == other.one_values
+    }
+}
+
+impl<T: LamportDigest> Eq for VerifyingKey<T> {}
+
+impl<T: LamportDigest> Hash for VerifyingKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.fingerprint.hash(state);
+    }
+}
+
+impl<T: LamportDigest> PartialOrd for VerifyingKey<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: LamportDigest> Ord for VerifyingKey<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.zero_values
+            .cmp(&other.zero_values)
+            .then_with(|| self.one_values.cmp(&other.one_values))
+    }
+}
+
 serde_impl!(VerifyingKey);
-vec_impl!(VerifyingKey);
+vec_impl!(@owned VerifyingKey);
+armor_impl!(VerifyingKey, "LAMPORT PUBLIC KEY");
 
 impl<T: LamportDigest> VerifyingKey<T> {
     /// Constructs a [VerifyingKey] from the byte sequence
     pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<VerifyingKey<T>> {
+        check_digest_size::<T>()?;
         let input = input.as_ref();
         let bits = T::digest_size_in_bits();
         let bytes = bits / 8;
@@ -30,11 +76,92 @@ impl<T: LamportDigest> VerifyingKey<T> {
             return Err(LamportError::InvalidPrivateKeyBytes);
         }
         let (zero_values, one_values) = separate_one_and_zero_values(input, bytes);
-        Ok(Self {
-            zero_values,
-            one_values,
-            algorithm: PhantomData,
-        })
+        Ok(Self::new(zero_values, one_values))
+    }
+
+    /// Constructs a [`VerifyingKey`] from the byte sequence, taking ownership of the buffer.
+    ///
+    /// The zero commitments reuse the allocation of `input`.
+    pub fn from_vec(input: Vec<u8>) -> LamportResult<VerifyingKey<T>> {
+        check_digest_size::<T>()?;
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+
+        if input.len() != bits * bytes * 2 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (zero_values, one_values) = separate_owned_one_and_zero_values(input, 0, bytes);
+        Ok(Self::new(zero_values, one_values))
+    }
+
+    /// The number of commitment rows in this key, one per digest bit.
+    pub fn rows(&self) -> usize {
+        self.zero_values.axes[0]
+    }
+
+    /// The commitment to the preimage revealed when bit `i` of the message digest is zero.
+    ///
+    /// Returns `None` if `i` is out of range.
+    pub fn zero_commitment(&self, i: usize) -> Option<&[u8]> {
+        if i < self.rows() {
+            Some(&self.zero_values[i])
+        } else {
+            None
+        }
+    }
+
+    /// The commitment to the preimage revealed when bit `i` of the message digest is one.
+    ///
+    /// Returns `None` if `i` is out of range.
+    pub fn one_commitment(&self, i: usize) -> Option<&[u8]> {
+        if i < self.rows() {
+            Some(&self.one_values[i])
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the commitment pairs in digest bit order.
+    ///
+    /// Item `i` is the zero commitment followed by the one commitment for bit `i`,
+    /// which is the order a signature is checked in.
+    pub fn commitments(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        let bytes = self.zero_values.axes[1];
+        self.zero_values
+            .data
+            .chunks_exact(bytes)
+            .zip(self.one_values.data.chunks_exact(bytes))
+    }
+
+    /// Streams the commitments in chunks of `rows` bit positions for verifiers
+    /// that cannot hold the whole key in memory.
+    ///
+    /// Each [`CommitmentChunk`] holds the pairs from [`VerifyingKey::commitments`]
+    /// concatenated, and a running SHA-256 digest over every commitment so far
+    /// so the receiver can check the stream against a trusted final digest. A
+    /// `rows` of zero is treated as one.
+    pub fn commitment_chunks(&self, rows: usize) -> CommitmentChunks<'_, T> {
+        CommitmentChunks {
+            key: self,
+            rows: rows.max(1),
+            next_row: 0,
+            hasher: stream_hasher(),
+        }
+    }
+
+    /// The digest of the commitment stream, for a [`ChunkedVerifier`] to trust.
+    ///
+    /// It is SHA-256 of a domain separator and the commitment pairs in bit
+    /// order, so it does not depend on how the stream is chunked and equals
+    /// the final running digest of [`VerifyingKey::commitment_chunks`] for
+    /// any chunk size.
+    pub fn commitment_stream_digest(&self) -> [u8; 32] {
+        let mut hasher = stream_hasher();
+        for (zero, one) in self.commitments() {
+            hasher.update(zero);
+            hasher.update(one);
+        }
+        hasher.finalize().into()
     }
 
     /// Converts the inner key data into a linearized vector.
@@ -49,7 +176,7 @@ impl<T: LamportDigest> VerifyingKey<T> {
     ///
     /// const SEED: [u8; 32] = [0; 32];
     /// let mut rng = ChaCha12Rng::from_seed(SEED);
-    /// let secret_key = SigningKey::<LamportFixedDigest<Sha256>>::random(&mut rng);
+    /// let secret_key = SigningKey::<LamportFixedDigest<Sha256>>::random(&mut rng).unwrap();
     /// let public_key = VerifyingKey::from(&secret_key);
     /// assert!(public_key.to_bytes().len() == 32 * 256 * 2);
     /// ```
@@ -61,6 +188,21 @@ impl<T: LamportDigest> VerifyingKey<T> {
             .collect()
     }
 
+    /// Converts the key into a fixed size array without allocating.
+    ///
+    /// `N` must equal the length of [`VerifyingKey::to_bytes`], or
+    /// [`LamportError::InvalidPrivateKeyBytes`] is returned as by [`VerifyingKey::from_bytes`].
+    pub fn to_array<const N: usize>(&self) -> LamportResult<[u8; N]> {
+        let half = self.zero_values.len();
+        if N != half + self.one_values.len() {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let mut bytes = [0u8; N];
+        bytes[..half].copy_from_slice(&self.zero_values.data);
+        bytes[half..].copy_from_slice(&self.one_values.data);
+        Ok(bytes)
+    }
+
     /// Verifies the [`Signature`].
     ///
     /// # Example
@@ -73,7 +215,7 @@ impl<T: LamportDigest> VerifyingKey<T> {
     ///
     /// const SEED: [u8; 32] = [0; 32];
     /// let mut rng = ChaCha12Rng::from_seed(SEED);
-    /// let mut private_key = SigningKey::<LamportFixedDigest<Sha256>>::random(rng);
+    /// let mut private_key = SigningKey::<LamportFixedDigest<Sha256>>::random(rng).unwrap();
     /// let public_key = VerifyingKey::from(&private_key);
     ///
     /// const MESSAGE: &[u8] = b"hello, world!";
@@ -81,14 +223,106 @@ impl<T: LamportDigest> VerifyingKey<T> {
     /// assert!(public_key.verify(&signature, MESSAGE).is_ok());
     /// ```
     pub fn verify<B: AsRef<[u8]>>(&self, signature: &Signature<T>, data: B) -> LamportResult<()> {
+        let data_digest = T::digest(data.as_ref());
+        self.verify_digest(signature, &data_digest)
+    }
+
+    /// Verifies the [`Signature`] after checking its layout with [`Signature::validate`].
+    ///
+    /// Use this for signatures from untrusted sources, so a malformed one is
+    /// reported as [`LamportError::InvalidSignatureShape`] before any of its
+    /// rows are read.
+    pub fn verify_strict<B: AsRef<[u8]>>(
+        &self,
+        signature: &Signature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        signature.validate()?;
+        self.verify(signature, data)
+    }
+
+    /// Verifies a [`Signature`] created with [`SigningKey::sign_vectored`].
+    pub fn verify_vectored(&self, signature: &Signature<T>, parts: &[&[u8]]) -> LamportResult<()> {
+        let data_digest = digest_vectored::<T>(parts);
+        self.verify_digest(signature, &data_digest)
+    }
+
+    /// The [`KeyId`] fingerprint of this key.
+    pub fn fingerprint(&self) -> KeyId {
+        self.fingerprint
+    }
+
+    pub(crate) fn new(zero_values: MultiVec<u8, 2>, one_values: MultiVec<u8, 2>) -> Self {
+        let fingerprint = KeyId::compute(T::digest_size_in_bits(), &zero_values, &one_values);
+        Self {
+            zero_values,
+            one_values,
+            fingerprint,
+            algorithm: PhantomData,
+        }
+    }
+
+    /// Verifies the [`Signature`] only if this key has the `expected` fingerprint.
+    ///
+    /// Returns [`LamportError::KeyIdMismatch`] without checking the signature
+    /// when the fingerprints differ.
+    pub fn verify_with_fingerprint<B: AsRef<[u8]>>(
+        &self,
+        signature: &Signature<T>,
+        data: B,
+        expected: &KeyId,
+    ) -> LamportResult<()> {
+        if self.fingerprint() != *expected {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        self.verify(signature, data)
+    }
+
+    /// Verifies an [`AttachedSignature`], rejecting it early if it was bound to another key.
+    pub fn verify_attached<B: AsRef<[u8]>>(
+        &self,
+        signature: &AttachedSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        self.verify_with_fingerprint(&signature.signature, data, &signature.key_id)
+    }
+
+    /// Finds which of the `keys` verifies the [`Signature`], if any.
+    ///
+    /// The message is hashed once, and candidates whose commitment for the
+    /// first digest bit does not match the first revealed preimage are skipped
+    /// without checking the rest of the signature.
+    pub fn verify_many_keys<B: AsRef<[u8]>>(
+        keys: &[Self],
+        signature: &Signature<T>,
+        data: B,
+    ) -> Option<usize> {
+        let data_digest = T::digest(data.as_ref());
+        let first_bit = data_digest.first()? & 1;
+        let first_row = T::digest(signature.data.data.get(..signature.data.axes[1])?);
+        keys.iter().position(|key| {
+            let commitment = if first_bit == 1 {
+                key.one_commitment(0)
+            } else {
+                key.zero_commitment(0)
+            };
+            commitment == Some(first_row.as_slice())
+                && key.verify_digest(signature, &data_digest).is_ok()
+        })
+    }
+
+    /// Verifies the [`Signature`] against a pre-computed message digest.
+    pub(crate) fn verify_digest(
+        &self,
+        signature: &Signature<T>,
+        data_digest: &[u8],
+    ) -> LamportResult<()> {
+        check_digest::<T>(data_digest)?;
         if signature.data.len() != self.one_values.len() {
             return Err(LamportError::InvalidSignatureBytes);
         }
 
-        let data = data.as_ref();
-        let data_digest = T::digest(data);
-
-        let res = data_digest.into_iter().enumerate().all(|(i, byte)| {
+        let res = data_digest.iter().enumerate().all(|(i, byte)| {
             (0..8).all(|j| {
                 let offset = i * 8 + j;
                 let choice = (byte >> j) & 1;
@@ -110,14 +344,309 @@ impl<T: LamportDigest> VerifyingKey<T> {
 }
 
 impl<T: LamportDigest> From<&SigningKey<T>> for VerifyingKey<T> {
+    /// # Panics
+    ///
+    /// Panics if the digest's output length changes with its input, since
+    /// every signing key was checked against the declared size when it was
+    /// created or loaded.
     fn from(value: &SigningKey<T>) -> Self {
-        let hashed_zero_values = T::hash(&value.zero_values);
-        let hashed_one_values = T::hash(&value.one_values);
+        let hashed_zero_values = T::hash(&value.zero_values).expect("checked digest size");
+        let hashed_one_values = T::hash(&value.one_values).expect("checked digest size");
+
+        VerifyingKey::new(hashed_zero_values, hashed_one_values)
+    }
+}
 
-        VerifyingKey {
-            zero_values: hashed_zero_values,
-            one_values: hashed_one_values,
+/// A [`VerifyingKey`] rearranged for repeated verification.
+///
+/// The zero and one commitments for each digest bit are stored next to each
+/// other, so verification walks a single contiguous table in bit order
+/// instead of jumping between two halves of the key. The canonical byte
+/// format is that of the [`VerifyingKey`] it was prepared from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreparedVerifyingKey<T: LamportDigest> {
+    pub(crate) commitments: MultiVec<u8, 3>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+impl<T: LamportDigest> From<&VerifyingKey<T>> for PreparedVerifyingKey<T> {
+    fn from(value: &VerifyingKey<T>) -> Self {
+        Self {
+            commitments: value.zero_values.interleave(&value.one_values),
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> From<VerifyingKey<T>> for PreparedVerifyingKey<T> {
+    fn from(value: VerifyingKey<T>) -> Self {
+        Self::from(&value)
+    }
+}
+
+impl<T: LamportDigest> PreparedVerifyingKey<T> {
+    /// Verifies the [`Signature`].
+    pub fn verify<B: AsRef<[u8]>>(&self, signature: &Signature<T>, data: B) -> LamportResult<()> {
+        let data_digest = T::digest(data.as_ref());
+        check_digest::<T>(&data_digest)?;
+        let bytes = self.commitments.axes[2];
+        if signature.data.len() * 2 != self.commitments.len() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+
+        let mut rows = signature.data.data.chunks_exact(bytes).enumerate();
+        let res = data_digest.iter().all(|byte| {
+            (0..8).all(|j| match rows.next() {
+                Some((i, row)) => {
+                    let choice = usize::from((byte >> j) & 1);
+                    T::digest(row) == self.commitments[(i, choice)]
+                }
+                None => false,
+            })
+        });
+        if res {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the key to the canonical [`VerifyingKey`] bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let bytes = self.commitments.axes[2];
+        let rows = self.commitments.data.chunks_exact(bytes);
+        let zero = rows.clone().step_by(2);
+        let one = rows.skip(1).step_by(2);
+        zero.chain(one).flatten().copied().collect()
+    }
+
+    /// Constructs a [`PreparedVerifyingKey`] from canonical [`VerifyingKey`] bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        VerifyingKey::from_bytes(input).map(Self::from)
+    }
+}
+
+serde_impl!(PreparedVerifyingKey);
+vec_impl!(PreparedVerifyingKey);
+
+/// A run of consecutive commitment pairs produced by [`VerifyingKey::commitment_chunks`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommitmentChunk {
+    /// The digest bit position of the first pair in the chunk.
+    pub first_row: usize,
+    /// The zero and one commitment of each bit position, concatenated.
+    pub data: Vec<u8>,
+    /// The [`VerifyingKey::commitment_stream_digest`] of every commitment
+    /// up to and including this chunk.
+    pub running_digest: [u8; 32],
+}
+
+/// Iterator returned by [`VerifyingKey::commitment_chunks`].
+#[derive(Debug, Clone)]
+pub struct CommitmentChunks<'a, T: LamportDigest> {
+    key: &'a VerifyingKey<T>,
+    rows: usize,
+    next_row: usize,
+    hasher: Sha256,
+}
+
+impl<T: LamportDigest> Iterator for CommitmentChunks<'_, T> {
+    type Item = CommitmentChunk;
+
+    fn next(&mut self) -> Option<CommitmentChunk> {
+        let total = self.key.rows();
+        if self.next_row >= total {
+            return None;
+        }
+        let first_row = self.next_row;
+        let end = total.min(first_row + self.rows);
+        let mut data = Vec::with_capacity((end - first_row) * self.key.zero_values.axes[1] * 2);
+        for (zero, one) in self.key.commitments().skip(first_row).take(end - first_row) {
+            data.extend_from_slice(zero);
+            data.extend_from_slice(one);
+        }
+        self.hasher.update(&data);
+        self.next_row = end;
+        Some(CommitmentChunk {
+            first_row,
+            data,
+            running_digest: self.hasher.clone().finalize().into(),
+        })
+    }
+}
+
+fn stream_hasher() -> Sha256 {
+    Sha256::new_with_prefix(CHUNK_DOMAIN)
+}
+
+/// Verifies a [`Signature`] a few rows at a time against commitments streamed
+/// by [`VerifyingKey::commitment_chunks`].
+///
+/// Only the message digest and a SHA-256 state are kept between calls, so a
+/// device can check a signature without ever holding the whole signature or
+/// key. The digest of the streamed commitments must match a trusted
+/// [`VerifyingKey::commitment_stream_digest`] for the result to be accepted,
+/// whatever size the chunks were.
+#[derive(Debug, Clone)]
+pub struct ChunkedVerifier<T: LamportDigest> {
+    data_digest: Vec<u8>,
+    expected_digest: [u8; 32],
+    hasher: Sha256,
+    next_row: usize,
+    valid: bool,
+    algorithm: PhantomData<T>,
+}
+
+impl<T: LamportDigest> ChunkedVerifier<T> {
+    /// Starts verifying a signature over `data` with commitments whose stream
+    /// digest is `expected_digest`.
+    pub fn new<B: AsRef<[u8]>>(data: B, expected_digest: [u8; 32]) -> LamportResult<Self> {
+        let data_digest = T::digest(data.as_ref());
+        check_digest::<T>(&data_digest)?;
+        Ok(Self {
+            data_digest,
+            expected_digest,
+            hasher: stream_hasher(),
+            next_row: 0,
+            valid: true,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The number of digest bit positions still to be checked.
+    pub fn remaining(&self) -> usize {
+        T::digest_size_in_bits() - self.next_row
+    }
+
+    /// Checks the signature rows for the bit positions covered by the chunk.
+    ///
+    /// `signature` must be the signature bytes for exactly those positions,
+    /// and chunks must arrive in order.
+    pub fn update(&mut self, commitments: &CommitmentChunk, signature: &[u8]) -> LamportResult<()> {
+        let bytes = T::digest_size_in_bits() / 8;
+        let rows = signature.len() / bytes;
+        if commitments.first_row != self.next_row
+            || rows == 0
+            || rows > self.remaining()
+            || signature.len() != rows * bytes
+            || commitments.data.len() != rows * bytes * 2
+        {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        self.hasher.update(&commitments.data);
+        for (i, (row, pair)) in signature
+            .chunks_exact(bytes)
+            .zip(commitments.data.chunks_exact(bytes * 2))
+            .enumerate()
+        {
+            let bit = self.next_row + i;
+            let choice = usize::from((self.data_digest[bit / 8] >> (bit % 8)) & 1);
+            let commitment = &pair[choice * bytes..(choice + 1) * bytes];
+            self.valid &= T::digest(row) == commitment;
+        }
+        self.next_row += rows;
+        Ok(())
+    }
+
+    /// Succeeds if every row was checked, every row matched and the streamed
+    /// commitments match the trusted digest.
+    pub fn finish(self) -> LamportResult<()> {
+        let complete = self.remaining() == 0 && self.valid;
+        let digest: [u8; 32] = self.hasher.finalize().into();
+        if complete && digest == self.expected_digest {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+}
+
+/// Everything needed to verify many signatures against one [`VerifyingKey`].
+///
+/// Created by [`VerifyingKey::precompute`]. It keeps the interleaved layout
+/// of a [`PreparedVerifyingKey`] together with the key's [`KeyId`], and its
+/// canonical bytes store that layout directly so loading a saved context does
+/// not rearrange the commitments.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VerifyContext<T: LamportDigest> {
+    pub(crate) prepared: PreparedVerifyingKey<T>,
+    pub(crate) key_id: KeyId,
+}
+
+serde_impl!(VerifyContext);
+vec_impl!(VerifyContext);
+
+impl<T: LamportDigest> VerifyingKey<T> {
+    /// Precomputes a [`VerifyContext`] for repeated verification with this key.
+    pub fn precompute(&self) -> VerifyContext<T> {
+        VerifyContext {
+            prepared: PreparedVerifyingKey::from(self),
+            key_id: self.fingerprint,
+        }
+    }
+}
+
+impl<T: LamportDigest> VerifyContext<T> {
+    /// The [`KeyId`] of the key this context verifies for.
+    pub fn fingerprint(&self) -> KeyId {
+        self.key_id
+    }
+
+    /// The prepared key.
+    pub fn prepared(&self) -> &PreparedVerifyingKey<T> {
+        &self.prepared
+    }
+
+    /// Verifies the [`Signature`].
+    pub fn verify<B: AsRef<[u8]>>(&self, signature: &Signature<T>, data: B) -> LamportResult<()> {
+        self.prepared.verify(signature, data)
+    }
+
+    /// Verifies an [`AttachedSignature`], rejecting it early if it was bound to another key.
+    pub fn verify_attached<B: AsRef<[u8]>>(
+        &self,
+        signature: &AttachedSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        if signature.key_id != self.key_id {
+            return Err(LamportError::KeyIdMismatch);
+        }
+        self.prepared.verify(&signature.signature, data)
+    }
+
+    /// Converts the context to canonical bytes: the [`KeyId`] followed by the
+    /// zero and one commitment of each digest bit in turn.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(KeyId::BYTES + self.prepared.commitments.len());
+        bytes.extend_from_slice(self.key_id.as_bytes());
+        bytes.extend_from_slice(self.prepared.commitments.as_ref());
+        bytes
+    }
+
+    /// Constructs a [`VerifyContext`] from canonical bytes.
+    ///
+    /// The stored [`KeyId`] must match the commitments.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let bits = T::digest_size_in_bits();
+        let bytes = bits / 8;
+        if input.len() != KeyId::BYTES + bits * bytes * 2 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (key_id, commitments) = input.split_at(KeyId::BYTES);
+        let key_id = KeyId::from_bytes(key_id)?;
+        let prepared = PreparedVerifyingKey {
+            commitments: MultiVec {
+                data: commitments.to_vec(),
+                axes: [bits, 2, bytes],
+            },
             algorithm: PhantomData,
+        };
+        let (zero_values, one_values) = separate_one_and_zero_values(&prepared.to_bytes(), bytes);
+        if KeyId::compute(bits, &zero_values, &one_values) != key_id {
+            return Err(LamportError::KeyIdMismatch);
         }
+        Ok(Self { prepared, key_id })
     }
 }
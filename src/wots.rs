@@ -0,0 +1,414 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size, check_security};
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+/// The chain lengths and counts for a Winternitz parameter and digest.
+///
+/// With `n` byte digests and `w = 2^log_w`, the message digest is split into
+/// `len1` base `w` digits followed by `len2` digits of the checksum
+/// `sum(w - 1 - digit)`, as in RFC 8391 §3.1.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct WotsParams {
+    pub(crate) log_w: u32,
+    pub(crate) n: usize,
+    pub(crate) len1: usize,
+    pub(crate) len2: usize,
+}
+
+impl WotsParams {
+    /// Supports `w` of 2, 4, 16 or 256 so digits never straddle a byte.
+    pub(crate) fn new<T: LamportDigest>(w: usize) -> LamportResult<Self> {
+        let log_w = match w {
+            2 => 1,
+            4 => 2,
+            16 => 4,
+            256 => 8,
+            _ => return Err(LamportError::InvalidWinternitzParameter(w)),
+        };
+        let n = T::digest_size_in_bits() / 8;
+        let len1 = (8 * n).div_ceil(log_w as usize);
+        // floor(log2(len1 * (w - 1)) / log_w) + 1
+        let max_checksum = len1 * (w - 1);
+        let floor_log2 = usize::BITS - 1 - max_checksum.leading_zeros();
+        let len2 = (floor_log2 / log_w) as usize + 1;
+        Ok(Self {
+            log_w,
+            n,
+            len1,
+            len2,
+        })
+    }
+
+    /// Parses the leading `log_w` byte of an encoding.
+    pub(crate) fn from_log_w<T: LamportDigest>(log_w: Option<&u8>) -> Option<Self> {
+        let log_w = u32::from(*log_w?);
+        if log_w == 0 || log_w > 8 {
+            return None;
+        }
+        Self::new::<T>(1 << log_w).ok()
+    }
+
+    pub(crate) fn w(&self) -> usize {
+        1 << self.log_w
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len1 + self.len2
+    }
+
+    /// The encoded `log_w`, which is at most 8.
+    pub(crate) fn log_w_byte(&self) -> u8 {
+        self.log_w.to_le_bytes()[0]
+    }
+
+    /// The number of chain steps to sign each digit of the digest and its checksum.
+    pub(crate) fn digits(&self, digest: &[u8]) -> Vec<usize> {
+        let mut digits = self.base_w(digest, self.len1);
+        let checksum = digits.iter().map(|d| self.w() - 1 - d).sum::<usize>();
+        let checksum_bits = self.len2 * self.log_w as usize;
+        let checksum_bytes = checksum_bits.div_ceil(8);
+        let shifted = (checksum as u64) << ((8 - checksum_bits % 8) % 8);
+        let encoded = shifted.to_be_bytes();
+        digits.extend(self.base_w(&encoded[8 - checksum_bytes..], self.len2));
+        digits
+    }
+
+    fn base_w(&self, input: &[u8], count: usize) -> Vec<usize> {
+        let mask = self.w() - 1;
+        let mut digits = Vec::with_capacity(count);
+        let mut bytes = input.iter();
+        let mut total = 0usize;
+        let mut bits = 0u32;
+        for _ in 0..count {
+            if bits == 0 {
+                total = bytes.next().map_or(0, |b| usize::from(*b));
+                bits = 8;
+            }
+            bits -= self.log_w;
+            digits.push((total >> bits) & mask);
+        }
+        digits
+    }
+}
+
+/// Applies `steps` iterations of the digest starting at `value`.
+fn chain<T: LamportDigest>(value: &[u8], steps: usize) -> Vec<u8> {
+    let mut value = value.to_vec();
+    for _ in 0..steps {
+        let next = T::digest(&value);
+        value.zeroize();
+        value = next;
+    }
+    value
+}
+
+/// A Winternitz one-time signing key.
+///
+/// Each of the `len` secret values is the start of a hash chain of length
+/// `w - 1`. A larger `w` gives fewer chains and so shorter keys and
+/// signatures, at the cost of up to `w - 1` hashes per chain when signing
+/// and verifying. With SHA-256, `w = 16` needs 67 chains, a 2,144 byte
+/// signature instead of the 8,192 bytes of a Lamport [`crate::Signature`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WotsSigningKey<T: LamportDigest> {
+    pub(crate) params: WotsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) used: bool,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(WotsSigningKey);
+vec_impl!(WotsSigningKey);
+
+impl<T: LamportDigest> Zeroize for WotsSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> WotsSigningKey<T> {
+    /// Constructs a [`WotsSigningKey`] with Winternitz parameter `w` of 2, 4, 16 or 256.
+    pub fn random(w: usize, mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let params = WotsParams::new::<T>(w)?;
+        let mut data = vec![0u8; params.len() * params.n];
+        rng.fill_bytes(&mut data);
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data,
+                axes: [params.len(), params.n],
+            },
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.params.w()
+    }
+
+    /// Has this key been used.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs the data.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<WotsSignature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        self.used = true;
+        let mut signature = Vec::with_capacity(self.values.len());
+        for (value, steps) in self
+            .values
+            .data
+            .chunks_exact(self.params.n)
+            .zip(self.params.digits(&digest))
+        {
+            signature.extend_from_slice(&chain::<T>(value, steps));
+        }
+        Ok(WotsSignature {
+            params: self.params,
+            values: MultiVec {
+                data: signature,
+                axes: self.values.axes,
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`WotsSigningKey`] to canonical bytes.
+    ///
+    /// The format is `log2(w)`, the used flag, then the secret values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.values.len());
+        bytes.push(self.params.log_w_byte());
+        bytes.push(u8::from(self.used));
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`WotsSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let params = WotsParams::from_log_w::<T>(input.first())
+            .ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 2 + params.len() * params.n || input[1] > 1 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[2..].to_vec(),
+                axes: [params.len(), params.n],
+            },
+            used: input[1] == 1,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A Winternitz one-time verifying key, the end of each hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WotsVerifyingKey<T: LamportDigest> {
+    pub(crate) params: WotsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(WotsVerifyingKey);
+vec_impl!(WotsVerifyingKey);
+
+impl<T: LamportDigest> From<&WotsSigningKey<T>> for WotsVerifyingKey<T> {
+    fn from(value: &WotsSigningKey<T>) -> Self {
+        let steps = value.params.w() - 1;
+        let data = value
+            .values
+            .data
+            .chunks_exact(value.params.n)
+            .flat_map(|v| chain::<T>(v, steps))
+            .collect();
+        Self {
+            params: value.params,
+            values: MultiVec {
+                data,
+                axes: value.values.axes,
+            },
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> WotsVerifyingKey<T> {
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.params.w()
+    }
+
+    /// Verifies the signature by completing each hash chain.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &WotsSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        if signature.params != self.params || signature.values.axes != self.values.axes {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        let last = self.params.w() - 1;
+        let valid = signature
+            .values
+            .data
+            .chunks_exact(self.params.n)
+            .zip(self.values.data.chunks_exact(self.params.n))
+            .zip(self.params.digits(&digest))
+            .all(|((value, end), digit)| chain::<T>(value, last - digit) == end);
+        if valid {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the [`WotsVerifyingKey`] to canonical bytes, `log2(w)` then the chain ends.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.values.len());
+        bytes.push(self.params.log_w_byte());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`WotsVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let params = WotsParams::from_log_w::<T>(input.first())
+            .ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 1 + params.len() * params.n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[1..].to_vec(),
+                axes: [params.len(), params.n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature generated by [`WotsSigningKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WotsSignature<T: LamportDigest> {
+    pub(crate) params: WotsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(WotsSignature);
+vec_impl!(WotsSignature);
+
+impl<T: LamportDigest> WotsSignature<T> {
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.params.w()
+    }
+
+    /// Converts the [`WotsSignature`] to canonical bytes, `log2(w)` then the chain values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.values.len());
+        bytes.push(self.params.log_w_byte());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`WotsSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let params = WotsParams::from_log_w::<T>(input.first())
+            .ok_or(LamportError::InvalidSignatureBytes)?;
+        if input.len() != 1 + params.len() * params.n {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[1..].to_vec(),
+                axes: [params.len(), params.n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn wots() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        for (w, len1, len2) in [(2, 256, 9), (4, 128, 5), (16, 64, 3), (256, 32, 2)] {
+            let params = WotsParams::new::<D>(w).unwrap();
+            assert_eq!((params.len1, params.len2), (len1, len2));
+
+            let mut sk = WotsSigningKey::<D>::random(w, &mut rng).unwrap();
+            let pk = WotsVerifyingKey::from(&sk);
+            let signature = sk.sign(b"wots").unwrap();
+            assert_eq!(signature.w(), w);
+            assert_eq!(signature.to_bytes().len(), 1 + (len1 + len2) * 32);
+            assert!(pk.verify(&signature, b"wots").is_ok());
+            assert!(pk.verify(&signature, b"stow").is_err());
+            assert_eq!(
+                sk.sign(b"again").unwrap_err(),
+                LamportError::PrivateKeyReuseError
+            );
+
+            let sk = WotsSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+            assert!(sk.used());
+            let pk = WotsVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+            let signature = WotsSignature::<D>::from_bytes(signature.to_bytes()).unwrap();
+            assert!(pk.verify(&signature, b"wots").is_ok());
+        }
+
+        // Raising a chain value breaks the checksum
+        let mut sk = WotsSigningKey::<D>::random(16, &mut rng).unwrap();
+        let pk = WotsVerifyingKey::from(&sk);
+        let mut signature = sk.sign(b"wots").unwrap();
+        let first = <D as LamportDigest>::digest(&signature.values.data[..32]);
+        signature.values.data[..32].copy_from_slice(&first);
+        assert!(pk.verify(&signature, b"wots").is_err());
+
+        let other = WotsSigningKey::<D>::random(4, &mut rng).unwrap();
+        let signature = WotsSigningKey::<D>::random(16, &mut rng)
+            .unwrap()
+            .sign(b"wots")
+            .unwrap();
+        assert!(WotsVerifyingKey::from(&other)
+            .verify(&signature, b"wots")
+            .is_err());
+        assert_eq!(
+            WotsSigningKey::<D>::random(8, &mut rng).unwrap_err(),
+            LamportError::InvalidWinternitzParameter(8)
+        );
+        assert!(WotsSignature::<D>::from_bytes([3u8; 100]).is_err());
+    }
+}
@@ -0,0 +1,373 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest, check_digest_size, check_security};
+use crate::wots::WotsParams;
+use crate::{LamportDigest, LamportError, LamportResult, MultiVec};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+const MASK_DOMAIN: &[u8] = b"lamport_signature_plus wots+ v1";
+
+/// The public function key and bitmasks of a WOTS+ key pair.
+///
+/// Both are derived from the public seed, so a verifying key only needs to
+/// carry the seed. The key is `H(tag || seed || 0)` and bitmask `j`, for
+/// chain step `j` in `1..w`, is `H(tag || seed || 1 || j)` with `j` a big
+/// endian `u32`.
+struct ChainMasks {
+    key: Vec<u8>,
+    bitmasks: Vec<Vec<u8>>,
+}
+
+impl ChainMasks {
+    fn new<T: LamportDigest>(params: &WotsParams, seed: &[u8]) -> Self {
+        let key = T::digest_concat(&[MASK_DOMAIN, seed, &[0]]);
+        let bitmasks = (1..params.w())
+            .map(|j| {
+                let j = u32::try_from(j).expect("w is at most 256");
+                T::digest_concat(&[MASK_DOMAIN, seed, &[1], &j.to_be_bytes()])
+            })
+            .collect();
+        Self { key, bitmasks }
+    }
+
+    /// Applies the chain steps `start + 1..=start + steps`, each computing
+    /// `H(key || (value ^ bitmask))`.
+    fn chain<T: LamportDigest>(&self, value: &[u8], start: usize, steps: usize) -> Vec<u8> {
+        let mut value = value.to_vec();
+        for bitmask in &self.bitmasks[start..start + steps] {
+            value.iter_mut().zip(bitmask).for_each(|(v, m)| *v ^= m);
+            let next = T::digest_concat(&[&self.key, &value]);
+            value.zeroize();
+            value = next;
+        }
+        value
+    }
+}
+
+/// A WOTS+ one-time signing key.
+///
+/// WOTS+ masks every chain step with a public bitmask before hashing with a
+/// public function key. Its security reduces to the second preimage
+/// resistance of the digest, rather than collision resistance as for
+/// [`WotsSigningKey`](crate::wots::WotsSigningKey), so shorter digests give the same security
+/// level. Signatures are the same size as WOTS; the verifying key also
+/// carries the `n` byte public seed the masks are derived from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WotsPlusSigningKey<T: LamportDigest> {
+    pub(crate) params: WotsParams,
+    pub(crate) seed: Vec<u8>,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) used: bool,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(WotsPlusSigningKey);
+vec_impl!(WotsPlusSigningKey);
+
+impl<T: LamportDigest> Zeroize for WotsPlusSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.values.zeroize();
+    }
+}
+
+impl<T: LamportDigest> WotsPlusSigningKey<T> {
+    /// Constructs a [`WotsPlusSigningKey`] with Winternitz parameter `w` of 2, 4, 16 or 256.
+    pub fn random(w: usize, mut rng: impl RngCore + CryptoRng) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let params = WotsParams::new::<T>(w)?;
+        let mut seed = vec![0u8; params.n];
+        rng.fill_bytes(&mut seed);
+        let mut data = vec![0u8; params.len() * params.n];
+        rng.fill_bytes(&mut data);
+        Ok(Self {
+            params,
+            seed,
+            values: MultiVec {
+                data,
+                axes: [params.len(), params.n],
+            },
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.params.w()
+    }
+
+    /// Has this key been used.
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Signs the data.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<WotsPlusSignature<T>> {
+        if self.used {
+            return Err(LamportError::PrivateKeyReuseError);
+        }
+        let digest = T::digest(data.as_ref());
+        check_digest::<T>(&digest)?;
+        self.used = true;
+        let masks = ChainMasks::new::<T>(&self.params, &self.seed);
+        let mut signature = Vec::with_capacity(self.values.len());
+        for (value, steps) in self
+            .values
+            .data
+            .chunks_exact(self.params.n)
+            .zip(self.params.digits(&digest))
+        {
+            signature.extend_from_slice(&masks.chain::<T>(value, 0, steps));
+        }
+        Ok(WotsPlusSignature {
+            params: self.params,
+            values: MultiVec {
+                data: signature,
+                axes: self.values.axes,
+            },
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Converts the [`WotsPlusSigningKey`] to canonical bytes.
+    ///
+    /// The format is `log2(w)`, the used flag, the public seed, then the secret values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(2 + self.seed.len() + self.values.len());
+        bytes.push(self.params.log_w_byte());
+        bytes.push(u8::from(self.used));
+        bytes.extend_from_slice(&self.seed);
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`WotsPlusSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let params = WotsParams::from_log_w::<T>(input.first())
+            .ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 2 + (params.len() + 1) * params.n || input[1] > 1 {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (seed, values) = input[2..].split_at(params.n);
+        Ok(Self {
+            params,
+            seed: seed.to_vec(),
+            values: MultiVec {
+                data: values.to_vec(),
+                axes: [params.len(), params.n],
+            },
+            used: input[1] == 1,
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A WOTS+ one-time verifying key, the public seed and the end of each hash chain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WotsPlusVerifyingKey<T: LamportDigest> {
+    pub(crate) params: WotsParams,
+    pub(crate) seed: Vec<u8>,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(WotsPlusVerifyingKey);
+vec_impl!(WotsPlusVerifyingKey);
+
+impl<T: LamportDigest> From<&WotsPlusSigningKey<T>> for WotsPlusVerifyingKey<T> {
+    fn from(value: &WotsPlusSigningKey<T>) -> Self {
+        let masks = ChainMasks::new::<T>(&value.params, &value.seed);
+        let steps = value.params.w() - 1;
+        let data = value
+            .values
+            .data
+            .chunks_exact(value.params.n)
+            .flat_map(|v| masks.chain::<T>(v, 0, steps))
+            .collect();
+        Self {
+            params: value.params,
+            seed: value.seed.clone(),
+            values: MultiVec {
+                data,
+                axes: value.values.axes,
+            },
+            algorithm: PhantomData,
+        }
+    }
+}
+
+impl<T: LamportDigest> WotsPlusVerifyingKey<T> {
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.params.w()
+    }
+
+    /// Verifies the signature by completing each masked hash chain.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &WotsPlusSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        if signature.params != self.params || signature.values.axes != self.values.axes {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        if signature.recover_ends(&self.seed, data.as_ref())? == self.values.data {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Converts the [`WotsPlusVerifyingKey`] to canonical bytes,
+    /// `log2(w)`, the public seed, then the chain ends.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.seed.len() + self.values.len());
+        bytes.push(self.params.log_w_byte());
+        bytes.extend_from_slice(&self.seed);
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`WotsPlusVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let params = WotsParams::from_log_w::<T>(input.first())
+            .ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 1 + (params.len() + 1) * params.n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (seed, values) = input[1..].split_at(params.n);
+        Ok(Self {
+            params,
+            seed: seed.to_vec(),
+            values: MultiVec {
+                data: values.to_vec(),
+                axes: [params.len(), params.n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature generated by [`WotsPlusSigningKey`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WotsPlusSignature<T: LamportDigest> {
+    pub(crate) params: WotsParams,
+    pub(crate) values: MultiVec<u8, 2>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(WotsPlusSignature);
+vec_impl!(WotsPlusSignature);
+
+impl<T: LamportDigest> WotsPlusSignature<T> {
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.params.w()
+    }
+
+    /// Completes each chain from the signature, giving the chain ends of the
+    /// verifying key with public seed `seed` if the signature is valid.
+    pub(crate) fn recover_ends(&self, seed: &[u8], data: &[u8]) -> LamportResult<Vec<u8>> {
+        let digest = T::digest(data);
+        check_digest::<T>(&digest)?;
+        let masks = ChainMasks::new::<T>(&self.params, seed);
+        let last = self.params.w() - 1;
+        Ok(self
+            .values
+            .data
+            .chunks_exact(self.params.n)
+            .zip(self.params.digits(&digest))
+            .flat_map(|(value, digit)| masks.chain::<T>(value, digit, last - digit))
+            .collect())
+    }
+
+    /// Converts the [`WotsPlusSignature`] to canonical bytes, `log2(w)` then the chain values.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.values.len());
+        bytes.push(self.params.log_w_byte());
+        bytes.extend_from_slice(&self.values.data);
+        bytes
+    }
+
+    /// Constructs a [`WotsPlusSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        let input = input.as_ref();
+        let params = WotsParams::from_log_w::<T>(input.first())
+            .ok_or(LamportError::InvalidSignatureBytes)?;
+        if input.len() != 1 + params.len() * params.n {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        Ok(Self {
+            params,
+            values: MultiVec {
+                data: input[1..].to_vec(),
+                axes: [params.len(), params.n],
+            },
+            algorithm: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wots::{WotsSigningKey, WotsVerifyingKey};
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn wots_plus() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        for w in [2, 4, 16, 256] {
+            let mut sk = WotsPlusSigningKey::<D>::random(w, &mut rng).unwrap();
+            let pk = WotsPlusVerifyingKey::from(&sk);
+            let signature = sk.sign(b"wots+").unwrap();
+            assert_eq!(signature.w(), w);
+            assert!(pk.verify(&signature, b"wots+").is_ok());
+            assert!(pk.verify(&signature, b"wots").is_err());
+            assert_eq!(
+                sk.sign(b"again").unwrap_err(),
+                LamportError::PrivateKeyReuseError
+            );
+
+            let sk = WotsPlusSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+            assert!(sk.used());
+            assert_eq!(WotsPlusVerifyingKey::from(&sk).to_bytes(), pk.to_bytes());
+            let pk = WotsPlusVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+            let signature = WotsPlusSignature::<D>::from_bytes(signature.to_bytes()).unwrap();
+            assert!(pk.verify(&signature, b"wots+").is_ok());
+        }
+
+        // The same secret values under another public seed give unrelated chains
+        let mut sk = WotsPlusSigningKey::<D>::random(16, &mut rng).unwrap();
+        let mut reseeded = sk.clone();
+        reseeded.seed = vec![0u8; 32];
+        let signature = sk.sign(b"wots+").unwrap();
+        assert!(WotsPlusVerifyingKey::from(&reseeded)
+            .verify(&signature, b"wots+")
+            .is_err());
+
+        // Plain WOTS and WOTS+ chains differ for the same secret values
+        let plain = WotsSigningKey::<D> {
+            params: sk.params,
+            values: sk.values.clone(),
+            used: false,
+            algorithm: PhantomData,
+        };
+        assert_ne!(
+            WotsVerifyingKey::from(&plain).values,
+            WotsPlusVerifyingKey::from(&sk).values
+        );
+    }
+}
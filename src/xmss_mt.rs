@@ -0,0 +1,620 @@
+/*
+    Copyright Michael Lodder. All Rights Reserved.
+    SPDX-License-Identifier: Apache-2.0
+*/
+use crate::hash::{check_digest_size, check_security};
+use crate::limits::Limits;
+use crate::wots::WotsParams;
+use crate::wots_plus::{WotsPlusSignature, WotsPlusSigningKey, WotsPlusVerifyingKey};
+use crate::{
+    KeyId, LamportDigest, LamportError, LamportResult, MerkleSubtree, MultiVec, SubtreeCache,
+};
+use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
+use zeroize::Zeroize;
+
+const SECRET_DOMAIN: &[u8] = b"lamport_signature_plus xmss^mt secret v1";
+const MASK_SEED_DOMAIN: &[u8] = b"lamport_signature_plus xmss^mt mask seed v1";
+const LEAF_DOMAIN: &[u8] = b"lamport_signature_plus xmss^mt leaf v1";
+const NODE_DOMAIN: &[u8] = b"lamport_signature_plus xmss^mt node v1";
+const MESSAGE_DOMAIN: &[u8] = b"lamport_signature_plus xmss^mt message v1";
+
+/// The largest total height, so indices fit in a `u64`.
+pub const XMSS_MT_MAX_HEIGHT: u32 = 60;
+/// The largest height of one subtree, bounding keygen and cache memory.
+pub const XMSS_MT_MAX_SUBTREE_HEIGHT: u32 = 20;
+
+/// The shape of an XMSS^MT hypertree.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct XmssMtParams {
+    height: u32,
+    layers: u32,
+    w: usize,
+}
+
+impl XmssMtParams {
+    /// A hypertree of `layers` layers of subtrees with `2^height` leaves in
+    /// total, and WOTS+ keys with Winternitz parameter `w`.
+    ///
+    /// `height` must be a multiple of `layers`, at most [`XMSS_MT_MAX_HEIGHT`],
+    /// and give subtrees of at most [`XMSS_MT_MAX_SUBTREE_HEIGHT`].
+    pub fn new(height: u32, layers: u32, w: usize) -> LamportResult<Self> {
+        if layers == 0
+            || height == 0
+            || height > XMSS_MT_MAX_HEIGHT
+            || !height.is_multiple_of(layers)
+            || height / layers > XMSS_MT_MAX_SUBTREE_HEIGHT
+            || !matches!(w, 2 | 4 | 16 | 256)
+        {
+            return Err(LamportError::InvalidHypertreeParameters);
+        }
+        Ok(Self { height, layers, w })
+    }
+
+    /// The total height.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of layers.
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// The height of each subtree.
+    pub fn subtree_height(&self) -> u32 {
+        self.height / self.layers
+    }
+
+    /// The Winternitz parameter.
+    pub fn w(&self) -> usize {
+        self.w
+    }
+
+    /// The number of signatures a key can make, `2^height`.
+    pub fn capacity(&self) -> u64 {
+        1 << self.height
+    }
+
+    pub(crate) fn to_bytes(self) -> [u8; 3] {
+        [
+            self.height.to_le_bytes()[0],
+            self.layers.to_le_bytes()[0],
+            self.w.trailing_zeros().to_le_bytes()[0],
+        ]
+    }
+
+    pub(crate) fn from_bytes(input: &[u8]) -> Option<Self> {
+        let log_w = u32::from(*input.get(2)?);
+        if log_w == 0 || log_w > 8 {
+            return None;
+        }
+        Self::new(u32::from(input[0]), u32::from(input[1]), 1 << log_w).ok()
+    }
+}
+
+/// The position of a one-time key or node in the hypertree.
+#[derive(Debug, Copy, Clone)]
+struct Address {
+    layer: u32,
+    tree: u64,
+}
+
+impl Address {
+    fn bytes(&self, leaf: u32) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..4].copy_from_slice(&self.layer.to_be_bytes());
+        bytes[4..12].copy_from_slice(&self.tree.to_be_bytes());
+        bytes[12..].copy_from_slice(&leaf.to_be_bytes());
+        bytes
+    }
+
+    /// The public seed of the WOTS+ key at `leaf`.
+    fn wots_seed<T: LamportDigest>(&self, pub_seed: &[u8], leaf: u32) -> Vec<u8> {
+        T::digest_concat(&[MASK_SEED_DOMAIN, pub_seed, &self.bytes(leaf)])
+    }
+
+    fn leaf_hash<T: LamportDigest>(&self, pub_seed: &[u8], leaf: u32, ends: &[u8]) -> Vec<u8> {
+        T::digest_concat(&[LEAF_DOMAIN, pub_seed, &self.bytes(leaf), ends])
+    }
+
+    fn node_hash<T: LamportDigest>(
+        &self,
+        pub_seed: &[u8],
+        level: u32,
+        index: u32,
+        left: &[u8],
+        right: &[u8],
+    ) -> Vec<u8> {
+        T::digest_concat(&[
+            NODE_DOMAIN,
+            pub_seed,
+            &self.bytes(index),
+            &level.to_be_bytes(),
+            left,
+            right,
+        ])
+    }
+}
+
+/// A multi-tree XMSS^MT style many-time signing key.
+///
+/// The key is a hypertree of WOTS+ keys: each subtree on the bottom layer
+/// signs messages with its leaves, and each subtree above signs the roots of
+/// the subtrees below. Every one-time key is derived from a secret seed and
+/// its address, so only the top subtree is built at key generation and a
+/// key covering `2^60` signatures is created as quickly as one covering
+/// `2^(60 / layers)`. A [`SubtreeCache`] keeps the subtree in use on each
+/// layer between signatures.
+///
+/// The key is stateful. Its canonical bytes include the index of the next
+/// one-time key and must be persisted after every signature, or a
+/// restored copy will sign again with keys that were already used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmssMtSigningKey<T: LamportDigest> {
+    pub(crate) params: XmssMtParams,
+    pub(crate) sk_seed: Vec<u8>,
+    pub(crate) pub_seed: Vec<u8>,
+    pub(crate) root: Vec<u8>,
+    pub(crate) next_index: u64,
+    pub(crate) cache: SubtreeCache,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(XmssMtSigningKey);
+vec_impl!(XmssMtSigningKey);
+
+impl<T: LamportDigest> Zeroize for XmssMtSigningKey<T> {
+    fn zeroize(&mut self) {
+        self.sk_seed.zeroize();
+        self.cache.clear();
+    }
+}
+
+impl<T: LamportDigest> XmssMtSigningKey<T> {
+    /// Generates a key by building the top subtree.
+    pub fn generate(
+        params: XmssMtParams,
+        mut rng: impl RngCore + CryptoRng,
+    ) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let n = T::digest_size_in_bits() / 8;
+        let mut sk_seed = vec![0u8; n];
+        rng.fill_bytes(&mut sk_seed);
+        let mut pub_seed = vec![0u8; n];
+        rng.fill_bytes(&mut pub_seed);
+        let mut key = Self {
+            params,
+            sk_seed,
+            pub_seed,
+            root: Vec::new(),
+            next_index: 0,
+            cache: SubtreeCache::new(),
+            algorithm: PhantomData,
+        };
+        key.root = key.subtree(params.layers - 1, 0)?.root().to_vec();
+        Ok(key)
+    }
+
+    /// The public key.
+    pub fn verifying_key(&self) -> XmssMtVerifyingKey<T> {
+        XmssMtVerifyingKey {
+            params: self.params,
+            pub_seed: self.pub_seed.clone(),
+            root: self.root.clone(),
+            algorithm: PhantomData,
+        }
+    }
+
+    /// The hypertree parameters.
+    pub fn params(&self) -> XmssMtParams {
+        self.params
+    }
+
+    /// The index of the next one-time key.
+    pub fn index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// The number of signatures left.
+    pub fn remaining(&self) -> u64 {
+        self.params.capacity() - self.next_index
+    }
+
+    /// Signs the data with the next one-time key.
+    ///
+    /// Returns [`LamportError::KeyPoolExhausted`] once every one-time key has been used.
+    pub fn sign<B: AsRef<[u8]>>(&mut self, data: B) -> LamportResult<XmssMtSignature<T>> {
+        if self.next_index >= self.params.capacity() {
+            return Err(LamportError::KeyPoolExhausted);
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+        let current = message_hash::<T>(&self.root, index, data.as_ref());
+        self.sign_hash(index, current)
+    }
+
+    /// Signs `current` with the one-time key at `index` and each subtree
+    /// root above it, without checking or advancing the next index.
+    pub(crate) fn sign_hash(
+        &mut self,
+        index: u64,
+        mut current: Vec<u8>,
+    ) -> LamportResult<XmssMtSignature<T>> {
+        let subtree_height = self.params.subtree_height();
+        let mut tree = index >> subtree_height;
+        let mut leaf = leaf_index(index, subtree_height);
+        let mut layers = Vec::with_capacity(self.params.layers as usize);
+        for layer in 0..self.params.layers {
+            let address = Address { layer, tree };
+            let signature = self.wots_key(address, leaf)?.sign(&current)?;
+            let subtree = self.subtree(layer, tree)?;
+            let path = subtree.auth_path(leaf)?;
+            current = subtree.root().to_vec();
+            layers.push((signature, path));
+            leaf = leaf_index(tree, subtree_height);
+            tree >>= subtree_height;
+        }
+        Ok(XmssMtSignature {
+            params: self.params,
+            index,
+            layers,
+        })
+    }
+
+    /// Converts the [`XmssMtSigningKey`] to canonical bytes.
+    ///
+    /// The format is the height, layers and `log2(w)` as single bytes, the
+    /// next index as a big endian `u64`, then the secret seed, public seed
+    /// and root. The subtree cache is rebuilt when needed.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(11 + self.sk_seed.len() * 3);
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.next_index.to_be_bytes());
+        bytes.extend_from_slice(&self.sk_seed);
+        bytes.extend_from_slice(&self.pub_seed);
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    /// Constructs a [`XmssMtSigningKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_security::<T>()?;
+        let input = input.as_ref();
+        let n = T::digest_size_in_bits() / 8;
+        let params = XmssMtParams::from_bytes(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 11 + 3 * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let next_index = u64::from_be_bytes(
+            input[3..11]
+                .try_into()
+                .map_err(|_| LamportError::InvalidPrivateKeyBytes)?,
+        );
+        if next_index > params.capacity() {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (sk_seed, rest) = input[11..].split_at(n);
+        let (pub_seed, root) = rest.split_at(n);
+        Ok(Self {
+            params,
+            sk_seed: sk_seed.to_vec(),
+            pub_seed: pub_seed.to_vec(),
+            root: root.to_vec(),
+            next_index,
+            cache: SubtreeCache::new(),
+            algorithm: PhantomData,
+        })
+    }
+
+    /// Derives the WOTS+ key at the address from the secret seed.
+    fn wots_key(&self, address: Address, leaf: u32) -> LamportResult<WotsPlusSigningKey<T>> {
+        let params = WotsParams::new::<T>(self.params.w)?;
+        let address_bytes = address.bytes(leaf);
+        let data = (0..params.len())
+            .flat_map(|chain| {
+                let chain = u32::try_from(chain).expect("fewer than 2^32 chains");
+                T::digest_concat(&[
+                    SECRET_DOMAIN,
+                    &self.sk_seed,
+                    &address_bytes,
+                    &chain.to_be_bytes(),
+                ])
+            })
+            .collect();
+        Ok(WotsPlusSigningKey {
+            params,
+            seed: address.wots_seed::<T>(&self.pub_seed, leaf),
+            values: MultiVec {
+                data,
+                axes: [params.len(), params.n],
+            },
+            used: false,
+            algorithm: PhantomData,
+        })
+    }
+
+    fn subtree(&mut self, layer: u32, tree: u64) -> LamportResult<&MerkleSubtree> {
+        let address = Address { layer, tree };
+        let height = self.params.subtree_height();
+        let mut leaves = Vec::with_capacity(1 << height);
+        if !self.cached(layer, tree) {
+            for leaf in 0..1u32 << height {
+                let wots = self.wots_key(address, leaf)?;
+                let ends = WotsPlusVerifyingKey::from(&wots).values.data;
+                leaves.push(address.leaf_hash::<T>(&self.pub_seed, leaf, &ends));
+            }
+        }
+        let pub_seed = &self.pub_seed;
+        Ok(self.cache.get_or_build(layer as usize, tree, || {
+            MerkleSubtree::build(
+                height,
+                |leaf| std::mem::take(&mut leaves[leaf as usize]),
+                |level, index, left, right| {
+                    address.node_hash::<T>(pub_seed, level, index, left, right)
+                },
+            )
+        }))
+    }
+
+    fn cached(&self, layer: u32, tree: u64) -> bool {
+        self.cache.contains(layer as usize, tree)
+    }
+}
+
+/// The public key of an [`XmssMtSigningKey`], the public seed and the root of the top subtree.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XmssMtVerifyingKey<T: LamportDigest> {
+    pub(crate) params: XmssMtParams,
+    pub(crate) pub_seed: Vec<u8>,
+    pub(crate) root: Vec<u8>,
+    pub(crate) algorithm: PhantomData<T>,
+}
+
+serde_impl!(XmssMtVerifyingKey);
+vec_impl!(XmssMtVerifyingKey);
+
+impl<T: LamportDigest> XmssMtVerifyingKey<T> {
+    /// The hypertree parameters.
+    pub fn params(&self) -> XmssMtParams {
+        self.params
+    }
+
+    /// The [`KeyId`] fingerprint of the key.
+    pub fn fingerprint(&self) -> KeyId {
+        KeyId::compute_encoded(b"XMSS^MT", T::digest_size_in_bits(), &self.to_bytes())
+    }
+
+    /// Verifies the signature by recomputing the root from each layer's
+    /// one-time signature and authentication path.
+    pub fn verify<B: AsRef<[u8]>>(
+        &self,
+        signature: &XmssMtSignature<T>,
+        data: B,
+    ) -> LamportResult<()> {
+        let current = message_hash::<T>(&self.root, signature.index, data.as_ref());
+        if self.recover_root(signature, current)? == self.root {
+            Ok(())
+        } else {
+            Err(LamportError::InvalidSignatureBytes)
+        }
+    }
+
+    /// Recomputes the root from the signature of `current`.
+    pub(crate) fn recover_root(
+        &self,
+        signature: &XmssMtSignature<T>,
+        mut current: Vec<u8>,
+    ) -> LamportResult<Vec<u8>> {
+        if signature.params != self.params || signature.index >= self.params.capacity() {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let subtree_height = self.params.subtree_height();
+        let mut tree = signature.index >> subtree_height;
+        let mut leaf = leaf_index(signature.index, subtree_height);
+        for ((wots, path), layer) in signature.layers.iter().zip(0..) {
+            let address = Address { layer, tree };
+            let seed = address.wots_seed::<T>(&self.pub_seed, leaf);
+            let ends = wots.recover_ends(&seed, &current)?;
+            let leaf_hash = address.leaf_hash::<T>(&self.pub_seed, leaf, &ends);
+            current = MerkleSubtree::root_from_path(
+                leaf,
+                &leaf_hash,
+                path,
+                |level, index, left, right| {
+                    address.node_hash::<T>(&self.pub_seed, level, index, left, right)
+                },
+            );
+            leaf = leaf_index(tree, subtree_height);
+            tree >>= subtree_height;
+        }
+        Ok(current)
+    }
+
+    /// Converts the [`XmssMtVerifyingKey`] to canonical bytes, the height,
+    /// layers and `log2(w)` as single bytes, then the public seed and root.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + self.pub_seed.len() * 2);
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.pub_seed);
+        bytes.extend_from_slice(&self.root);
+        bytes
+    }
+
+    /// Constructs a [`XmssMtVerifyingKey`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        check_digest_size::<T>()?;
+        let input = input.as_ref();
+        let n = T::digest_size_in_bits() / 8;
+        let params = XmssMtParams::from_bytes(input).ok_or(LamportError::InvalidPrivateKeyBytes)?;
+        if input.len() != 3 + 2 * n {
+            return Err(LamportError::InvalidPrivateKeyBytes);
+        }
+        let (pub_seed, root) = input[3..].split_at(n);
+        Ok(Self {
+            params,
+            pub_seed: pub_seed.to_vec(),
+            root: root.to_vec(),
+            algorithm: PhantomData,
+        })
+    }
+}
+
+/// A signature generated by [`XmssMtSigningKey`].
+///
+/// It holds the index of the one-time key and, for each layer from the
+/// bottom, a WOTS+ signature and the authentication path of its key.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct XmssMtSignature<T: LamportDigest> {
+    pub(crate) params: XmssMtParams,
+    pub(crate) index: u64,
+    pub(crate) layers: Vec<(WotsPlusSignature<T>, Vec<Vec<u8>>)>,
+}
+
+serde_impl!(@limits XmssMtSignature);
+vec_impl!(XmssMtSignature);
+
+impl<T: LamportDigest> XmssMtSignature<T> {
+    /// The index of the one-time key that made the signature.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+
+    /// Converts the [`XmssMtSignature`] to canonical bytes.
+    ///
+    /// The format is the height, layers and `log2(w)` as single bytes, the
+    /// index as a big endian `u64`, then for each layer the WOTS+ chain
+    /// values followed by the authentication path.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.params.to_bytes());
+        bytes.extend_from_slice(&self.index.to_be_bytes());
+        for (wots, path) in &self.layers {
+            bytes.extend_from_slice(&wots.values.data);
+            path.iter().for_each(|node| bytes.extend_from_slice(node));
+        }
+        bytes
+    }
+
+    /// Constructs a [`XmssMtSignature`] from canonical bytes.
+    pub fn from_bytes<B: AsRef<[u8]>>(input: B) -> LamportResult<Self> {
+        Self::from_bytes_with_limits(input.as_ref(), &Limits::DEFAULT)
+    }
+
+    /// Constructs a [`XmssMtSignature`] from canonical bytes no larger than `limits`.
+    pub fn from_bytes_with_limits(input: &[u8], limits: &Limits) -> LamportResult<Self> {
+        limits.check(input.len())?;
+        let params = XmssMtParams::from_bytes(input).ok_or(LamportError::InvalidSignatureBytes)?;
+        let wots = WotsParams::new::<T>(params.w)?;
+        let wots_length = wots.len() * wots.n;
+        let path_length = params.subtree_height() as usize * wots.n;
+        if input.len() != 11 + params.layers as usize * (wots_length + path_length) {
+            return Err(LamportError::InvalidSignatureBytes);
+        }
+        let index = u64::from_be_bytes(
+            input[3..11]
+                .try_into()
+                .map_err(|_| LamportError::InvalidSignatureBytes)?,
+        );
+        let layers = input[11..]
+            .chunks_exact(wots_length + path_length)
+            .map(|layer| {
+                let (values, path) = layer.split_at(wots_length);
+                (
+                    WotsPlusSignature {
+                        params: wots,
+                        values: MultiVec {
+                            data: values.to_vec(),
+                            axes: [wots.len(), wots.n],
+                        },
+                        algorithm: PhantomData,
+                    },
+                    path.chunks_exact(wots.n).map(<[u8]>::to_vec).collect(),
+                )
+            })
+            .collect();
+        Ok(Self {
+            params,
+            index,
+            layers,
+        })
+    }
+}
+
+/// Binds the message to the key and the one-time key index.
+fn message_hash<T: LamportDigest>(root: &[u8], index: u64, data: &[u8]) -> Vec<u8> {
+    T::digest_concat(&[MESSAGE_DOMAIN, root, &index.to_be_bytes(), data])
+}
+
+/// The leaf of a subtree addressed by the low `height` bits.
+fn leaf_index(index: u64, height: u32) -> u32 {
+    u32::try_from(index & ((1 << height) - 1)).expect("subtree heights are at most 20")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LamportFixedDigest;
+    use rand::SeedableRng;
+    use sha2::Sha256;
+    const SEED: [u8; 32] = [3u8; 32];
+
+    #[test]
+    fn xmss_mt() {
+        type D = LamportFixedDigest<Sha256>;
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let params = XmssMtParams::new(4, 2, 16).unwrap();
+        let mut sk = XmssMtSigningKey::<D>::generate(params, &mut rng).unwrap();
+        let pk = sk.verifying_key();
+        assert_eq!(sk.remaining(), 16);
+
+        let mut signatures = Vec::new();
+        for i in 0..16u8 {
+            let signature = sk.sign([i]).unwrap();
+            assert_eq!(signature.index(), u64::from(i));
+            assert!(pk.verify(&signature, [i]).is_ok());
+            assert!(pk.verify(&signature, [i + 1]).is_err());
+            signatures.push(signature);
+        }
+        assert_eq!(
+            sk.sign(b"more").unwrap_err(),
+            LamportError::KeyPoolExhausted
+        );
+
+        // Signatures from one key index cannot be moved to another
+        let mut moved = signatures[5].clone();
+        moved.index = 6;
+        assert!(pk.verify(&moved, [5]).is_err());
+
+        let bytes = signatures[9].to_bytes();
+        let decoded = XmssMtSignature::<D>::from_bytes(&bytes).unwrap();
+        assert!(pk.verify(&decoded, [9]).is_ok());
+        assert!(XmssMtSignature::<D>::from_bytes(&bytes[1..]).is_err());
+        let pk = XmssMtVerifyingKey::<D>::from_bytes(pk.to_bytes()).unwrap();
+        assert!(pk.verify(&decoded, [9]).is_ok());
+
+        // Restoring the key keeps the index, and the cache is rebuilt on demand
+        let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+        let mut sk = XmssMtSigningKey::<D>::generate(params, &mut rng).unwrap();
+        sk.sign(b"first").unwrap();
+        let mut restored = XmssMtSigningKey::<D>::from_bytes(sk.to_bytes()).unwrap();
+        assert_eq!(restored.index(), 1);
+        let signature = restored.sign(b"second").unwrap();
+        assert_eq!(signature.to_bytes(), sk.sign(b"second").unwrap().to_bytes());
+
+        // A key for 2^60 signatures only builds its top subtree up front
+        let params = XmssMtParams::new(60, 12, 16).unwrap();
+        assert_eq!(params.capacity(), 1 << 60);
+        let mut sk = XmssMtSigningKey::<D>::generate(params, &mut rng).unwrap();
+        assert_eq!(sk.cache.len(), 1);
+        let signature = sk.sign(b"large").unwrap();
+        assert!(sk.verifying_key().verify(&signature, b"large").is_ok());
+
+        for (height, layers, w) in [(0, 1, 16), (61, 1, 16), (10, 3, 16), (21, 1, 16), (4, 2, 8)] {
+            assert_eq!(
+                XmssMtParams::new(height, layers, w).unwrap_err(),
+                LamportError::InvalidHypertreeParameters
+            );
+        }
+    }
+}
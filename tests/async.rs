@@ -0,0 +1,85 @@
+#![cfg(feature = "async")]
+use futures_core::Stream;
+use lamport_signature_plus::{generate_keys, LamportError, LamportFixedDigest};
+use rand::SeedableRng;
+use sha2::Sha256;
+use std::future::Future;
+use std::io;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll, Waker};
+
+type D = LamportFixedDigest<Sha256>;
+
+/// Yields the chunks, returning `Pending` before each one like a slow socket.
+struct Chunks {
+    chunks: Vec<io::Result<Vec<u8>>>,
+    ready: bool,
+}
+
+impl Chunks {
+    fn new(chunks: Vec<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            chunks,
+            ready: false,
+        }
+    }
+}
+
+impl Stream for Chunks {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if !self.ready {
+            self.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.ready = false;
+        if self.chunks.is_empty() {
+            Poll::Ready(None)
+        } else {
+            Poll::Ready(Some(self.chunks.remove(0)))
+        }
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[test]
+fn verify_async_stream() {
+    let rng = rand_chacha::ChaCha8Rng::from_seed([5u8; 32]);
+    let (mut sk, pk) = generate_keys::<D, _>(rng).unwrap();
+    let message = (0..10_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let signature = sk.sign(&message).unwrap();
+
+    let chunks = message.chunks(999).map(|c| Ok(c.to_vec())).collect();
+    assert!(block_on(pk.verify_async_stream(&signature, Chunks::new(chunks))).is_ok());
+
+    let mut tampered = message
+        .chunks(999)
+        .map(|c| Ok(c.to_vec()))
+        .collect::<Vec<_>>();
+    tampered.pop();
+    assert!(block_on(pk.verify_async_stream(&signature, Chunks::new(tampered))).is_err());
+
+    let failing = vec![
+        Ok(message[..10].to_vec()),
+        Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")),
+    ];
+    let err = block_on(pk.verify_async_stream(&signature, Chunks::new(failing))).unwrap_err();
+    assert!(matches!(
+        err,
+        LamportError::IoError {
+            kind: io::ErrorKind::ConnectionReset,
+            ..
+        }
+    ));
+}
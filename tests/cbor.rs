@@ -0,0 +1,28 @@
+#![cfg(feature = "cbor")]
+use lamport_signature_plus::{generate_keys, LamportFixedDigest};
+use rand::SeedableRng;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+
+#[test]
+fn sign_and_verify_serde() {
+    let rng = rand_chacha::ChaCha8Rng::from_seed([9u8; 32]);
+    let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+
+    let mut value = HashMap::new();
+    for (i, name) in ["zeta", "alpha", "mu", "b", "longer key"]
+        .iter()
+        .enumerate()
+    {
+        value.insert(name.to_string(), i as u64 * 1000);
+    }
+    let signature = sk.sign_serde(&value).unwrap();
+    assert!(pk.verify_serde(&signature, &value).is_ok());
+
+    // The same map in a different container and insertion order encodes identically.
+    let ordered = value.clone().into_iter().collect::<BTreeMap<_, _>>();
+    assert!(pk.verify_serde(&signature, &ordered).is_ok());
+
+    value.insert("mu".to_string(), 1);
+    assert!(pk.verify_serde(&signature, &value).is_err());
+}
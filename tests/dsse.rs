@@ -0,0 +1,75 @@
+#![cfg(feature = "dsse")]
+use lamport_signature_plus::dsse::{pae, DsseEnvelope};
+use lamport_signature_plus::{
+    generate_keys, KeyRegistry, LamportError, LamportFixedDigest, SignerPolicy,
+};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+type D = LamportFixedDigest<Sha256>;
+
+#[test]
+fn pre_authentication_encoding() {
+    assert_eq!(
+        pae("http://example.com/HelloWorld", b"hello world"),
+        b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+    );
+    assert_eq!(pae("", b""), b"DSSEv1 0  0 ");
+}
+
+#[test]
+fn sign_and_verify_envelope() {
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([7u8; 32]);
+    let (mut first, first_pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let (mut second, second_pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let (_, outsider) = generate_keys::<D, _>(&mut rng).unwrap();
+
+    let statement = br#"{"_type":"https://in-toto.io/Statement/v1"}"#;
+    let mut envelope = DsseEnvelope::new("application/vnd.in-toto+json", statement.to_vec());
+    let key_id = envelope.sign(&mut first).unwrap();
+    assert_eq!(envelope.signatures()[0].keyid(), key_id.to_string());
+    assert!(envelope.verify(&first_pk).is_ok());
+    assert!(envelope.verify(&second_pk).is_err());
+
+    envelope.sign(&mut second).unwrap();
+    let json = envelope.to_json();
+    let parsed = DsseEnvelope::from_json(&json).unwrap();
+    assert_eq!(parsed, envelope);
+    assert_eq!(parsed.payload(), statement);
+
+    let mut registry = KeyRegistry::new();
+    registry.insert(first_pk.clone());
+    registry.insert(second_pk);
+    registry.insert(outsider);
+    assert!(parsed
+        .verify_policy(&registry, SignerPolicy::Threshold(2))
+        .is_ok());
+    assert_eq!(
+        parsed.verify_policy(&registry, SignerPolicy::All),
+        Err(LamportError::PolicyNotSatisfied {
+            valid: 2,
+            required: 3
+        })
+    );
+
+    // The signature covers the payload type as well as the payload
+    let retyped = json.replace("application/vnd.in-toto+json", "text/plain");
+    let retyped = DsseEnvelope::from_json(&retyped).unwrap();
+    assert!(retyped.verify(&first_pk).is_err());
+}
+
+#[test]
+fn parse_envelope() {
+    let json = r#"{"payload":"aGk_","payloadType":"t","signatures":[{"sig":"AA=="}]}"#;
+    let envelope = DsseEnvelope::from_json(json).unwrap();
+    assert_eq!(envelope.payload(), b"hi?");
+    assert_eq!(envelope.signatures()[0].keyid(), "");
+    assert_eq!(envelope.signatures()[0].sig(), &[0u8]);
+    assert!(envelope.to_json().contains(r#""payload":"aGk/""#));
+
+    assert_eq!(
+        DsseEnvelope::from_json(r#"{"payload":"aGk","signatures":[]}"#),
+        Err(LamportError::InvalidEnvelope)
+    );
+    assert!(DsseEnvelope::from_json("not json").is_err());
+}
@@ -0,0 +1,147 @@
+//! Timing leak checks, see the `dudect` module.
+//!
+//! The checks are ignored by default since they are slow and only meaningful
+//! in release mode on an idle machine.
+#![cfg(feature = "dudect")]
+use lamport_signature_plus::dudect::{t_test, Class, TimingReport};
+use lamport_signature_plus::{LamportFixedDigest, Signature, SigningKey, VerifyingKey};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sha2::Sha256;
+
+type D = LamportFixedDigest<Sha256>;
+
+const SAMPLES: usize = 20_000;
+const MESSAGE: &[u8] = b"dudect";
+
+fn rng(tag: u8) -> ChaCha8Rng {
+    ChaCha8Rng::from_seed([tag; 32])
+}
+
+fn check(name: &str, report: TimingReport) {
+    println!(
+        "{}: t = {:.3} over {} samples",
+        name, report.t, report.samples
+    );
+    assert!(!report.is_leaky(), "{} leaks timing: {:?}", name, report);
+}
+
+#[test]
+fn report_counts_cropped_samples() {
+    let report = t_test(100, rng(0), |class| class, |_| {});
+    assert!(report.samples <= 100);
+    assert!(report.samples >= 90);
+}
+
+#[test]
+#[ignore = "timing measurement"]
+fn detects_early_exit_comparison() {
+    let secret = vec![0xa5u8; 4096];
+    let mut inputs = rng(1);
+    let report = t_test(
+        SAMPLES,
+        rng(2),
+        |class| match class {
+            Class::Fixed => secret.clone(),
+            Class::Random => {
+                let mut guess = secret.clone();
+                guess[inputs.next_u32() as usize % 16] ^= 1;
+                guess
+            }
+        },
+        |guess| {
+            std::hint::black_box(guess == secret);
+        },
+    );
+    assert!(report.is_leaky(), "the harness missed a leak: {:?}", report);
+}
+
+#[test]
+#[ignore = "timing measurement"]
+fn sign() {
+    let mut keys = rng(3);
+    let fixed = SigningKey::<D>::random(&mut keys).unwrap();
+    let report = t_test(
+        SAMPLES,
+        rng(4),
+        |class| match class {
+            Class::Fixed => fixed.clone(),
+            Class::Random => SigningKey::<D>::random(&mut keys).unwrap(),
+        },
+        |mut key| {
+            std::hint::black_box(key.sign(MESSAGE).unwrap());
+        },
+    );
+    check("sign", report);
+}
+
+#[test]
+#[ignore = "timing measurement"]
+fn verify() {
+    let mut keys = rng(5);
+    let mut signed = || {
+        let mut sk = SigningKey::<D>::random(&mut keys).unwrap();
+        let signature = sk.sign(MESSAGE).unwrap();
+        (VerifyingKey::from(&sk), signature)
+    };
+    let fixed = signed();
+    let report = t_test(
+        SAMPLES,
+        rng(6),
+        |class| match class {
+            Class::Fixed => fixed.clone(),
+            Class::Random => signed(),
+        },
+        |(pk, signature): (VerifyingKey<D>, Signature<D>)| {
+            std::hint::black_box(pk.verify(&signature, MESSAGE)).unwrap();
+        },
+    );
+    check("verify", report);
+}
+
+#[test]
+#[ignore = "timing measurement"]
+fn from_bytes() {
+    let mut keys = rng(7);
+    let fixed = SigningKey::<D>::random(&mut keys).unwrap().to_bytes();
+    let report = t_test(
+        SAMPLES,
+        rng(8),
+        |class| match class {
+            Class::Fixed => fixed.clone(),
+            Class::Random => {
+                // Same allocation as the fixed class, only the secret differs
+                let mut bytes = fixed.clone();
+                keys.fill_bytes(&mut bytes[1..]);
+                bytes
+            }
+        },
+        |bytes| {
+            std::hint::black_box(SigningKey::<D>::from_bytes(bytes).unwrap());
+        },
+    );
+    check("from_bytes", report);
+}
+
+#[test]
+#[ignore = "timing measurement"]
+fn combine() {
+    let mut keys = rng(9);
+    let mut shares = || {
+        let sk = SigningKey::<D>::random(&mut keys).unwrap();
+        sk.split(2, 3, &mut keys).unwrap()
+    };
+    let fixed = shares();
+    let report = t_test(
+        SAMPLES / 4,
+        rng(10),
+        |class| match class {
+            Class::Fixed => fixed.clone(),
+            Class::Random => shares(),
+        },
+        |shares| {
+            std::hint::black_box(SigningKey::combine(&shares[..2]).unwrap());
+        },
+    );
+    check("combine", report);
+}
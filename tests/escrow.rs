@@ -0,0 +1,62 @@
+#![cfg(feature = "escrow")]
+use lamport_signature_plus::{
+    generate_keys, HpkeSecretKey, KeyEscrow, KeyFactory, LamportFixedDigest, SeedEscrow,
+};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+#[test]
+fn escrow_and_recover() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([11u8; 32]);
+    let (sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let officers = (0..3)
+        .map(|_| HpkeSecretKey::random(&mut rng))
+        .collect::<Vec<_>>();
+    let recipients = officers[..2]
+        .iter()
+        .map(HpkeSecretKey::public_key)
+        .collect::<Vec<_>>();
+
+    let escrow = sk.escrow(&recipients, &mut rng).unwrap();
+    assert_eq!(escrow.recipients(), 2);
+    let escrow = KeyEscrow::<D>::from_bytes(escrow.to_bytes()).unwrap();
+    for officer in &officers[..2] {
+        let mut recovered = escrow.recover(officer).unwrap();
+        assert_eq!(recovered.to_bytes(), sk.to_bytes());
+        let signature = recovered.sign(b"recovered").unwrap();
+        assert!(pk.verify(&signature, b"recovered").is_ok());
+    }
+    assert!(escrow.recover(&officers[2]).is_err());
+
+    let mut bytes = escrow.to_bytes();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    let tampered = KeyEscrow::<D>::from_bytes(&bytes).unwrap();
+    assert!(tampered.recover(&officers[0]).is_err());
+    assert!(sk.escrow(&[], &mut rng).is_err());
+}
+
+#[test]
+fn escrow_and_recover_seed() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([12u8; 32]);
+    let factory = KeyFactory::<D>::random(&mut rng).unwrap();
+    let officers = (0..2)
+        .map(|_| HpkeSecretKey::random(&mut rng))
+        .collect::<Vec<_>>();
+
+    let escrow = factory
+        .escrow(&[officers[0].public_key()], &mut rng)
+        .unwrap();
+    assert_eq!(escrow.recipients(), 1);
+    let escrow = SeedEscrow::<D>::from_bytes(escrow.to_bytes()).unwrap();
+    let recovered = escrow.recover(&officers[0]).unwrap();
+    assert_eq!(recovered.to_bytes(), factory.to_bytes());
+    assert_eq!(recovered.verifying_key(7), factory.verifying_key(7));
+    assert!(escrow.recover(&officers[1]).is_err());
+
+    // A seed escrow is not a key escrow
+    assert!(KeyEscrow::<D>::from_bytes(escrow.to_bytes()).is_err());
+    assert!(factory.escrow(&[], &mut rng).is_err());
+}
@@ -0,0 +1,307 @@
+#![cfg(feature = "unstable-flash")]
+use lamport_signature_plus::unstable::flash::{BlockStore, FlashKeyPool, MemoryBlockStore};
+use lamport_signature_plus::{
+    LamportError, LamportFixedDigest, LamportResult, SeedKdf, SigningKey, VerifyingKey,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use sha2::Sha256;
+
+type D = LamportFixedDigest<Sha256>;
+
+const PAGE_SIZE: usize = 256;
+const PAGES: usize = 8;
+const KEY: &[u8] = b"storage key held by the secure element";
+
+/// Loses power before the write after `writes_left` reaches zero.
+#[derive(Debug)]
+struct PowerLoss {
+    inner: MemoryBlockStore,
+    writes_left: usize,
+}
+
+impl BlockStore for PowerLoss {
+    fn page_size(&self) -> usize {
+        self.inner.page_size()
+    }
+
+    fn page_count(&self) -> usize {
+        self.inner.page_count()
+    }
+
+    fn read(&self, page: usize, offset: usize, buffer: &mut [u8]) -> LamportResult<()> {
+        self.inner.read(page, offset, buffer)
+    }
+
+    fn write(&mut self, page: usize, offset: usize, data: &[u8]) -> LamportResult<()> {
+        if self.writes_left == 0 {
+            return Err(LamportError::General("power lost".to_string()));
+        }
+        self.writes_left -= 1;
+        self.inner.write(page, offset, data)
+    }
+
+    fn erase(&mut self, page: usize) -> LamportResult<()> {
+        self.inner.erase(page)
+    }
+}
+
+#[test]
+fn memory_store_enforces_flash_semantics() {
+    let mut store = MemoryBlockStore::new(PAGE_SIZE, 2);
+    store.write(0, 0, &[0xf0]).unwrap();
+    store.write(0, 0, &[0x30]).unwrap();
+    assert_eq!(
+        store.write(0, 0, &[0x70]).unwrap_err(),
+        LamportError::InvalidBlockStore
+    );
+    assert!(store.write(0, PAGE_SIZE - 1, &[0, 0]).is_err());
+    assert!(store.read(2, 0, &mut [0]).is_err());
+
+    store.erase(0).unwrap();
+    let mut byte = [0u8];
+    store.read(0, 0, &mut byte).unwrap();
+    assert_eq!(byte, [0xff]);
+    assert_eq!(store.erase_counts(), &[1, 0]);
+}
+
+#[test]
+fn pool_signs_each_key_once() {
+    let rng = ChaCha8Rng::from_seed([1u8; 32]);
+    let store = MemoryBlockStore::new(PAGE_SIZE, PAGES);
+    let mut pool = FlashKeyPool::<D, _>::provision(store, 20, SeedKdf::V1, KEY, rng).unwrap();
+    assert_eq!(pool.capacity(), 20);
+    assert_eq!(pool.remaining(), 20);
+    let published = pool.verifying_key(0).unwrap();
+
+    let (index, signature) = pool.sign(b"first").unwrap();
+    assert_eq!(index, 0);
+    assert_eq!(signature.verifying_key(), &published);
+    assert!(signature.verify(b"first").is_ok());
+    assert!(pool.is_used(0).unwrap());
+    assert_eq!(
+        pool.verifying_key(0).unwrap_err(),
+        LamportError::PrivateKeyReuseError
+    );
+
+    // Reopening keeps the used flags, and the seeds span page boundaries
+    let mut pool = FlashKeyPool::<D, _>::open(pool.into_inner(), KEY).unwrap();
+    assert_eq!(pool.kdf(), SeedKdf::V1);
+    assert_eq!(pool.remaining(), 19);
+    for expected in 1..20 {
+        let (index, signature) = pool.sign(b"next").unwrap();
+        assert_eq!(index, expected);
+        assert!(signature.verify(b"next").is_ok());
+    }
+    assert_eq!(
+        pool.sign(b"more").unwrap_err(),
+        LamportError::KeyPoolExhausted
+    );
+
+    // Provisioning erased the pages once and signing never erased them
+    let store = pool.into_inner();
+    assert!(store.erase_counts().iter().all(|count| *count == 0));
+    let rng = ChaCha8Rng::from_seed([2u8; 32]);
+    let pool = FlashKeyPool::<D, _>::provision(store, 20, SeedKdf::V1, KEY, rng).unwrap();
+    let store = pool.into_inner();
+    assert_eq!(store.erase_counts()[..3], [1, 1, 1]);
+    assert_eq!(store.erase_counts()[3..], [0; PAGES - 3]);
+}
+
+#[test]
+fn pool_rejects_unformatted_and_small_stores() {
+    let rng = ChaCha8Rng::from_seed([3u8; 32]);
+    assert_eq!(
+        FlashKeyPool::<D, _>::open(MemoryBlockStore::new(PAGE_SIZE, PAGES), KEY).unwrap_err(),
+        LamportError::InvalidBlockStore
+    );
+    assert_eq!(
+        FlashKeyPool::<D, _>::provision(
+            MemoryBlockStore::new(PAGE_SIZE, 1),
+            20,
+            SeedKdf::V1,
+            KEY,
+            rng
+        )
+        .unwrap_err(),
+        LamportError::InvalidBlockStore
+    );
+}
+
+/// Claims more address space than exists, so layout sizes overflow.
+#[derive(Debug)]
+struct HugeStore {
+    word_size: usize,
+}
+
+impl BlockStore for HugeStore {
+    fn page_size(&self) -> usize {
+        1 << (usize::BITS - 1)
+    }
+
+    fn page_count(&self) -> usize {
+        4
+    }
+
+    fn word_size(&self) -> usize {
+        self.word_size
+    }
+
+    fn read(&self, _page: usize, _offset: usize, buffer: &mut [u8]) -> LamportResult<()> {
+        buffer.fill(0xff);
+        Ok(())
+    }
+
+    fn write(&mut self, _page: usize, _offset: usize, _data: &[u8]) -> LamportResult<()> {
+        Ok(())
+    }
+
+    fn erase(&mut self, _page: usize) -> LamportResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn pool_rejects_layouts_that_overflow() {
+    let mut rng = ChaCha8Rng::from_seed([3u8; 32]);
+    for word_size in [1, 1 << (usize::BITS - 1)] {
+        assert_eq!(
+            FlashKeyPool::<D, _>::provision(HugeStore { word_size }, 4, SeedKdf::V1, KEY, &mut rng)
+                .unwrap_err(),
+            LamportError::InvalidBlockStore
+        );
+    }
+
+    // A corrupt header cannot claim more keys than the store holds
+    let pool = FlashKeyPool::<D, _>::provision(
+        MemoryBlockStore::new(PAGE_SIZE, PAGES),
+        4,
+        SeedKdf::V1,
+        KEY,
+        rng,
+    )
+    .unwrap();
+    let mut store = pool.into_inner();
+    let mut header = [0u8; 13];
+    store.read(0, 0, &mut header).unwrap();
+    store.erase(0).unwrap();
+    header[9..13].copy_from_slice(&u32::MAX.to_be_bytes());
+    store.write(0, 0, &header).unwrap();
+    assert_eq!(
+        FlashKeyPool::<D, _>::open(store, KEY).unwrap_err(),
+        LamportError::InvalidBlockStore
+    );
+}
+
+#[test]
+fn power_loss_never_reuses_a_key() {
+    let rng = ChaCha8Rng::from_seed([4u8; 32]);
+    let store = PowerLoss {
+        inner: MemoryBlockStore::new(PAGE_SIZE, PAGES),
+        writes_left: usize::MAX,
+    };
+    let pool = FlashKeyPool::<D, _>::provision(store, 4, SeedKdf::V1, KEY, rng).unwrap();
+    let mut store = pool.into_inner();
+
+    // Losing power before the flag is written leaves the key unused
+    store.writes_left = 0;
+    let mut pool = FlashKeyPool::<D, _>::open(store, KEY).unwrap();
+    assert!(pool.sign(b"lost").is_err());
+    assert!(!pool.is_used(0).unwrap());
+
+    // Losing power after the flag but before the seed is wiped loses the key
+    let mut store = pool.into_inner();
+    store.writes_left = 1;
+    let mut pool = FlashKeyPool::<D, _>::open(store, KEY).unwrap();
+    assert!(pool.sign(b"lost").is_err());
+    let mut store = pool.into_inner();
+    store.writes_left = usize::MAX;
+    let mut pool = FlashKeyPool::<D, _>::open(store, KEY).unwrap();
+    assert!(pool.is_used(0).unwrap());
+    let (index, signature) = pool.sign(b"after reboot").unwrap();
+    assert_eq!(index, 1);
+    assert!(signature.verify(b"after reboot").is_ok());
+}
+
+#[test]
+fn interrupted_provisioning_is_not_a_pool() {
+    let rng = ChaCha8Rng::from_seed([5u8; 32]);
+    let mut store = PowerLoss {
+        inner: MemoryBlockStore::new(PAGE_SIZE, PAGES),
+        writes_left: 10,
+    };
+    assert!(FlashKeyPool::<D, _>::provision(&mut store, 20, SeedKdf::V1, KEY, rng).is_err());
+    assert_eq!(
+        FlashKeyPool::<D, _>::open(&mut store, KEY).unwrap_err(),
+        LamportError::InvalidBlockStore
+    );
+}
+
+#[test]
+fn ecc_store_programs_each_word_once() {
+    let mut store = MemoryBlockStore::with_word_size(PAGE_SIZE, 2, 16);
+    assert_eq!(store.word_size(), 16);
+    assert!(store.write(0, 8, &[0u8; 16]).is_err());
+    assert!(store.write(0, 0, &[0u8; 8]).is_err());
+    store.write(0, 0, &[0xf0; 16]).unwrap();
+    assert_eq!(
+        store.write(0, 0, &[0x30; 16]).unwrap_err(),
+        LamportError::InvalidBlockStore
+    );
+    store.write(0, 0, &[0u8; 16]).unwrap();
+
+    let rng = ChaCha8Rng::from_seed([6u8; 32]);
+    let store = MemoryBlockStore::with_word_size(PAGE_SIZE, PAGES, 16);
+    let mut pool = FlashKeyPool::<D, _>::provision(store, 20, SeedKdf::V1, KEY, rng).unwrap();
+    let published = pool.verifying_key(1).unwrap();
+    pool.sign(b"first").unwrap();
+    let mut pool = FlashKeyPool::<D, _>::open(pool.into_inner(), KEY).unwrap();
+    assert_eq!(pool.remaining(), 19);
+    let (index, signature) = pool.sign(b"second").unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(signature.verifying_key(), &published);
+    assert!(signature.verify(b"second").is_ok());
+}
+
+#[test]
+fn words_larger_than_a_seed() {
+    let rng = ChaCha8Rng::from_seed([8u8; 32]);
+    let store = MemoryBlockStore::with_word_size(PAGE_SIZE, PAGES * 4, 64);
+    let mut pool = FlashKeyPool::<D, _>::provision(store, 8, SeedKdf::V1, KEY, rng).unwrap();
+    let published = pool.verifying_key(1).unwrap();
+    pool.sign(b"first").unwrap();
+    let mut pool = FlashKeyPool::<D, _>::open(pool.into_inner(), KEY).unwrap();
+    assert_eq!(pool.remaining(), 7);
+    assert!(pool.is_used(0).unwrap());
+    let (index, signature) = pool.sign(b"second").unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(signature.verifying_key(), &published);
+    assert!(signature.verify(b"second").is_ok());
+}
+
+#[test]
+fn seeds_are_encrypted_at_rest() {
+    let rng = ChaCha8Rng::from_seed([7u8; 32]);
+    let store = MemoryBlockStore::new(PAGE_SIZE, PAGES);
+    let pool = FlashKeyPool::<D, _>::provision(store, 4, SeedKdf::V1, KEY, rng).unwrap();
+    let published = (0..4)
+        .map(|index| pool.verifying_key(index).unwrap())
+        .collect::<Vec<_>>();
+    let store = pool.into_inner();
+
+    // No 32 byte window of the flash expands to one of the keys
+    let mut flash = vec![0u8; PAGE_SIZE];
+    store.read(0, 0, &mut flash).unwrap();
+    for window in flash.windows(32) {
+        let seed = <[u8; 32]>::try_from(window).unwrap();
+        let sk = SigningKey::<D>::from_seed_with_kdf(&seed, SeedKdf::V1).unwrap();
+        assert!(!published.contains(&VerifyingKey::from(&sk)));
+    }
+
+    assert_eq!(
+        FlashKeyPool::<D, _>::open(store.clone(), b"another key").unwrap_err(),
+        LamportError::InvalidStorageKey
+    );
+    let pool = FlashKeyPool::<D, _>::open(store, KEY).unwrap();
+    assert_eq!(pool.verifying_key(3).unwrap(), published[3]);
+}
@@ -0,0 +1,137 @@
+//! Format stability checks against serialized fixtures committed under `tests/fixtures`.
+//!
+//! Every fixture is decoded on each test run so accidental changes to the
+//! byte formats are caught before release. The fixtures are produced by the
+//! ignored `regenerate_fixtures` test, which must only be run when a format
+//! change is intentional.
+use blake2::Blake2s256;
+use lamport_signature_plus::{
+    LamportDigest, LamportExtendableDigest, LamportFixedDigest, Signature, SignatureShare,
+    SigningKey, SigningKeyShare, VerifyingKey,
+};
+use rand::SeedableRng;
+use sha2::Sha256;
+use sha3::{Sha3_256, Shake128};
+use std::path::PathBuf;
+
+const SEED: [u8; 32] = [7u8; 32];
+const MESSAGE: &[u8] = b"lamport golden fixture";
+const THRESHOLD: usize = 2;
+const SHARES: usize = 3;
+
+fn fixture_dir(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+fn read_fixture(name: &str, file: &str) -> Vec<u8> {
+    let path = fixture_dir(name).join(file);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("unable to read {}: {}", path.display(), e))
+}
+
+fn write_fixtures<T: LamportDigest>(name: &str) {
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed(SEED);
+    let sk = SigningKey::<T>::random(&mut rng).unwrap();
+    let pk = VerifyingKey::from(&sk);
+    let shares = sk.split(THRESHOLD, SHARES, &mut rng).unwrap();
+    let signature_shares = shares
+        .iter()
+        .map(|share| {
+            SigningKeyShare::<T>::from_bytes(share.to_bytes())
+                .unwrap()
+                .sign(MESSAGE)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    let signature = SigningKey::<T>::from_bytes(sk.to_bytes())
+        .unwrap()
+        .sign(MESSAGE)
+        .unwrap();
+
+    let dir = fixture_dir(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("signing_key.bin"), sk.to_bytes()).unwrap();
+    std::fs::write(dir.join("verifying_key.bin"), pk.to_bytes()).unwrap();
+    std::fs::write(dir.join("signature.bin"), signature.to_bytes()).unwrap();
+    for (i, (share, signature_share)) in shares.iter().zip(&signature_shares).enumerate() {
+        std::fs::write(
+            dir.join(format!("signing_key_share_{}.bin", i + 1)),
+            share.to_bytes(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(format!("signature_share_{}.bin", i + 1)),
+            signature_share.to_bytes(),
+        )
+        .unwrap();
+    }
+}
+
+fn check_fixtures<T: LamportDigest>(name: &str) {
+    let sk_bytes = read_fixture(name, "signing_key.bin");
+    let pk_bytes = read_fixture(name, "verifying_key.bin");
+    let signature_bytes = read_fixture(name, "signature.bin");
+
+    let sk = SigningKey::<T>::from_bytes(&sk_bytes).unwrap();
+    assert!(!sk.used());
+    assert_eq!(sk.to_bytes(), sk_bytes);
+
+    let pk = VerifyingKey::<T>::from_bytes(&pk_bytes).unwrap();
+    assert_eq!(pk.to_bytes(), pk_bytes);
+    assert_eq!(VerifyingKey::from(&sk).to_bytes(), pk_bytes);
+
+    let signature = Signature::<T>::from_bytes(&signature_bytes).unwrap();
+    assert_eq!(signature.to_bytes(), signature_bytes);
+    assert!(pk.verify(&signature, MESSAGE).is_ok());
+
+    let mut shares = Vec::with_capacity(SHARES);
+    let mut signature_shares = Vec::with_capacity(SHARES);
+    for i in 1..=SHARES {
+        let share_bytes = read_fixture(name, &format!("signing_key_share_{}.bin", i));
+        let share = SigningKeyShare::<T>::from_bytes(&share_bytes).unwrap();
+        assert_eq!(share.to_bytes(), share_bytes);
+        shares.push(share);
+
+        let signature_share_bytes = read_fixture(name, &format!("signature_share_{}.bin", i));
+        let signature_share = SignatureShare::<T>::from_bytes(&signature_share_bytes).unwrap();
+        assert_eq!(signature_share.to_bytes(), signature_share_bytes);
+        signature_shares.push(signature_share);
+    }
+
+    let restored = SigningKey::<T>::combine(&shares[1..]).unwrap();
+    assert_eq!(restored.to_bytes(), sk_bytes);
+
+    let combined = Signature::combine(&signature_shares[..THRESHOLD]).unwrap();
+    assert_eq!(combined.to_bytes(), signature_bytes);
+}
+
+#[test]
+fn sha256_fixtures() {
+    check_fixtures::<LamportFixedDigest<Sha256>>("sha256");
+}
+
+#[test]
+fn sha3_256_fixtures() {
+    check_fixtures::<LamportFixedDigest<Sha3_256>>("sha3_256");
+}
+
+#[test]
+fn blake2s256_fixtures() {
+    check_fixtures::<LamportFixedDigest<Blake2s256>>("blake2s256");
+}
+
+#[test]
+fn shake128_fixtures() {
+    check_fixtures::<LamportExtendableDigest<Shake128>>("shake128");
+}
+
+#[test]
+#[ignore = "only run when a format change is intentional"]
+fn regenerate_fixtures() {
+    write_fixtures::<LamportFixedDigest<Sha256>>("sha256");
+    write_fixtures::<LamportFixedDigest<Sha3_256>>("sha3_256");
+    write_fixtures::<LamportFixedDigest<Blake2s256>>("blake2s256");
+    write_fixtures::<LamportExtendableDigest<Shake128>>("shake128");
+}
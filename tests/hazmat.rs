@@ -0,0 +1,31 @@
+#![cfg(feature = "hazmat")]
+use lamport_signature_plus::{generate_keys, hazmat, LamportFixedDigest};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+#[test]
+fn sign_and_verify_bits() {
+    let rng = rand_chacha::ChaCha8Rng::from_seed([5u8; 32]);
+    let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+    let bits = [0xA5u8; 32];
+
+    assert!(hazmat::sign_bits(&mut sk.clone(), &bits[..31]).is_err());
+    let signature = hazmat::sign_bits(&mut sk, &bits).unwrap();
+    assert!(sk.used());
+    assert!(hazmat::verify_bits(&pk, &signature, &bits).is_ok());
+    assert!(hazmat::verify_bits(&pk, &signature, &[0x5Au8; 32]).is_err());
+    assert!(hazmat::sign_bits(&mut sk, &bits).is_err());
+}
+
+#[test]
+fn force_reset_used() {
+    let rng = rand_chacha::ChaCha8Rng::from_seed([6u8; 32]);
+    let (mut sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(rng).unwrap();
+    sk.sign(b"first").unwrap();
+    assert!(sk.sign(b"second").is_err());
+
+    hazmat::force_reset_used(&mut sk);
+    assert!(!sk.used());
+    let signature = sk.sign(b"second").unwrap();
+    assert!(pk.verify(&signature, b"second").is_ok());
+}
@@ -0,0 +1,27 @@
+#![cfg(feature = "key_wrap")]
+use lamport_signature_plus::{generate_keys, LamportError, LamportFixedDigest, SigningKey};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+#[test]
+fn wrap_and_unwrap() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([12u8; 32]);
+    let (sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+
+    for kek in [[1u8; 16].as_slice(), &[2u8; 24], &[3u8; 32]] {
+        let wrapped = sk.wrap(kek).unwrap();
+        assert_eq!(wrapped.len(), sk.to_bytes().len().div_ceil(8) * 8 + 8);
+        let mut unwrapped = SigningKey::<D>::unwrap(kek, &wrapped).unwrap();
+        assert_eq!(unwrapped.to_bytes(), sk.to_bytes());
+        let signature = unwrapped.sign(b"unwrapped").unwrap();
+        assert!(pk.verify(&signature, b"unwrapped").is_ok());
+    }
+
+    let wrapped = sk.wrap(&[1u8; 32]).unwrap();
+    assert_eq!(
+        SigningKey::<D>::unwrap(&[2u8; 32], &wrapped).unwrap_err(),
+        LamportError::InvalidKeyWrap
+    );
+    assert!(sk.wrap(&[1u8; 20]).is_err());
+}
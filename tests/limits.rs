@@ -0,0 +1,86 @@
+use lamport_signature_plus::{
+    armor, generate_keys, Bounded, KeyRegistry, LamportError, LamportFixedDigest, Limits,
+    Signature, DEFAULT_MAX_INPUT_BYTES,
+};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+type D = LamportFixedDigest<Sha256>;
+
+#[test]
+fn input_size_limits() {
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([3u8; 32]);
+    let (mut sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let signature = sk.sign(b"limits").unwrap();
+    let json = serde_json::to_string(&signature).unwrap();
+    let mut registry = KeyRegistry::new();
+    registry.insert(pk);
+    let registry_bytes = registry.to_bytes();
+    let armored = signature.to_armor();
+    let small = Limits::new(1024);
+
+    assert_eq!(Limits::default().max_input_bytes(), DEFAULT_MAX_INPUT_BYTES);
+    assert!(serde_json::from_str::<Signature<D>>(&json).is_ok());
+    assert!(serde_json::from_str::<Bounded<Signature<D>, 8192>>(&json).is_ok());
+    assert!(KeyRegistry::<D>::from_bytes(&registry_bytes).is_ok());
+
+    let err = serde_json::from_str::<Bounded<Signature<D>, 1024>>(&json).unwrap_err();
+    assert!(err.to_string().contains("exceeds the limit of 1024 bytes"));
+    let hostile = format!("[{}]", vec!["0"; 2048].join(","));
+    assert!(serde_json::from_str::<Bounded<Signature<D>, 1024>>(&hostile).is_err());
+    assert_eq!(
+        KeyRegistry::<D>::from_bytes_with_limits(&registry_bytes, &small).unwrap_err(),
+        LamportError::InputTooLarge {
+            length: registry_bytes.len(),
+            limit: 1024,
+        }
+    );
+    assert!(matches!(
+        armor::decode_with_limits(&armored, &small),
+        Err(LamportError::InputTooLarge { .. })
+    ));
+    assert!(Signature::<D>::from_armor(&armored).is_ok());
+}
+
+#[test]
+fn text_limits_follow_the_encoding() {
+    #[derive(serde::Serialize)]
+    struct Field<'a>(
+        #[serde(serialize_with = "lamport_signature_plus::base64url::serialize")] &'a Signature<D>,
+    );
+
+    #[derive(serde::Deserialize)]
+    struct BoundedField<const MAX: usize>(
+        #[serde(deserialize_with = "lamport_signature_plus::base64url::deserialize")]
+        #[allow(dead_code)]
+        Bounded<Signature<D>, MAX>,
+    );
+
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([4u8; 32]);
+    let (mut sk, _) = generate_keys::<D, _>(&mut rng).unwrap();
+    let signature = sk.sign(b"limits").unwrap();
+    let length = signature.to_bytes().len();
+    let hex = serde_json::to_string(&signature).unwrap();
+    let base64url = serde_json::to_string(&Field(&signature)).unwrap();
+    assert!(base64url.len() < hex.len());
+
+    // The exact size is accepted in both encodings and one byte less is not
+    macro_rules! check {
+        ($ty:ident, $json:expr, $max:expr, $ok:expr) => {
+            assert_eq!(serde_json::from_str::<$ty<{ $max }>>($json).is_ok(), $ok);
+        };
+    }
+    type Hex<const MAX: usize> = Bounded<Signature<D>, MAX>;
+    check!(Hex, &hex, 256 * 32, true);
+    check!(Hex, &hex, 256 * 32 - 1, false);
+    check!(BoundedField, &base64url, 256 * 32, true);
+    check!(BoundedField, &base64url, 256 * 32 - 1, false);
+    assert_eq!(length, 256 * 32);
+
+    // Over-long base64url is rejected by its length before it is decoded
+    let hostile = format!("\"{}\"", "!".repeat(4 * length));
+    let err = serde_json::from_str::<BoundedField<{ 256 * 32 }>>(&hostile)
+        .map(|_| ())
+        .unwrap_err();
+    assert!(err.to_string().contains("exceeds the limit of 8192 bytes"));
+}
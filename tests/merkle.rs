@@ -0,0 +1,106 @@
+use lamport_signature_plus::{
+    generate_keys, ConsistencyProof, InclusionProof, KeyId, KeyLog, LamportError,
+    LamportFixedDigest, MerkleTree,
+};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+#[test]
+fn inclusion_proofs_for_every_leaf() {
+    for size in 1..=9u8 {
+        let leaves = (0..size).map(|i| KeyId::from([i; 32])).collect::<Vec<_>>();
+        let tree = MerkleTree::new(leaves.clone()).unwrap();
+        let root = tree.root();
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = tree.prove(i).unwrap();
+            assert!(proof.verify(leaf, &root).is_ok());
+            assert!(proof.verify(&KeyId::from([0xFF; 32]), &root).is_err());
+
+            let restored = InclusionProof::from_bytes(proof.to_bytes()).unwrap();
+            assert_eq!(restored, proof);
+        }
+        assert!(tree.prove(leaves.len()).is_err());
+    }
+    assert!(MerkleTree::new(Vec::new()).is_err());
+}
+
+#[test]
+fn inclusion_proof_for_verifying_keys() {
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([9u8; 32]);
+    let keys = (0..4)
+        .map(|_| {
+            generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng)
+                .unwrap()
+                .1
+        })
+        .collect::<Vec<_>>();
+    let tree = MerkleTree::from_verifying_keys(&keys).unwrap();
+    let proof = tree.prove(2).unwrap();
+    assert!(proof.verify_key(&keys[2], &tree.root()).is_ok());
+    assert!(proof.verify_key(&keys[1], &tree.root()).is_err());
+}
+
+#[test]
+fn authentication_paths_for_signing_keys() {
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([7u8; 32]);
+    let (mut signing_keys, keys): (Vec<_>, Vec<_>) = (0..5)
+        .map(|_| generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap())
+        .unzip();
+    let tree = MerkleTree::from_verifying_keys(&keys).unwrap();
+    let root = tree.root();
+    for (i, key) in keys.iter().enumerate() {
+        let proof = tree.prove_key(key).unwrap();
+        assert_eq!(proof.index(), i as u64);
+        let signature = signing_keys[i].sign(b"batch").unwrap();
+        assert!(proof
+            .verify_signature(key, &signature, b"batch", &root)
+            .is_ok());
+        assert!(proof
+            .verify_signature(key, &signature, b"other", &root)
+            .is_err());
+        let other = &keys[(i + 1) % keys.len()];
+        assert!(proof
+            .verify_signature(other, &signature, b"batch", &root)
+            .is_err());
+    }
+    let (_, outsider) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+    assert!(tree.prove_key(&outsider).is_err());
+}
+
+#[test]
+fn key_log_consistency() {
+    let mut log = KeyLog::new();
+    let mut roots = Vec::new();
+    for i in 0..12u8 {
+        assert_eq!(log.append(KeyId::from([i; 32])), i as u64);
+        roots.push(log.root().unwrap());
+    }
+    let tree = MerkleTree::new((0..12u8).map(|i| KeyId::from([i; 32])).collect()).unwrap();
+    assert_eq!(log.root().unwrap(), tree.root());
+
+    for old_size in 1..=12u64 {
+        for new_size in old_size..=12u64 {
+            let proof = log.prove_consistency(old_size, new_size).unwrap();
+            let old_root = &roots[old_size as usize - 1];
+            let new_root = &roots[new_size as usize - 1];
+            assert!(proof.verify(old_root, new_root).is_ok());
+            if old_size != new_size {
+                assert!(proof.verify(new_root, new_root).is_err());
+            }
+            let restored = ConsistencyProof::from_bytes(proof.to_bytes()).unwrap();
+            assert_eq!(restored, proof);
+        }
+        let inclusion = log.prove_inclusion(old_size - 1, 12).unwrap();
+        assert!(inclusion
+            .verify(log.get(old_size - 1).unwrap(), &roots[11])
+            .is_ok());
+    }
+
+    let restored = KeyLog::from_bytes(log.to_bytes()).unwrap();
+    assert_eq!(restored, log);
+    assert_eq!(
+        KeyLog::from_bytes(&log.to_bytes()[1..]).unwrap_err(),
+        LamportError::InvalidKeyLogBytes
+    );
+    assert!(log.prove_consistency(5, 13).is_err());
+}
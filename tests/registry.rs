@@ -0,0 +1,41 @@
+use lamport_signature_plus::{generate_keys, KeyRegistry, LamportError, LamportFixedDigest};
+use rand::SeedableRng;
+use sha2::Sha256;
+
+#[test]
+fn registry_verify_and_revoke() {
+    let mut rng = rand_chacha::ChaCha8Rng::from_seed([11u8; 32]);
+    let (mut sk1, pk1) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+    let (mut sk2, pk2) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+    let (_, pk3) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+
+    let mut registry = KeyRegistry::new();
+    let id1 = registry.insert(pk1);
+    let id2 = registry.insert(pk2);
+    assert_eq!(registry.len(), 2);
+    assert!(!registry.contains(&pk3.fingerprint()));
+
+    let message = b"hello, world!";
+    let signature1 = sk1.sign(message).unwrap();
+    let signature2 = sk2.sign_attached(message).unwrap();
+    assert!(registry.verify(&signature1, message, &id1).is_ok());
+    assert!(registry.verify(&signature1, message, &id2).is_err());
+    assert!(registry.verify_attached(&signature2, message).is_ok());
+    assert!(matches!(
+        registry.verify(&signature1, message, &pk3.fingerprint()),
+        Err(LamportError::UnknownKeyId)
+    ));
+
+    registry.revoke(&id2).unwrap();
+    assert!(registry.is_revoked(&id2));
+    assert!(matches!(
+        registry.verify_attached(&signature2, message),
+        Err(LamportError::RevokedKey)
+    ));
+
+    let restored =
+        KeyRegistry::<LamportFixedDigest<Sha256>>::from_bytes(registry.to_bytes()).unwrap();
+    assert_eq!(restored.to_bytes(), registry.to_bytes());
+    assert!(restored.is_revoked(&id2));
+    assert!(restored.verify(&signature1, message, &id1).is_ok());
+}
@@ -0,0 +1,120 @@
+#![cfg(feature = "signature")]
+use lamport_signature_plus::{
+    generate_keys, LamportFixedDigest, SharedSigningKey, Signature, VerifyingKey,
+};
+use rand::SeedableRng;
+use sha2::Sha256;
+use signature::{Signer, SignerMut, Verifier};
+
+type Digest = LamportFixedDigest<Sha256>;
+
+fn sign_generic<S: Signer<Signature<Digest>>>(signer: &S, msg: &[u8]) -> Signature<Digest> {
+    signer.sign(msg)
+}
+
+fn verify_generic<V: Verifier<Signature<Digest>>>(
+    verifier: &V,
+    msg: &[u8],
+    signature: &Signature<Digest>,
+) -> bool {
+    verifier.verify(msg, signature).is_ok()
+}
+
+#[test]
+fn signer_mut_and_verifier() {
+    let rng = rand_chacha::ChaCha8Rng::from_seed([3u8; 32]);
+    let (mut sk, pk) = generate_keys::<Digest, _>(rng).unwrap();
+
+    let signature = SignerMut::try_sign(&mut sk, b"hello").unwrap();
+    assert!(verify_generic(&pk, b"hello", &signature));
+    assert!(!verify_generic(&pk, b"goodbye", &signature));
+    assert!(SignerMut::try_sign(&mut sk, b"again").is_err());
+}
+
+#[test]
+fn shared_signing_key_signs_once() {
+    let rng = rand_chacha::ChaCha8Rng::from_seed([4u8; 32]);
+    let (sk, pk) = generate_keys::<Digest, _>(rng).unwrap();
+    let signer = SharedSigningKey::from(sk);
+    assert!(!signer.used());
+    assert_eq!(signer.verifying_key().to_bytes(), pk.to_bytes());
+
+    let signature = sign_generic(&signer, b"hello");
+    assert!(signer.used());
+    assert!(Verifier::verify(&pk, b"hello", &signature).is_ok());
+    assert!(signer.try_sign(b"hello").is_err());
+
+    let sk = signer.into_inner().unwrap();
+    assert!(sk.used());
+    assert_eq!(VerifyingKey::from(&sk).to_bytes(), pk.to_bytes());
+}
+
+#[test]
+fn digest_signer_and_verifier() {
+    use sha2::Digest as _;
+    use signature::{DigestSigner, DigestVerifier};
+
+    let rng = rand_chacha::ChaCha8Rng::from_seed([5u8; 32]);
+    let (sk, pk) = generate_keys::<Digest, _>(rng).unwrap();
+    let signer = SharedSigningKey::from(sk);
+    let message = vec![7u8; 1 << 16];
+
+    let mut hasher = Sha256::new();
+    for chunk in message.chunks(1000) {
+        hasher.update(chunk);
+    }
+    let signature = signer.try_sign_digest(hasher).unwrap();
+    assert!(pk.verify(&signature, &message).is_ok());
+    assert!(pk
+        .verify_digest(Sha256::new().chain_update(&message), &signature)
+        .is_ok());
+    assert!(pk
+        .verify_digest(Sha256::new().chain_update(b"other"), &signature)
+        .is_err());
+    assert!(signer.try_sign_digest(Sha256::new()).is_err());
+}
+
+#[test]
+fn keypair_traits() {
+    use lamport_signature_plus::Keypair;
+
+    fn public_key<K: signature::Keypair<VerifyingKey = VerifyingKey<Digest>>>(key: &K) -> Vec<u8> {
+        key.verifying_key().to_bytes()
+    }
+
+    let rng = rand_chacha::ChaCha8Rng::from_seed([6u8; 32]);
+    let (sk, pk) = generate_keys::<Digest, _>(rng).unwrap();
+    assert_eq!(public_key(&sk), pk.to_bytes());
+
+    let keypair = Keypair::from(sk.clone());
+    assert_eq!(public_key(&keypair), pk.to_bytes());
+    assert_eq!(
+        AsRef::<VerifyingKey<Digest>>::as_ref(&keypair).to_bytes(),
+        pk.to_bytes()
+    );
+
+    let signer = SharedSigningKey::from(sk);
+    assert_eq!(public_key(&signer), pk.to_bytes());
+}
+
+#[test]
+fn signature_encoding() {
+    use signature::SignatureEncoding;
+
+    fn round_trip<S: SignatureEncoding>(signature: &S) -> S {
+        S::try_from(signature.to_bytes().as_ref())
+            .ok()
+            .expect("valid encoding")
+    }
+
+    let rng = rand_chacha::ChaCha8Rng::from_seed([7u8; 32]);
+    let (mut sk, pk) = generate_keys::<Digest, _>(rng).unwrap();
+    let signature = sk.sign(b"hello").unwrap();
+    assert_eq!(SignatureEncoding::encoded_len(&signature), 256 * 32);
+    assert_eq!(SignatureEncoding::to_vec(&signature), signature.to_bytes());
+
+    let decoded = round_trip(&signature);
+    assert_eq!(decoded.to_bytes(), signature.to_bytes());
+    assert!(pk.verify(&decoded, b"hello").is_ok());
+    assert!(Signature::<Digest>::try_from(&signature.to_bytes()[1..]).is_err());
+}
@@ -0,0 +1,25 @@
+#![cfg(feature = "test_utils")]
+use lamport_signature_plus::test_utils::{test_key_shares, test_keypair, test_signing_key};
+use lamport_signature_plus::{LamportFixedDigest, SigningKey};
+use sha2::Sha256;
+
+type D = LamportFixedDigest<Sha256>;
+
+#[test]
+fn fixtures_are_deterministic() {
+    let a = test_signing_key::<D, _>("alice");
+    let (_, pk) = test_keypair::<D, _>("alice");
+    let b = test_signing_key::<D, _>("bob");
+    assert_eq!(a.to_bytes(), test_signing_key::<D, _>("alice").to_bytes());
+    assert_ne!(a.to_bytes(), b.to_bytes());
+
+    let shares = test_key_shares::<D, _>("alice", 2, 3).unwrap();
+    let again = test_key_shares::<D, _>("alice", 2, 3).unwrap();
+    assert_eq!(shares[1].to_bytes(), again[1].to_bytes());
+    let combined = SigningKey::combine(&shares[..2]).unwrap();
+    assert_eq!(combined.to_bytes(), a.to_bytes());
+
+    let mut a = a;
+    let signature = a.sign(b"fixture").unwrap();
+    assert!(pk.verify(&signature, b"fixture").is_ok());
+}
@@ -1,18 +1,34 @@
-use lamport_signature_plus::{generate_keys, LamportFixedDigest, Signature};
-use rand::SeedableRng;
+#[cfg(feature = "unstable-threshold-session")]
+use lamport_signature_plus::unstable::threshold_session::{
+    SessionCommitment, SessionRequest, SessionState, ThresholdParticipant, ThresholdSession,
+};
+use lamport_signature_plus::{
+    generate_keys, CombineResult, LamportError, LamportFixedDigest, PartialSignatureMsg,
+    RefreshMsg, ShareBundle, SignRequest, Signature, SigningKey, SigningKeyShare,
+    MAX_SIGN_REQUEST_MESSAGE_BYTES, SESSION_ID_BYTES,
+};
+use rand::{RngCore, SeedableRng};
 use sha2::Sha256;
 
 #[test]
 fn partial_sign() {
     let mut rng = rand_chacha::ChaChaRng::from_entropy();
     for _ in 0..10 {
-        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng);
+        let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
         let message = b"hello, world!";
         let mut shares = sk.split(3, 5, &mut rng).unwrap();
+        assert!(shares.iter().all(|share| share.threshold() == 3));
+        assert!(!shares[0].used());
         let signatures = shares
             .iter_mut()
             .map(|share| share.sign(message).unwrap())
             .collect::<Vec<_>>();
+        assert!(shares[0].used());
+        for (i, signature) in signatures.iter().enumerate() {
+            assert_eq!(usize::from(signature.identifier()), i + 1);
+            assert_eq!(signature.identifier(), shares[i].identifier());
+            assert_eq!(signature.threshold(), 3);
+        }
 
         let res = Signature::combine(&signatures[..3]);
         assert!(res.is_ok());
@@ -23,3 +39,275 @@ fn partial_sign() {
         assert!(res.is_err());
     }
 }
+
+#[cfg(feature = "unstable-threshold-session")]
+#[test]
+fn threshold_session() {
+    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+    let mut participants = sk
+        .split(3, 5, &mut rng)
+        .unwrap()
+        .into_iter()
+        .map(ThresholdParticipant::new)
+        .collect::<Vec<_>>();
+
+    let message = b"hello, world!";
+    let mut session = ThresholdSession::new(pk.clone(), 3, message, &mut rng).unwrap();
+    assert!(matches!(
+        session.finalize(),
+        Err(LamportError::InvalidSessionState)
+    ));
+
+    let request = session.request().clone();
+    let wire = SignRequest::from(&request);
+    assert_eq!(SessionRequest::from(wire), request);
+    for participant in participants.iter_mut().take(3) {
+        let commitment = participant.commit(&request).unwrap();
+        session.add_commitment(&commitment).unwrap();
+    }
+    assert_eq!(session.state(), SessionState::CollectingSignatures);
+    let late = participants[3].commit(&request).unwrap();
+    assert!(session.add_commitment(&late).is_err());
+
+    let wrong = participants[3].reveal(&request.session_id).unwrap();
+    assert!(session.add_signature_share(wrong).is_err());
+    for participant in participants.iter_mut().take(3) {
+        let share = participant.reveal(&request.session_id).unwrap();
+        session.add_signature_share(share).unwrap();
+    }
+    assert!(session.is_ready());
+    let signature = session.finalize().unwrap();
+    assert_eq!(session.state(), SessionState::Complete);
+    assert!(session.finalize().is_err());
+    assert!(pk.verify(&signature, message).is_ok());
+}
+
+#[cfg(feature = "unstable-threshold-session")]
+#[test]
+fn threshold_session_exclude_and_abort() {
+    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let (sk, pk) = generate_keys::<LamportFixedDigest<Sha256>, _>(&mut rng).unwrap();
+    let mut participants = sk
+        .split(2, 4, &mut rng)
+        .unwrap()
+        .into_iter()
+        .map(ThresholdParticipant::new)
+        .collect::<Vec<_>>();
+
+    let message = b"hello, world!";
+    let mut session = ThresholdSession::new(pk.clone(), 2, message, &mut rng).unwrap();
+    let request = session.request().clone();
+    for participant in participants.iter_mut().take(2) {
+        let commitment = participant.commit(&request).unwrap();
+        session.add_commitment(&commitment).unwrap();
+    }
+    let first = participants[0].reveal(&request.session_id).unwrap();
+    session.add_signature_share(first).unwrap();
+
+    // The second participant stops responding and is replaced
+    let silent = participants[1].identifier();
+    session.exclude(silent).unwrap();
+    assert_eq!(session.state(), SessionState::CollectingCommitments);
+    assert_eq!(session.excluded().collect::<Vec<_>>(), [silent]);
+    let late = participants[1].reveal(&request.session_id).unwrap();
+    let late_commitment = SessionCommitment {
+        session_id: request.session_id,
+        identifier: silent,
+        commitment: [0u8; 32],
+    };
+    assert_eq!(
+        session.add_commitment(&late_commitment).unwrap_err(),
+        LamportError::InvalidParticipant(silent)
+    );
+    let commitment = participants[2].commit(&request).unwrap();
+    session.add_commitment(&commitment).unwrap();
+    assert_eq!(
+        session.add_signature_share(late).unwrap_err(),
+        LamportError::InvalidParticipant(silent)
+    );
+    let share = participants[2].reveal(&request.session_id).unwrap();
+    session.add_signature_share(share).unwrap();
+    let signature = session.finalize().unwrap();
+    assert!(pk.verify(&signature, message).is_ok());
+    assert!(session.exclude(1).is_err());
+    assert!(session.abort().is_err());
+
+    // Shares split for another threshold are rejected with the participant
+    let mut other = sk
+        .split(3, 3, &mut rng)
+        .unwrap()
+        .into_iter()
+        .map(ThresholdParticipant::new)
+        .collect::<Vec<_>>();
+    let mut session = ThresholdSession::new(pk, 2, message, &mut rng).unwrap();
+    let request = session.request().clone();
+    for participant in other.iter_mut().take(2) {
+        let commitment = participant.commit(&request).unwrap();
+        session.add_commitment(&commitment).unwrap();
+    }
+    let share = other[0].reveal(&request.session_id).unwrap();
+    assert_eq!(
+        session.add_signature_share(share).unwrap_err(),
+        LamportError::ThresholdMismatch {
+            identifier: other[0].identifier(),
+            expected: 2,
+            found: 3,
+        }
+    );
+
+    session.abort().unwrap();
+    assert_eq!(session.state(), SessionState::Aborted);
+    let share = other[1].reveal(&request.session_id).unwrap();
+    assert_eq!(
+        session.add_signature_share(share).unwrap_err(),
+        LamportError::InvalidSessionState
+    );
+    assert!(session.finalize().is_err());
+}
+
+#[test]
+fn threshold_messages() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let (sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let mut shares = sk.split(2, 3, &mut rng).unwrap();
+    let mut session_id = [0u8; SESSION_ID_BYTES];
+    rng.fill_bytes(&mut session_id);
+    let request = SignRequest {
+        session_id,
+        message: b"hello, world!".to_vec(),
+    };
+    assert_eq!(
+        SignRequest::from_bytes(request.to_bytes()).unwrap(),
+        request
+    );
+
+    let partials = shares
+        .iter_mut()
+        .take(2)
+        .map(|share| {
+            let msg = PartialSignatureMsg {
+                session_id: request.session_id,
+                share: share.sign(&request.message).unwrap(),
+            };
+            PartialSignatureMsg::<D>::from_bytes(msg.to_bytes()).unwrap()
+        })
+        .collect::<Vec<_>>();
+    let result = CombineResult {
+        session_id: request.session_id,
+        signature: Signature::combine(&partials.into_iter().map(|m| m.share).collect::<Vec<_>>())
+            .unwrap(),
+    };
+    let result = CombineResult::<D>::from_bytes(result.to_bytes()).unwrap();
+    assert!(pk.verify(&result.signature, &request.message).is_ok());
+
+    let mut bytes = result.to_bytes();
+    bytes[0] = 2;
+    assert!(matches!(
+        CombineResult::<D>::from_bytes(&bytes),
+        Err(LamportError::UnsupportedMessageVersion(2))
+    ));
+    let large = SignRequest {
+        session_id: request.session_id,
+        message: vec![0u8; MAX_SIGN_REQUEST_MESSAGE_BYTES + 1],
+    };
+    assert!(SignRequest::from_bytes(large.to_bytes()).is_err());
+}
+
+#[test]
+fn refresh_shares() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let (sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let shares = sk.split(2, 3, &mut rng).unwrap();
+    let messages = shares
+        .iter()
+        .map(|share| share.refresh_messages(3, &mut rng).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut refreshed = shares.clone();
+    for share in refreshed.iter_mut() {
+        for msg in messages.iter().flatten() {
+            let msg = RefreshMsg::<D>::from_bytes(msg.to_bytes()).unwrap();
+            if msg.recipient() == share.identifier() {
+                share.apply_refresh(&msg).unwrap();
+            } else {
+                assert!(share.apply_refresh(&msg).is_err());
+            }
+        }
+    }
+    assert_ne!(refreshed[0].to_bytes(), shares[0].to_bytes());
+    let combined = SigningKey::combine(&refreshed[1..]).unwrap();
+    assert_eq!(combined.to_bytes(), sk.to_bytes());
+
+    let mixed = [shares[0].clone(), refreshed[1].clone()];
+    let combined = SigningKey::combine(&mixed).unwrap();
+    assert_ne!(combined.to_bytes(), sk.to_bytes());
+
+    let signatures = refreshed
+        .iter_mut()
+        .take(2)
+        .map(|share| share.sign(b"refreshed").unwrap())
+        .collect::<Vec<_>>();
+    let signature = Signature::combine(&signatures).unwrap();
+    assert!(pk.verify(&signature, b"refreshed").is_ok());
+}
+
+#[test]
+fn split_into_writers() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let (sk, _) = generate_keys::<D, _>(&mut rng).unwrap();
+    let mut writers = vec![Vec::new(); 4];
+    sk.split_into_writers(3, &mut writers, &mut rng).unwrap();
+    let shares = writers
+        .iter()
+        .map(|w| SigningKeyShare::<D>::from_bytes(w).unwrap())
+        .collect::<Vec<_>>();
+    let combined = SigningKey::combine(&shares[1..]).unwrap();
+    assert_eq!(combined.to_bytes(), sk.to_bytes());
+    assert!(SigningKey::combine(&shares[..2]).is_err());
+
+    assert!(sk.split_into_writers(5, &mut writers, &mut rng).is_err());
+
+    // Invalid splits are rejected before anything is written
+    let mut writers = vec![Vec::new(); 256];
+    assert!(sk.split_into_writers(2, &mut writers, &mut rng).is_err());
+    assert!(writers.iter().all(Vec::is_empty));
+    let mut writers = vec![Vec::new(); 3];
+    assert!(sk.split_into_writers(1, &mut writers, &mut rng).is_err());
+    assert!(writers.iter().all(Vec::is_empty));
+}
+
+#[test]
+fn share_bundles() {
+    type D = LamportFixedDigest<Sha256>;
+    let mut rng = rand_chacha::ChaChaRng::from_entropy();
+    let (sk, pk) = generate_keys::<D, _>(&mut rng).unwrap();
+    let mut shares = sk.split(2, 3, &mut rng).unwrap();
+
+    let bundle = ShareBundle::from_key_shares(shares.clone()).unwrap();
+    let bundle = ShareBundle::<D>::from_bytes(bundle.to_bytes()).unwrap();
+    assert_eq!(bundle.len(), 3);
+    assert_eq!(bundle.threshold(), 2);
+    assert!(bundle.signature_shares().is_none());
+    assert_eq!(bundle.combine_key().unwrap().to_bytes(), sk.to_bytes());
+
+    let message = b"bundled";
+    let signatures = shares
+        .iter_mut()
+        .map(|s| s.sign(message).unwrap())
+        .collect::<Vec<_>>();
+    let bundle = ShareBundle::from_signature_shares(signatures.clone(), message).unwrap();
+    let bytes = bundle.to_bytes();
+    let bundle = ShareBundle::<D>::from_bytes(&bytes).unwrap();
+    assert_eq!(bundle.message_digest().unwrap().len(), 32);
+    let signature = bundle.combine_signature().unwrap();
+    assert!(pk.verify(&signature, message).is_ok());
+
+    assert!(ShareBundle::<D>::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let duplicate = vec![signatures[0].clone(), signatures[0].clone()];
+    assert!(ShareBundle::from_signature_shares(duplicate, message).is_err());
+    assert!(ShareBundle::<D>::from_key_shares(Vec::new()).is_err());
+}